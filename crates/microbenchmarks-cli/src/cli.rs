@@ -1,80 +1,273 @@
-use std::error::Error;
+use std::{error::Error, path::PathBuf};
 
-use clap::{command, Args, Parser, Subcommand};
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    Arg, ArgAction, ArgMatches, Args, CommandFactory, FromArgMatches, Parser,
+    Subcommand,
+};
+use microbenchmarks::{
+    find_microbenchmark, microbenchmarks, ElementType, Microbenchmark, Sweep,
+    WorkgroupSweep,
+};
+use uwgpu::{PassMode, SubmissionMode};
 
 #[derive(Parser)]
 #[command(version, about = "CLI tool for executing µwgpu microbenchmarks", long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
-    pub microbenchmark: Microbenchmarks,
+    pub command: Command,
 }
 
+/// Subcommands besides the microbenchmarks, which get added by [parse]
 #[derive(Subcommand)]
-#[command(name = "Microbenchmark")]
-pub enum Microbenchmarks {
-    /// Run the matmul microbenchmark
-    MatMul(MicrobenchmarkParams<2>),
-    /// Run the convolution microbenchmark
-    Convolution(MicrobenchmarkParams<2>),
-    /// Run the scan microbenchmark
-    Scan(MicrobenchmarkParams<1>),
-    /// Run the reduction microbenchmark
-    Reduction(MicrobenchmarkParams<1>),
-    /// Run the buffer to buffer copy microbenchmark
-    BufferToBuffer(MicrobenchmarkParams<1>),
-    /// Run the buffer to texture copy microbenchmark
-    BufferToTexture(MicrobenchmarkParams<2>),
-    /// Run the texture to texture copy microbenchmark
-    TextureToTexture(MicrobenchmarkParams<2>),
+pub enum Command {
+    /// Run the benchmarks defined by a spec file, or by every spec file in a
+    /// directory
+    Spec(SpecParams),
+    /// Search for the fastest workgroup size of a microbenchmark
+    Tune(TuneParams),
+    /// Run every microbenchmark and place the computational ones on the
+    /// roofline of the device, given by its peak FLOPS and bandwidth
+    Roofline(RooflineParams),
 }
 
-/// Common parameters shared by microbenchmarks
+/// Parameters for running benchmark specs
 #[derive(Args)]
-pub struct MicrobenchmarkParams<const DIMS: usize> {
-    #[arg(short, long, value_parser = parse_array::<DIMS, u32>)]
-    pub workgroup: Vec<[u32; DIMS]>,
+pub struct SpecParams {
+    /// Spec file or directory of spec files
+    pub path: PathBuf,
+    /// Workgroup sizes to run instead of the ones given by the specs
+    #[arg(short, long, value_parser = |s: &str| parse_workgroup(s, 3))]
+    pub workgroup: Vec<(u32, u32, u32)>,
+    /// How to split the timed iterations into command buffers, instead of
+    /// the one given by the specs
+    #[arg(long, value_parser = submission_mode_parser())]
+    pub submission_mode: Option<SubmissionMode>,
+    /// How to split the timed iterations into compute passes, instead of the
+    /// one given by the specs
+    #[arg(long, value_parser = pass_mode_parser())]
+    pub pass_mode: Option<PassMode>,
 }
 
+/// Parameters for auto-tuning the workgroup size of a microbenchmark
 #[derive(Args)]
-pub struct WorkgroupSize<const DIMS: usize> {}
+pub struct TuneParams {
+    /// Microbenchmark to tune, such as "matmul"
+    #[arg(value_parser = parse_microbenchmark)]
+    pub microbenchmark: &'static dyn Microbenchmark,
+    /// Problem size to tune for, the microbenchmark's default if not given
+    #[arg(short, long)]
+    pub size: Option<usize>,
+    /// Element type to tune for, the microbenchmark's default if not given
+    #[arg(short, long)]
+    pub element_type: Option<ElementType>,
+    /// Measured iterations of each run in the first round, doubled every
+    /// round. A 16th of the microbenchmark's default if not given
+    #[arg(short, long)]
+    pub count: Option<usize>,
+}
 
-/// Parse an array of values
-fn parse_array<const SIZE: usize, T>(
-    s: &str,
-) -> Result<[T; SIZE], Box<dyn Error + Send + Sync + 'static>>
-where
-    T: std::str::FromStr,
-    T::Err: Error + Send + Sync + 'static,
-{
-    let array: Result<Vec<T>, _> =
-        s.split(',').map(|array| array.parse::<T>()).collect();
+/// Parameters for building the roofline of the device
+#[derive(Args)]
+pub struct RooflineParams {
+    /// Print the roofline as JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
 
-    let array = array?;
+/// What to run, as parsed from the command line arguments
+pub enum Invocation {
+    /// Sweep a microbenchmark over the workgroup and problem sizes given
+    Sweep(Sweep),
+    /// Run one of the other subcommands
+    Command(Command),
+}
+
+/// Parses the command line arguments, with a subcommand for each of the
+/// registered microbenchmarks.
+pub fn parse() -> Invocation {
+    let cli = microbenchmarks().fold(Cli::command(), |cli, microbenchmark| {
+        cli.subcommand(microbenchmark_subcommand(microbenchmark))
+    });
+    let matches = cli.get_matches();
 
-    let array: [T; SIZE] = array.try_into().map_err(|v: Vec<_>| {
-        format!(
-            "invalid array: expected {} elements but found {}",
-            SIZE,
-            v.len()
+    match matches.subcommand() {
+        Some((name, subcommand_matches)) => {
+            match find_microbenchmark(&name.replace('-', "_")) {
+                Some(microbenchmark) => {
+                    Invocation::Sweep(sweep(microbenchmark, subcommand_matches))
+                }
+                None => Invocation::Command(
+                    Cli::from_arg_matches(&matches)
+                        .unwrap_or_else(|err| err.exit())
+                        .command,
+                ),
+            }
+        }
+        None => unreachable!("a subcommand is required"),
+    }
+}
+
+fn microbenchmark_subcommand(
+    microbenchmark: &'static dyn Microbenchmark,
+) -> clap::Command {
+    let dims = microbenchmark.workgroup_dimensions();
+    let defaults = microbenchmark.default_parameters();
+    let element_types = microbenchmark.element_types();
+
+    let command = clap::Command::new(microbenchmark.name().replace('_', "-"))
+        .about(format!("Run the {} microbenchmark", microbenchmark.title()))
+        .long_about(microbenchmark.description())
+        .arg(
+            Arg::new("workgroup")
+                .short('w')
+                .long("workgroup")
+                .action(ArgAction::Append)
+                .value_parser(move |s: &str| parse_workgroup(s, dims))
+                .help(format!(
+                    "Workgroup size to run, as {} comma-separated values. Runs \
+                     a default set of sizes if none are given",
+                    dims
+                )),
         )
-    })?;
+        .arg(
+            Arg::new("size")
+                .short('s')
+                .long("size")
+                .action(ArgAction::Append)
+                .value_parser(clap::value_parser!(usize))
+                .default_values(
+                    microbenchmark.default_sizes().iter().map(usize::to_string),
+                )
+                .help(format!(
+                    "{}. Can be given multiple times to run each size",
+                    microbenchmark.size_description()
+                )),
+        )
+        .arg(
+            Arg::new("warmup")
+                .long("warmup")
+                .value_parser(clap::value_parser!(usize))
+                .default_value(defaults.warmup_count.to_string())
+                .help("Amount of iterations to run before the measured ones"),
+        )
+        .arg(
+            Arg::new("count")
+                .short('c')
+                .long("count")
+                .value_parser(clap::value_parser!(usize))
+                .default_value(defaults.count.to_string())
+                .help("Amount of measured iterations"),
+        );
+
+    if element_types.len() < 2 {
+        return command;
+    }
 
-    Ok(array)
+    command.arg(
+        Arg::new("element-type")
+            .short('e')
+            .long("element-type")
+            .action(ArgAction::Append)
+            .value_parser(
+                PossibleValuesParser::new(
+                    element_types.iter().map(ElementType::name),
+                )
+                .map(|name| name.parse::<ElementType>().expect("known name")),
+            )
+            .default_value(defaults.element_type.name())
+            .help(
+                "Type of the elements the microbenchmark works with. Can be \
+                 given multiple times to run each type",
+            ),
+    )
 }
 
-impl Microbenchmarks {
-    /// Checks if the workgroups parameter is empty
-    pub fn workgroups_empty(&self) -> bool {
-        match self {
-            Microbenchmarks::MatMul(params)
-            | Microbenchmarks::Convolution(params)
-            | Microbenchmarks::BufferToTexture(params)
-            | Microbenchmarks::TextureToTexture(params) => {
-                params.workgroup.is_empty()
+/// Sweep of the microbenchmark over the workgroup sizes (or the default
+/// ones) and problem sizes given
+fn sweep(
+    microbenchmark: &'static dyn Microbenchmark,
+    matches: &ArgMatches,
+) -> Sweep {
+    let get = |id: &str| matches.get_one::<usize>(id).copied();
+
+    Sweep {
+        microbenchmarks: vec![microbenchmark],
+        workgroups: match matches.get_many::<(u32, u32, u32)>("workgroup") {
+            Some(workgroups) => {
+                WorkgroupSweep::List(workgroups.copied().collect())
             }
-            Microbenchmarks::BufferToBuffer(params)
-            | Microbenchmarks::Scan(params)
-            | Microbenchmarks::Reduction(params) => params.workgroup.is_empty(),
+            None => WorkgroupSweep::Default,
+        },
+        sizes: matches
+            .get_many::<usize>("size")
+            .expect("has default")
+            .copied()
+            .collect(),
+        element_types: matches
+            .try_get_many::<ElementType>("element-type")
+            .ok()
+            .flatten()
+            .map(|element_types| element_types.copied().collect())
+            .unwrap_or_default(),
+        warmup_count: get("warmup"),
+        count: get("count"),
+        seed: None,
+    }
+}
+
+/// Parse the name of a microbenchmark, with either `-` or `_` as separators
+fn parse_microbenchmark(
+    s: &str,
+) -> Result<&'static dyn Microbenchmark, Box<dyn Error + Send + Sync + 'static>>
+{
+    find_microbenchmark(&s.replace('-', "_"))
+        .ok_or_else(|| format!("unknown microbenchmark \"{}\"", s).into())
+}
+
+/// Parser for a [SubmissionMode], named like in the specs but with `-` as the
+/// separator
+fn submission_mode_parser() -> impl TypedValueParser<Value = SubmissionMode> {
+    PossibleValuesParser::new(["single", "per-batch", "per-iteration"]).map(
+        |mode| match mode.as_str() {
+            "single" => SubmissionMode::Single,
+            "per-batch" => SubmissionMode::PerBatch,
+            _ => SubmissionMode::PerIteration,
+        },
+    )
+}
+
+/// Parser for a [PassMode], named like in the specs but with `-` as the
+/// separator
+fn pass_mode_parser() -> impl TypedValueParser<Value = PassMode> {
+    PossibleValuesParser::new(["per-batch", "per-iteration"]).map(|mode| {
+        match mode.as_str() {
+            "per-batch" => PassMode::PerBatch,
+            _ => PassMode::PerIteration,
         }
+    })
+}
+
+/// Parse a workgroup size with the given amount of dimensions, the missing
+/// dimensions are set to 1
+fn parse_workgroup(
+    s: &str,
+    dims: usize,
+) -> Result<(u32, u32, u32), Box<dyn Error + Send + Sync + 'static>> {
+    let sizes = s
+        .split(',')
+        .map(|size| size.parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>()?;
+
+    if sizes.len() != dims {
+        return Err(format!(
+            "invalid workgroup size: expected {} elements but found {}",
+            dims,
+            sizes.len()
+        )
+        .into());
     }
+
+    let size = |dim: usize| sizes.get(dim).copied().unwrap_or(1);
+    Ok((size(0), size(1), size(2)))
 }
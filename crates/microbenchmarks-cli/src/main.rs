@@ -1,88 +1,239 @@
-use clap::Parser;
-use cli::{Cli, Microbenchmarks};
+use cli::{Command, Invocation, RooflineParams, SpecParams, TuneParams};
 use microbenchmarks::{
-    convolution::convolution_benchmark,
-    matmul::matmul_benchmark,
-    memcpy::{
-        buffer_to_buffer::buffer_to_buffer_benchmark,
-        buffer_to_texture::buffer_to_texture_benchmark,
-        texture_to_texture::texture_to_texture_benchmark,
-    },
-    reduction_sum::reduction_sum_benchmark,
-    scan::scan_benchmark,
-    BenchmarkError,
+    find_microbenchmark, Roofline, RooflineError, Sweep, TuneError, Tuner,
 };
+use uwgpu::{BenchmarkSpec, GPUContext};
 
 mod cli;
 mod print_error;
 mod print_results;
 
-use print_error::print_error;
-use print_results::PrintableResults;
+use print_error::{print_error, print_spec_error};
+use print_results::print_results;
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
+    match cli::parse() {
+        Invocation::Sweep(sweep) => run_sweep(sweep).await,
+        Invocation::Command(Command::Spec(params)) => run_specs(params).await,
+        Invocation::Command(Command::Tune(params)) => run_tuner(params).await,
+        Invocation::Command(Command::Roofline(params)) => {
+            run_roofline(params).await
+        }
+    }
+}
+
+/// Runs the sweep and prints its results ordered by their parameters
+async fn run_sweep(sweep: Sweep) {
+    let gpu = match GPUContext::new(None).await {
+        Ok(gpu) => gpu,
+        Err(err) => return print_error(err.into()),
+    };
+
+    let results = sweep.run(&gpu).await;
+
+    for (key, outcome) in results.runs {
+        let microbenchmark = sweep
+            .microbenchmarks
+            .iter()
+            .find(|microbenchmark| microbenchmark.name() == key.microbenchmark)
+            .expect("ran microbenchmarks are part of the sweep");
+        let (x, y, z) = key.workgroup_size;
+        let label = match microbenchmark.element_types().len() {
+            1 => format!("{} (size {})", microbenchmark.title(), key.size),
+            _ => format!(
+                "{} ({}, size {})",
+                microbenchmark.title(),
+                key.element_type,
+                key.size
+            ),
+        };
+
+        match outcome {
+            Ok(run) => print_results(
+                &label,
+                &[x, y, z][..microbenchmark.workgroup_dimensions()],
+                &run.results,
+                run.metrics
+                    .iter()
+                    .map(|metric| (metric.label(), metric.display_value())),
+                run.accuracy.as_ref(),
+            ),
+            Err(err) => print_error(err),
+        }
+    }
+
+    for key in results.unsupported {
+        println!(
+            "\nSkipped {} with size {}, element type {} and workgroup size \
+             {:?}: not supported by the device's limits or features",
+            key.microbenchmark, key.size, key.element_type, key.workgroup_size
+        );
+    }
+}
+
+/// Runs the auto-tuner and prints the ranking of the workgroup sizes
+async fn run_tuner(params: TuneParams) {
+    let gpu = match GPUContext::new(None).await {
+        Ok(gpu) => gpu,
+        Err(err) => return print_error(err.into()),
+    };
 
-    if cli.microbenchmark.workgroups_empty() {
-        eprintln!("No workgroup sizes specified so no benchmarks will be run. Specify workgroup sizes with -w flag.");
-        return;
+    let mut tuner = Tuner::new(params.microbenchmark);
+    if let Some(size) = params.size {
+        tuner.size = size;
+    }
+    if let Some(element_type) = params.element_type {
+        tuner.element_type = element_type;
+    }
+    if let Some(count) = params.count {
+        tuner.initial_count = count;
     }
 
-    let result = run_microbenchmark(cli.microbenchmark).await;
+    let results = match tuner.tune(&gpu).await {
+        Ok(results) => results,
+        Err(TuneError::AllFailed(err)) => return print_error(err),
+        Err(err) => return println!("{}", err),
+    };
 
-    if let Err(err) = result {
+    let dims = params.microbenchmark.workgroup_dimensions();
+    println!(
+        "\n{} workgroup sizes (size {}, element type {}), from fastest to \
+         slowest",
+        params.microbenchmark.title(),
+        tuner.size,
+        tuner.element_type
+    );
+    println!("-----------------------------");
+    for tuned in &results.ranking {
+        let (x, y, z) = tuned.workgroup_size;
+        let workgroup_size: Vec<String> =
+            [x, y, z][..dims].iter().map(u32::to_string).collect();
+        println!(
+            "{}: {:.4}ms per iteration (95% CI {:.4}-{:.4}ms, {} runs)",
+            workgroup_size.join("x"),
+            tuned.mean_ns / 1_000_000.0,
+            tuned.lower_ns / 1_000_000.0,
+            tuned.upper_ns / 1_000_000.0,
+            tuned.samples,
+        );
+    }
+    println!("-----------------------------");
+    if !results.is_conclusive() {
+        println!(
+            "The best workgroup size isn't clearly faster than the runner-up, \
+             try tuning again with a higher --count"
+        );
+    }
+    for (workgroup_size, err) in results.failed {
+        println!("\nWorkgroup size {:?} failed to run:", workgroup_size);
         print_error(err);
     }
 }
 
-async fn run_microbenchmark(
-    microbenchmark: Microbenchmarks,
-) -> Result<(), BenchmarkError> {
-    match microbenchmark {
-        Microbenchmarks::MatMul(params) => {
-            for wg in params.workgroup {
-                let result = matmul_benchmark(wg.into()).await?;
-                result.print_results(wg);
-            }
-        }
-        Microbenchmarks::Convolution(params) => {
-            for wg in params.workgroup {
-                let result = convolution_benchmark(wg.into()).await?;
-                result.print_results(wg);
-            }
-        }
-        Microbenchmarks::Scan(params) => {
-            for wg in params.workgroup {
-                let result = scan_benchmark(wg[0]).await?;
-                result.print_results(wg);
-            }
-        }
-        Microbenchmarks::Reduction(params) => {
-            for wg in params.workgroup {
-                let result = reduction_sum_benchmark(wg[0]).await?;
-                result.print_results(wg);
-            }
-        }
-        Microbenchmarks::BufferToBuffer(params) => {
-            for wg in params.workgroup {
-                let result = buffer_to_buffer_benchmark(wg[0]).await?;
-                result.print_results(wg);
-            }
+/// Runs every microbenchmark with its defaults and prints the roofline built
+/// from the results
+async fn run_roofline(params: RooflineParams) {
+    let gpu = match GPUContext::new(None).await {
+        Ok(gpu) => gpu,
+        Err(err) => return print_error(err.into()),
+    };
+
+    let roofline = match Roofline::from_sweep(&Sweep::default().run(&gpu).await)
+    {
+        Ok(roofline) => roofline,
+        Err(RooflineError::Benchmark(err)) => return print_error(err),
+        Err(err) => return println!("{}", err),
+    };
+
+    if params.json {
+        let json = serde_json::to_string_pretty(&roofline)
+            .expect("the roofline is serializable");
+        return println!("{}", json);
+    }
+
+    println!("\nRoofline");
+    println!("-----------------------------");
+    println!("Peak GFLOPS: {:.3}", roofline.peak_flops / 1_000_000_000.0);
+    println!(
+        "Peak bandwidth (GB/s): {:.3}",
+        roofline.peak_bandwidth / 1_000_000_000.0
+    );
+    println!("Ridge point: {:.3} FLOPs/byte", roofline.ridge_point());
+    println!("-----------------------------");
+    for point in &roofline.points {
+        let title = find_microbenchmark(&point.microbenchmark)
+            .expect("placed microbenchmarks are registered")
+            .title();
+        let workgroup_size: Vec<String> =
+            point.workgroup_size.iter().map(u32::to_string).collect();
+        println!(
+            "{} ({}, size {}) [{}]: {:.3} FLOPs/byte, {:.3} of {:.3} GFLOPS \
+             ({:.1}% of roof, {} bound)",
+            title,
+            point.element_type,
+            point.size,
+            workgroup_size.join("x"),
+            point.arithmetic_intensity,
+            point.flops / 1_000_000_000.0,
+            point.attainable_flops / 1_000_000_000.0,
+            point.percent_of_roof,
+            match point.memory_bound {
+                true => "memory",
+                false => "compute",
+            },
+        );
+    }
+    println!("-----------------------------");
+}
+
+/// Runs every spec with each of its workgroup sizes, a failing run doesn't
+/// stop the rest from running.
+async fn run_specs(params: SpecParams) {
+    let specs = if params.path.is_dir() {
+        BenchmarkSpec::load_dir(&params.path)
+    } else {
+        BenchmarkSpec::load(&params.path).map(|spec| vec![spec])
+    };
+    let specs = match specs {
+        Ok(specs) => specs,
+        Err(err) => return print_spec_error(err),
+    };
+
+    let gpu = match GPUContext::new(None).await {
+        Ok(gpu) => gpu,
+        Err(err) => return print_error(err.into()),
+    };
+
+    for mut spec in specs {
+        if let Some(submission_mode) = params.submission_mode {
+            spec.submission_mode = submission_mode;
         }
-        Microbenchmarks::BufferToTexture(params) => {
-            for wg in params.workgroup {
-                let result = buffer_to_texture_benchmark(wg.into()).await?;
-                result.print_results(wg);
-            }
+        if let Some(pass_mode) = params.pass_mode {
+            spec.pass_mode = pass_mode;
         }
-        Microbenchmarks::TextureToTexture(params) => {
-            for wg in params.workgroup {
-                let result = texture_to_texture_benchmark(wg.into()).await?;
-                result.print_results(wg);
+
+        let workgroups = if params.workgroup.is_empty() {
+            spec.workgroup_sizes.clone()
+        } else {
+            params
+                .workgroup
+                .iter()
+                .map(|wg| [wg.0, wg.1, wg.2])
+                .collect()
+        };
+
+        for wg in workgroups {
+            match spec.run(&gpu, wg).await {
+                Ok(result) => print_results(
+                    &result.name,
+                    &wg,
+                    &result.results,
+                    result.metrics.iter().cloned(),
+                    None,
+                ),
+                Err(err) => print_spec_error(err),
             }
         }
     }
-
-    Ok(())
 }
@@ -4,6 +4,7 @@ use microbenchmarks::{
     },
     BenchmarkError,
 };
+use uwgpu::SpecError;
 
 pub fn print_error(error: BenchmarkError) {
     match error {
@@ -46,6 +47,41 @@ pub fn print_error(error: BenchmarkError) {
         BenchmarkError::MapTimestamp(_) => {
             println!("Couldn't read the timestamp query results")
         }
+        BenchmarkError::ShaderAnalysis(analysis_error) => {
+            println!("Couldn't reflect the shader bindings: {}", analysis_error)
+        }
+        BenchmarkError::CreateResources(resources_error) => println!(
+            "Couldn't create the shader resources: {}",
+            resources_error
+        ),
+        BenchmarkError::IncorrectOutput { output, expected } => println!(
+            "The microbenchmark computed {} instead of {}, so it wasn't run",
+            output, expected
+        ),
+        BenchmarkError::InvalidStride { stride, elements } => println!(
+            "The stride {} must be a power of 2 no larger than {}",
+            stride, elements
+        ),
+        BenchmarkError::WorkgroupSizeNotPowerOfTwo(workgroup_size) => println!(
+            "The workgroup size {} must be a power of 2",
+            workgroup_size
+        ),
+    }
+}
+
+pub fn print_spec_error(error: SpecError) {
+    match error {
+        SpecError::ShaderAnalysis(analysis_error) => {
+            print_error(analysis_error.into())
+        }
+        SpecError::CreateResources(resources_error) => {
+            print_error(resources_error.into())
+        }
+        SpecError::CreatePipeline(pipeline_error) => {
+            print_error(pipeline_error.into())
+        }
+        SpecError::MapTimestamp(map_error) => print_error(map_error.into()),
+        spec_error => println!("Invalid spec: {}", spec_error),
     }
 }
 
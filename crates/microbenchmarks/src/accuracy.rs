@@ -0,0 +1,150 @@
+//! Numerical accuracy of the computational microbenchmarks against f64 CPU
+//! references, see [Accuracy]
+
+use uwgpu::{
+    wgpu::{BufferDescriptor, BufferUsages, MapMode},
+    wgpu_async::AsyncBuffer,
+    GPUContext,
+};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::ElementType;
+
+/// Most output elements compared against the reference, larger outputs are
+/// sampled, see [sample_indices].
+pub const MAX_ACCURACY_SAMPLES: usize = 4096;
+
+/// Errors of a microbenchmark's output against a reference computed on the
+/// CPU with f64, see
+/// [Microbenchmark::accuracy](crate::Microbenchmark::accuracy).
+///
+/// Different GPUs and browsers can produce different results for the same
+/// computation, such as by fusing multiply-adds or summing in a different
+/// order.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Accuracy {
+    /// Largest absolute difference from the reference
+    pub max_abs_error: f64,
+    /// Largest difference from the reference relative to its magnitude,
+    /// ignoring the references of 0
+    pub max_rel_error: f64,
+    /// Largest difference from the reference in units in the last place of
+    /// the element type at the reference value, see [ElementType::ulp]
+    pub max_ulp_error: f64,
+    /// Amount of output elements compared
+    pub samples: usize,
+}
+
+impl Accuracy {
+    /// Accuracy of the `(output, reference)` pairs of values of the given
+    /// element type, a NaN output counts as an infinite error.
+    pub fn measure(
+        element_type: ElementType,
+        values: impl IntoIterator<Item = (f64, f64)>,
+    ) -> Self {
+        values.into_iter().fold(
+            Accuracy::default(),
+            |accuracy, (output, reference)| {
+                let abs_error = match (output - reference).abs() {
+                    error if error.is_nan() => f64::INFINITY,
+                    error => error,
+                };
+                let rel_error = match reference {
+                    0.0 => 0.0,
+                    _ => abs_error / reference.abs(),
+                };
+                let ulp_error = abs_error / element_type.ulp(reference);
+
+                Accuracy {
+                    max_abs_error: accuracy.max_abs_error.max(abs_error),
+                    max_rel_error: accuracy.max_rel_error.max(rel_error),
+                    max_ulp_error: accuracy.max_ulp_error.max(ulp_error),
+                    samples: accuracy.samples + 1,
+                }
+            },
+        )
+    }
+
+    /// Whether the output matched the reference exactly
+    pub fn is_exact(&self) -> bool { self.max_abs_error == 0.0 }
+}
+
+/// Indices of the output elements of an output of `len` elements that get
+/// compared, all of them if there are at most [MAX_ACCURACY_SAMPLES] or
+/// evenly spaced ones including the first and last otherwise.
+pub(crate) fn sample_indices(len: usize) -> Vec<usize> {
+    if len <= MAX_ACCURACY_SAMPLES {
+        return (0..len).collect();
+    }
+
+    (0..MAX_ACCURACY_SAMPLES)
+        .map(|i| i * (len - 1) / (MAX_ACCURACY_SAMPLES - 1))
+        .collect()
+}
+
+/// Buffer that `size` bytes of a microbenchmark's output can be copied to in
+/// order to read them back with [read_staging_buffer].
+pub(crate) fn staging_buffer(gpu: &GPUContext, size: u64) -> AsyncBuffer {
+    gpu.create_buffer(&BufferDescriptor {
+        label: Some("Staging Buffer"),
+        size,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Reads the contents of a buffer created with [staging_buffer].
+pub(crate) async fn read_staging_buffer(buffer: &AsyncBuffer) -> Vec<u8> {
+    let slice = buffer.slice(..);
+    slice
+        .map_async(MapMode::Read)
+        .await
+        .expect("the staging buffer is mappable");
+    let contents = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_measured() {
+        let accuracy = Accuracy::measure(
+            ElementType::F32,
+            [(1.0, 1.0), (2.0 + 2f64.powi(-22), 2.0), (0.0, 0.0)],
+        );
+
+        assert_eq!(accuracy.samples, 3);
+        assert_eq!(accuracy.max_abs_error, 2f64.powi(-22));
+        assert_eq!(accuracy.max_rel_error, 2f64.powi(-23));
+        assert_eq!(accuracy.max_ulp_error, 1.0);
+        assert!(!accuracy.is_exact());
+
+        let accuracy =
+            Accuracy::measure(ElementType::I32, [(7.0, 7.0), (3.0, 5.0)]);
+        assert_eq!(accuracy.max_ulp_error, 2.0);
+        assert!(Accuracy::measure(ElementType::U32, [(7.0, 7.0)]).is_exact());
+
+        let accuracy = Accuracy::measure(ElementType::F16, [(f64::NAN, 1.0)]);
+        assert_eq!(accuracy.max_abs_error, f64::INFINITY);
+    }
+
+    #[test]
+    fn samples_cover_the_output() {
+        assert_eq!(sample_indices(3), [0, 1, 2]);
+
+        let len = 1_000_000;
+        let indices = sample_indices(len);
+        assert_eq!(indices.len(), MAX_ACCURACY_SAMPLES);
+        assert_eq!(indices.first(), Some(&0));
+        assert_eq!(indices.last(), Some(&(len - 1)));
+        assert!(indices.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
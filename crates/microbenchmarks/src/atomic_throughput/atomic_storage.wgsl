@@ -0,0 +1,19 @@
+// Atomics in storage memory, invocation i operating on address i % ADDRESSES
+@group(0) @binding(0) var<storage, read_write> counters: array<atomic<u32>>;
+
+const INVOCATIONS: u32 = $invocations$u;
+const OPERATIONS: u32 = $operations$u;
+const ADDRESSES: u32 = $addresses$u;
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let invocation = global_id.x;
+    if invocation >= INVOCATIONS {
+        return;
+    }
+
+    let counter = &counters[invocation % ADDRESSES];
+    for (var i = 0u; i < OPERATIONS; i++) {
+        $operation$
+    }
+}
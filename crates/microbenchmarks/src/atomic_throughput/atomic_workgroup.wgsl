@@ -0,0 +1,31 @@
+// Atomics in workgroup memory, local invocation i of every workgroup
+// operating on address i % ADDRESSES
+@group(0) @binding(0) var<storage, read_write> counters: array<u32>;
+
+const INVOCATIONS: u32 = $invocations$u;
+const OPERATIONS: u32 = $operations$u;
+const ADDRESSES: u32 = $addresses$u;
+
+var<workgroup> workgroup_counters: array<atomic<u32>, $addresses$>;
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>) {
+    let invocation = global_id.x;
+
+    if invocation < INVOCATIONS {
+        let counter = &workgroup_counters[local_index % ADDRESSES];
+        for (var i = 0u; i < OPERATIONS; i++) {
+            $operation$
+        }
+    }
+
+    // The counters of every workgroup are the result so none of the atomics
+    // is dead code
+    workgroupBarrier();
+    for (var address = local_index; address < ADDRESSES; address += $workgroup_x$u) {
+        counters[workgroup_id.x * ADDRESSES + address] =
+            atomicLoad(&workgroup_counters[address]);
+    }
+}
@@ -0,0 +1,485 @@
+//! Atomic operation throughput microbenchmarks, from every invocation
+//! contending for the same address to each invocation having its own
+
+use std::collections::HashMap;
+
+use uwgpu::{
+    wgpu::{
+        BufferDescriptor, BufferUsages, Limits, ShaderModuleDescriptor,
+        ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
+    GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// Every invocation on the same address
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 1,
+    warmup_count: 10,
+    count: 100,
+    element_type: ElementType::F32,
+};
+
+/// Invocations running the atomics
+const INVOCATIONS: u32 = 65_536;
+
+/// Atomic operations of each invocation
+const OPERATIONS: u32 = 64;
+
+/// The atomic operation measured, each of them is a separate
+/// [AtomicThroughput] microbenchmark for each [AtomicMemory].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtomicOperation {
+    /// `atomicAdd` of 1
+    Add,
+    /// `atomicMax` of a value that grows with every operation
+    Max,
+    /// An increment with an `atomicCompareExchangeWeak` loop, which retries
+    /// until the exchange succeeds. Only the successful exchanges are counted
+    /// as operations.
+    CompareExchange,
+}
+
+impl AtomicOperation {
+    /// Statement of the shaders doing the operation on `counter`, in the
+    /// loop over `i`.
+    fn statement(&self) -> &'static str {
+        match self {
+            AtomicOperation::Add => "atomicAdd(counter, 1u);",
+            AtomicOperation::Max => {
+                "atomicMax(counter, invocation * OPERATIONS + i);"
+            }
+            AtomicOperation::CompareExchange => {
+                "var old = atomicLoad(counter);
+            loop {
+                let result = atomicCompareExchangeWeak(counter, old, old + 1u);
+                if result.exchanged {
+                    break;
+                }
+                old = result.old_value;
+            }"
+            }
+        }
+    }
+}
+
+/// The memory the atomics are in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtomicMemory {
+    /// A storage buffer shared by every workgroup
+    Storage,
+    /// Workgroup memory, so only the invocations of the same workgroup
+    /// contend with each other
+    Workgroup,
+}
+
+/// Microbenchmark for measuring the throughput of an atomic operation, the
+/// [MicrobenchmarkParameters::size] is the amount of addresses the
+/// invocations are spread over.
+///
+/// Invocation `i` operates on address `i % size`, for workgroup memory `i`
+/// being the index of the invocation within its workgroup. So a size of 1 has
+/// every invocation contending for the same address, and a size of at least
+/// the amount of invocations (or of the workgroup size for workgroup memory)
+/// has each invocation on its own address.
+///
+/// The workgroup size will dictate the size of workgroups used for the
+/// computation.
+pub async fn atomic_throughput_benchmark(
+    gpu: &GPUContext,
+    operation: AtomicOperation,
+    memory: AtomicMemory,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<AtomicThroughputResults, BenchmarkError> {
+    let addresses = parameters.size as u32;
+    let buffers = Buffers::new(memory, addresses, workgroup_size, gpu);
+    let pipeline = atomic_throughput_pipeline(
+        gpu,
+        &buffers,
+        operation,
+        memory,
+        addresses,
+        workgroup_size,
+    )
+    .await?;
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: workgroups_dispatch(workgroup_size),
+        dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(AtomicThroughputResults {
+        results,
+        parameters,
+        operation,
+        memory,
+    })
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the atomic throughput microbenchmarks. See
+/// [atomic_throughput_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_eval]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct AtomicThroughputResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// Operation the benchmark ran with
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub operation: AtomicOperation,
+    /// Memory the benchmark ran with
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub memory: AtomicMemory,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl AtomicThroughputResults {
+    /// Get the amount of atomic operations per second
+    pub fn operations(&self) -> f64 {
+        (operations_per_iteration() as f64 * self.results.count as f64)
+            / (self.results.total_time(TimeUnit::Second))
+    }
+}
+
+/// Atomic throughput microbenchmark of an operation on a memory, see
+/// [atomic_throughput_benchmark].
+pub struct AtomicThroughput(pub AtomicOperation, pub AtomicMemory);
+
+impl Microbenchmark for AtomicThroughput {
+    fn name(&self) -> &'static str {
+        match (self.0, self.1) {
+            (AtomicOperation::Add, AtomicMemory::Storage) => "atomic_add",
+            (AtomicOperation::Add, AtomicMemory::Workgroup) => {
+                "atomic_add_workgroup"
+            }
+            (AtomicOperation::Max, AtomicMemory::Storage) => "atomic_max",
+            (AtomicOperation::Max, AtomicMemory::Workgroup) => {
+                "atomic_max_workgroup"
+            }
+            (AtomicOperation::CompareExchange, AtomicMemory::Storage) => {
+                "atomic_compare_exchange"
+            }
+            (AtomicOperation::CompareExchange, AtomicMemory::Workgroup) => {
+                "atomic_compare_exchange_workgroup"
+            }
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match (self.0, self.1) {
+            (AtomicOperation::Add, AtomicMemory::Storage) => {
+                "Atomic Add Throughput"
+            }
+            (AtomicOperation::Add, AtomicMemory::Workgroup) => {
+                "Workgroup Atomic Add Throughput"
+            }
+            (AtomicOperation::Max, AtomicMemory::Storage) => {
+                "Atomic Max Throughput"
+            }
+            (AtomicOperation::Max, AtomicMemory::Workgroup) => {
+                "Workgroup Atomic Max Throughput"
+            }
+            (AtomicOperation::CompareExchange, AtomicMemory::Storage) => {
+                "Atomic Compare-Exchange Throughput"
+            }
+            (AtomicOperation::CompareExchange, AtomicMemory::Workgroup) => {
+                "Workgroup Atomic Compare-Exchange Throughput"
+            }
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.1 {
+            AtomicMemory::Storage => "This microbenchmark tests the throughput of atomic operations on a storage buffer, with the invocations spread over an amount of addresses that goes from every invocation contending for the same one to each invocation having its own.",
+            AtomicMemory::Workgroup => "This microbenchmark tests the throughput of atomic operations on workgroup memory, with the invocations of each workgroup spread over an amount of addresses that goes from every invocation contending for the same one to each invocation having its own.",
+        }
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str {
+        "Amount of addresses the invocations are spread over, 1 for every \
+         invocation on the same address"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn default_sizes(&self) -> Vec<usize> {
+        // Powers of 4 up to an address per invocation
+        let max_addresses = match self.1 {
+            AtomicMemory::Storage => INVOCATIONS as usize,
+            AtomicMemory::Workgroup => 256,
+        };
+        std::iter::successors(Some(1), |addresses| Some(addresses * 4))
+            .take_while(|addresses| *addresses <= max_addresses)
+            .collect()
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let addresses = parameters.size as u32;
+        let fits_workgroup_memory = match self.1 {
+            AtomicMemory::Storage => true,
+            AtomicMemory::Workgroup => {
+                addresses as u64 * size_of::<u32>() as u64
+                    <= limits.max_compute_workgroup_storage_size as u64
+            }
+        };
+
+        addresses > 0
+            && fits_workgroup_memory
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroups_dispatch(workgroup_size.0),
+                counters_size(self.1, addresses, workgroup_size.0),
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(atomic_throughput_benchmark(
+                gpu,
+                self.0,
+                self.1,
+                workgroup_size.0,
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = AtomicThroughputResults {
+            results: results.clone(),
+            parameters: *parameters,
+            operation: self.0,
+            memory: self.1,
+        };
+        vec![Metric::operations(results.operations())]
+    }
+}
+
+/// Amount of atomic operations done by each iteration of the microbenchmark.
+fn operations_per_iteration() -> usize {
+    INVOCATIONS as usize * OPERATIONS as usize
+}
+
+/// Size in bytes of the counters buffer: the counters themselves for storage
+/// memory, or where each workgroup writes its counters for workgroup memory.
+fn counters_size(
+    memory: AtomicMemory,
+    addresses: u32,
+    workgroup_size: u32,
+) -> u64 {
+    let counters = match memory {
+        AtomicMemory::Storage => addresses as u64,
+        AtomicMemory::Workgroup => {
+            INVOCATIONS.div_ceil(workgroup_size) as u64 * addresses as u64
+        }
+    };
+    counters * size_of::<u32>() as u64
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    counters_buffer: AsyncBuffer,
+}
+
+impl Buffers {
+    fn new(
+        memory: AtomicMemory,
+        addresses: u32,
+        workgroup_size: u32,
+        gpu: &GPUContext,
+    ) -> Self {
+        let counters_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Counters Buffer"),
+            size: counters_size(memory, addresses, workgroup_size),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        Self { counters_buffer }
+    }
+}
+
+/// Pipeline needed for microbenchmark
+async fn atomic_throughput_pipeline<'a>(
+    gpu: &'a GPUContext,
+    buffers: &'a Buffers,
+    operation: AtomicOperation,
+    memory: AtomicMemory,
+    addresses: u32,
+    workgroup_size: u32,
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    let shader = match memory {
+        AtomicMemory::Storage => include_str!("atomic_storage.wgsl"),
+        AtomicMemory::Workgroup => include_str!("atomic_workgroup.wgsl"),
+    }
+    .replace("$operation$", operation.statement())
+    .replace("$invocations$", &INVOCATIONS.to_string())
+    .replace("$operations$", &OPERATIONS.to_string())
+    .replace("$addresses$", &addresses.to_string());
+
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("atomic throughput shader"),
+            source: ShaderSource::Wgsl(shader.into()),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([(
+            0,
+            buffers.counters_buffer.as_entire_binding(),
+        )]),
+        gpu,
+        workgroup_size: Some((workgroup_size, 1, 1)),
+    })
+    .await
+}
+
+fn workgroups_dispatch(workgroup_size: u32) -> Vec<(u32, u32, u32)> {
+    vec![(INVOCATIONS.div_ceil(workgroup_size), 1, 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::{
+        wgpu::{BufferDescriptor, BufferUsages, MapMode},
+        Benchmark, GPUContext,
+    };
+
+    use super::*;
+
+    /// Verifies that no operation is lost, the increments adding up to every
+    /// operation and the maximum being the largest value given.
+    #[tokio::test]
+    async fn atomic_throughput_works() {
+        let gpu = GPUContext::new(None).await.unwrap();
+        let workgroup_size = 64;
+
+        for operation in [
+            AtomicOperation::Add,
+            AtomicOperation::Max,
+            AtomicOperation::CompareExchange,
+        ] {
+            for memory in [AtomicMemory::Storage, AtomicMemory::Workgroup] {
+                for addresses in [1, 16] {
+                    let buffers =
+                        Buffers::new(memory, addresses, workgroup_size, &gpu);
+                    let pipeline = atomic_throughput_pipeline(
+                        &gpu,
+                        &buffers,
+                        operation,
+                        memory,
+                        addresses,
+                        workgroup_size,
+                    )
+                    .await
+                    .unwrap();
+
+                    let size = counters_size(memory, addresses, workgroup_size);
+                    let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+                        label: Some("Staging Buffer"),
+                        size,
+                        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    });
+
+                    Benchmark {
+                        warmup_count: 0,
+                        count: 1,
+                        finalize_encoder_callback: Some(&|encoder| {
+                            encoder.copy_buffer_to_buffer(
+                                &buffers.counters_buffer,
+                                0,
+                                &staging_buffer,
+                                0,
+                                size,
+                            )
+                        }),
+                        workgroups_dispatch: workgroups_dispatch(
+                            workgroup_size,
+                        ),
+                        dispatch_callback: None,
+                        ..Default::default()
+                    }
+                    .run(pipeline)
+                    .await
+                    .unwrap();
+
+                    let staging_slice = staging_buffer.slice(..);
+                    staging_slice.map_async(MapMode::Read).await.unwrap();
+
+                    let counters: Vec<u32> = {
+                        let counters_raw = staging_slice.get_mapped_range();
+                        bytemuck::cast_slice(&counters_raw).to_vec()
+                    };
+
+                    staging_buffer.unmap();
+
+                    let label = format!("{operation:?} {memory:?} {addresses}");
+                    match operation {
+                        AtomicOperation::Add
+                        | AtomicOperation::CompareExchange => assert_eq!(
+                            counters.iter().map(|&c| c as usize).sum::<usize>(),
+                            operations_per_iteration(),
+                            "{label}"
+                        ),
+                        AtomicOperation::Max => assert_eq!(
+                            counters.iter().max(),
+                            Some(&(INVOCATIONS * OPERATIONS - 1)),
+                            "{label}"
+                        ),
+                    }
+                }
+            }
+        }
+    }
+}
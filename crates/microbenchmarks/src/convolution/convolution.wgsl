@@ -1,6 +1,6 @@
-@group(0) @binding(0) var<storage, read> inputMatrix: array<f32>;
-@group(0) @binding(1) var<storage, read> kernel: array<f32>;
-@group(0) @binding(2) var<storage, read_write> result: array<f32>;
+@group(0) @binding(0) var<storage, read> inputMatrix: array<$element$>;
+@group(0) @binding(1) var<storage, read> kernel: array<$element$>;
+@group(0) @binding(2) var<storage, read_write> result: array<$element$>;
 @group(0) @binding(3) var<uniform> matrixSize: u32;
 @group(0) @binding(4) var<uniform> kernelSize: u32;
 
@@ -13,7 +13,7 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         return;
     }
 
-    var sum: f32 = 0.0;
+    var sum: $element$ = $element$(0);
     for (var ky = 0u; ky < kernelSize; ky++) {
         for (var kx = 0u; kx < kernelSize; kx++) {
 						// kernel should be applied mirrored
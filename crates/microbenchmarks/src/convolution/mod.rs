@@ -2,11 +2,10 @@
 
 use std::collections::HashMap;
 
-use rand::{thread_rng, Rng};
 use uwgpu::TimeUnit;
 use uwgpu::{
     wgpu::{
-        util::BufferInitDescriptor, BufferDescriptor, BufferUsages,
+        util::BufferInitDescriptor, BufferDescriptor, BufferUsages, Limits,
         ShaderModuleDescriptor, ShaderSource,
     },
     wgpu_async::AsyncBuffer,
@@ -14,39 +13,143 @@ use uwgpu::{
     GPUContext, PipelineParameters,
 };
 
-use crate::BenchmarkError;
+use crate::{
+    accuracy::{read_staging_buffer, sample_indices, staging_buffer},
+    microbenchmark::{
+        default_workgroups_2d, fits_limits, AccuracyFuture, Metric,
+        Microbenchmark, MicrobenchmarkParameters, RunFuture,
+    },
+    Accuracy, BenchmarkError, ElementType,
+};
 
-const BENCHMARK_MATRIX_DIMS: usize = 1024;
+/// 1024x1024 input matrix
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 1024,
+    warmup_count: 300,
+    count: 3000,
+    element_type: ElementType::F32,
+};
 const KERNEL_MATRIX_DIMS: usize = 3;
-const BENCHMARK_WARMUP_COUNT: usize = 300;
-const BENCHMARK_ITERATIONS: usize = 3000;
 
 /// Microbenchmark for convolution.
 ///
-/// Applies a random 3x3 kernel to a 1024x1024 matrix of random data.
+/// Applies a random 3x3 kernel to a square matrix of random data, the
+/// [MicrobenchmarkParameters::size] is its amount of rows and columns and the
+/// [MicrobenchmarkParameters::element_type] the type of their elements.
 pub async fn convolution_benchmark(
+    gpu: &GPUContext,
     workgroup_size: (u32, u32),
+    parameters: MicrobenchmarkParameters,
 ) -> Result<ConvolutionResults, BenchmarkError> {
-    let gpu = GPUContext::new(None).await?;
-    let buffers =
-        Buffers::<BENCHMARK_MATRIX_DIMS, KERNEL_MATRIX_DIMS>::new_with_random_inputs(&gpu);
-    let pipeline =
-        convolution_pipeline(&gpu, &buffers, &workgroup_size).await?;
+    let buffers = Buffers::new_with_random_inputs(
+        parameters.size,
+        KERNEL_MATRIX_DIMS,
+        parameters.element_type,
+        gpu,
+    );
+    let pipeline = convolution_pipeline(gpu, &buffers, &workgroup_size).await?;
 
     let results = Benchmark {
-        warmup_count: BENCHMARK_WARMUP_COUNT,
-        count: BENCHMARK_ITERATIONS,
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
         finalize_encoder_callback: None,
         workgroups_dispatch: workgroups_dispatch(
-            BENCHMARK_MATRIX_DIMS,
+            parameters.size,
             workgroup_size,
         ),
         dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(ConvolutionResults {
+        results,
+        parameters,
+    })
+}
+
+/// Accuracy of a single convolution of random data with the parameters of
+/// [convolution_benchmark], against a reference computed on the CPU.
+pub async fn convolution_accuracy(
+    gpu: &GPUContext,
+    workgroup_size: (u32, u32),
+    parameters: MicrobenchmarkParameters,
+) -> Result<Accuracy, BenchmarkError> {
+    let element_type = parameters.element_type;
+    let matrix_dims = parameters.size;
+    let input_matrix = element_type.random(matrix_dims * matrix_dims);
+    let kernel = element_type.random(KERNEL_MATRIX_DIMS * KERNEL_MATRIX_DIMS);
+    let buffers =
+        Buffers::new_from_inputs(&input_matrix, &kernel, element_type, gpu);
+    let pipeline = convolution_pipeline(gpu, &buffers, &workgroup_size).await?;
+
+    let result_size = element_type.buffer_size(input_matrix.len());
+    let staging_buffer = staging_buffer(gpu, result_size);
+    Benchmark {
+        warmup_count: 0,
+        count: 1,
+        workgroups_dispatch: workgroups_dispatch(matrix_dims, workgroup_size),
+        finalize_encoder_callback: Some(&|encoder| {
+            encoder.copy_buffer_to_buffer(
+                &buffers.result_buffer,
+                0,
+                &staging_buffer,
+                0,
+                result_size,
+            )
+        }),
+        ..Default::default()
     }
     .run(pipeline)
     .await?;
+    let result =
+        element_type.decode(&read_staging_buffer(&staging_buffer).await);
+
+    Ok(Accuracy::measure(
+        element_type,
+        sample_indices(input_matrix.len()).into_iter().map(|i| {
+            let (y, x) = (i / matrix_dims, i % matrix_dims);
+            let reference = convolution_reference(
+                &input_matrix,
+                &kernel,
+                matrix_dims,
+                x,
+                y,
+            );
+
+            (result[i], reference)
+        }),
+    ))
+}
 
-    Ok(ConvolutionResults(results))
+/// The element `(x, y)` of the convolution computed by the shader, with the
+/// kernel mirrored and the elements outside of the matrix counting as 0.
+fn convolution_reference(
+    input_matrix: &[f64],
+    kernel: &[f64],
+    matrix_dims: usize,
+    x: usize,
+    y: usize,
+) -> f64 {
+    let kernel_dims = kernel.len().isqrt();
+    let in_matrix = |i: isize| (0..matrix_dims as isize).contains(&i);
+
+    (0..kernel_dims)
+        .flat_map(|ky| (0..kernel_dims).map(move |kx| (kx, ky)))
+        .map(|(kx, ky)| {
+            let i_x = (x + kernel_dims / 2) as isize - kx as isize;
+            let i_y = (y + kernel_dims / 2) as isize - ky as isize;
+
+            match in_matrix(i_x) && in_matrix(i_y) {
+                true => {
+                    input_matrix[i_y as usize * matrix_dims + i_x as usize]
+                        * kernel[ky * kernel_dims + kx]
+                }
+                false => 0.0,
+            }
+        })
+        .sum()
 }
 
 #[cfg(feature = "wasm")]
@@ -56,92 +159,194 @@ use wasm_bindgen::prelude::*;
 /// [convolution_benchmark].
 ///
 /// Wraps a [BenchmarkResults] with some convenience methods.
-#[cfg_attr(
-    feature = "serde",
-    derive(serde::Serialize, serde::Deserialize),
-    serde(transparent)
-)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_eval]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-pub struct ConvolutionResults(
+pub struct ConvolutionResults {
+    /// Timings of the benchmark
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-    pub  BenchmarkResults,
-);
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl ConvolutionResults {
     /// Get the amount of FLOPS (floating point operations per second)
     pub fn flops(&self) -> f64 {
-        // 4 corners * ( 4 muls + 4 sums)
-        const FLOPS_CORNERS: usize = 4 * (4 + 4);
-        // 4 * elements in borders without corners * ( 6 muls + 6 sums)
-        const FLOPS_INNER_BORDERS: usize =
-            4 * (BENCHMARK_MATRIX_DIMS - 2) * (6 + 6);
-        // elements in inner matrix (no borders) * ( 9 muls + 9 sums)
-        const FLOPS_INNER_MATRIX: usize =
-            (BENCHMARK_MATRIX_DIMS - 1) * (BENCHMARK_MATRIX_DIMS - 1) * (9 + 9);
-
-        const NUM_FLOPS_PER_ITER: usize =
-            FLOPS_CORNERS + FLOPS_INNER_BORDERS + FLOPS_INNER_MATRIX;
-
-        (NUM_FLOPS_PER_ITER as f64 * self.0.count as f64)
-            / (self.0.total_time(TimeUnit::Second))
+        (flops_per_iteration(self.parameters.size) as f64
+            * self.results.count as f64)
+            / (self.results.total_time(TimeUnit::Second))
     }
 }
 
+/// Convolution microbenchmark, see [convolution_benchmark].
+pub struct Convolution;
+
+impl Microbenchmark for Convolution {
+    fn name(&self) -> &'static str { "convolution" }
+
+    fn title(&self) -> &'static str { "Convolution" }
+
+    fn description(&self) -> &'static str {
+        "This microbenchmark tests the performance of a naive convolution between a 1024x1024 matrix and a 3x3 kernel."
+    }
+
+    fn element_types(&self) -> &'static [ElementType] { ElementType::ALL }
+
+    fn workgroup_dimensions(&self) -> usize { 2 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_2d()
+    }
+
+    fn size_description(&self) -> &'static str {
+        "Dimensions of the square input matrix"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        fits_limits(
+            limits,
+            workgroup_size,
+            || workgroups_dispatch(size, (workgroup_size.0, workgroup_size.1)),
+            parameters.element_type.buffer_size(size * size),
+        )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(convolution_benchmark(
+                gpu,
+                (workgroup_size.0, workgroup_size.1),
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn accuracy<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> AccuracyFuture<'a> {
+        Box::pin(async move {
+            Ok(Some(
+                convolution_accuracy(
+                    gpu,
+                    (workgroup_size.0, workgroup_size.1),
+                    parameters,
+                )
+                .await?,
+            ))
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = ConvolutionResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::flops(results.flops())]
+    }
+
+    fn arithmetic_intensity(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+    ) -> Option<f64> {
+        // reads the input matrix and kernel and writes the result
+        let n = parameters.size;
+        let bytes = (2 * n * n + 9) * parameters.element_type.size();
+        Some(flops_per_iteration(n) as f64 / bytes as f64)
+    }
+}
+
+/// Amount of floating point operations done by each iteration of the
+/// microbenchmark with a `matrix_dims` x `matrix_dims` input matrix.
+fn flops_per_iteration(matrix_dims: usize) -> usize {
+    // 4 corners * ( 4 muls + 4 sums)
+    let flops_corners = 4 * (4 + 4);
+    // 4 * elements in borders without corners * ( 6 muls + 6 sums)
+    let flops_inner_borders = 4 * (matrix_dims - 2) * (6 + 6);
+    // elements in inner matrix (no borders) * ( 9 muls + 9 sums)
+    let flops_inner_matrix = (matrix_dims - 1) * (matrix_dims - 1) * (9 + 9);
+
+    flops_corners + flops_inner_borders + flops_inner_matrix
+}
+
 /// GPU buffers needed for microbenchmark
 ///
-/// Matrices are assumed to be square matrices with MATRIX_DIMS x MATRIX_DIMS
-/// dimensions.
-struct Buffers<const MATRIX_DIMS: usize, const KERNEL_DIMS: usize> {
+/// Matrices are assumed to be square matrices of `element_type`.
+struct Buffers {
     input_matrix_buffer: AsyncBuffer,
     kernel_buffer: AsyncBuffer,
     result_buffer: AsyncBuffer,
     matrix_size_buffer: AsyncBuffer,
     kernel_size_buffer: AsyncBuffer,
+    element_type: ElementType,
 }
 
-impl<const MATRIX_DIMS: usize, const KERNEL_DIMS: usize>
-    Buffers<MATRIX_DIMS, KERNEL_DIMS>
-{
-    const MATRIX_SIZE: usize = MATRIX_DIMS * MATRIX_DIMS;
-    const KERNEL_SIZE: usize = KERNEL_DIMS * KERNEL_DIMS;
-
-    fn new_with_random_inputs(gpu: &GPUContext) -> Self {
-        let mut input_matrix = vec![0_f32; Self::MATRIX_SIZE];
-        let mut kernel_data = vec![0_f32; Self::KERNEL_SIZE];
-
-        let mut rng = thread_rng();
-
-        rng.fill(input_matrix.as_mut_slice());
-        rng.fill(kernel_data.as_mut_slice());
+impl Buffers {
+    fn new_with_random_inputs(
+        matrix_dims: usize,
+        kernel_dims: usize,
+        element_type: ElementType,
+        gpu: &GPUContext,
+    ) -> Self {
+        let input_matrix = element_type.random(matrix_dims * matrix_dims);
+        let kernel_data = element_type.random(kernel_dims * kernel_dims);
 
-        Self::new_from_inputs(&input_matrix, &kernel_data, &gpu)
+        Self::new_from_inputs(&input_matrix, &kernel_data, element_type, gpu)
     }
 
+    /// The matrices' dimensions are taken from the square root of the
+    /// amount of elements of each one.
     fn new_from_inputs(
-        input_matrix_data: &[f32],
-        kernel_data: &[f32],
+        input_matrix_data: &[f64],
+        kernel_data: &[f64],
+        element_type: ElementType,
         gpu: &GPUContext,
     ) -> Self {
-        assert_eq!(input_matrix_data.len(), Self::MATRIX_SIZE);
-        assert_eq!(kernel_data.len(), Self::KERNEL_SIZE);
+        let matrix_dims = input_matrix_data.len().isqrt();
+        let kernel_dims = kernel_data.len().isqrt();
+        assert_eq!(input_matrix_data.len(), matrix_dims * matrix_dims);
+        assert_eq!(kernel_data.len(), kernel_dims * kernel_dims);
 
         let matrix_a_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
             label: Some("Matrix A Buffer"),
-            contents: bytemuck::cast_slice(input_matrix_data),
+            contents: &element_type.encode(input_matrix_data.iter().copied()),
             usage: BufferUsages::STORAGE,
         });
 
         let kernel_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
             label: Some("Kernel Buffer"),
-            contents: bytemuck::cast_slice(kernel_data),
+            contents: &element_type.encode(kernel_data.iter().copied()),
             usage: BufferUsages::STORAGE,
         });
 
         let result_buffer = gpu.create_buffer(&BufferDescriptor {
             label: Some("Result Buffer"),
-            size: (Self::MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
+            size: element_type.buffer_size(input_matrix_data.len()),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
             mapped_at_creation: false,
         });
@@ -149,14 +354,14 @@ impl<const MATRIX_DIMS: usize, const KERNEL_DIMS: usize>
         let matrix_size_buffer =
             gpu.create_buffer_init(&BufferInitDescriptor {
                 label: Some("Matrix Size Buffer"),
-                contents: bytemuck::cast_slice(&[MATRIX_DIMS]),
+                contents: bytemuck::cast_slice(&[matrix_dims]),
                 usage: BufferUsages::UNIFORM,
             });
 
         let kernel_size_buffer =
             gpu.create_buffer_init(&BufferInitDescriptor {
                 label: Some("Kernel Size Buffer"),
-                contents: bytemuck::cast_slice(&[KERNEL_DIMS]),
+                contents: bytemuck::cast_slice(&[kernel_dims]),
                 usage: BufferUsages::UNIFORM,
             });
 
@@ -166,27 +371,28 @@ impl<const MATRIX_DIMS: usize, const KERNEL_DIMS: usize>
             result_buffer,
             matrix_size_buffer,
             kernel_size_buffer,
+            element_type,
         }
     }
 }
 
 /// Pipeline needed for microbenchmark
 ///
-/// Matrices are assumed to be square matrices with MATRIX_DIMS x MATRIX_DIMS
-/// dimensions.
-async fn convolution_pipeline<
-    'a,
-    const MATRIX_DIMS: usize,
-    const KERNEL_DIMS: usize,
->(
+/// Matrices are assumed to be square matrices.
+async fn convolution_pipeline<'a>(
     gpu: &'a GPUContext,
-    buffers: &'a Buffers<MATRIX_DIMS, KERNEL_DIMS>,
+    buffers: &'a Buffers,
     workgroup_size: &(u32, u32),
 ) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
     BenchmarkComputePipeline::new(PipelineParameters {
         shader: ShaderModuleDescriptor {
             label: Some("convolution shader"),
-            source: ShaderSource::Wgsl(include_str!("convolution.wgsl").into()),
+            source: ShaderSource::Wgsl(
+                buffers
+                    .element_type
+                    .shader(include_str!("convolution.wgsl"))
+                    .into(),
+            ),
         },
         entry_point: "main",
         bind_group_0: HashMap::from([
@@ -218,12 +424,68 @@ mod tests {
     use std::iter;
 
     use uwgpu::{
+        estimate_shader_cost,
         wgpu::{BufferDescriptor, BufferUsages, MapMode},
-        Benchmark, GPUContext,
+        Benchmark, GPUContext, ShaderCostParameters,
     };
 
     use super::*;
 
+    /// Cross-checks [flops_per_iteration] against the estimate from analyzing
+    /// the shader.
+    #[test]
+    fn flops_match_shader_estimate() {
+        let matrix_dims = DEFAULT_PARAMETERS.size;
+        let cost = estimate_shader_cost(&ShaderCostParameters {
+            shader: ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(
+                    ElementType::F32
+                        .shader(include_str!("convolution.wgsl"))
+                        .into(),
+                ),
+            },
+            entry_point: "main",
+            workgroup_size: Some((8, 8, 1)),
+            uniforms: HashMap::from([
+                ((0, 3), matrix_dims as i64),
+                ((0, 4), KERNEL_MATRIX_DIMS as i64),
+            ]),
+        })
+        .unwrap()
+        .for_invocations((matrix_dims * matrix_dims) as u64);
+
+        // The estimate assumes every invocation applies the whole kernel,
+        // including the ones at the borders
+        assert_eq!(cost.unresolved_loops, 0);
+        let relative_error =
+            (cost.flops - flops_per_iteration(matrix_dims) as f64).abs()
+                / cost.flops;
+        assert!(relative_error < 0.01, "relative error: {relative_error}");
+    }
+
+    /// Verifies the kernel of the CPU reference is mirrored like in the
+    /// shader.
+    #[test]
+    fn reference_mirrors_the_kernel() {
+        let input_matrix: Vec<f64> = (1..=9).map(|i| i as f64).collect();
+        let identity = [0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+        // Picks the element to the right of each one
+        let left = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+
+        for (x, y) in [(0, 0), (1, 2), (2, 1)] {
+            let element = input_matrix[y * 3 + x];
+            assert_eq!(
+                convolution_reference(&input_matrix, &identity, 3, x, y),
+                element
+            );
+            assert_eq!(
+                convolution_reference(&input_matrix, &left, 3, x, y),
+                if x < 2 { element + 1.0 } else { 0.0 }
+            );
+        }
+    }
+
     /// Verifies that the convolutoin shader computes the operation correctly.
     #[tokio::test]
     async fn convolution_works() {
@@ -232,16 +494,16 @@ mod tests {
         const KERNEL_DIMS: usize = 3;
 
         // Incremental gradient matrix [1, 2, 3, 4, 5 ; 6, 7, ... ;...]
-        let input_matrix: Vec<f32> =
-            (1..=MATRIX_SIZE).map(|i| i as f32).collect();
+        let input_matrix: Vec<f64> =
+            (1..=MATRIX_SIZE).map(|i| i as f64).collect();
 
         // One of the sobel operators
-        let kernel: [f32; KERNEL_DIMS * KERNEL_DIMS] =
+        let kernel: [f64; KERNEL_DIMS * KERNEL_DIMS] =
             [1.0, 0.0, -1.0, 2.0, 0.0, -2.0, 1.0, 0.0, -1.0];
 
         // Results in a matrix where only values in the edge columns are
         // different, everything in the center columns is 6 for edge rows or 8.
-        let expected_result: Vec<f32> = (0..MATRIX_DIMS)
+        let expected_result: Vec<f64> = (0..MATRIX_DIMS)
             .flat_map(|row| {
                 let left_edge = if row != 0 {
                     input_matrix[(row - 1) * MATRIX_DIMS + 1] * kernel[0]
@@ -276,9 +538,10 @@ mod tests {
 
         let workgroup_size = (8, 8);
         let gpu = GPUContext::new(None).await.unwrap();
-        let buffers = Buffers::<MATRIX_DIMS, KERNEL_DIMS>::new_from_inputs(
+        let buffers = Buffers::new_from_inputs(
             &input_matrix,
             &kernel,
+            ElementType::F32,
             &gpu,
         );
         let pipeline = convolution_pipeline(&gpu, &buffers, &workgroup_size)
@@ -309,6 +572,7 @@ mod tests {
                     (MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
                 )
             }),
+            ..Default::default()
         }
         .run(pipeline)
         .await
@@ -317,10 +581,8 @@ mod tests {
         let staging_slice = staging_buffer.slice(..);
         staging_slice.map_async(MapMode::Read).await.unwrap();
 
-        let result_data: Vec<f32> = {
-            let result_data_raw: &[u8] = &*staging_slice.get_mapped_range();
-            bytemuck::cast_slice(&result_data_raw).to_vec()
-        };
+        let result_data =
+            ElementType::F32.decode(&staging_slice.get_mapped_range());
 
         staging_buffer.unmap();
 
@@ -337,8 +599,12 @@ mod tests {
 
         let workgroup_size = (8, 8);
         let gpu = GPUContext::new(None).await.unwrap();
-        let buffers =
-            Buffers::<MATRIX_DIMS, KERNEL_DIMS>::new_with_random_inputs(&gpu);
+        let buffers = Buffers::new_with_random_inputs(
+            MATRIX_DIMS,
+            KERNEL_DIMS,
+            ElementType::F32,
+            &gpu,
+        );
         let pipeline = convolution_pipeline(&gpu, &buffers, &workgroup_size)
             .await
             .unwrap();
@@ -367,6 +633,7 @@ mod tests {
                     (MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
                 )
             }),
+            ..Default::default()
         }
         .run(pipeline)
         .await
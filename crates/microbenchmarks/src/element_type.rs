@@ -0,0 +1,258 @@
+//! Scalar types the computational microbenchmarks can work with, see
+//! [ElementType]
+
+use std::{fmt, str::FromStr};
+
+use half::f16;
+use rand::{thread_rng, Rng};
+use thiserror::Error;
+use uwgpu::wgpu::Features;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Scalar type of the data a microbenchmark works with, see
+/// [Microbenchmark::element_types](crate::Microbenchmark::element_types).
+///
+/// The shaders of the microbenchmarks that support several element types use
+/// the `$element$` placeholder for it, see [ElementType::shader].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub enum ElementType {
+    /// 32 bit floats
+    #[default]
+    F32,
+    /// 16 bit floats, requires [Features::SHADER_F16]
+    F16,
+    /// 32 bit signed integers
+    I32,
+    /// 32 bit unsigned integers
+    U32,
+}
+
+/// Error parsing an [ElementType] from its WGSL name
+#[derive(Debug, Clone, Error)]
+#[error("unknown element type \"{0}\", expected one of f32, f16, i32 or u32")]
+pub struct ParseElementTypeError(String);
+
+impl ElementType {
+    /// Every element type
+    pub const ALL: &'static [ElementType] = &[
+        ElementType::F32,
+        ElementType::F16,
+        ElementType::I32,
+        ElementType::U32,
+    ];
+
+    /// Name of the type in WGSL
+    pub fn name(&self) -> &'static str {
+        match self {
+            ElementType::F32 => "f32",
+            ElementType::F16 => "f16",
+            ElementType::I32 => "i32",
+            ElementType::U32 => "u32",
+        }
+    }
+
+    /// Size in bytes of a single element
+    pub fn size(&self) -> usize {
+        match self {
+            ElementType::F16 => 2,
+            ElementType::F32 | ElementType::I32 | ElementType::U32 => 4,
+        }
+    }
+
+    /// Size in bytes of a buffer holding `len` elements, rounded up to the 4
+    /// byte alignment buffers need.
+    pub fn buffer_size(&self, len: usize) -> u64 {
+        (len * self.size()).next_multiple_of(4) as u64
+    }
+
+    /// Features the device needs to use this type in shaders
+    pub fn required_features(&self) -> Features {
+        match self {
+            ElementType::F16 => Features::SHADER_F16,
+            ElementType::F32 | ElementType::I32 | ElementType::U32 => {
+                Features::empty()
+            }
+        }
+    }
+
+    /// Whether the type is a floating point one
+    pub fn is_float(&self) -> bool {
+        matches!(self, ElementType::F32 | ElementType::F16)
+    }
+
+    /// Unit in the last place at `value`, the distance between the
+    /// representable values of this type around it. 1 for the integer types.
+    pub fn ulp(&self, value: f64) -> f64 {
+        let (mantissa_bits, min_exponent) = match self {
+            ElementType::F32 => (23, -126),
+            ElementType::F16 => (10, -14),
+            ElementType::I32 | ElementType::U32 => return 1.0,
+        };
+
+        let exponent = match value {
+            0.0 => min_exponent,
+            _ => (value.abs().log2().floor() as i32).max(min_exponent),
+        };
+        2f64.powi(exponent - mantissa_bits)
+    }
+
+    /// The shader source with its `$element$` placeholders replaced by this
+    /// type, along with the extension it needs enabled if any.
+    pub fn shader(&self, source: &str) -> String {
+        let source = source.replace("$element$", self.name());
+
+        match self {
+            ElementType::F16 => format!("enable f16;\n\n{source}"),
+            ElementType::F32 | ElementType::I32 | ElementType::U32 => source,
+        }
+    }
+
+    /// Encodes the values as the contents of a buffer of this type, padded
+    /// with zeroes to a [ElementType::buffer_size].
+    ///
+    /// The values are converted with `as` casts, so the integer types
+    /// truncate and saturate them.
+    pub fn encode(&self, values: impl IntoIterator<Item = f64>) -> Vec<u8> {
+        let mut bytes: Vec<u8> = values
+            .into_iter()
+            .flat_map(|value| match self {
+                ElementType::F32 => (value as f32).to_le_bytes().to_vec(),
+                ElementType::F16 => f16::from_f64(value).to_le_bytes().to_vec(),
+                ElementType::I32 => (value as i32).to_le_bytes().to_vec(),
+                ElementType::U32 => (value as u32).to_le_bytes().to_vec(),
+            })
+            .collect();
+
+        bytes.resize(bytes.len().next_multiple_of(4), 0);
+        bytes
+    }
+
+    /// Decodes the contents of a buffer of this type, ignoring any trailing
+    /// bytes that don't make up a whole element.
+    pub fn decode(&self, bytes: &[u8]) -> Vec<f64> {
+        bytes
+            .chunks_exact(self.size())
+            .map(|chunk| match self {
+                ElementType::F32 => {
+                    f32::from_le_bytes(chunk.try_into().unwrap()) as f64
+                }
+                ElementType::F16 => {
+                    f16::from_le_bytes(chunk.try_into().unwrap()).to_f64()
+                }
+                ElementType::I32 => {
+                    i32::from_le_bytes(chunk.try_into().unwrap()) as f64
+                }
+                ElementType::U32 => {
+                    u32::from_le_bytes(chunk.try_into().unwrap()) as f64
+                }
+            })
+            .collect()
+    }
+
+    /// `len` random values representable by this type, in `[0, 1)` for
+    /// floats and in `[0, 16)` for integers so that sums and products of
+    /// them don't overflow too soon.
+    pub fn random(&self, len: usize) -> Vec<f64> {
+        let mut rng = thread_rng();
+
+        (0..len)
+            .map(|_| match self {
+                ElementType::F32 => rng.gen::<f32>() as f64,
+                ElementType::F16 => f16::from_f32(rng.gen()).to_f64(),
+                ElementType::I32 | ElementType::U32 => {
+                    rng.gen_range(0..16) as f64
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for ElementType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ElementType {
+    type Err = ParseElementTypeError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        ElementType::ALL
+            .iter()
+            .copied()
+            .find(|element_type| element_type.name() == name)
+            .ok_or_else(|| ParseElementTypeError(name.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies the values survive a round trip through the buffer contents,
+    /// and that the contents are padded for buffers.
+    #[test]
+    fn encoding_round_trips() {
+        let values = [0.0, 1.0, 2.5, 15.0, 1024.0];
+
+        for &element_type in ElementType::ALL {
+            let bytes = element_type.encode(values);
+            assert_eq!(
+                bytes.len() as u64,
+                element_type.buffer_size(values.len())
+            );
+
+            let decoded = element_type.decode(&bytes);
+            let expected = values.map(|value| match element_type.is_float() {
+                true => value,
+                false => value.trunc(),
+            });
+            assert_eq!(decoded[..values.len()], expected);
+        }
+
+        assert_eq!(ElementType::F16.encode([1.0, 2.0, 3.0]).len(), 8);
+        assert_eq!(ElementType::I32.encode([-3.7]), (-3i32).to_le_bytes());
+    }
+
+    #[test]
+    fn shader_placeholders_are_replaced() {
+        let source = "var<storage> data: array<$element$>;";
+
+        assert_eq!(
+            ElementType::I32.shader(source),
+            "var<storage> data: array<i32>;"
+        );
+        assert_eq!(
+            ElementType::F16.shader(source),
+            "enable f16;\n\nvar<storage> data: array<f16>;"
+        );
+    }
+
+    #[test]
+    fn ulps_match_the_spacing_of_values() {
+        assert_eq!(ElementType::F32.ulp(1.0), f32::EPSILON as f64);
+        assert_eq!(ElementType::F32.ulp(-3.0), 2.0 * f32::EPSILON as f64);
+        assert_eq!(ElementType::F16.ulp(1.0), f16::EPSILON.to_f64());
+        assert_eq!(ElementType::F16.ulp(0.0), 2f64.powi(-24));
+        assert_eq!(ElementType::I32.ulp(1000.0), 1.0);
+    }
+
+    #[test]
+    fn names_parse() {
+        for &element_type in ElementType::ALL {
+            assert_eq!(
+                element_type.to_string().parse::<ElementType>().unwrap(),
+                element_type
+            );
+        }
+        assert!("f64".parse::<ElementType>().is_err());
+    }
+}
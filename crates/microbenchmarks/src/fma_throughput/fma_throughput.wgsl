@@ -0,0 +1,38 @@
+@group(0) @binding(0) var<storage, read_write> result: array<vec4<f32>>;
+@group(0) @binding(1) var<uniform> iterations: u32;
+
+// 8 independent chains of FMAs so that their latency can be hidden, each one
+// adds a different constant so the compiler can't merge them.
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= arrayLength(&result) {
+        return;
+    }
+
+    // Seeded from the invocation so the chains can't be computed at compile
+    // time
+    let seed = f32(global_id.x) * 1e-7;
+    var x0 = vec4<f32>(seed);
+    var x1 = vec4<f32>(seed);
+    var x2 = vec4<f32>(seed);
+    var x3 = vec4<f32>(seed);
+    var x4 = vec4<f32>(seed);
+    var x5 = vec4<f32>(seed);
+    var x6 = vec4<f32>(seed);
+    var x7 = vec4<f32>(seed);
+    let a = vec4<f32>(0.9999);
+
+    for (var i = 0u; i < iterations; i++) {
+        x0 = fma(x0, a, vec4<f32>(1e-4));
+        x1 = fma(x1, a, vec4<f32>(2e-4));
+        x2 = fma(x2, a, vec4<f32>(3e-4));
+        x3 = fma(x3, a, vec4<f32>(4e-4));
+        x4 = fma(x4, a, vec4<f32>(5e-4));
+        x5 = fma(x5, a, vec4<f32>(6e-4));
+        x6 = fma(x6, a, vec4<f32>(7e-4));
+        x7 = fma(x7, a, vec4<f32>(8e-4));
+    }
+
+    // Every chain is part of the result so none of them is dead code
+    result[global_id.x] = x0 + x1 + x2 + x3 + x4 + x5 + x6 + x7;
+}
@@ -0,0 +1,334 @@
+//! FMA (fused multiply-add) throughput microbenchmark, used as the peak FLOPS
+//! of the [Roofline](crate::Roofline)
+
+use std::collections::HashMap;
+
+use uwgpu::{
+    wgpu::{
+        util::BufferInitDescriptor, BufferDescriptor, BufferUsages, Limits,
+        ShaderModuleDescriptor, ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
+    GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// 65536 invocations
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 65_536,
+    warmup_count: 10,
+    count: 100,
+    element_type: ElementType::F32,
+};
+
+/// Iterations of the loop of FMAs of each invocation
+const FMA_ITERATIONS: u32 = 1024;
+
+/// Independent chains of `vec4<f32>` FMAs of each invocation
+const FMA_CHAINS: usize = 8;
+
+/// Microbenchmark for the peak floating point throughput, the
+/// [MicrobenchmarkParameters::size] is the amount of invocations.
+///
+/// Each invocation runs several independent chains of `vec4<f32>` FMAs
+/// entirely in registers, so memory accesses don't limit it.
+pub async fn fma_throughput_benchmark(
+    gpu: &GPUContext,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<FmaThroughputResults, BenchmarkError> {
+    let buffers = Buffers::new(parameters.size, gpu);
+    let pipeline =
+        fma_throughput_pipeline(gpu, &buffers, workgroup_size).await?;
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: workgroups_dispatch(
+            parameters.size,
+            workgroup_size,
+        ),
+        dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(FmaThroughputResults {
+        results,
+        parameters,
+    })
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the FMA throughput microbenchmark. See
+/// [fma_throughput_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_eval]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct FmaThroughputResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl FmaThroughputResults {
+    /// Get the amount of FLOPS (floating point operations per second), each
+    /// FMA counting as 2
+    pub fn flops(&self) -> f64 {
+        (flops_per_iteration(self.parameters.size) as f64
+            * self.results.count as f64)
+            / (self.results.total_time(TimeUnit::Second))
+    }
+}
+
+/// FMA throughput microbenchmark, see [fma_throughput_benchmark].
+pub struct FmaThroughput;
+
+impl Microbenchmark for FmaThroughput {
+    fn name(&self) -> &'static str { "fma_throughput" }
+
+    fn title(&self) -> &'static str { "FMA Throughput" }
+
+    fn description(&self) -> &'static str {
+        "This microbenchmark tests the peak floating point performance of your GPU, with every invocation running chains of fused multiply-adds of 32bit floats without accessing memory."
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str { "Amount of invocations" }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        fits_limits(
+            limits,
+            workgroup_size,
+            || workgroups_dispatch(size, workgroup_size.0),
+            (size * size_of::<[f32; 4]>()) as u64,
+        )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(fma_throughput_benchmark(gpu, workgroup_size.0, parameters)
+                .await?
+                .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = FmaThroughputResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::flops(results.flops())]
+    }
+
+    fn arithmetic_intensity(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+    ) -> Option<f64> {
+        let bytes = parameters.size * size_of::<[f32; 4]>();
+        Some(flops_per_iteration(parameters.size) as f64 / bytes as f64)
+    }
+}
+
+/// Amount of floating point operations done by each iteration of the
+/// microbenchmark with `invocations` invocations.
+fn flops_per_iteration(invocations: usize) -> usize {
+    // 1 mul to seed the chains
+    let flops_seed = 1;
+    // vec4 FMAs of every chain, 2 flops per component
+    let flops_fmas = FMA_ITERATIONS as usize * FMA_CHAINS * 4 * 2;
+    // vec4 sums to combine the chains
+    let flops_result = (FMA_CHAINS - 1) * 4;
+
+    invocations * (flops_seed + flops_fmas + flops_result)
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    result_buffer: AsyncBuffer,
+    iterations_buffer: AsyncBuffer,
+}
+
+impl Buffers {
+    fn new(invocations: usize, gpu: &GPUContext) -> Self {
+        let result_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Result Buffer"),
+            size: (invocations * size_of::<[f32; 4]>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let iterations_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Iterations Buffer"),
+            contents: bytemuck::cast_slice(&[FMA_ITERATIONS]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        Self {
+            result_buffer,
+            iterations_buffer,
+        }
+    }
+}
+
+/// Pipeline needed for microbenchmark
+async fn fma_throughput_pipeline<'a>(
+    gpu: &'a GPUContext,
+    buffers: &'a Buffers,
+    workgroup_size: u32,
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("fma throughput shader"),
+            source: ShaderSource::Wgsl(
+                include_str!("fma_throughput.wgsl").into(),
+            ),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([
+            (0, buffers.result_buffer.as_entire_binding()),
+            (1, buffers.iterations_buffer.as_entire_binding()),
+        ]),
+        gpu,
+        workgroup_size: Some((workgroup_size, 1, 1)),
+    })
+    .await
+}
+
+fn workgroups_dispatch(
+    invocations: usize,
+    workgroup_size: u32,
+) -> Vec<(u32, u32, u32)> {
+    vec![(invocations.div_ceil(workgroup_size as usize) as u32, 1, 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::{
+        estimate_shader_cost,
+        wgpu::{BufferDescriptor, BufferUsages, MapMode},
+        Benchmark, GPUContext, ShaderCostParameters,
+    };
+
+    use super::*;
+
+    /// Cross-checks [flops_per_iteration] against the estimate from analyzing
+    /// the shader.
+    #[test]
+    fn flops_match_shader_estimate() {
+        let invocations = DEFAULT_PARAMETERS.size;
+        let cost = estimate_shader_cost(&ShaderCostParameters {
+            shader: ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(
+                    include_str!("fma_throughput.wgsl").into(),
+                ),
+            },
+            entry_point: "main",
+            workgroup_size: Some((64, 1, 1)),
+            uniforms: HashMap::from([((0, 1), FMA_ITERATIONS as i64)]),
+        })
+        .unwrap()
+        .for_invocations(invocations as u64);
+
+        assert_eq!(cost.unresolved_loops, 0);
+        assert_eq!(cost.flops, flops_per_iteration(invocations) as f64);
+    }
+
+    /// Verifies that every invocation writes its result.
+    #[tokio::test]
+    async fn fma_throughput_works() {
+        const INVOCATIONS: usize = 1000;
+
+        let workgroup_size = 64;
+        let gpu = GPUContext::new(None).await.unwrap();
+        let buffers = Buffers::new(INVOCATIONS, &gpu);
+        let pipeline = fma_throughput_pipeline(&gpu, &buffers, workgroup_size)
+            .await
+            .unwrap();
+
+        let result_size = (INVOCATIONS * size_of::<[f32; 4]>()) as u64;
+        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Staging Buffer"),
+            size: result_size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let _results = Benchmark {
+            warmup_count: 0,
+            count: 1,
+            workgroups_dispatch: workgroups_dispatch(
+                INVOCATIONS,
+                workgroup_size,
+            ),
+            dispatch_callback: None,
+            finalize_encoder_callback: Some(&|encoder| {
+                encoder.copy_buffer_to_buffer(
+                    &buffers.result_buffer,
+                    0,
+                    &staging_buffer,
+                    0,
+                    result_size,
+                )
+            }),
+            ..Default::default()
+        }
+        .run(pipeline)
+        .await
+        .unwrap();
+
+        let staging_slice = staging_buffer.slice(..);
+        staging_slice.map_async(MapMode::Read).await.unwrap();
+
+        let result_data: Vec<f32> = {
+            let result_data_raw: &[u8] = &staging_slice.get_mapped_range();
+            bytemuck::cast_slice(result_data_raw).to_vec()
+        };
+
+        staging_buffer.unmap();
+
+        assert!(result_data.iter().all(|&x| x.is_finite() && x > 0.0));
+    }
+}
@@ -0,0 +1,33 @@
+// Elements packed in words, ELEMENTS_PER_WORD per word from the lowest byte
+// up for u8 elements, each already in the range of the bins
+@group(0) @binding(0) var<storage, read> data: array<u32>;
+@group(0) @binding(1) var<storage, read_write> bins: array<atomic<u32>>;
+
+const BINS: u32 = $bins$u;
+const ELEMENTS_PER_WORD: u32 = $elements_per_word$u;
+const WORDS_PER_INVOCATION: u32 = $words_per_invocation$u;
+
+fn element(word: u32, i: u32) -> u32 {
+    if ELEMENTS_PER_WORD == 1u {
+        return word;
+    }
+    return (word >> (8u * i)) & 0xffu;
+}
+
+// Every element is counted straight into the bins in global memory
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(local_invocation_index) local_index: u32,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>) {
+    let first = workgroup_id.x * WORDS_PER_INVOCATION * $workgroup_x$u;
+
+    // Neighbouring invocations read neighbouring words
+    for (var k = 0u; k < WORDS_PER_INVOCATION; k++) {
+        let index = first + k * $workgroup_x$u + local_index;
+        if index < arrayLength(&data) {
+            let word = data[index];
+            for (var i = 0u; i < ELEMENTS_PER_WORD; i++) {
+                atomicAdd(&bins[element(word, i) % BINS], 1u);
+            }
+        }
+    }
+}
@@ -0,0 +1,538 @@
+//! Histogram microbenchmarks, counting the elements of each bin with atomics
+//! either straight in global memory or in workgroup memory first, see
+//! [HistogramStrategy]
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+use uwgpu::{
+    wgpu::{
+        util::BufferInitDescriptor, BufferDescriptor, BufferUsages, Limits,
+        ShaderModuleDescriptor, ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
+    GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// 256 bins, one per value of a byte
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 256,
+    warmup_count: 10,
+    count: 100,
+    element_type: ElementType::U32,
+};
+
+/// Elements counted in every iteration
+const ELEMENTS: usize = 4_194_304;
+
+/// Words of the input read by each invocation
+const WORDS_PER_INVOCATION: u32 = 16;
+
+/// Exponent of [HistogramDistribution::Skewed]
+const SKEW: i32 = 8;
+
+/// How the bins get counted, each of them is a separate [Histogram]
+/// microbenchmark for each [HistogramData] and [HistogramDistribution].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HistogramStrategy {
+    /// Every element is an atomic add to its bin in global memory, so every
+    /// invocation contends with all the others.
+    Global,
+    /// Each workgroup counts its elements with atomics on its own copy of
+    /// the bins in workgroup memory, then adds them to the bins in global
+    /// memory. It needs the bins to fit in workgroup memory.
+    Privatized,
+}
+
+/// The type of the elements counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HistogramData {
+    /// An element per word read
+    U32,
+    /// 4 elements per word read, so at most 256 bins
+    U8,
+}
+
+impl HistogramData {
+    fn elements_per_word(&self) -> usize {
+        match self {
+            HistogramData::U32 => 1,
+            HistogramData::U8 => 4,
+        }
+    }
+
+    /// Most bins the elements can fill.
+    fn max_bins(&self) -> usize {
+        match self {
+            HistogramData::U32 => u32::MAX as usize,
+            HistogramData::U8 => 256,
+        }
+    }
+}
+
+/// How the elements are spread over the bins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HistogramDistribution {
+    /// Every bin is as likely
+    Uniform,
+    /// Bin `bins * r^8` for a uniformly random `r` in [0, 1), so over 70% of
+    /// the elements fall in the first sixteenth of the bins and contend for
+    /// the same atomics.
+    Skewed,
+}
+
+impl HistogramDistribution {
+    /// Random elements in the range of `bins` bins.
+    fn random(&self, len: usize, bins: usize) -> Vec<u32> {
+        let mut rng = thread_rng();
+        (0..len)
+            .map(|_| match self {
+                HistogramDistribution::Uniform => rng.gen_range(0..bins) as u32,
+                HistogramDistribution::Skewed => {
+                    (bins as f64 * rng.gen::<f64>().powi(SKEW)) as u32
+                }
+            })
+            .collect()
+    }
+}
+
+/// Microbenchmark for computing the histogram of a fixed amount of elements
+/// with the given strategy, the [MicrobenchmarkParameters::size] is the
+/// amount of bins.
+///
+/// The workgroup size will dictate the size of workgroups used for the
+/// computation.
+pub async fn histogram_benchmark(
+    gpu: &GPUContext,
+    strategy: HistogramStrategy,
+    data: HistogramData,
+    distribution: HistogramDistribution,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<HistogramResults, BenchmarkError> {
+    let bins = parameters.size;
+    let buffers =
+        Buffers::new(gpu, data, &distribution.random(ELEMENTS, bins), bins);
+    let pipeline =
+        histogram_pipeline(gpu, &buffers, strategy, data, bins, workgroup_size)
+            .await?;
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: workgroups_dispatch(data, workgroup_size),
+        dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(HistogramResults {
+        results,
+        parameters,
+        strategy,
+        data,
+        distribution,
+    })
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the histogram microbenchmarks. See [histogram_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_eval]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct HistogramResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// Strategy the benchmark ran with
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub strategy: HistogramStrategy,
+    /// Type of the elements the benchmark counted
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub data: HistogramData,
+    /// Distribution of the elements the benchmark counted
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub distribution: HistogramDistribution,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl HistogramResults {
+    /// Get the amount of elements counted per second
+    pub fn elements(&self) -> f64 {
+        (ELEMENTS as f64 * self.results.count as f64)
+            / self.results.total_time(TimeUnit::Second)
+    }
+}
+
+/// Histogram microbenchmark with the given strategy, type of elements and
+/// distribution, see [histogram_benchmark].
+pub struct Histogram(
+    pub HistogramStrategy,
+    pub HistogramData,
+    pub HistogramDistribution,
+);
+
+impl Microbenchmark for Histogram {
+    fn name(&self) -> &'static str {
+        use HistogramData::*;
+        use HistogramDistribution::*;
+        use HistogramStrategy::*;
+
+        match (self.0, self.1, self.2) {
+            (Global, U32, Uniform) => "histogram_global_u32",
+            (Global, U32, Skewed) => "histogram_global_u32_skewed",
+            (Global, U8, Uniform) => "histogram_global_u8",
+            (Global, U8, Skewed) => "histogram_global_u8_skewed",
+            (Privatized, U32, Uniform) => "histogram_privatized_u32",
+            (Privatized, U32, Skewed) => "histogram_privatized_u32_skewed",
+            (Privatized, U8, Uniform) => "histogram_privatized_u8",
+            (Privatized, U8, Skewed) => "histogram_privatized_u8_skewed",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        use HistogramData::*;
+        use HistogramDistribution::*;
+        use HistogramStrategy::*;
+
+        match (self.0, self.1, self.2) {
+            (Global, U32, Uniform) => "Global Atomics Histogram of u32",
+            (Global, U32, Skewed) => "Global Atomics Histogram of u32 (Skewed)",
+            (Global, U8, Uniform) => "Global Atomics Histogram of u8",
+            (Global, U8, Skewed) => "Global Atomics Histogram of u8 (Skewed)",
+            (Privatized, U32, Uniform) => "Privatized Histogram of u32",
+            (Privatized, U32, Skewed) => "Privatized Histogram of u32 (Skewed)",
+            (Privatized, U8, Uniform) => "Privatized Histogram of u8",
+            (Privatized, U8, Skewed) => "Privatized Histogram of u8 (Skewed)",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.0 {
+            HistogramStrategy::Global => "This microbenchmark tests the performance of computing a histogram in your GPU, with every element being an atomic add to its bin in global memory. The elements are either spread uniformly over the bins or skewed towards the first ones, which makes more of the atomics contend for the same bins.",
+            HistogramStrategy::Privatized => "This microbenchmark tests the performance of computing a histogram in your GPU, with each workgroup counting its elements with atomics on its own bins in workgroup memory before adding them to the bins in global memory. The elements are either spread uniformly over the bins or skewed towards the first ones, which makes more of the atomics contend for the same bins.",
+        }
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str { "Amount of bins" }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn default_sizes(&self) -> Vec<usize> {
+        // Powers of 4 from 16 bins up to what the elements can fill or 4096
+        std::iter::successors(Some(16), |bins| Some(bins * 4))
+            .take_while(|bins| *bins <= self.1.max_bins().min(4096))
+            .collect()
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let bins = parameters.size;
+        let bins_size = (bins * size_of::<u32>()) as u64;
+        let fits_workgroup_memory = match self.0 {
+            HistogramStrategy::Global => true,
+            HistogramStrategy::Privatized => {
+                bins_size <= limits.max_compute_workgroup_storage_size as u64
+            }
+        };
+
+        bins > 0
+            && bins <= self.1.max_bins()
+            && fits_workgroup_memory
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroups_dispatch(self.1, workgroup_size.0),
+                bins_size.max(data_size(self.1)),
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(histogram_benchmark(
+                gpu,
+                self.0,
+                self.1,
+                self.2,
+                workgroup_size.0,
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = HistogramResults {
+            results: results.clone(),
+            parameters: *parameters,
+            strategy: self.0,
+            data: self.1,
+            distribution: self.2,
+        };
+        vec![Metric::elements(results.elements())]
+    }
+}
+
+/// Words of the input, with [HistogramData::elements_per_word] elements
+/// each.
+fn words(data: HistogramData) -> usize { ELEMENTS / data.elements_per_word() }
+
+/// Size in bytes of the input.
+fn data_size(data: HistogramData) -> u64 {
+    (words(data) * size_of::<u32>()) as u64
+}
+
+/// Elements packed into words, from the lowest byte up for
+/// [HistogramData::U8].
+fn pack(data: HistogramData, elements: &[u32]) -> Vec<u32> {
+    match data {
+        HistogramData::U32 => elements.to_vec(),
+        HistogramData::U8 => elements
+            .chunks(4)
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .rev()
+                    .fold(0, |word, &byte| (word << 8) | (byte & 0xff))
+            })
+            .collect(),
+    }
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    data_buffer: AsyncBuffer,
+    bins_buffer: AsyncBuffer,
+}
+
+impl Buffers {
+    fn new(
+        gpu: &GPUContext,
+        data: HistogramData,
+        elements: &[u32],
+        bins: usize,
+    ) -> Self {
+        let data_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Data Buffer"),
+            contents: bytemuck::cast_slice(&pack(data, elements)),
+            usage: BufferUsages::STORAGE,
+        });
+
+        let bins_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Bins Buffer"),
+            size: (bins * size_of::<u32>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            data_buffer,
+            bins_buffer,
+        }
+    }
+}
+
+/// Pipeline needed for microbenchmark
+async fn histogram_pipeline<'a>(
+    gpu: &'a GPUContext,
+    buffers: &'a Buffers,
+    strategy: HistogramStrategy,
+    data: HistogramData,
+    bins: usize,
+    workgroup_size: u32,
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    let shader = match strategy {
+        HistogramStrategy::Global => include_str!("histogram_global.wgsl"),
+        HistogramStrategy::Privatized => {
+            include_str!("histogram_privatized.wgsl")
+        }
+    }
+    .replace("$bins$", &bins.to_string())
+    .replace("$elements_per_word$", &data.elements_per_word().to_string())
+    .replace("$words_per_invocation$", &WORDS_PER_INVOCATION.to_string());
+
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("histogram shader"),
+            source: ShaderSource::Wgsl(shader.into()),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([
+            (0, buffers.data_buffer.as_entire_binding()),
+            (1, buffers.bins_buffer.as_entire_binding()),
+        ]),
+        gpu,
+        workgroup_size: Some((workgroup_size, 1, 1)),
+    })
+    .await
+}
+
+fn workgroups_dispatch(
+    data: HistogramData,
+    workgroup_size: u32,
+) -> Vec<(u32, u32, u32)> {
+    let words_per_workgroup =
+        WORDS_PER_INVOCATION as usize * workgroup_size as usize;
+    vec![(words(data).div_ceil(words_per_workgroup) as u32, 1, 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::{
+        wgpu::{BufferDescriptor, BufferUsages, MapMode},
+        Benchmark, GPUContext,
+    };
+
+    use super::*;
+
+    /// Verifies that the elements are in the range of the bins, and that the
+    /// skewed ones are mostly in the first bins.
+    #[test]
+    fn distributions_fit_the_bins() {
+        for bins in [1, 16, 256] {
+            for distribution in [
+                HistogramDistribution::Uniform,
+                HistogramDistribution::Skewed,
+            ] {
+                let elements = distribution.random(10_000, bins);
+                assert!(elements.iter().all(|&element| element < bins as u32));
+            }
+        }
+
+        let skewed = HistogramDistribution::Skewed.random(10_000, 256);
+        let first_bins = skewed.iter().filter(|&&element| element < 16).count();
+        assert!(first_bins > 6_000);
+    }
+
+    /// Verifies that u8 elements are packed from the lowest byte up.
+    #[test]
+    fn u8_elements_are_packed() {
+        assert_eq!(
+            pack(HistogramData::U8, &[1, 2, 3, 4, 255, 0, 0, 0]),
+            vec![0x04030201, 0x000000ff]
+        );
+    }
+
+    /// Verifies that every element is counted in its bin, by each strategy
+    /// and for each type of element.
+    #[tokio::test]
+    async fn histogram_works() {
+        let gpu = GPUContext::new(None).await.unwrap();
+        let workgroup_size = 64;
+
+        for strategy in
+            [HistogramStrategy::Global, HistogramStrategy::Privatized]
+        {
+            for data in [HistogramData::U32, HistogramData::U8] {
+                for bins in [16, 200] {
+                    let elements =
+                        HistogramDistribution::Skewed.random(ELEMENTS, bins);
+                    let mut expected_bins = vec![0_u32; bins];
+                    for &element in &elements {
+                        expected_bins[element as usize] += 1;
+                    }
+
+                    let buffers = Buffers::new(&gpu, data, &elements, bins);
+                    let pipeline = histogram_pipeline(
+                        &gpu,
+                        &buffers,
+                        strategy,
+                        data,
+                        bins,
+                        workgroup_size,
+                    )
+                    .await
+                    .unwrap();
+
+                    let bins_size = (bins * size_of::<u32>()) as u64;
+                    let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+                        label: Some("Staging Buffer"),
+                        size: bins_size,
+                        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    });
+
+                    Benchmark {
+                        warmup_count: 0,
+                        count: 1,
+                        finalize_encoder_callback: Some(&|encoder| {
+                            encoder.copy_buffer_to_buffer(
+                                &buffers.bins_buffer,
+                                0,
+                                &staging_buffer,
+                                0,
+                                bins_size,
+                            )
+                        }),
+                        workgroups_dispatch: workgroups_dispatch(
+                            data,
+                            workgroup_size,
+                        ),
+                        dispatch_callback: None,
+                        ..Default::default()
+                    }
+                    .run(pipeline)
+                    .await
+                    .unwrap();
+
+                    let staging_slice = staging_buffer.slice(..);
+                    staging_slice.map_async(MapMode::Read).await.unwrap();
+
+                    let result_bins: Vec<u32> = {
+                        let result_bins_raw = staging_slice.get_mapped_range();
+                        bytemuck::cast_slice(&result_bins_raw).to_vec()
+                    };
+
+                    staging_buffer.unmap();
+
+                    assert_eq!(
+                        result_bins, expected_bins,
+                        "{strategy:?} {data:?} {bins}"
+                    );
+                }
+            }
+        }
+    }
+}
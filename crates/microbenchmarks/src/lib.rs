@@ -3,17 +3,45 @@
 #![feature(cfg_eval)]
 
 use thiserror::Error;
-use uwgpu::{CreatePipelineError, GetGPUContextError, MapTimestampResultError};
+use uwgpu::{
+    CreatePipelineError, CreateShaderResourcesError, GetGPUContextError,
+    MapTimestampResultError, ShaderAnalysisError,
+};
 
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
+pub use accuracy::{Accuracy, MAX_ACCURACY_SAMPLES};
+pub use element_type::{ElementType, ParseElementTypeError};
+pub use microbenchmark::{
+    find_microbenchmark, microbenchmarks, AccuracyFuture, Metric, MetricKind,
+    Microbenchmark, MicrobenchmarkParameters, RunFuture,
+};
+pub use roofline::{Roofline, RooflineError, RooflinePoint};
+pub use sweep::{
+    powers_of_two, Sweep, SweepCase, SweepKey, SweepResults, SweepRun,
+    WorkgroupSweep,
+};
+pub use tune::{TuneError, TuneResults, TunedWorkgroup, Tuner};
 pub use uwgpu;
+mod accuracy;
+pub mod atomic_throughput;
 pub mod convolution;
+mod element_type;
+pub mod fma_throughput;
+pub mod histogram;
 pub mod matmul;
 pub mod memcpy;
+mod microbenchmark;
+pub mod pointer_chase;
 pub mod reduction_sum;
+mod roofline;
 pub mod scan;
+pub mod sort;
+pub mod stencil_3d;
+mod sweep;
+mod tune;
+pub mod workgroup_memory;
 
 /// An error trying to execute a benchmark
 #[derive(Debug, Clone, Error)]
@@ -34,4 +62,38 @@ pub enum BenchmarkError {
         "error trying to read the timestamp queries from the compute pipeline: {0}"
     )]
     MapTimestamp(#[from] MapTimestampResultError),
+    /// An error trying to reflect the bindings of the microbenchmark's shader.
+    /// See [ShaderAnalysisError].
+    #[error("error trying to reflect the shader bindings: {0}")]
+    ShaderAnalysis(#[from] ShaderAnalysisError),
+    /// An error trying to create the resources bound by the microbenchmark's
+    /// shader. See [CreateShaderResourcesError].
+    #[error("error trying to create the shader resources: {0}")]
+    CreateResources(#[from] CreateShaderResourcesError),
+    /// The microbenchmark computed a wrong output when checking it before
+    /// running it, so its timings wouldn't be meaningful.
+    #[error(
+        "the microbenchmark computed {output} instead of {expected} on this GPU"
+    )]
+    IncorrectOutput {
+        /// Value computed by the microbenchmark
+        output: f64,
+        /// Value it should have computed
+        expected: f64,
+    },
+    /// The stride of a strided access doesn't visit every element once, it
+    /// must be a power of 2 no larger than the amount of elements accessed.
+    #[error(
+        "the stride {stride} must be a power of 2 no larger than {elements}"
+    )]
+    InvalidStride {
+        /// Stride requested
+        stride: u32,
+        /// Amount of elements accessed
+        elements: u32,
+    },
+    /// The microbenchmark only works with workgroup sizes that are a power of
+    /// 2.
+    #[error("the workgroup size {0} must be a power of 2")]
+    WorkgroupSizeNotPowerOfTwo(u32),
 }
@@ -1,6 +1,6 @@
-@group(0) @binding(0) var<storage, read> matrixA: array<f32>;
-@group(0) @binding(1) var<storage, read> matrixB: array<f32>;
-@group(0) @binding(2) var<storage, read_write> result: array<f32>;
+@group(0) @binding(0) var<storage, read> matrixA: array<$element$>;
+@group(0) @binding(1) var<storage, read> matrixB: array<$element$>;
+@group(0) @binding(2) var<storage, read_write> result: array<$element$>;
 @group(0) @binding(3) var<uniform> matrixSize: u32;
 
 @compute @workgroup_size($workgroup$)
@@ -12,12 +12,12 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
 
     // Assume square matrix
     if row < N && col < N {
-        var sum: f32 = 0.0;
+        var sum: $element$ = $element$(0);
 
         // Perform the dot product for row of A and column of B
         for (var k: u32 = 0; k < N; k = k + 1) {
-            let a: f32 = matrixA[(row * N) + k];
-            let b: f32 = matrixB[(k * N) + col];
+            let a: $element$ = matrixA[(row * N) + k];
+            let b: $element$ = matrixB[(k * N) + col];
             sum = sum + (a * b);
         }
 
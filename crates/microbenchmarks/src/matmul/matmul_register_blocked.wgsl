@@ -0,0 +1,79 @@
+@group(0) @binding(0) var<storage, read> matrixA: array<$element$>;
+@group(0) @binding(1) var<storage, read> matrixB: array<$element$>;
+@group(0) @binding(2) var<storage, read_write> result: array<$element$>;
+@group(0) @binding(3) var<uniform> matrixSize: u32;
+
+// Square workgroups, each invocation computes a 4x4 micro-tile of the result
+// so each workgroup computes a BLOCK x BLOCK block
+const TILE: u32 = $workgroup_x$;
+const BLOCK: u32 = 4 * $workgroup_x$;
+
+// BLOCK x TILE block of rows of A and TILE x BLOCK block of columns of B
+// $tile_elements$ gets replaced by the amount of elements of each tile,
+// workgroup arrays need a literal size
+var<workgroup> tileA: array<$element$, $tile_elements$>;
+var<workgroup> tileB: array<$element$, $tile_elements$>;
+
+@compute @workgroup_size($workgroup$)
+fn main(
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+) {
+    let N = matrixSize;
+    let tiles = (N + TILE - 1) / TILE;
+    // The rows and columns of the micro-tile are interleaved TILE apart, so
+    // neighboring invocations access neighboring elements
+    let firstRow = workgroup_id.x * BLOCK + local_id.x;
+    let firstCol = workgroup_id.y * BLOCK + local_id.y;
+
+    var sums: array<vec4<$element$>, 4>;
+
+    for (var t: u32 = 0; t < tiles; t = t + 1) {
+        let k = t * TILE;
+
+        // Every invocation loads 4 elements of each tile, padding the tiles
+        // past the edges of the matrices with zeroes
+        for (var i: u32 = 0; i < 4; i = i + 1) {
+            let row = firstRow + i * TILE;
+            var a: $element$ = $element$(0);
+            if row < N && k + local_id.y < N {
+                a = matrixA[row * N + k + local_id.y];
+            }
+            tileA[(local_id.x + i * TILE) * TILE + local_id.y] = a;
+
+            let col = firstCol + i * TILE;
+            var b: $element$ = $element$(0);
+            if k + local_id.x < N && col < N {
+                b = matrixB[(k + local_id.x) * N + col];
+            }
+            tileB[local_id.x * BLOCK + local_id.y + i * TILE] = b;
+        }
+
+        workgroupBarrier();
+
+        for (var j: u32 = 0; j < TILE; j = j + 1) {
+            let b = vec4<$element$>(
+                tileB[j * BLOCK + local_id.y],
+                tileB[j * BLOCK + local_id.y + TILE],
+                tileB[j * BLOCK + local_id.y + 2 * TILE],
+                tileB[j * BLOCK + local_id.y + 3 * TILE],
+            );
+            for (var i: u32 = 0; i < 4; i = i + 1) {
+                let a = tileA[(local_id.x + i * TILE) * TILE + j];
+                sums[i] = sums[i] + a * b;
+            }
+        }
+
+        workgroupBarrier();
+    }
+
+    for (var i: u32 = 0; i < 4; i = i + 1) {
+        let row = firstRow + i * TILE;
+        for (var j: u32 = 0; j < 4; j = j + 1) {
+            let col = firstCol + j * TILE;
+            if row < N && col < N {
+                result[(row * N) + col] = sums[i][j];
+            }
+        }
+    }
+}
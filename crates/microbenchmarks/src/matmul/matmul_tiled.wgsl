@@ -0,0 +1,56 @@
+@group(0) @binding(0) var<storage, read> matrixA: array<$element$>;
+@group(0) @binding(1) var<storage, read> matrixB: array<$element$>;
+@group(0) @binding(2) var<storage, read_write> result: array<$element$>;
+@group(0) @binding(3) var<uniform> matrixSize: u32;
+
+// Square workgroups, each of them computes a TILE x TILE block of the result
+const TILE: u32 = $workgroup_x$;
+
+// $tile_elements$ gets replaced by the amount of elements of each tile,
+// workgroup arrays need a literal size
+var<workgroup> tileA: array<$element$, $tile_elements$>;
+var<workgroup> tileB: array<$element$, $tile_elements$>;
+
+@compute @workgroup_size($workgroup$)
+fn main(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+) {
+    let row: u32 = global_id.x;
+    let col: u32 = global_id.y;
+    let N = matrixSize;
+    let tiles = (N + TILE - 1) / TILE;
+
+    var sum: $element$ = $element$(0);
+
+    for (var t: u32 = 0; t < tiles; t = t + 1) {
+        // Every invocation loads one element of each tile, padding the tiles
+        // past the edges of the matrices with zeroes
+        let k = t * TILE;
+        let tileIndex = local_id.x * TILE + local_id.y;
+
+        var a: $element$ = $element$(0);
+        if row < N && k + local_id.y < N {
+            a = matrixA[row * N + k + local_id.y];
+        }
+        tileA[tileIndex] = a;
+
+        var b: $element$ = $element$(0);
+        if k + local_id.x < N && col < N {
+            b = matrixB[(k + local_id.x) * N + col];
+        }
+        tileB[tileIndex] = b;
+
+        workgroupBarrier();
+
+        for (var i: u32 = 0; i < TILE; i = i + 1) {
+            sum = sum + tileA[local_id.x * TILE + i] * tileB[i * TILE + local_id.y];
+        }
+
+        workgroupBarrier();
+    }
+
+    if row < N && col < N {
+        result[(row * N) + col] = sum;
+    }
+}
@@ -1,53 +1,209 @@
-//! Matrix multiplication microbenchmarks
+//! Matrix multiplication microbenchmarks, with a kernel for each way of
+//! reusing the elements loaded from the matrices, see [MatmulKernel]
 
 use std::collections::HashMap;
 
-use rand::{thread_rng, Rng};
 use uwgpu::TimeUnit;
 use uwgpu::{
-    wgpu::{
-        util::BufferInitDescriptor, BufferDescriptor, BufferUsages,
-        ShaderModuleDescriptor, ShaderSource,
+    reflect_bindings,
+    wgpu::{Limits, ShaderModuleDescriptor, ShaderSource},
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, BindingInit,
+    CreatePipelineError, GPUContext, PipelineParameters, ShaderResources,
+};
+
+use crate::{
+    accuracy::{read_staging_buffer, sample_indices, staging_buffer},
+    microbenchmark::{
+        default_workgroups_2d, fits_limits, AccuracyFuture, Metric,
+        Microbenchmark, MicrobenchmarkParameters, RunFuture,
     },
-    wgpu_async::AsyncBuffer,
-    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
-    GPUContext, PipelineParameters,
+    Accuracy, BenchmarkError, ElementType,
 };
 
-use crate::BenchmarkError;
+/// 1024x1024 matrices
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 1024,
+    warmup_count: 20,
+    count: 30,
+    element_type: ElementType::F32,
+};
+
+/// The ways of computing the matrix multiplication, each of them is a
+/// separate [Matmul] microbenchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatmulKernel {
+    /// One invocation per element of the result, reading the rows and
+    /// columns it multiplies straight from global memory.
+    Naive,
+    /// One invocation per element of the result, with square workgroups
+    /// loading tiles of the matrices into workgroup memory so each element
+    /// loaded gets reused by a whole row or column of the workgroup.
+    Tiled,
+    /// Like [MatmulKernel::Tiled], but each invocation computes a 4x4
+    /// micro-tile of the result, reusing the elements it reads from
+    /// workgroup memory through registers.
+    RegisterBlocked,
+}
+
+impl MatmulKernel {
+    /// Every kernel, from the simplest to the most optimized.
+    pub const ALL: &'static [MatmulKernel] = &[
+        MatmulKernel::Naive,
+        MatmulKernel::Tiled,
+        MatmulKernel::RegisterBlocked,
+    ];
+
+    /// Side of the square block of the result computed by each invocation.
+    fn micro_tile(&self) -> u32 {
+        match self {
+            MatmulKernel::Naive | MatmulKernel::Tiled => 1,
+            MatmulKernel::RegisterBlocked => 4,
+        }
+    }
+
+    /// Amount of elements of each tile of the matrices loaded into workgroup
+    /// memory by workgroups of `tile` x `tile` invocations.
+    fn tile_elements(&self, tile: u32) -> u32 {
+        match self {
+            MatmulKernel::Naive => 0,
+            MatmulKernel::Tiled | MatmulKernel::RegisterBlocked => {
+                self.micro_tile() * tile * tile
+            }
+        }
+    }
 
-const BENCHMARK_MATRIX_DIMS: usize = 1024;
-const BENCHMARK_WARMUP_COUNT: usize = 20;
-const BENCHMARK_ITERATIONS: usize = 30;
+    /// Bytes of workgroup memory used by workgroups of `tile` x `tile`
+    /// invocations, for a tile of rows and a tile of columns.
+    fn workgroup_storage_size(
+        &self,
+        tile: u32,
+        element_type: ElementType,
+    ) -> u64 {
+        2 * self.tile_elements(tile) as u64 * element_type.size() as u64
+    }
+}
 
 /// Microbenchmark for matrix mulitplication
 ///
-/// Multiplies 2 randomly initialized 1024x1024 matrices repeatedly.
+/// Multiplies 2 randomly initialized square matrices repeatedly with the
+/// given kernel, the [MicrobenchmarkParameters::size] is their amount of rows
+/// and columns and the [MicrobenchmarkParameters::element_type] the type of
+/// their elements.
 ///
 /// The workgroup size will dictate the size of workgroups used for the
-/// computation.
+/// computation, it must be square for the kernels using workgroup memory.
 pub async fn matmul_benchmark(
+    gpu: &GPUContext,
+    kernel: MatmulKernel,
     workgroup_size: (u32, u32),
+    parameters: MicrobenchmarkParameters,
 ) -> Result<MatmulResults, BenchmarkError> {
-    let gpu = GPUContext::new(None).await?;
-    let buffers =
-        Buffers::<BENCHMARK_MATRIX_DIMS>::new_with_random_inputs(&gpu);
-    let pipeline = matmul_pipeline(&gpu, &buffers, &workgroup_size).await?;
+    let element_type = parameters.element_type;
+    let matrix_size = parameters.size * parameters.size;
+    let matrix_a = element_type.random(matrix_size);
+    let matrix_b = element_type.random(matrix_size);
+    let resources = matmul_resources(
+        gpu,
+        kernel,
+        parameters.size,
+        &workgroup_size,
+        element_type,
+        |i| matrix_a[i],
+        |i| matrix_b[i],
+    )?;
+    let pipeline =
+        matmul_pipeline(gpu, kernel, &resources, &workgroup_size, element_type)
+            .await?;
 
     let results = Benchmark {
-        warmup_count: BENCHMARK_WARMUP_COUNT,
-        count: BENCHMARK_ITERATIONS,
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
         finalize_encoder_callback: None,
         workgroups_dispatch: workgroups_dispatch(
-            BENCHMARK_MATRIX_DIMS,
+            kernel,
+            parameters.size,
             workgroup_size,
         ),
         dispatch_callback: None,
+        ..Default::default()
     }
     .run(pipeline)
     .await?;
 
-    Ok(MatmulResults(results))
+    Ok(MatmulResults {
+        results,
+        parameters,
+    })
+}
+
+/// Accuracy of a single matrix multiplication of random matrices with the
+/// kernel and parameters of [matmul_benchmark], against a reference computed
+/// on the CPU.
+pub async fn matmul_accuracy(
+    gpu: &GPUContext,
+    kernel: MatmulKernel,
+    workgroup_size: (u32, u32),
+    parameters: MicrobenchmarkParameters,
+) -> Result<Accuracy, BenchmarkError> {
+    let element_type = parameters.element_type;
+    let matrix_dims = parameters.size;
+    let matrix_size = matrix_dims * matrix_dims;
+    let matrix_a = element_type.random(matrix_size);
+    let matrix_b = element_type.random(matrix_size);
+    let resources = matmul_resources(
+        gpu,
+        kernel,
+        matrix_dims,
+        &workgroup_size,
+        element_type,
+        |i| matrix_a[i],
+        |i| matrix_b[i],
+    )?;
+    let pipeline =
+        matmul_pipeline(gpu, kernel, &resources, &workgroup_size, element_type)
+            .await?;
+
+    let result_size = element_type.buffer_size(matrix_size);
+    let staging_buffer = staging_buffer(gpu, result_size);
+    Benchmark {
+        warmup_count: 0,
+        count: 1,
+        workgroups_dispatch: workgroups_dispatch(
+            kernel,
+            matrix_dims,
+            workgroup_size,
+        ),
+        finalize_encoder_callback: Some(&|encoder| {
+            encoder.copy_buffer_to_buffer(
+                resources.buffer(0, 2).expect("result is bound"),
+                0,
+                &staging_buffer,
+                0,
+                result_size,
+            )
+        }),
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+    let result =
+        element_type.decode(&read_staging_buffer(&staging_buffer).await);
+
+    Ok(Accuracy::measure(
+        element_type,
+        sample_indices(matrix_size).into_iter().map(|i| {
+            let (row, column) = (i / matrix_dims, i % matrix_dims);
+            let reference = (0..matrix_dims)
+                .map(|k| {
+                    matrix_a[row * matrix_dims + k]
+                        * matrix_b[k * matrix_dims + column]
+                })
+                .sum();
+
+            (result[i], reference)
+        }),
+    ))
 }
 
 #[cfg(feature = "wasm")]
@@ -57,129 +213,273 @@ use wasm_bindgen::prelude::*;
 /// [matmul_benchmark].
 ///
 /// Wraps a [BenchmarkResults] with some convenience methods.
-#[cfg_attr(
-    feature = "serde",
-    derive(serde::Serialize, serde::Deserialize),
-    serde(transparent)
-)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_eval]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-pub struct MatmulResults(
+pub struct MatmulResults {
+    /// Timings of the benchmark
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-    pub  BenchmarkResults,
-);
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl MatmulResults {
     /// Get the total amount of time in seconds spent executing the
     /// microbenchmark
-    pub fn total_time_s(&self) -> f64 { self.0.total_time(TimeUnit::Second) }
+    pub fn total_time_s(&self) -> f64 {
+        self.results.total_time(TimeUnit::Second)
+    }
 
     /// Get the amount of time per iteration in ms
     pub fn time_per_iteration_ms(&self) -> f64 {
-        self.0.time_per_iteration(TimeUnit::Milli)
+        self.results.time_per_iteration(TimeUnit::Milli)
     }
 
     /// Get the amount of FLOPS (floating point operations per second)
     pub fn flops(&self) -> f64 {
-        /// Reference for the amount of FLOPs in a matrix multiplication:
-        /// https://math.stackexchange.com/questions/3512976/proof-of-of-flops-in-matrix-multiplication
-        const NUM_FLOPS_PER_ITER: usize = BENCHMARK_MATRIX_DIMS
-            * BENCHMARK_MATRIX_DIMS
-            * (2 * BENCHMARK_MATRIX_DIMS - 1);
-
-        (NUM_FLOPS_PER_ITER as f64 * self.0.count as f64)
+        (flops_per_iteration(self.parameters.size) as f64
+            * self.results.count as f64)
             / (self.total_time_s())
     }
 }
 
-/// GPU buffers needed for microbenchmark
-///
-/// Matrices are assumed to be square matrices with MATRIX_DIMS x MATRIX_DIMS
-/// dimensions.
-struct Buffers<const MATRIX_DIMS: usize> {
-    matrix_a_buffer: AsyncBuffer,
-    matrix_b_buffer: AsyncBuffer,
-    result_buffer: AsyncBuffer,
-    matrix_size_buffer: AsyncBuffer,
-}
+/// Matrix multiplication microbenchmark with the given kernel, see
+/// [matmul_benchmark].
+pub struct Matmul(pub MatmulKernel);
+
+impl Microbenchmark for Matmul {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            MatmulKernel::Naive => "matmul",
+            MatmulKernel::Tiled => "matmul_tiled",
+            MatmulKernel::RegisterBlocked => "matmul_register_blocked",
+        }
+    }
 
-impl<const MATRIX_DIMS: usize> Buffers<MATRIX_DIMS> {
-    const MATRIX_SIZE: usize = MATRIX_DIMS * MATRIX_DIMS;
+    fn title(&self) -> &'static str {
+        match self.0 {
+            MatmulKernel::Naive => "Matrix Multiplication",
+            MatmulKernel::Tiled => "Tiled Matrix Multiplication",
+            MatmulKernel::RegisterBlocked => {
+                "Register-Blocked Matrix Multiplication"
+            }
+        }
+    }
 
-    fn new_with_random_inputs(gpu: &GPUContext) -> Self {
-        let mut matrix_a_data = vec![0_f32; Self::MATRIX_SIZE];
-        let mut matrix_b_data = vec![0_f32; Self::MATRIX_SIZE];
+    fn description(&self) -> &'static str {
+        match self.0 {
+            MatmulKernel::Naive => "This microbenchmark tests the performance of a naive matrix multiplication between two 1024x1024 matrices of 32bit floats.",
+            MatmulKernel::Tiled => "This microbenchmark tests the performance of a matrix multiplication between two 1024x1024 matrices of 32bit floats that loads tiles of them into workgroup memory, so they're read from global memory fewer times. Compare it to the naive matrix multiplication to see how much your GPU gains from workgroup memory.",
+            MatmulKernel::RegisterBlocked => "This microbenchmark tests the performance of a matrix multiplication between two 1024x1024 matrices of 32bit floats that loads tiles of them into workgroup memory, with every invocation computing a 4x4 block of the result from registers.",
+        }
+    }
 
-        let mut rng = thread_rng();
+    fn element_types(&self) -> &'static [ElementType] { ElementType::ALL }
 
-        rng.fill(matrix_a_data.as_mut_slice());
-        rng.fill(matrix_b_data.as_mut_slice());
+    fn workgroup_dimensions(&self) -> usize { 2 }
 
-        Self::new_from_inputs(&matrix_a_data, &matrix_b_data, &gpu)
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        match self.0 {
+            MatmulKernel::Naive => default_workgroups_2d(),
+            MatmulKernel::Tiled | MatmulKernel::RegisterBlocked => {
+                [4, 8, 16].map(|size| (size, size, 1)).to_vec()
+            }
+        }
     }
 
-    fn new_from_inputs(
-        matrix_a_data: &[f32],
-        matrix_b_data: &[f32],
-        gpu: &GPUContext,
-    ) -> Self {
-        let matrix_a_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Matrix A Buffer"),
-            contents: bytemuck::cast_slice(matrix_a_data),
-            usage: BufferUsages::STORAGE,
-        });
+    fn size_description(&self) -> &'static str {
+        "Dimensions of the square matrices"
+    }
 
-        let matrix_b_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Matrix B Buffer"),
-            contents: bytemuck::cast_slice(matrix_b_data),
-            usage: BufferUsages::STORAGE,
-        });
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
 
-        let result_buffer = gpu.create_buffer(&BufferDescriptor {
-            label: Some("Result Buffer"),
-            size: (Self::MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
-            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
-            mapped_at_creation: false,
-        });
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        let kernel = self.0;
+        let (x, y, _) = workgroup_size;
+        let fits_workgroup_memory = match kernel {
+            MatmulKernel::Naive => true,
+            MatmulKernel::Tiled | MatmulKernel::RegisterBlocked => {
+                x == y
+                    && kernel.workgroup_storage_size(x, parameters.element_type)
+                        <= limits.max_compute_workgroup_storage_size as u64
+            }
+        };
 
-        let matrix_size_buffer =
-            gpu.create_buffer_init(&BufferInitDescriptor {
-                label: Some("Matrix Size Buffer"),
-                contents: bytemuck::cast_slice(&[MATRIX_DIMS]),
-                usage: BufferUsages::UNIFORM,
-            });
+        fits_workgroup_memory
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroups_dispatch(kernel, size, (x, y)),
+                parameters.element_type.buffer_size(size * size),
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(matmul_benchmark(
+                gpu,
+                self.0,
+                (workgroup_size.0, workgroup_size.1),
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn accuracy<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> AccuracyFuture<'a> {
+        Box::pin(async move {
+            Ok(Some(
+                matmul_accuracy(
+                    gpu,
+                    self.0,
+                    (workgroup_size.0, workgroup_size.1),
+                    parameters,
+                )
+                .await?,
+            ))
+        })
+    }
 
-        Self {
-            matrix_a_buffer,
-            matrix_b_buffer,
-            result_buffer,
-            matrix_size_buffer,
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = MatmulResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::flops(results.flops())]
+    }
+
+    fn arithmetic_intensity(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+    ) -> Option<f64> {
+        // reads both input matrices and writes the result
+        let n = parameters.size;
+        let bytes = 3 * n * n * parameters.element_type.size();
+        Some(flops_per_iteration(n) as f64 / bytes as f64)
+    }
+}
+
+/// Amount of floating point operations done by each iteration of the
+/// microbenchmark with `matrix_dims` x `matrix_dims` matrices.
+///
+/// Reference for the amount of FLOPs in a matrix multiplication:
+/// https://math.stackexchange.com/questions/3512976/proof-of-of-flops-in-matrix-multiplication
+fn flops_per_iteration(matrix_dims: usize) -> usize {
+    matrix_dims * matrix_dims * (2 * matrix_dims - 1)
+}
+
+/// Shader of the kernel, for matrices of `element_type` and workgroups of
+/// `workgroup_size`
+fn matmul_shader(
+    kernel: MatmulKernel,
+    element_type: ElementType,
+    workgroup_size: &(u32, u32),
+) -> ShaderModuleDescriptor<'static> {
+    let source = match kernel {
+        MatmulKernel::Naive => include_str!("matmul.wgsl"),
+        MatmulKernel::Tiled => include_str!("matmul_tiled.wgsl"),
+        MatmulKernel::RegisterBlocked => {
+            include_str!("matmul_register_blocked.wgsl")
         }
+    };
+
+    ShaderModuleDescriptor {
+        label: Some("matmul shader"),
+        source: ShaderSource::Wgsl(
+            element_type
+                .shader(source)
+                .replace(
+                    "$tile_elements$",
+                    &kernel.tile_elements(workgroup_size.0).to_string(),
+                )
+                .into(),
+        ),
     }
 }
 
-/// Pipeline needed for microbenchmark
+/// GPU resources needed for microbenchmark, created from the shader's
+/// bindings.
 ///
-/// Matrices are assumed to be square matrices with MATRIX_DIMS x MATRIX_DIMS
-/// dimensions.
-async fn matmul_pipeline<'a, const MATRIX_DIMS: usize>(
+/// Matrices are assumed to be square matrices of `element_type` with
+/// `matrix_dims` x `matrix_dims` dimensions, each of their elements is
+/// generated from its index by `matrix_a` and `matrix_b`.
+fn matmul_resources(
+    gpu: &GPUContext,
+    kernel: MatmulKernel,
+    matrix_dims: usize,
+    workgroup_size: &(u32, u32),
+    element_type: ElementType,
+    matrix_a: impl Fn(usize) -> f64,
+    matrix_b: impl Fn(usize) -> f64,
+) -> Result<ShaderResources, BenchmarkError> {
+    let matrix_size = matrix_dims * matrix_dims;
+    // naga can't parse f16 shaders yet, but the bindings are the same for
+    // every element type so they're reflected from the f32 shader and the
+    // matrices are encoded as bytes of the actual type.
+    let bindings = reflect_bindings(
+        &matmul_shader(kernel, ElementType::F32, workgroup_size),
+        "main",
+        Some((workgroup_size.0, workgroup_size.1, 1)),
+    )?;
+    let matrix_a = element_type.encode((0..matrix_size).map(matrix_a));
+    let matrix_b = element_type.encode((0..matrix_size).map(matrix_b));
+    let result = element_type.encode((0..matrix_size).map(|_| 0.0));
+
+    Ok(ShaderResources::new(
+        gpu,
+        &bindings,
+        HashMap::from([
+            ((0, 0), BindingInit::Bytes(&matrix_a)),
+            ((0, 1), BindingInit::Bytes(&matrix_b)),
+            ((0, 2), BindingInit::Bytes(&result)),
+            (
+                (0, 3),
+                BindingInit::Generated {
+                    len: 1,
+                    generator: Box::new(|_| matrix_dims as f64),
+                },
+            ),
+        ]),
+    )?)
+}
+
+/// Pipeline needed for microbenchmark
+async fn matmul_pipeline<'a>(
     gpu: &'a GPUContext,
-    buffers: &'a Buffers<MATRIX_DIMS>,
+    kernel: MatmulKernel,
+    resources: &'a ShaderResources,
     workgroup_size: &(u32, u32),
+    element_type: ElementType,
 ) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
     BenchmarkComputePipeline::new(PipelineParameters {
-        shader: ShaderModuleDescriptor {
-            label: Some("matmul shader"),
-            source: ShaderSource::Wgsl(include_str!("matmul.wgsl").into()),
-        },
+        shader: matmul_shader(kernel, element_type, workgroup_size),
         entry_point: "main",
-        bind_group_0: HashMap::from([
-            (0, buffers.matrix_a_buffer.as_entire_binding()),
-            (1, buffers.matrix_b_buffer.as_entire_binding()),
-            (2, buffers.result_buffer.as_entire_binding()),
-            (3, buffers.matrix_size_buffer.as_entire_binding()),
-        ]),
+        bind_group_0: resources.bind_group_entries(0),
         gpu,
         workgroup_size: Some((workgroup_size.0, workgroup_size.1, 1)),
     })
@@ -187,38 +487,106 @@ async fn matmul_pipeline<'a, const MATRIX_DIMS: usize>(
 }
 
 fn workgroups_dispatch(
+    kernel: MatmulKernel,
     matrix_dims: usize,
     workgroup_size: (u32, u32),
 ) -> Vec<(u32, u32, u32)> {
-    vec![(
-        1 + (matrix_dims / (workgroup_size.0 as usize)) as u32,
-        1 + (matrix_dims / (workgroup_size.1 as usize)) as u32,
-        1,
-    )]
+    match kernel {
+        MatmulKernel::Naive => vec![(
+            1 + (matrix_dims / (workgroup_size.0 as usize)) as u32,
+            1 + (matrix_dims / (workgroup_size.1 as usize)) as u32,
+            1,
+        )],
+        MatmulKernel::Tiled | MatmulKernel::RegisterBlocked => {
+            let block = |size: u32| (size * kernel.micro_tile()) as usize;
+            vec![(
+                matrix_dims.div_ceil(block(workgroup_size.0)) as u32,
+                matrix_dims.div_ceil(block(workgroup_size.1)) as u32,
+                1,
+            )]
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use uwgpu::{
+        estimate_shader_cost,
         wgpu::{BufferDescriptor, BufferUsages, MapMode},
-        Benchmark, GPUContext,
+        Benchmark, GPUContext, ShaderCostParameters,
     };
 
     use super::*;
 
-    /// Verifies that the matmul shader computes the matrix multiplication
-    /// correctly.
+    /// Cross-checks [flops_per_iteration] against the estimate from analyzing
+    /// the shader.
+    #[test]
+    fn flops_match_shader_estimate() {
+        let matrix_dims = DEFAULT_PARAMETERS.size;
+        let cost = estimate_shader_cost(&ShaderCostParameters {
+            shader: matmul_shader(
+                MatmulKernel::Naive,
+                ElementType::F32,
+                &(8, 8),
+            ),
+            entry_point: "main",
+            workgroup_size: Some((8, 8, 1)),
+            uniforms: HashMap::from([((0, 3), matrix_dims as i64)]),
+        })
+        .unwrap()
+        .for_invocations((matrix_dims * matrix_dims) as u64);
+
+        // The shader does N sums per dot product instead of N - 1, because it
+        // starts adding from 0
+        assert_eq!(cost.unresolved_loops, 0);
+        assert_eq!(
+            cost.flops,
+            (flops_per_iteration(matrix_dims) + matrix_dims * matrix_dims)
+                as f64
+        );
+        assert_eq!(
+            cost.bytes_read,
+            (matrix_dims.pow(3) * 2 * size_of::<f32>()) as f64
+        );
+    }
+
+    /// Verifies the tiled kernels do the same operations as the naive one
+    /// when the matrices are made of whole blocks.
+    #[test]
+    fn tiled_flops_match_naive() {
+        let matrix_dims = DEFAULT_PARAMETERS.size;
+        let tile = 8;
+
+        for kernel in [MatmulKernel::Tiled, MatmulKernel::RegisterBlocked] {
+            let block = (tile * kernel.micro_tile()) as usize;
+            let cost = estimate_shader_cost(&ShaderCostParameters {
+                shader: matmul_shader(kernel, ElementType::F32, &(tile, tile)),
+                entry_point: "main",
+                workgroup_size: Some((tile, tile, 1)),
+                uniforms: HashMap::from([((0, 3), matrix_dims as i64)]),
+            })
+            .unwrap()
+            .for_invocations(
+                (matrix_dims / block).pow(2) as u64 * (tile * tile) as u64,
+            );
+
+            assert_eq!(cost.unresolved_loops, 0, "{kernel:?}");
+            assert_eq!(
+                cost.flops,
+                (flops_per_iteration(matrix_dims) + matrix_dims * matrix_dims)
+                    as f64,
+                "{kernel:?}"
+            );
+        }
+    }
+
+    /// Verifies that the matmul shaders compute the matrix multiplication
+    /// correctly, with matrices that aren't made of whole tiles.
     #[tokio::test]
     async fn matmul_works() {
         const MATRIX_DIMS: usize = 100;
         const MATRIX_SIZE: usize = MATRIX_DIMS * MATRIX_DIMS;
 
-        // Incremental matrix [0, 1, 2, 3, 4 ; 5, 6, ... ;...]
-        let matrix_a: Vec<f32> = (0..MATRIX_SIZE).map(|i| i as f32).collect();
-
-        // Matrix of all 1s
-        let matrix_b: Vec<f32> = (0..MATRIX_SIZE).map(|_| 1.0).collect();
-
         // Results in a matrix where all values in a row are the sum of
         // that row in matrix a
         let expected_result: Vec<f32> = (0..MATRIX_SIZE)
@@ -232,52 +600,94 @@ mod tests {
 
         let workgroup_size = (8, 8);
         let gpu = GPUContext::new(None).await.unwrap();
-        let buffers =
-            Buffers::<MATRIX_DIMS>::new_from_inputs(&matrix_a, &matrix_b, &gpu);
-        let pipeline = matmul_pipeline(&gpu, &buffers, &workgroup_size)
+
+        for &kernel in MatmulKernel::ALL {
+            // Incremental matrix [0, 1, 2, 3, 4 ; 5, 6, ... ;...] times a
+            // matrix of all 1s
+            let resources = matmul_resources(
+                &gpu,
+                kernel,
+                MATRIX_DIMS,
+                &workgroup_size,
+                ElementType::F32,
+                |i| i as f64,
+                |_| 1.0,
+            )
+            .unwrap();
+            let pipeline = matmul_pipeline(
+                &gpu,
+                kernel,
+                &resources,
+                &workgroup_size,
+                ElementType::F32,
+            )
             .await
             .unwrap();
 
-        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
-            label: Some("Staging Buffer"),
-            size: (MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
-            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+            let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+                label: Some("Staging Buffer"),
+                size: (MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
 
-        let _results = Benchmark {
-            warmup_count: 0,
-            count: 1,
-            workgroups_dispatch: workgroups_dispatch(
-                BENCHMARK_MATRIX_DIMS,
-                workgroup_size,
-            ),
-            dispatch_callback: None,
-            finalize_encoder_callback: Some(&|encoder| {
-                encoder.copy_buffer_to_buffer(
-                    &buffers.result_buffer,
-                    0,
-                    &staging_buffer,
-                    0,
-                    (MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
-                )
-            }),
-        }
-        .run(pipeline)
-        .await
-        .unwrap();
+            let _results = Benchmark {
+                warmup_count: 0,
+                count: 1,
+                workgroups_dispatch: workgroups_dispatch(
+                    kernel,
+                    MATRIX_DIMS,
+                    workgroup_size,
+                ),
+                dispatch_callback: None,
+                finalize_encoder_callback: Some(&|encoder| {
+                    encoder.copy_buffer_to_buffer(
+                        resources.buffer(0, 2).unwrap(),
+                        0,
+                        &staging_buffer,
+                        0,
+                        (MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
+                    )
+                }),
+                ..Default::default()
+            }
+            .run(pipeline)
+            .await
+            .unwrap();
 
-        let staging_slice = staging_buffer.slice(..);
-        staging_slice.map_async(MapMode::Read).await.unwrap();
+            let staging_slice = staging_buffer.slice(..);
+            staging_slice.map_async(MapMode::Read).await.unwrap();
 
-        let result_data: Vec<f32> = {
-            let result_data_raw: &[u8] = &*staging_slice.get_mapped_range();
-            bytemuck::cast_slice(&result_data_raw).to_vec()
+            let result_data: Vec<f32> = {
+                let result_data_raw: &[u8] = &staging_slice.get_mapped_range();
+                bytemuck::cast_slice(result_data_raw).to_vec()
+            };
+
+            staging_buffer.unmap();
+
+            assert_eq!(result_data, expected_result, "{kernel:?}");
+        }
+    }
+
+    /// Verifies the accuracy check compares against the right reference, as
+    /// integer matrix multiplications have to match it exactly.
+    #[tokio::test]
+    async fn integer_matmul_is_exact() {
+        let gpu = GPUContext::new(None).await.unwrap();
+        let parameters = MicrobenchmarkParameters {
+            size: 70,
+            element_type: ElementType::I32,
+            ..DEFAULT_PARAMETERS
         };
 
-        staging_buffer.unmap();
+        for &kernel in MatmulKernel::ALL {
+            let accuracy = matmul_accuracy(&gpu, kernel, (8, 8), parameters)
+                .await
+                .unwrap();
 
-        assert_eq!(result_data, expected_result);
+            assert_eq!(accuracy.samples, 70 * 70);
+            assert!(accuracy.is_exact(), "{kernel:?}: {accuracy:?}");
+        }
     }
 
     /// Verifies that instantiating the matmul buffers with random inputs
@@ -289,10 +699,25 @@ mod tests {
 
         let workgroup_size = (8, 8);
         let gpu = GPUContext::new(None).await.unwrap();
-        let buffers = Buffers::<MATRIX_DIMS>::new_with_random_inputs(&gpu);
-        let pipeline = matmul_pipeline(&gpu, &buffers, &workgroup_size)
-            .await
-            .unwrap();
+        let resources = matmul_resources(
+            &gpu,
+            MatmulKernel::Naive,
+            MATRIX_DIMS,
+            &workgroup_size,
+            ElementType::F32,
+            |_| rand::random(),
+            |_| rand::random(),
+        )
+        .unwrap();
+        let pipeline = matmul_pipeline(
+            &gpu,
+            MatmulKernel::Naive,
+            &resources,
+            &workgroup_size,
+            ElementType::F32,
+        )
+        .await
+        .unwrap();
 
         let staging_buffer = gpu.create_buffer(&BufferDescriptor {
             label: Some("Staging Buffer"),
@@ -305,19 +730,21 @@ mod tests {
             warmup_count: 0,
             count: 1,
             workgroups_dispatch: workgroups_dispatch(
-                BENCHMARK_MATRIX_DIMS,
+                MatmulKernel::Naive,
+                MATRIX_DIMS,
                 workgroup_size,
             ),
             dispatch_callback: None,
             finalize_encoder_callback: Some(&|encoder| {
                 encoder.copy_buffer_to_buffer(
-                    &buffers.result_buffer,
+                    resources.buffer(0, 2).unwrap(),
                     0,
                     &staging_buffer,
                     0,
                     (MATRIX_SIZE * std::mem::size_of::<f32>()) as u64,
                 )
             }),
+            ..Default::default()
         }
         .run(pipeline)
         .await
@@ -0,0 +1,417 @@
+//! Microbenchmarks for reading a buffer in order versus in a random order
+
+use std::collections::HashMap;
+
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use uwgpu::{
+    wgpu::{
+        util::BufferInitDescriptor, BufferDescriptor, BufferUsages, Limits,
+        ShaderModuleDescriptor, ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
+    GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// 1MiB size buffer
+///
+/// 1 MiB = 2^20 = 1_048_576
+///
+/// 1MiB / 4bytes = 262_144
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 262_144,
+    warmup_count: 10000,
+    count: 100000,
+    element_type: ElementType::F32,
+};
+
+/// Order in which the elements of the buffer are read, each of them is a
+/// separate [BufferAccess] microbenchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AccessPattern {
+    /// Each invocation reads the element at its own index, so neighbouring
+    /// invocations read neighbouring elements.
+    Sequential,
+    /// Each invocation reads the element at its position in a random
+    /// permutation of the buffer, so neighbouring invocations read scattered
+    /// elements.
+    Shuffled,
+}
+
+impl AccessPattern {
+    /// Index of the element read by each invocation.
+    fn indices(&self, buffer_size: usize) -> Vec<u32> {
+        let mut indices: Vec<u32> = (0..buffer_size as u32).collect();
+        match self {
+            AccessPattern::Sequential => {}
+            AccessPattern::Shuffled => indices.shuffle(&mut thread_rng()),
+        }
+        indices
+    }
+}
+
+/// Microbenchmark for measuring the bandwidth of reading a buffer with the
+/// given access pattern, the [MicrobenchmarkParameters::size] is the amount of
+/// u32 elements read.
+///
+/// Each invocation reads its index from an index buffer and copies the
+/// element at it to a destination buffer, so both patterns move the same
+/// amount of memory and only differ in the order `source` is read in.
+///
+/// The workgroup size will dictate the size of workgroups used for the
+/// computation.
+pub async fn buffer_access_benchmark(
+    gpu: &GPUContext,
+    pattern: AccessPattern,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<BufferAccessResults, BenchmarkError> {
+    let buffers =
+        Buffers::new_with_random_inputs(pattern, parameters.size, gpu);
+    let pipeline =
+        buffer_access_pipeline(gpu, &buffers, workgroup_size).await?;
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: workgroups_dispatch(
+            parameters.size,
+            workgroup_size,
+        ),
+        dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(BufferAccessResults {
+        results,
+        parameters,
+        pattern,
+    })
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the buffer access benchmarks. See [buffer_access_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_eval]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct BufferAccessResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// Access pattern the benchmark ran with
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub pattern: AccessPattern,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl BufferAccessResults {
+    /// Get the total amount of time in seconds spent executing the
+    /// microbenchmark
+    pub fn total_time_s(&self) -> f64 {
+        self.results.total_time(TimeUnit::Second)
+    }
+
+    /// Get the amount of time per iteration in ms
+    pub fn time_per_iteration_ms(&self) -> f64 {
+        self.results.time_per_iteration(TimeUnit::Milli)
+    }
+
+    /// Get the Bandwidth of the reads in bytes per second, counting the
+    /// element and the index read by each invocation and the element it
+    /// writes.
+    pub fn bandwidth(&self) -> f64 {
+        ((self.parameters.size
+            * 3
+            * std::mem::size_of::<u32>()
+            * self.results.count) as f64)
+            / self.total_time_s()
+    }
+}
+
+/// Buffer reading microbenchmark with the given access pattern, see
+/// [buffer_access_benchmark].
+pub struct BufferAccess(pub AccessPattern);
+
+impl Microbenchmark for BufferAccess {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            AccessPattern::Sequential => "buffer_sequential",
+            AccessPattern::Shuffled => "buffer_shuffled",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self.0 {
+            AccessPattern::Sequential => "Sequential Buffer Reads",
+            AccessPattern::Shuffled => "Shuffled Buffer Reads",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.0 {
+            AccessPattern::Sequential => "This microbenchmark tests the bandwidth of reading a buffer in the GPU in order, with neighbouring invocations reading neighbouring elements so their reads can be coalesced. Compare it with the shuffled reads to see how much your GPU relies on coalescing.",
+            AccessPattern::Shuffled => "This microbenchmark tests the bandwidth of reading a buffer in the GPU in a random order, with neighbouring invocations reading scattered elements so their reads can't be coalesced. Compare it with the sequential reads to see how much your GPU relies on coalescing.",
+        }
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str {
+        "Amount of u32 elements to read"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        fits_limits(
+            limits,
+            workgroup_size,
+            || workgroups_dispatch(size, workgroup_size.0),
+            (size * size_of::<u32>()) as u64,
+        )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(buffer_access_benchmark(
+                gpu,
+                self.0,
+                workgroup_size.0,
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = BufferAccessResults {
+            results: results.clone(),
+            parameters: *parameters,
+            pattern: self.0,
+        };
+        vec![Metric::bandwidth(results.bandwidth())]
+    }
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    source_buffer: AsyncBuffer,
+    index_buffer: AsyncBuffer,
+    destination_buffer: AsyncBuffer,
+}
+
+impl Buffers {
+    fn new_with_random_inputs(
+        pattern: AccessPattern,
+        buffer_size: usize,
+        gpu: &GPUContext,
+    ) -> Self {
+        let mut source_buffer_data = vec![0_u32; buffer_size];
+
+        let mut rng = thread_rng();
+
+        rng.fill(source_buffer_data.as_mut_slice());
+
+        Self::new_from_source_data(
+            source_buffer_data.as_slice(),
+            &pattern.indices(buffer_size),
+            gpu,
+        )
+    }
+
+    fn new_from_source_data(
+        source_data: &[u32],
+        indices: &[u32],
+        gpu: &GPUContext,
+    ) -> Self {
+        let source_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Source Buffer"),
+            contents: bytemuck::cast_slice(source_data),
+            usage: BufferUsages::STORAGE,
+        });
+
+        let index_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: BufferUsages::STORAGE,
+        });
+
+        let destination_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Destination Buffer"),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            size: std::mem::size_of_val(source_data) as u64,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            source_buffer,
+            index_buffer,
+            destination_buffer,
+        }
+    }
+}
+
+/// Pipeline needed for microbenchmark.
+async fn buffer_access_pipeline<'a>(
+    gpu: &'a GPUContext,
+    buffers: &'a Buffers,
+    workgroup_size: u32,
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("buffer access shader"),
+            source: ShaderSource::Wgsl(
+                include_str!("buffer_access.wgsl").into(),
+            ),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([
+            (0, buffers.source_buffer.as_entire_binding()),
+            (1, buffers.index_buffer.as_entire_binding()),
+            (2, buffers.destination_buffer.as_entire_binding()),
+        ]),
+        gpu,
+        workgroup_size: Some((workgroup_size, 1, 1)),
+    })
+    .await
+}
+
+fn workgroups_dispatch(
+    buffer_size: usize,
+    workgroup_size: u32,
+) -> Vec<(u32, u32, u32)> {
+    vec![(buffer_size.div_ceil(workgroup_size as usize) as u32, 1, 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::{
+        wgpu::{BufferDescriptor, BufferUsages, MapMode},
+        Benchmark, GPUContext,
+    };
+
+    use super::*;
+
+    /// Verifies that the shuffled indices read every element once.
+    #[test]
+    fn shuffled_indices_are_a_permutation() {
+        let mut indices = AccessPattern::Shuffled.indices(1000);
+        assert_ne!(indices, AccessPattern::Sequential.indices(1000));
+
+        indices.sort_unstable();
+        assert_eq!(indices, AccessPattern::Sequential.indices(1000));
+    }
+
+    #[tokio::test]
+    async fn verify_buffer_access_works() {
+        const BUFFER_SIZE: usize = 262_144;
+
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        let mut source_buffer_data = vec![0_u32; BUFFER_SIZE];
+        let mut rng = thread_rng();
+        rng.fill(source_buffer_data.as_mut_slice());
+
+        for pattern in [AccessPattern::Sequential, AccessPattern::Shuffled] {
+            let indices = pattern.indices(BUFFER_SIZE);
+            let expected_result: Vec<u32> = indices
+                .iter()
+                .map(|&index| source_buffer_data[index as usize])
+                .collect();
+
+            let buffers = Buffers::new_from_source_data(
+                source_buffer_data.as_slice(),
+                &indices,
+                &gpu,
+            );
+
+            let workgroup_size = 64;
+            let pipeline =
+                buffer_access_pipeline(&gpu, &buffers, workgroup_size)
+                    .await
+                    .unwrap();
+
+            let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+                label: Some("Staging Buffer"),
+                size: (BUFFER_SIZE * std::mem::size_of::<u32>()) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let _results = Benchmark {
+                warmup_count: 0,
+                count: 1,
+                finalize_encoder_callback: Some(&|encoder| {
+                    encoder.copy_buffer_to_buffer(
+                        &buffers.destination_buffer,
+                        0,
+                        &staging_buffer,
+                        0,
+                        (BUFFER_SIZE * std::mem::size_of::<u32>()) as u64,
+                    )
+                }),
+                workgroups_dispatch: workgroups_dispatch(
+                    BUFFER_SIZE,
+                    workgroup_size,
+                ),
+                dispatch_callback: None,
+                ..Default::default()
+            }
+            .run(pipeline)
+            .await
+            .unwrap();
+
+            let staging_slice = staging_buffer.slice(..);
+            staging_slice.map_async(MapMode::Read).await.unwrap();
+
+            let result_data: Vec<u32> = {
+                let result_data_raw = staging_slice.get_mapped_range();
+                bytemuck::cast_slice(&result_data_raw).to_vec()
+            };
+
+            staging_buffer.unmap();
+
+            assert_eq!(result_data, expected_result, "{pattern:?}");
+        }
+    }
+}
@@ -0,0 +1,13 @@
+@group(0) @binding(0) var<storage, read> source: array<u32>;
+// Element of `source` read by each invocation, either in order or a random
+// permutation of them
+@group(0) @binding(1) var<storage, read> indices: array<u32>;
+@group(0) @binding(2) var<storage, read_write> destination: array<u32>;
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let index: u32 = global_id.x;
+    if index < arrayLength(&indices) {
+        destination[index] = source[indices[index]];
+    }
+}
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use rand::{thread_rng, Rng};
 use uwgpu::{
     wgpu::{
-        util::BufferInitDescriptor, BufferDescriptor, BufferUsages,
+        util::BufferInitDescriptor, BufferDescriptor, BufferUsages, Limits,
         ShaderModuleDescriptor, ShaderSource,
     },
     wgpu_async::AsyncBuffer,
@@ -13,44 +13,59 @@ use uwgpu::{
     GPUContext, PipelineParameters, TimeUnit,
 };
 
-use crate::BenchmarkError;
+use crate::{
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
 
 /// 1MiB size buffer
 ///
 /// 1 MiB = 2^20 = 1_048_576
 ///
 /// 1MiB / 4bytes = 262_144
-const BENCHMARK_BUFFER_SIZE: usize = 262_144;
-const BENCHMARK_WARMUP_COUNT: usize = 10000;
-const BENCHMARK_ITERATIONS: usize = 100000;
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 262_144,
+    warmup_count: 10000,
+    count: 100000,
+    element_type: ElementType::F32,
+};
 
 /// Microbenchmark for measuring the Buffer -> Buffer memory copy BW within the
-/// GPU
+/// GPU, the [MicrobenchmarkParameters::size] is the amount of u32 elements
+/// copied.
 ///
 /// The workgroup size will dictate the size of workgroups used for the
 /// computation.
 pub async fn buffer_to_buffer_benchmark(
+    gpu: &GPUContext,
     workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
 ) -> Result<BufferToBufferResults, BenchmarkError> {
-    let gpu = GPUContext::new(None).await?;
-    let buffers = Buffers::new_with_random_inputs(&gpu);
+    let buffers = Buffers::new_with_random_inputs(parameters.size, gpu);
     let pipeline =
-        buffer_to_buffer_pipeline(&gpu, &buffers, workgroup_size).await?;
+        buffer_to_buffer_pipeline(gpu, &buffers, workgroup_size).await?;
 
     let results = Benchmark {
-        warmup_count: BENCHMARK_WARMUP_COUNT,
-        count: BENCHMARK_ITERATIONS,
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
         finalize_encoder_callback: None,
         workgroups_dispatch: workgroups_dispatch(
-            BENCHMARK_BUFFER_SIZE,
+            parameters.size,
             workgroup_size,
         ),
         dispatch_callback: None,
+        ..Default::default()
     }
     .run(pipeline)
     .await?;
 
-    Ok(BufferToBufferResults(results))
+    Ok(BufferToBufferResults {
+        results,
+        parameters,
+    })
 }
 
 #[cfg(feature = "wasm")]
@@ -61,36 +76,108 @@ use wasm_bindgen::prelude::*;
 ///
 /// Wraps a [BenchmarkResults] with some convenience methods.
 #[cfg_eval]
-#[cfg_attr(
-    feature = "serde",
-    derive(serde::Serialize, serde::Deserialize),
-    serde(transparent)
-)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-pub struct BufferToBufferResults(
+pub struct BufferToBufferResults {
+    /// Timings of the benchmark
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-    pub  BenchmarkResults,
-);
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl BufferToBufferResults {
     /// Get the total amount of time in seconds spent executing the
     /// microbenchmark
-    pub fn total_time_s(&self) -> f64 { self.0.total_time(TimeUnit::Second) }
+    pub fn total_time_s(&self) -> f64 {
+        self.results.total_time(TimeUnit::Second)
+    }
 
     /// Get the amount of time per iteration in ms
     pub fn time_per_iteration_ms(&self) -> f64 {
-        self.0.time_per_iteration(TimeUnit::Milli)
+        self.results.time_per_iteration(TimeUnit::Milli)
     }
 
     /// Get the Bandwidth of memory copy in bytes per second
     pub fn bandwidth(&self) -> f64 {
-        ((BENCHMARK_BUFFER_SIZE * std::mem::size_of::<u32>() * self.0.count)
-            as f64)
+        ((self.parameters.size
+            * std::mem::size_of::<u32>()
+            * self.results.count) as f64)
             / self.total_time_s()
     }
 }
 
+/// Buffer to buffer copy microbenchmark, see [buffer_to_buffer_benchmark].
+pub struct BufferToBuffer;
+
+impl Microbenchmark for BufferToBuffer {
+    fn name(&self) -> &'static str { "buffer_to_buffer" }
+
+    fn title(&self) -> &'static str { "Memory Copy From Buffer To Buffer" }
+
+    fn description(&self) -> &'static str {
+        "This microbenchmark tests the performance of copying memory between buffers in the GPU. It's a naive un-optimized implementation so it won't reflect the true bandwidth of your GPU."
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str {
+        "Amount of u32 elements to copy"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        // The destination buffer is twice as big as the source one
+        fits_limits(
+            limits,
+            workgroup_size,
+            || workgroups_dispatch(size, workgroup_size.0),
+            (size * 2 * size_of::<u32>()) as u64,
+        )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(
+                buffer_to_buffer_benchmark(gpu, workgroup_size.0, parameters)
+                    .await?
+                    .results,
+            )
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = BufferToBufferResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::bandwidth(results.bandwidth())]
+    }
+}
+
 /// GPU buffers needed for microbenchmark
 struct Buffers {
     source_buffer: AsyncBuffer,
@@ -98,9 +185,8 @@ struct Buffers {
 }
 
 impl Buffers {
-    fn new_with_random_inputs(gpu: &GPUContext) -> Self {
-        let mut source_buffer_data: Box<[u32; BENCHMARK_BUFFER_SIZE]> =
-            Box::new([0_u32; 262_144]);
+    fn new_with_random_inputs(buffer_size: usize, gpu: &GPUContext) -> Self {
+        let mut source_buffer_data = vec![0_u32; buffer_size];
 
         let mut rng = thread_rng();
 
@@ -119,7 +205,7 @@ impl Buffers {
         let destination_buffer = gpu.create_buffer(&BufferDescriptor {
             label: Some("Destination Buffer"),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
-            size: BENCHMARK_BUFFER_SIZE as u64 * 8,
+            size: source_data.len() as u64 * 8,
             mapped_at_creation: false,
         });
 
@@ -174,10 +260,11 @@ mod tests {
 
     #[tokio::test]
     async fn verify_buffer_copy_works() {
+        const BUFFER_SIZE: usize = 262_144;
+
         let gpu = GPUContext::new(None).await.unwrap();
 
-        let mut source_buffer_data: Box<[u32; BENCHMARK_BUFFER_SIZE]> =
-            Box::new([0_u32; 262_144]);
+        let mut source_buffer_data = vec![0_u32; BUFFER_SIZE];
         let mut rng = thread_rng();
         rng.fill(source_buffer_data.as_mut_slice());
 
@@ -194,7 +281,7 @@ mod tests {
 
         let staging_buffer = gpu.create_buffer(&BufferDescriptor {
             label: Some("Staging Buffer"),
-            size: (BENCHMARK_BUFFER_SIZE * std::mem::size_of::<u32>()) as u64,
+            size: (BUFFER_SIZE * std::mem::size_of::<u32>()) as u64,
             usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -208,14 +295,15 @@ mod tests {
                     0,
                     &staging_buffer,
                     0,
-                    2u64.pow(20),
+                    (BUFFER_SIZE * std::mem::size_of::<u32>()) as u64,
                 )
             }),
             workgroups_dispatch: workgroups_dispatch(
-                BENCHMARK_BUFFER_SIZE,
+                BUFFER_SIZE,
                 workgroup_size,
             ),
             dispatch_callback: None,
+            ..Default::default()
         }
         .run(pipeline)
         .await
@@ -231,6 +319,6 @@ mod tests {
 
         staging_buffer.unmap();
 
-        assert_eq!(&result_data, &*expected_result);
+        assert_eq!(result_data, expected_result);
     }
 }
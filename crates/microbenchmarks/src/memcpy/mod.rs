@@ -1,5 +1,8 @@
 //! Microbenchmarks having to do with memory throughput
 
+pub mod buffer_access;
 pub mod buffer_to_buffer;
 pub mod buffer_to_texture;
+pub mod strided_copy;
+pub mod texture_3d_to_texture_3d;
 pub mod texture_to_texture;
@@ -0,0 +1,459 @@
+//! Microbenchmarks for the buffer to buffer copy throughput with strided and
+//! misaligned accesses, which can't be coalesced as well as the contiguous
+//! ones of [buffer_to_buffer](super::buffer_to_buffer)
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+use uwgpu::{
+    wgpu::{
+        util::BufferInitDescriptor, BufferDescriptor, BufferUsages, Limits,
+        ShaderModuleDescriptor, ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
+    GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// Amount of u32 elements copied, the default size of
+/// [buffer_to_buffer](super::buffer_to_buffer) so that copying with a stride
+/// of 1 is comparable to it
+///
+/// 1MiB / 4bytes = 262_144
+const COPY_ELEMENTS: u32 = 262_144;
+
+/// Stride of 16 elements, 64 bytes
+const STRIDED_DEFAULT_PARAMETERS: MicrobenchmarkParameters =
+    MicrobenchmarkParameters {
+        size: 16,
+        warmup_count: 1000,
+        count: 10000,
+        element_type: ElementType::F32,
+    };
+
+/// Offset of 1 element, 4 bytes
+const MISALIGNED_DEFAULT_PARAMETERS: MicrobenchmarkParameters =
+    MicrobenchmarkParameters {
+        size: 1,
+        warmup_count: 1000,
+        count: 10000,
+        element_type: ElementType::F32,
+    };
+
+/// What the [MicrobenchmarkParameters::size] of a [StridedCopy] changes about
+/// the accesses, each of them is a separate microbenchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StridedAccess {
+    /// The size is the stride in elements between the accesses of
+    /// neighbouring invocations, a power of 2.
+    Strided,
+    /// The size is the offset in elements of the accesses from the start of
+    /// the buffers, which are contiguous but not aligned to the segments
+    /// memory is accessed in.
+    Misaligned,
+}
+
+impl StridedAccess {
+    /// Stride and offset of the accesses for the given size.
+    fn stride_and_offset(&self, size: usize) -> (u32, u32) {
+        match self {
+            StridedAccess::Strided => (size as u32, 0),
+            StridedAccess::Misaligned => (1, size as u32),
+        }
+    }
+}
+
+/// Microbenchmark for measuring the Buffer -> Buffer memory copy BW within the
+/// GPU with the accesses of neighbouring invocations being strided or
+/// misaligned, the [MicrobenchmarkParameters::size] is either the stride or
+/// the offset, see [StridedAccess].
+///
+/// The same amount of elements is always copied, each of them once, so only
+/// the access pattern changes the bandwidth.
+///
+/// The workgroup size will dictate the size of workgroups used for the
+/// computation.
+///
+/// Fails with [BenchmarkError::InvalidStride] if the stride is not a power of
+/// 2 or is larger than the amount of elements copied, since the strided
+/// accesses wouldn't visit every element once.
+pub async fn strided_copy_benchmark(
+    gpu: &GPUContext,
+    access: StridedAccess,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<StridedCopyResults, BenchmarkError> {
+    let (stride, offset) = access.stride_and_offset(parameters.size);
+    if !(stride.is_power_of_two() && stride <= COPY_ELEMENTS) {
+        return Err(BenchmarkError::InvalidStride {
+            stride,
+            elements: COPY_ELEMENTS,
+        });
+    }
+
+    let buffers = Buffers::new_with_random_inputs(stride, offset, gpu);
+    let pipeline = strided_copy_pipeline(gpu, &buffers, workgroup_size).await?;
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: workgroups_dispatch(workgroup_size),
+        dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(StridedCopyResults {
+        results,
+        parameters,
+        access,
+    })
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the strided and misaligned copy benchmarks. See
+/// [strided_copy_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_eval]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct StridedCopyResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// What the size of the parameters meant
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub access: StridedAccess,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl StridedCopyResults {
+    /// Get the total amount of time in seconds spent executing the
+    /// microbenchmark
+    pub fn total_time_s(&self) -> f64 {
+        self.results.total_time(TimeUnit::Second)
+    }
+
+    /// Get the amount of time per iteration in ms
+    pub fn time_per_iteration_ms(&self) -> f64 {
+        self.results.time_per_iteration(TimeUnit::Milli)
+    }
+
+    /// Get the effective Bandwidth of memory copy in bytes per second, only
+    /// counting the bytes copied and not the rest of the memory segments
+    /// accessed
+    pub fn bandwidth(&self) -> f64 {
+        ((COPY_ELEMENTS as usize
+            * std::mem::size_of::<u32>()
+            * self.results.count) as f64)
+            / self.total_time_s()
+    }
+}
+
+/// Strided or misaligned copy microbenchmark, see [strided_copy_benchmark].
+pub struct StridedCopy(pub StridedAccess);
+
+impl Microbenchmark for StridedCopy {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            StridedAccess::Strided => "strided_copy",
+            StridedAccess::Misaligned => "misaligned_copy",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self.0 {
+            StridedAccess::Strided => "Strided Memory Copy",
+            StridedAccess::Misaligned => "Misaligned Memory Copy",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.0 {
+            StridedAccess::Strided => "This microbenchmark tests the effective bandwidth of copying memory between buffers in the GPU when neighbouring invocations access elements a stride apart, from 1 to 1024 elements. The larger the stride the less the accesses can be coalesced, showing how much your GPU punishes uncoalesced accesses.",
+            StridedAccess::Misaligned => "This microbenchmark tests the effective bandwidth of copying memory between buffers in the GPU when the accesses are contiguous but shifted by a few elements, so they aren't aligned to the segments memory is accessed in.",
+        }
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str {
+        match self.0 {
+            StridedAccess::Strided => {
+                "Stride in u32 elements between the accesses of neighbouring \
+                 invocations, a power of 2"
+            }
+            StridedAccess::Misaligned => {
+                "Offset in u32 elements of the accesses from the start of the \
+                 buffers"
+            }
+        }
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        match self.0 {
+            StridedAccess::Strided => STRIDED_DEFAULT_PARAMETERS,
+            StridedAccess::Misaligned => MISALIGNED_DEFAULT_PARAMETERS,
+        }
+    }
+
+    fn default_sizes(&self) -> Vec<usize> {
+        match self.0 {
+            // 1, 2, 4, ... 1024
+            StridedAccess::Strided => {
+                (0..=10).map(|power| 1 << power).collect()
+            }
+            // 4 to 128 bytes
+            StridedAccess::Misaligned => vec![1, 2, 4, 8, 16, 32],
+        }
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let (stride, offset) = self.0.stride_and_offset(parameters.size);
+        // See [BenchmarkError::InvalidStride]
+        stride.is_power_of_two()
+            && stride <= COPY_ELEMENTS
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroups_dispatch(workgroup_size.0),
+                ((COPY_ELEMENTS as usize + offset as usize) * size_of::<u32>())
+                    as u64,
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(strided_copy_benchmark(
+                gpu,
+                self.0,
+                workgroup_size.0,
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = StridedCopyResults {
+            results: results.clone(),
+            parameters: *parameters,
+            access: self.0,
+        };
+        vec![Metric::bandwidth(results.bandwidth())]
+    }
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    source_buffer: AsyncBuffer,
+    destination_buffer: AsyncBuffer,
+    access_buffer: AsyncBuffer,
+}
+
+impl Buffers {
+    fn new_with_random_inputs(
+        stride: u32,
+        offset: u32,
+        gpu: &GPUContext,
+    ) -> Self {
+        let mut source_buffer_data =
+            vec![0_u32; (COPY_ELEMENTS + offset) as usize];
+
+        let mut rng = thread_rng();
+
+        rng.fill(source_buffer_data.as_mut_slice());
+
+        Self::new_from_source_data(
+            source_buffer_data.as_slice(),
+            stride,
+            offset,
+            gpu,
+        )
+    }
+
+    /// The source data must have [COPY_ELEMENTS] elements after the offset.
+    fn new_from_source_data(
+        source_data: &[u32],
+        stride: u32,
+        offset: u32,
+        gpu: &GPUContext,
+    ) -> Self {
+        let source_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Source Buffer"),
+            contents: bytemuck::cast_slice(source_data),
+            usage: BufferUsages::STORAGE,
+        });
+
+        let destination_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Destination Buffer"),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            size: std::mem::size_of_val(source_data) as u64,
+            mapped_at_creation: false,
+        });
+
+        let access_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Access Buffer"),
+            contents: bytemuck::cast_slice(&[stride, offset, COPY_ELEMENTS]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        Self {
+            source_buffer,
+            destination_buffer,
+            access_buffer,
+        }
+    }
+}
+
+/// Pipeline needed for microbenchmark.
+async fn strided_copy_pipeline<'a>(
+    gpu: &'a GPUContext,
+    buffers: &'a Buffers,
+    workgroup_size: u32,
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("strided copy shader"),
+            source: ShaderSource::Wgsl(
+                include_str!("strided_copy.wgsl").into(),
+            ),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([
+            (0, buffers.source_buffer.as_entire_binding()),
+            (1, buffers.destination_buffer.as_entire_binding()),
+            (2, buffers.access_buffer.as_entire_binding()),
+        ]),
+        gpu,
+        workgroup_size: Some((workgroup_size, 1, 1)),
+    })
+    .await
+}
+
+fn workgroups_dispatch(workgroup_size: u32) -> Vec<(u32, u32, u32)> {
+    vec![(COPY_ELEMENTS.div_ceil(workgroup_size), 1, 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::{
+        wgpu::{BufferDescriptor, BufferUsages, MapMode},
+        Benchmark, GPUContext,
+    };
+
+    use super::*;
+
+    /// Verifies that every element after the offset gets copied, whatever
+    /// the stride.
+    #[tokio::test]
+    async fn verify_strided_copy_works() {
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        for (access, size) in [
+            (StridedAccess::Strided, 1),
+            (StridedAccess::Strided, 32),
+            (StridedAccess::Strided, 1024),
+            (StridedAccess::Strided, COPY_ELEMENTS as usize),
+            (StridedAccess::Misaligned, 3),
+        ] {
+            let (stride, offset) = access.stride_and_offset(size);
+            let buffer_size = (COPY_ELEMENTS + offset) as usize;
+
+            let mut source_buffer_data = vec![0_u32; buffer_size];
+            let mut rng = thread_rng();
+            rng.fill(source_buffer_data.as_mut_slice());
+
+            let buffers = Buffers::new_from_source_data(
+                source_buffer_data.as_slice(),
+                stride,
+                offset,
+                &gpu,
+            );
+
+            let workgroup_size = 64;
+            let pipeline =
+                strided_copy_pipeline(&gpu, &buffers, workgroup_size)
+                    .await
+                    .unwrap();
+
+            let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+                label: Some("Staging Buffer"),
+                size: (buffer_size * std::mem::size_of::<u32>()) as u64,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let _results = Benchmark {
+                warmup_count: 0,
+                count: 1,
+                finalize_encoder_callback: Some(&|encoder| {
+                    encoder.copy_buffer_to_buffer(
+                        &buffers.destination_buffer,
+                        0,
+                        &staging_buffer,
+                        0,
+                        (buffer_size * std::mem::size_of::<u32>()) as u64,
+                    )
+                }),
+                workgroups_dispatch: workgroups_dispatch(workgroup_size),
+                dispatch_callback: None,
+                ..Default::default()
+            }
+            .run(pipeline)
+            .await
+            .unwrap();
+
+            let staging_slice = staging_buffer.slice(..);
+            staging_slice.map_async(MapMode::Read).await.unwrap();
+
+            let result_data: Vec<u32> = {
+                let result_data_raw = staging_slice.get_mapped_range();
+                bytemuck::cast_slice(&result_data_raw).to_vec()
+            };
+
+            staging_buffer.unmap();
+
+            assert_eq!(
+                result_data[offset as usize..],
+                source_buffer_data[offset as usize..],
+                "{access:?} {size}"
+            );
+        }
+    }
+}
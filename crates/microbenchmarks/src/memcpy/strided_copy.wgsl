@@ -0,0 +1,29 @@
+struct Access {
+    // Elements between the accesses of neighbouring invocations
+    stride: u32,
+    // Elements the accesses are shifted by from the start of the buffers
+    offset: u32,
+    // Amount of elements copied, a multiple of the stride
+    len: u32,
+}
+
+@group(0) @binding(0) var<storage, read> copy_source: array<u32>;
+@group(0) @binding(1) var<storage, read_write> copy_destination: array<u32>;
+@group(0) @binding(2) var<uniform> access: Access;
+
+// Every element is copied once: invocation i copies element i * stride, which
+// wraps around to the next element of the first stride once past the end, so
+// neighbouring invocations are always `stride` elements apart. It's computed
+// from the amount of strides that fit in the buffer since `i * stride` would
+// overflow for large strides
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= access.len {
+        return;
+    }
+
+    let strides = access.len / access.stride;
+    let index = (global_id.x % strides) * access.stride + global_id.x / strides
+        + access.offset;
+    copy_destination[index] = copy_source[index];
+}
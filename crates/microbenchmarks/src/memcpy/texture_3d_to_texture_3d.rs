@@ -0,0 +1,378 @@
+//! Microbenchmark for 3D texture to 3D texture copy throughput
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+use uwgpu::{
+    wgpu::{
+        BindingResource, Extent3d, Limits, ShaderModuleDescriptor,
+        ShaderSource, Texture, TextureDescriptor, TextureDimension,
+        TextureFormat, TextureUsages, TextureViewDescriptor,
+    },
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
+    GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    microbenchmark::{
+        default_workgroups_3d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// 256x256x256 texture
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 256,
+    warmup_count: 100,
+    count: 1000,
+    element_type: ElementType::F32,
+};
+
+/// Microbenchmark for measuring the 3D Texture -> 3D Texture memory copy BW
+/// within the GPU, the [MicrobenchmarkParameters::size] is the width, height
+/// and depth of the textures.
+pub async fn texture_3d_to_texture_3d_benchmark(
+    gpu: &GPUContext,
+    workgroup_size: (u32, u32, u32),
+    parameters: MicrobenchmarkParameters,
+) -> Result<Texture3dToTexture3dResults, BenchmarkError> {
+    let textures = Bindings::new_with_random_inputs(parameters.size, gpu);
+    let pipeline =
+        texture_3d_to_texture_3d_pipeline(gpu, &textures, workgroup_size)
+            .await?;
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: workgroups_dispatch(
+            parameters.size,
+            workgroup_size,
+        ),
+        dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(Texture3dToTexture3dResults {
+        results,
+        parameters,
+    })
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the memcpy 3D texture->3D texture benchmark. See
+/// [texture_3d_to_texture_3d_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_eval]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Texture3dToTexture3dResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Texture3dToTexture3dResults {
+    /// Get the Bandwidth of memory copy in bytes per second
+    pub fn bandwidth(&self) -> f64 {
+        let memory_size = self.parameters.size.pow(3);
+
+        ((memory_size * std::mem::size_of::<u32>() * self.results.count) as f64)
+            / self.results.total_time(TimeUnit::Second)
+    }
+}
+
+/// 3D texture to 3D texture copy microbenchmark, see
+/// [texture_3d_to_texture_3d_benchmark].
+pub struct Texture3dToTexture3d;
+
+impl Microbenchmark for Texture3dToTexture3d {
+    fn name(&self) -> &'static str { "texture_3d_to_texture_3d" }
+
+    fn title(&self) -> &'static str {
+        "Memory Copy From 3D Texture To 3D Texture"
+    }
+
+    fn description(&self) -> &'static str {
+        "This microbenchmark tests the performance of copying memory from a 3D texture to a 3D storage texture in the GPU, using 3D workgroups. It's a naive un-optimized implementation so it won't reflect the true bandwidth of your GPU."
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 3 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_3d()
+    }
+
+    fn size_description(&self) -> &'static str {
+        "Width, height and depth of the textures"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        size <= limits.max_texture_dimension_3d as usize
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroups_dispatch(size, workgroup_size),
+                0,
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(texture_3d_to_texture_3d_benchmark(
+                gpu,
+                workgroup_size,
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = Texture3dToTexture3dResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::bandwidth(results.bandwidth())]
+    }
+}
+
+/// GPU binding needed for microbenchmark
+///
+/// The textures are cubic, with the cubic root of the amount of elements of
+/// the source data as their width, height and depth.
+struct Bindings {
+    source_texture: Texture,
+    destination_texture: Texture,
+}
+
+impl Bindings {
+    fn new_with_random_inputs(texture_dims: usize, gpu: &GPUContext) -> Self {
+        let mut source_texture_data = vec![0u32; texture_dims.pow(3)];
+
+        thread_rng().fill(source_texture_data.as_mut_slice());
+
+        Self::new_from_source_data(source_texture_data.as_slice(), gpu)
+    }
+
+    fn new_from_source_data(source_data: &[u32], gpu: &GPUContext) -> Self {
+        let texture_dims = (source_data.len() as f64).cbrt().round() as usize;
+        assert_eq!(source_data.len(), texture_dims.pow(3));
+
+        let size = Extent3d {
+            width: texture_dims as u32,
+            height: texture_dims as u32,
+            depth_or_array_layers: texture_dims as u32,
+        };
+
+        let source_texture = gpu.create_texture_with_data(
+            &TextureDescriptor {
+                label: Some("Source Texture"),
+                size,
+                format: TextureFormat::Rgba8Uint,
+                usage: TextureUsages::TEXTURE_BINDING,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D3,
+                view_formats: &[],
+            },
+            Default::default(),
+            bytemuck::cast_slice(source_data),
+        );
+
+        let destination_texture = gpu.create_texture(&TextureDescriptor {
+            label: Some("Destination Texture"),
+            size,
+            format: TextureFormat::Rgba8Uint,
+            usage: TextureUsages::COPY_SRC
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::STORAGE_BINDING,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D3,
+            view_formats: &[],
+        });
+
+        Self {
+            source_texture,
+            destination_texture,
+        }
+    }
+}
+
+/// Pipeline needed for microbenchmark.
+async fn texture_3d_to_texture_3d_pipeline<'a>(
+    gpu: &'a GPUContext,
+    textures: &'a Bindings,
+    workgroup_size: (u32, u32, u32),
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("3D texture to 3D texture copy shader"),
+            source: ShaderSource::Wgsl(
+                include_str!("texture_3d_to_texture_3d.wgsl").into(),
+            ),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([
+            (
+                0,
+                BindingResource::TextureView(
+                    &textures
+                        .source_texture
+                        .create_view(&TextureViewDescriptor::default()),
+                ),
+            ),
+            (
+                1,
+                BindingResource::TextureView(
+                    &textures
+                        .destination_texture
+                        .create_view(&TextureViewDescriptor::default()),
+                ),
+            ),
+        ]),
+        gpu,
+
+        workgroup_size: Some(workgroup_size),
+    })
+    .await
+}
+
+fn workgroups_dispatch(
+    texture_dims: usize,
+    workgroup_size: (u32, u32, u32),
+) -> Vec<(u32, u32, u32)> {
+    vec![(
+        1 + (texture_dims / (workgroup_size.0 as usize)) as u32,
+        1 + (texture_dims / (workgroup_size.1 as usize)) as u32,
+        1 + (texture_dims / (workgroup_size.2 as usize)) as u32,
+    )]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use uwgpu::{
+        wgpu::{
+            BufferDescriptor, BufferUsages, ImageCopyBuffer, ImageDataLayout,
+            MapMode,
+        },
+        Benchmark, GPUContext,
+    };
+
+    use super::*;
+
+    /// Verifies that every layer of the 3D texture gets copied.
+    #[tokio::test]
+    async fn verify_texture_3d_to_texture_3d_copy_works() {
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        const TEXTURE_DIMS: usize = 10;
+        const MEMORY_SIZE: usize = TEXTURE_DIMS * TEXTURE_DIMS * TEXTURE_DIMS;
+
+        let source_data: Vec<u32> =
+            (0..MEMORY_SIZE).map(|i| i as u32).collect();
+
+        let expected_result = source_data.clone();
+
+        let textures = Bindings::new_from_source_data(&source_data, &gpu);
+
+        let workgroup_size = (4, 4, 4);
+        let pipeline =
+            texture_3d_to_texture_3d_pipeline(&gpu, &textures, workgroup_size)
+                .await
+                .unwrap();
+
+        // rows have to be padded to multiples of 256 bytes
+        let row_len = TEXTURE_DIMS.next_multiple_of(64);
+        let staging_buffer_size = row_len * TEXTURE_DIMS * TEXTURE_DIMS;
+        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Staging Buffer"),
+            size: (staging_buffer_size * std::mem::size_of::<u32>()) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let _results = Benchmark {
+            warmup_count: 0,
+            count: 1,
+            finalize_encoder_callback: Some(&|encoder| {
+                encoder.copy_texture_to_buffer(
+                    textures.destination_texture.as_image_copy(),
+                    ImageCopyBuffer {
+                        buffer: &staging_buffer,
+                        layout: ImageDataLayout {
+                            bytes_per_row: Some(
+                                (row_len * std::mem::size_of::<u32>()) as u32,
+                            ),
+                            rows_per_image: Some(TEXTURE_DIMS as u32),
+                            ..Default::default()
+                        },
+                    },
+                    Extent3d {
+                        width: TEXTURE_DIMS as u32,
+                        height: TEXTURE_DIMS as u32,
+                        depth_or_array_layers: TEXTURE_DIMS as u32,
+                    },
+                )
+            }),
+            workgroups_dispatch: workgroups_dispatch(
+                TEXTURE_DIMS,
+                workgroup_size,
+            ),
+            dispatch_callback: None,
+            ..Default::default()
+        }
+        .run(pipeline)
+        .await
+        .unwrap();
+
+        let staging_slice = staging_buffer.slice(..);
+        staging_slice.map_async(MapMode::Read).await.unwrap();
+
+        let result_data: Vec<u32> = {
+            let result_data_raw: &[u8] = &staging_slice.get_mapped_range();
+            bytemuck::cast_slice(result_data_raw).to_vec()
+        };
+
+        staging_buffer.unmap();
+
+        // drop the padding at the end of each row
+        let result_data: Vec<u32> = result_data
+            .chunks(row_len)
+            .flat_map(|row| &row[..TEXTURE_DIMS])
+            .copied()
+            .collect();
+
+        assert_eq!(&result_data, &expected_result);
+    }
+}
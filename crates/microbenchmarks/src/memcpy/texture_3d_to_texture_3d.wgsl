@@ -0,0 +1,20 @@
+@group(0) @binding(0) var copy_source: texture_3d<u32>;
+@group(0) @binding(1) var copy_destination: texture_storage_3d<rgba8uint, write>;
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    let y = global_id.y;
+    let z = global_id.z;
+
+    let dims = textureDimensions(copy_destination);
+
+    if x < dims.x && y < dims.y && z < dims.z {
+
+        let pos = vec3<u32>(x, y, z);
+
+        let texel = textureLoad(copy_source, pos, 0);
+
+        textureStore(copy_destination, pos, texel);
+    }
+}
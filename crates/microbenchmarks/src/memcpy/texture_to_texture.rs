@@ -5,46 +5,59 @@ use std::collections::HashMap;
 use rand::{thread_rng, Rng};
 use uwgpu::{
     wgpu::{
-        BindingResource, Extent3d, ShaderModuleDescriptor, ShaderSource,
-        Texture, TextureDescriptor, TextureDimension, TextureFormat,
-        TextureUsages, TextureViewDescriptor,
+        BindingResource, Extent3d, Limits, ShaderModuleDescriptor,
+        ShaderSource, Texture, TextureDescriptor, TextureDimension,
+        TextureFormat, TextureUsages, TextureViewDescriptor,
     },
     Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
     GPUContext, PipelineParameters, TimeUnit,
 };
 
-use crate::BenchmarkError;
+use crate::{
+    microbenchmark::{
+        default_workgroups_texture, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
 
-const BENCHMARK_TEXTURE_DIMS: usize = 1024;
-const BENCHMARK_MEMORY_SIZE: usize =
-    BENCHMARK_TEXTURE_DIMS * BENCHMARK_TEXTURE_DIMS;
-const BENCHMARK_WARMUP_COUNT: usize = 1000;
-const BENCHMARK_ITERATIONS: usize = 10000;
+/// 1024x1024 texture
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 1024,
+    warmup_count: 1000,
+    count: 10000,
+    element_type: ElementType::F32,
+};
 
 /// Microbenchmark for measuring the Texture -> Texture memory copy BW within
-/// the GPU
+/// the GPU, the [MicrobenchmarkParameters::size] is the width and height of
+/// the textures.
 pub async fn texture_to_texture_benchmark(
+    gpu: &GPUContext,
     workgroup_size: (u32, u32),
+    parameters: MicrobenchmarkParameters,
 ) -> Result<TextureToTextureResults, BenchmarkError> {
-    let gpu = GPUContext::new(None).await?;
-    let textures =
-        Bindings::<BENCHMARK_TEXTURE_DIMS>::new_with_random_inputs(&gpu);
+    let textures = Bindings::new_with_random_inputs(parameters.size, gpu);
     let pipeline =
-        texture_to_texture_pipeline(&gpu, &textures, workgroup_size).await?;
+        texture_to_texture_pipeline(gpu, &textures, workgroup_size).await?;
     let results = Benchmark {
-        warmup_count: BENCHMARK_WARMUP_COUNT,
-        count: BENCHMARK_ITERATIONS,
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
         finalize_encoder_callback: None,
         workgroups_dispatch: workgroups_dispatch(
-            BENCHMARK_TEXTURE_DIMS,
+            parameters.size,
             workgroup_size,
         ),
         dispatch_callback: None,
+        ..Default::default()
     }
     .run(pipeline)
     .await?;
 
-    Ok(TextureToTextureResults(results))
+    Ok(TextureToTextureResults {
+        results,
+        parameters,
+    })
 }
 
 #[cfg(feature = "wasm")]
@@ -55,38 +68,116 @@ use wasm_bindgen::prelude::*;
 ///
 /// Wraps a [BenchmarkResults] with some convenience methods.
 #[cfg_eval]
-#[cfg_attr(
-    feature = "serde",
-    derive(serde::Serialize, serde::Deserialize),
-    serde(transparent)
-)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-pub struct TextureToTextureResults(
+pub struct TextureToTextureResults {
+    /// Timings of the benchmark
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-    pub  BenchmarkResults,
-);
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl TextureToTextureResults {
     /// Get the Bandwidth of memory copy in bytes per second
     pub fn bandwidth(&self) -> f64 {
-        ((BENCHMARK_MEMORY_SIZE * std::mem::size_of::<u32>() * self.0.count)
-            as f64)
-            / self.0.total_time(TimeUnit::Second)
+        let memory_size = self.parameters.size * self.parameters.size;
+
+        ((memory_size * std::mem::size_of::<u32>() * self.results.count) as f64)
+            / self.results.total_time(TimeUnit::Second)
+    }
+}
+
+/// Texture to texture copy microbenchmark, see [texture_to_texture_benchmark].
+pub struct TextureToTexture;
+
+impl Microbenchmark for TextureToTexture {
+    fn name(&self) -> &'static str { "texture_to_texture" }
+
+    fn title(&self) -> &'static str { "Memory Copy From Texture To Texture" }
+
+    fn description(&self) -> &'static str {
+        "This microbenchmark tests the performance of copying memory from a texture to a storage texture in the GPU. It's a naive un-optimized implementation so it won't reflect the true bandwidth of your GPU."
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 2 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_texture()
+    }
+
+    fn size_description(&self) -> &'static str {
+        "Width and height of the textures"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        size <= limits.max_texture_dimension_2d as usize
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || {
+                    workgroups_dispatch(
+                        size,
+                        (workgroup_size.0, workgroup_size.1),
+                    )
+                },
+                0,
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(texture_to_texture_benchmark(
+                gpu,
+                (workgroup_size.0, workgroup_size.1),
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = TextureToTextureResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::bandwidth(results.bandwidth())]
     }
 }
 
 /// GPU binding needed for microbenchmark
-struct Bindings<const TEXTURE_DIMS: usize> {
+///
+/// The textures are square, with the square root of the amount of elements of
+/// the source data as their width and height.
+struct Bindings {
     source_texture: Texture,
     destination_texture: Texture,
 }
 
-impl<const TEXTURE_DIMS: usize> Bindings<TEXTURE_DIMS> {
-    const MEM_SIZE: usize = TEXTURE_DIMS * TEXTURE_DIMS;
-
-    fn new_with_random_inputs(gpu: &GPUContext) -> Self {
-        let mut source_texture_data = vec![0u32; Self::MEM_SIZE];
+impl Bindings {
+    fn new_with_random_inputs(texture_dims: usize, gpu: &GPUContext) -> Self {
+        let mut source_texture_data = vec![0u32; texture_dims * texture_dims];
 
         let mut rng = thread_rng();
 
@@ -96,14 +187,15 @@ impl<const TEXTURE_DIMS: usize> Bindings<TEXTURE_DIMS> {
     }
 
     fn new_from_source_data(source_data: &[u32], gpu: &GPUContext) -> Self {
-        assert_eq!(source_data.len(), Self::MEM_SIZE);
+        let texture_dims = source_data.len().isqrt();
+        assert_eq!(source_data.len(), texture_dims * texture_dims);
 
         let source_texture = gpu.create_texture_with_data(
             &TextureDescriptor {
                 label: Some("Source Texture"),
                 size: Extent3d {
-                    width: TEXTURE_DIMS as u32,
-                    height: TEXTURE_DIMS as u32,
+                    width: texture_dims as u32,
+                    height: texture_dims as u32,
                     depth_or_array_layers: 1,
                 },
                 format: TextureFormat::Rgba8Uint,
@@ -120,8 +212,8 @@ impl<const TEXTURE_DIMS: usize> Bindings<TEXTURE_DIMS> {
         let destination_texture = gpu.create_texture(&TextureDescriptor {
             label: Some("Destination Texture"),
             size: Extent3d {
-                width: TEXTURE_DIMS as u32,
-                height: TEXTURE_DIMS as u32,
+                width: texture_dims as u32,
+                height: texture_dims as u32,
                 depth_or_array_layers: 1,
             },
             format: TextureFormat::Rgba8Uint,
@@ -142,9 +234,9 @@ impl<const TEXTURE_DIMS: usize> Bindings<TEXTURE_DIMS> {
 }
 
 /// Pipeline needed for microbenchmark.
-async fn texture_to_texture_pipeline<'a, const TEXTURE_DIMS: usize>(
+async fn texture_to_texture_pipeline<'a>(
     gpu: &'a GPUContext,
-    textures: &'a Bindings<TEXTURE_DIMS>,
+    textures: &'a Bindings,
     workgroup_size: (u32, u32),
 ) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
     BenchmarkComputePipeline::new(PipelineParameters {
@@ -216,10 +308,7 @@ mod tests {
 
         let expected_result = source_buffer_data.clone();
 
-        let buffers = Bindings::<TEXTURE_DIMS>::new_from_source_data(
-            &source_buffer_data,
-            &gpu,
-        );
+        let buffers = Bindings::new_from_source_data(&source_buffer_data, &gpu);
 
         let workgroup_size = (8, 8);
         let pipeline =
@@ -263,6 +352,7 @@ mod tests {
                 workgroup_size,
             ),
             dispatch_callback: None,
+            ..Default::default()
         }
         .run(pipeline)
         .await
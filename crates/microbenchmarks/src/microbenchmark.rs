@@ -0,0 +1,532 @@
+//! Common interface implemented by every microbenchmark, see [Microbenchmark],
+//! and the registry of all of them, see [microbenchmarks]
+
+use std::{future::Future, pin::Pin};
+
+use uwgpu::{
+    wgpu::{Features, Limits},
+    BenchmarkResults, GPUContext,
+};
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    atomic_throughput::{AtomicMemory, AtomicOperation, AtomicThroughput},
+    convolution::Convolution,
+    fma_throughput::FmaThroughput,
+    histogram::{
+        Histogram, HistogramData, HistogramDistribution, HistogramStrategy,
+    },
+    matmul::{Matmul, MatmulKernel},
+    memcpy::{
+        buffer_access::{AccessPattern, BufferAccess},
+        buffer_to_buffer::BufferToBuffer,
+        buffer_to_texture::BufferToTexture,
+        strided_copy::{StridedAccess, StridedCopy},
+        texture_3d_to_texture_3d::Texture3dToTexture3d,
+        texture_to_texture::TextureToTexture,
+    },
+    pointer_chase::PointerChase,
+    reduction_sum::{ReductionKernel, ReductionSum},
+    scan::{Scan, ScanKernel, ScanMode},
+    sort::{Sort, SortElements, SortKernel},
+    stencil_3d::Stencil3d,
+    workgroup_memory::WorkgroupMemory,
+    Accuracy, BenchmarkError, ElementType,
+};
+
+/// Every microbenchmark, in the order they should be presented.
+///
+/// Adding a microbenchmark to this list makes it available to the CLI and the
+/// WASM module. The website only shows the ones with a `MicrobenchmarkKind`
+/// in the web server, which also need a kind in the database.
+static MICROBENCHMARKS: &[&dyn Microbenchmark] = &[
+    &Matmul(MatmulKernel::Naive),
+    &Matmul(MatmulKernel::Tiled),
+    &Matmul(MatmulKernel::RegisterBlocked),
+    &Convolution,
+    &ReductionSum(ReductionKernel::MultiPass),
+    &ReductionSum(ReductionKernel::AtomicCombine),
+    &ReductionSum(ReductionKernel::SinglePass),
+    &Scan(ScanKernel::Sklansky),
+    &Scan(ScanKernel::Blelloch(ScanMode::Inclusive)),
+    &Scan(ScanKernel::Blelloch(ScanMode::Exclusive)),
+    &Scan(ScanKernel::DecoupledLookBack(ScanMode::Inclusive)),
+    &Scan(ScanKernel::DecoupledLookBack(ScanMode::Exclusive)),
+    &Stencil3d,
+    &FmaThroughput,
+    &BufferAccess(AccessPattern::Sequential),
+    &BufferAccess(AccessPattern::Shuffled),
+    &BufferToBuffer,
+    &StridedCopy(StridedAccess::Strided),
+    &StridedCopy(StridedAccess::Misaligned),
+    &BufferToTexture,
+    &TextureToTexture,
+    &Texture3dToTexture3d,
+    &PointerChase,
+    &AtomicThroughput(AtomicOperation::Add, AtomicMemory::Storage),
+    &AtomicThroughput(AtomicOperation::Add, AtomicMemory::Workgroup),
+    &AtomicThroughput(AtomicOperation::Max, AtomicMemory::Storage),
+    &AtomicThroughput(AtomicOperation::Max, AtomicMemory::Workgroup),
+    &AtomicThroughput(AtomicOperation::CompareExchange, AtomicMemory::Storage),
+    &AtomicThroughput(
+        AtomicOperation::CompareExchange,
+        AtomicMemory::Workgroup,
+    ),
+    &WorkgroupMemory,
+    &Sort(SortKernel::Bitonic, SortElements::Keys),
+    &Sort(SortKernel::Bitonic, SortElements::Pairs),
+    &Sort(SortKernel::Radix, SortElements::Keys),
+    &Sort(SortKernel::Radix, SortElements::Pairs),
+    &Histogram(
+        HistogramStrategy::Global,
+        HistogramData::U32,
+        HistogramDistribution::Uniform,
+    ),
+    &Histogram(
+        HistogramStrategy::Global,
+        HistogramData::U32,
+        HistogramDistribution::Skewed,
+    ),
+    &Histogram(
+        HistogramStrategy::Global,
+        HistogramData::U8,
+        HistogramDistribution::Uniform,
+    ),
+    &Histogram(
+        HistogramStrategy::Global,
+        HistogramData::U8,
+        HistogramDistribution::Skewed,
+    ),
+    &Histogram(
+        HistogramStrategy::Privatized,
+        HistogramData::U32,
+        HistogramDistribution::Uniform,
+    ),
+    &Histogram(
+        HistogramStrategy::Privatized,
+        HistogramData::U32,
+        HistogramDistribution::Skewed,
+    ),
+    &Histogram(
+        HistogramStrategy::Privatized,
+        HistogramData::U8,
+        HistogramDistribution::Uniform,
+    ),
+    &Histogram(
+        HistogramStrategy::Privatized,
+        HistogramData::U8,
+        HistogramDistribution::Skewed,
+    ),
+];
+
+/// Iterates over every microbenchmark.
+pub fn microbenchmarks() -> impl Iterator<Item = &'static dyn Microbenchmark> {
+    MICROBENCHMARKS.iter().copied()
+}
+
+/// Finds the microbenchmark with the given [Microbenchmark::name].
+pub fn find_microbenchmark(name: &str) -> Option<&'static dyn Microbenchmark> {
+    microbenchmarks().find(|microbenchmark| microbenchmark.name() == name)
+}
+
+/// Future returned by [Microbenchmark::run].
+///
+/// It isn't [Send] since the futures of WebGPU in the browser aren't either.
+pub type RunFuture<'a> = Pin<
+    Box<dyn Future<Output = Result<BenchmarkResults, BenchmarkError>> + 'a>,
+>;
+
+/// Future returned by [Microbenchmark::accuracy].
+pub type AccuracyFuture<'a> = Pin<
+    Box<dyn Future<Output = Result<Option<Accuracy>, BenchmarkError>> + 'a>,
+>;
+
+/// A microbenchmark that can be ran with different workgroup sizes.
+pub trait Microbenchmark: Sync {
+    /// Unique identifier in `snake_case`, such as `matmul`.
+    fn name(&self) -> &'static str;
+
+    /// Human readable name, such as "Matrix Multiplication".
+    fn title(&self) -> &'static str;
+
+    /// Short description of what's being measured.
+    fn description(&self) -> &'static str;
+
+    /// Amount of dimensions of the workgroup sizes the microbenchmark takes,
+    /// the sizes of the dimensions past these must be 1.
+    fn workgroup_dimensions(&self) -> usize;
+
+    /// Workgroup sizes to run when none are given.
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)>;
+
+    /// What [MicrobenchmarkParameters::size] means for this microbenchmark,
+    /// such as "Dimensions of the square matrices".
+    fn size_description(&self) -> &'static str;
+
+    /// Parameters to run with when none are given.
+    fn default_parameters(&self) -> MicrobenchmarkParameters;
+
+    /// Problem sizes a [Sweep](crate::Sweep) runs when none are given, see
+    /// [MicrobenchmarkParameters::size]. Only the size of the
+    /// [Microbenchmark::default_parameters] by default.
+    fn default_sizes(&self) -> Vec<usize> {
+        vec![self.default_parameters().size]
+    }
+
+    /// Whether the microbenchmark only runs when asked for by name, because
+    /// it relies on behavior WebGPU doesn't guarantee. These are left out of
+    /// [Sweep::default](crate::Sweep::default). [false] by default.
+    fn opt_in(&self) -> bool { false }
+
+    /// Element types the microbenchmark can work with, see
+    /// [MicrobenchmarkParameters::element_type]. Only [ElementType::F32] by
+    /// default.
+    fn element_types(&self) -> &'static [ElementType] { &[ElementType::F32] }
+
+    /// Whether the microbenchmark can work with the element type on a device
+    /// with the given features.
+    fn supports_element_type(
+        &self,
+        element_type: ElementType,
+        features: Features,
+    ) -> bool {
+        self.element_types().contains(&element_type)
+            && features.contains(element_type.required_features())
+    }
+
+    /// Whether the microbenchmark can run with the given workgroup size and
+    /// parameters on a device with the given limits, see [fits_limits].
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool;
+
+    /// Runs the microbenchmark on the GPU with the given workgroup size and
+    /// parameters.
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a>;
+
+    /// Runs a single iteration of the microbenchmark with random inputs and
+    /// compares its output against a reference computed on the CPU, see
+    /// [Accuracy].
+    ///
+    /// [None] for the microbenchmarks that don't compute anything to compare,
+    /// which is the default.
+    fn accuracy<'a>(
+        &'a self,
+        _gpu: &'a GPUContext,
+        _workgroup_size: (u32, u32, u32),
+        _parameters: MicrobenchmarkParameters,
+    ) -> AccuracyFuture<'a> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// The metrics that summarize the results of a run with the given
+    /// parameters.
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric>;
+
+    /// Floating point operations per byte of memory traffic of a run with the
+    /// given parameters, placing it on the [Roofline](crate::Roofline).
+    ///
+    /// The traffic is the compulsory one, reading every input and writing
+    /// every output once, so caches can make the achieved intensity higher.
+    /// [None] for the microbenchmarks that don't compute anything, which is
+    /// the default.
+    fn arithmetic_intensity(
+        &self,
+        _parameters: &MicrobenchmarkParameters,
+    ) -> Option<f64> {
+        None
+    }
+}
+
+/// Problem size, element type and iteration counts of a [Microbenchmark]
+/// run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct MicrobenchmarkParameters {
+    /// Size of the problem, its meaning depends on the microbenchmark, see
+    /// [Microbenchmark::size_description].
+    pub size: usize,
+    /// Amount of iterations to run before the measured ones.
+    pub warmup_count: usize,
+    /// Amount of measured iterations.
+    pub count: usize,
+    /// Type of the data the microbenchmark works with, see
+    /// [Microbenchmark::element_types].
+    pub element_type: ElementType,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl MicrobenchmarkParameters {
+    /// Creates the parameters of a run with [ElementType::F32] elements.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(size: usize, warmup_count: usize, count: usize) -> Self {
+        Self {
+            size,
+            warmup_count,
+            count,
+            element_type: ElementType::F32,
+        }
+    }
+}
+
+/// A value computed from the [BenchmarkResults] of a [Microbenchmark].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Metric {
+    /// What's being measured.
+    pub kind: MetricKind,
+    /// Value in the base unit of the [MetricKind].
+    pub value: f64,
+}
+
+/// The kinds of [Metric]s reported by the microbenchmarks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub enum MetricKind {
+    /// Floating point operations per second.
+    Flops,
+    /// Bytes per second.
+    Bandwidth,
+    /// Seconds per memory access.
+    Latency,
+    /// Operations per second, for operations other than floating point
+    /// ones, such as atomics.
+    Operations,
+    /// Keys sorted per second.
+    Keys,
+    /// Input elements processed per second, for microbenchmarks that go
+    /// through their input once such as histograms.
+    Elements,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Metric {
+    /// Floating point operations per second.
+    pub fn flops(value: f64) -> Self {
+        Self {
+            kind: MetricKind::Flops,
+            value,
+        }
+    }
+
+    /// Bytes per second.
+    pub fn bandwidth(value: f64) -> Self {
+        Self {
+            kind: MetricKind::Bandwidth,
+            value,
+        }
+    }
+
+    /// Seconds per memory access.
+    pub fn latency(value: f64) -> Self {
+        Self {
+            kind: MetricKind::Latency,
+            value,
+        }
+    }
+
+    /// Operations per second, for operations other than floating point ones.
+    pub fn operations(value: f64) -> Self {
+        Self {
+            kind: MetricKind::Operations,
+            value,
+        }
+    }
+
+    /// Keys sorted per second.
+    pub fn keys(value: f64) -> Self {
+        Self {
+            kind: MetricKind::Keys,
+            value,
+        }
+    }
+
+    /// Input elements processed per second.
+    pub fn elements(value: f64) -> Self {
+        Self {
+            kind: MetricKind::Elements,
+            value,
+        }
+    }
+
+    /// Label of the metric including the unit it's displayed in, for example
+    /// "GFLOPS".
+    pub fn label(&self) -> String {
+        match self.kind {
+            MetricKind::Flops => "GFLOPS",
+            MetricKind::Bandwidth => "Bandwidth (GB/s)",
+            MetricKind::Latency => "Latency (ns)",
+            MetricKind::Operations => "GOPS",
+            MetricKind::Keys => "GKeys/s",
+            MetricKind::Elements => "GElements/s",
+        }
+        .to_string()
+    }
+
+    /// The value in the unit given by [Metric::label].
+    pub fn display_value(&self) -> f64 {
+        match self.kind {
+            MetricKind::Flops
+            | MetricKind::Bandwidth
+            | MetricKind::Operations
+            | MetricKind::Keys
+            | MetricKind::Elements => self.value / 1_000_000_000.0,
+            MetricKind::Latency => self.value * 1_000_000_000.0,
+        }
+    }
+}
+
+/// Checks the common device limits a microbenchmark run can exceed: the size
+/// of the workgroups, the amount of workgroups dispatched in each dimension
+/// and the size in bytes of the largest storage buffer bound.
+///
+/// The dispatches are only computed once the workgroup size is known to be
+/// valid, so they can divide by it.
+pub(crate) fn fits_limits(
+    limits: &Limits,
+    workgroup_size: (u32, u32, u32),
+    dispatch: impl FnOnce() -> Vec<(u32, u32, u32)>,
+    largest_buffer_size: u64,
+) -> bool {
+    let (x, y, z) = workgroup_size;
+
+    x > 0
+        && y > 0
+        && z > 0
+        && x <= limits.max_compute_workgroup_size_x
+        && y <= limits.max_compute_workgroup_size_y
+        && z <= limits.max_compute_workgroup_size_z
+        && (x as u64 * y as u64 * z as u64)
+            <= limits.max_compute_invocations_per_workgroup as u64
+        && dispatch().iter().all(|&(x, y, z)| {
+            [x, y, z].iter().all(|&count| {
+                count <= limits.max_compute_workgroups_per_dimension
+            })
+        })
+        && largest_buffer_size <= limits.max_storage_buffer_binding_size as u64
+        && largest_buffer_size <= limits.max_buffer_size
+}
+
+/// Workgroup sizes `(x, y)` of 2D microbenchmarks, trying different shapes
+/// for each amount of invocations.
+///
+/// Accessing same-row should be faster than accessing different rows which is
+/// why mostly column-dominant workgroups are used.
+pub(crate) fn default_workgroups_2d() -> Vec<(u32, u32, u32)> {
+    [
+        (4, 8),
+        (2, 16),
+        (1, 32),
+        (8, 8),
+        (4, 16),
+        (2, 32),
+        (1, 64),
+        (8, 16),
+        (4, 32),
+        (2, 64),
+        (1, 128),
+        (16, 16),
+        (8, 32),
+        (4, 64),
+        (2, 128),
+        (1, 256),
+    ]
+    .map(|(x, y)| (x, y, 1))
+    .to_vec()
+}
+
+/// Workgroup sizes `(x, y)` of the microbenchmarks working on 2D textures.
+pub(crate) fn default_workgroups_texture() -> Vec<(u32, u32, u32)> {
+    [
+        (4, 4),
+        (1, 32),
+        (32, 1),
+        (4, 8),
+        (8, 4),
+        (8, 8),
+        (1, 64),
+        (64, 1),
+        (16, 8),
+        (8, 16),
+        (16, 16),
+    ]
+    .map(|(x, y)| (x, y, 1))
+    .to_vec()
+}
+
+/// Workgroup sizes `(x, y, z)` of the microbenchmarks working on 3D grids or
+/// textures, from cubes to flat slabs of each amount of invocations.
+pub(crate) fn default_workgroups_3d() -> Vec<(u32, u32, u32)> {
+    vec![
+        (4, 4, 2),
+        (8, 8, 1),
+        (4, 4, 4),
+        (8, 4, 2),
+        (16, 4, 1),
+        (8, 8, 2),
+        (16, 8, 1),
+        (8, 4, 4),
+        (8, 8, 4),
+        (16, 4, 4),
+        (16, 16, 1),
+    ]
+}
+
+/// Workgroup sizes of 1D microbenchmarks.
+pub(crate) fn default_workgroups_1d() -> Vec<(u32, u32, u32)> {
+    [8, 16, 32, 64, 128, 256].map(|x| (x, 1, 1)).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Verifies the names are unique, the default parameters aren't empty and
+    /// the default workgroups fit the microbenchmarks' dimensions.
+    #[test]
+    fn registry_is_consistent() {
+        let names: HashSet<&str> = microbenchmarks()
+            .map(|microbenchmark| microbenchmark.name())
+            .collect();
+        assert_eq!(names.len(), MICROBENCHMARKS.len());
+
+        for microbenchmark in microbenchmarks() {
+            assert_eq!(
+                find_microbenchmark(microbenchmark.name()).unwrap().title(),
+                microbenchmark.title()
+            );
+
+            let parameters = microbenchmark.default_parameters();
+            assert!(parameters.size > 0 && parameters.count > 0);
+            assert!(microbenchmark
+                .default_sizes()
+                .iter()
+                .all(|size| *size > 0));
+
+            let dims = microbenchmark.workgroup_dimensions();
+            for (x, y, z) in microbenchmark.default_workgroups() {
+                assert!([x, y, z][..dims].iter().all(|size| *size > 0));
+                assert!([x, y, z][dims..].iter().all(|size| *size == 1));
+            }
+        }
+    }
+}
@@ -0,0 +1,366 @@
+//! Pointer-chasing microbenchmark for the latency of memory accesses, swept
+//! over working sets from a few KiB to hundreds of MiB so the size of each
+//! level of the cache hierarchy shows up as a jump in latency
+
+use std::collections::HashMap;
+
+use rand::{seq::SliceRandom, thread_rng};
+use uwgpu::{
+    wgpu::{
+        util::BufferInitDescriptor, BufferDescriptor, BufferUsages, Limits,
+        ShaderModuleDescriptor, ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
+    GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    microbenchmark::{
+        fits_limits, Metric, Microbenchmark, MicrobenchmarkParameters,
+        RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// 1MiB working set
+///
+/// 1MiB / 4bytes = 262_144
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 262_144,
+    warmup_count: 5,
+    count: 50,
+    element_type: ElementType::F32,
+};
+
+/// Dependent loads of each invocation in every iteration
+const LOADS: u32 = 16_384;
+
+/// Elements between the nodes of the chain, 128 bytes so that no two nodes
+/// share a cache line on any common GPU
+const NODE_STRIDE: usize = 32;
+
+/// Working sets of the default sweep, in u32 elements: every power of two
+/// from 4KiB to 256MiB.
+const WORKING_SET_SIZES: std::ops::RangeInclusive<u32> = 10..=26;
+
+/// Microbenchmark for measuring the latency of memory accesses, the
+/// [MicrobenchmarkParameters::size] is the working set in u32 elements,
+/// rounded down to whole nodes of the chain.
+///
+/// The working set is a chain of nodes linked in a single random cycle, which
+/// each invocation follows for a fixed amount of loads. Every load depends on
+/// the previous one, so they can't overlap and each takes the full latency of
+/// the level of the cache hierarchy the working set fits in.
+///
+/// A single workgroup is dispatched, with a workgroup size of 1 it measures
+/// the latency of a lone access. Bigger workgroups chase from different nodes
+/// at the same time, showing how many accesses the memory system can have in
+/// flight before the latency grows.
+pub async fn pointer_chase_benchmark(
+    gpu: &GPUContext,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<PointerChaseResults, BenchmarkError> {
+    let buffers = Buffers::new(
+        &random_chain(nodes(parameters.size)),
+        workgroup_size,
+        gpu,
+    );
+    let pipeline =
+        pointer_chase_pipeline(gpu, &buffers, workgroup_size).await?;
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: vec![(1, 1, 1)],
+        dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(PointerChaseResults {
+        results,
+        parameters,
+    })
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the pointer-chase microbenchmark. See
+/// [pointer_chase_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_eval]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct PointerChaseResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl PointerChaseResults {
+    /// Get the latency of each load in seconds
+    pub fn latency(&self) -> f64 {
+        self.results.time_per_iteration(TimeUnit::Second) / LOADS as f64
+    }
+
+    /// Get the latency of each load in nanoseconds
+    pub fn latency_ns(&self) -> f64 {
+        self.results.time_per_iteration(TimeUnit::Nano) / LOADS as f64
+    }
+
+    /// Get the working set in bytes, after rounding it down to whole nodes
+    pub fn working_set_bytes(&self) -> usize {
+        nodes(self.parameters.size) * NODE_STRIDE * size_of::<u32>()
+    }
+}
+
+/// Pointer-chase microbenchmark, see [pointer_chase_benchmark].
+pub struct PointerChase;
+
+impl Microbenchmark for PointerChase {
+    fn name(&self) -> &'static str { "pointer_chase" }
+
+    fn title(&self) -> &'static str { "Pointer-Chase Memory Latency" }
+
+    fn description(&self) -> &'static str {
+        "This microbenchmark tests the latency of memory accesses in your GPU, by following a randomly linked chain where every load depends on the previous one. Sweeping the size of the chain from a few KiB to hundreds of MiB shows the latency of each level of the cache hierarchy and where it ends."
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> { vec![(1, 1, 1)] }
+
+    fn size_description(&self) -> &'static str {
+        "Working set in u32 elements, rounded down to whole 128 byte nodes"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn default_sizes(&self) -> Vec<usize> {
+        WORKING_SET_SIZES.map(|power| 1 << power).collect()
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let chain_size = nodes(parameters.size) * NODE_STRIDE;
+        fits_limits(
+            limits,
+            workgroup_size,
+            || vec![(1, 1, 1)],
+            (chain_size * size_of::<u32>()) as u64,
+        )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(pointer_chase_benchmark(gpu, workgroup_size.0, parameters)
+                .await?
+                .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = PointerChaseResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::latency(results.latency())]
+    }
+}
+
+/// Amount of nodes of the chain for a working set of `size` elements, at
+/// least 1.
+fn nodes(size: usize) -> usize { (size / NODE_STRIDE).max(1) }
+
+/// Chain of `nodes` nodes linked in a single cycle in a random order, so that
+/// following it from any node visits all of them.
+fn random_chain(nodes: usize) -> Vec<u32> {
+    let mut order: Vec<usize> = (0..nodes).collect();
+    order.shuffle(&mut thread_rng());
+
+    let mut chain = vec![0_u32; nodes * NODE_STRIDE];
+    for (i, &node) in order.iter().enumerate() {
+        let next = order[(i + 1) % nodes];
+        chain[node * NODE_STRIDE] = (next * NODE_STRIDE) as u32;
+    }
+    chain
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    chain_buffer: AsyncBuffer,
+    result_buffer: AsyncBuffer,
+    loads_buffer: AsyncBuffer,
+}
+
+impl Buffers {
+    fn new(chain: &[u32], invocations: u32, gpu: &GPUContext) -> Self {
+        let chain_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Chain Buffer"),
+            contents: bytemuck::cast_slice(chain),
+            usage: BufferUsages::STORAGE,
+        });
+
+        let result_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Result Buffer"),
+            size: (invocations as usize * size_of::<u32>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let loads_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Loads Buffer"),
+            contents: bytemuck::cast_slice(&[LOADS]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        Self {
+            chain_buffer,
+            result_buffer,
+            loads_buffer,
+        }
+    }
+}
+
+/// Pipeline needed for microbenchmark
+async fn pointer_chase_pipeline<'a>(
+    gpu: &'a GPUContext,
+    buffers: &'a Buffers,
+    workgroup_size: u32,
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("pointer chase shader"),
+            source: ShaderSource::Wgsl(
+                include_str!("pointer_chase.wgsl")
+                    .replace("$node_stride$", &NODE_STRIDE.to_string())
+                    .into(),
+            ),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([
+            (0, buffers.chain_buffer.as_entire_binding()),
+            (1, buffers.result_buffer.as_entire_binding()),
+            (2, buffers.loads_buffer.as_entire_binding()),
+        ]),
+        gpu,
+        workgroup_size: Some((workgroup_size, 1, 1)),
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::{
+        wgpu::{BufferDescriptor, BufferUsages, MapMode},
+        Benchmark, GPUContext,
+    };
+
+    use super::*;
+
+    /// Verifies that following the chain from the first node visits every
+    /// node before coming back to it.
+    #[test]
+    fn chain_is_a_single_cycle() {
+        for nodes in [1, 2, 100] {
+            let chain = random_chain(nodes);
+            assert_eq!(chain.len(), nodes * NODE_STRIDE);
+
+            let mut visited = vec![false; nodes];
+            let mut next = 0;
+            for _ in 0..nodes {
+                assert!(!visited[next / NODE_STRIDE]);
+                visited[next / NODE_STRIDE] = true;
+                next = chain[next] as usize;
+            }
+            assert_eq!(next, 0);
+        }
+    }
+
+    /// Verifies that every invocation follows the chain for [LOADS] loads.
+    #[tokio::test]
+    async fn pointer_chase_works() {
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        let chain = random_chain(nodes(1 << 16));
+        let workgroup_size = 4;
+        let expected_result: Vec<u32> = (0..workgroup_size)
+            .map(|invocation| {
+                let mut next = invocation as usize * NODE_STRIDE;
+                for _ in 0..LOADS {
+                    next = chain[next] as usize;
+                }
+                next as u32
+            })
+            .collect();
+
+        let buffers = Buffers::new(&chain, workgroup_size, &gpu);
+        let pipeline = pointer_chase_pipeline(&gpu, &buffers, workgroup_size)
+            .await
+            .unwrap();
+
+        let result_size = (workgroup_size as usize * size_of::<u32>()) as u64;
+        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Staging Buffer"),
+            size: result_size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Benchmark {
+            warmup_count: 0,
+            count: 1,
+            finalize_encoder_callback: Some(&|encoder| {
+                encoder.copy_buffer_to_buffer(
+                    &buffers.result_buffer,
+                    0,
+                    &staging_buffer,
+                    0,
+                    result_size,
+                )
+            }),
+            workgroups_dispatch: vec![(1, 1, 1)],
+            dispatch_callback: None,
+            ..Default::default()
+        }
+        .run(pipeline)
+        .await
+        .unwrap();
+
+        let staging_slice = staging_buffer.slice(..);
+        staging_slice.map_async(MapMode::Read).await.unwrap();
+
+        let result_data: Vec<u32> = {
+            let result_data_raw = staging_slice.get_mapped_range();
+            bytemuck::cast_slice(&result_data_raw).to_vec()
+        };
+
+        staging_buffer.unmap();
+
+        assert_eq!(result_data, expected_result);
+    }
+}
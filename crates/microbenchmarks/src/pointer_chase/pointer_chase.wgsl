@@ -0,0 +1,25 @@
+// Nodes of a single random cycle, each one holds the index of the next one
+// and they're spaced out so no two share a cache line
+@group(0) @binding(0) var<storage, read> chain: array<u32>;
+@group(0) @binding(1) var<storage, read_write> result: array<u32>;
+@group(0) @binding(2) var<uniform> loads: u32;
+
+// Spacing in elements between the nodes of the chain
+const NODE_STRIDE: u32 = $node_stride$u;
+
+// Each invocation follows the chain from a different node, every load
+// depending on the previous one so their latencies can't overlap
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let nodes = arrayLength(&chain) / NODE_STRIDE;
+    var next = (global_id.x % nodes) * NODE_STRIDE;
+
+    for (var i = 0u; i < loads; i++) {
+        next = chain[next];
+    }
+
+    // Where the chain ended is the result so none of the loads is dead code
+    if global_id.x < arrayLength(&result) {
+        result[global_id.x] = next;
+    }
+}
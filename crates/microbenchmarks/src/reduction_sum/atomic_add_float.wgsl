@@ -0,0 +1,20 @@
+// WGSL only has atomic integers, so the float is kept as its bits and added
+// to with a compare-exchange loop. Every failed exchange means another
+// workgroup's add succeeded, so the loop doesn't wait on anyone.
+@group(0) @binding(1) var<storage, read_write> result: atomic<u32>;
+
+fn atomic_clear() {
+    atomicStore(&result, bitcast<u32>($element$(0)));
+}
+
+fn atomic_add(value: $element$) {
+    var old = atomicLoad(&result);
+    loop {
+        let sum = bitcast<u32>(bitcast<$element$>(old) + value);
+        let exchange = atomicCompareExchangeWeak(&result, old, sum);
+        if exchange.exchanged {
+            break;
+        }
+        old = exchange.old_value;
+    }
+}
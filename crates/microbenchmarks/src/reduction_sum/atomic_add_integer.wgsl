@@ -0,0 +1,9 @@
+@group(0) @binding(1) var<storage, read_write> result: atomic<$element$>;
+
+fn atomic_clear() {
+    atomicStore(&result, $element$(0));
+}
+
+fn atomic_add(value: $element$) {
+    atomicAdd(&result, value);
+}
@@ -1,56 +1,272 @@
-//! Reduction sum microbenchmark
+//! Reduction sum microbenchmarks, with a kernel for each way of combining the
+//! sums of the workgroups, see [ReductionKernel]
 
 use std::collections::HashMap;
 
-use rand::{thread_rng, Rng};
 use uwgpu::TimeUnit;
 use uwgpu::{
     wgpu::{
-        util::BufferInitDescriptor, BufferDescriptor, BufferUsages,
-        ShaderModuleDescriptor, ShaderSource,
+        util::BufferInitDescriptor, BindGroup, BufferDescriptor, BufferUsages,
+        ComputePass, Limits, ShaderModuleDescriptor, ShaderSource,
     },
     wgpu_async::AsyncBuffer,
-    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
-    GPUContext, PipelineParameters,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, BindGroupParams,
+    CreatePipelineError, GPUContext, PipelineParameters,
 };
 
-use crate::BenchmarkError;
+use crate::{
+    accuracy::{read_staging_buffer, staging_buffer},
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, AccuracyFuture, Metric,
+        Microbenchmark, MicrobenchmarkParameters, RunFuture,
+    },
+    Accuracy, BenchmarkError, ElementType,
+};
+
+/// 262,144 elements, a 1MiB buffer of f32
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 262_144,
+    warmup_count: 2000,
+    count: 20000,
+    element_type: ElementType::F32,
+};
+
+/// Element types of the kernels using atomics, which WGSL only has of 32 bits
+const ATOMIC_ELEMENT_TYPES: &[ElementType] =
+    &[ElementType::F32, ElementType::I32, ElementType::U32];
+
+/// The ways of combining the sums of the workgroups into the final sum, each
+/// of them is a separate [ReductionSum] microbenchmark.
+///
+/// Every workgroup sums its chunk of the array in workgroup memory, they only
+/// differ in what happens with the sums of the workgroups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReductionKernel {
+    /// A tree reduction with a dispatch per level, each one summing the sums
+    /// of the previous one until a single sum is left.
+    MultiPass,
+    /// A single reduction dispatch where the workgroups add their sums to
+    /// the result atomically, after a dispatch that clears it.
+    AtomicCombine,
+    /// A single dispatch where the last workgroup to finish, found with an
+    /// atomic counter, sums the sums of the rest.
+    ///
+    /// It relies on the sums of the other workgroups being visible to the
+    /// last one, which WebGPU doesn't guarantee, so it's opt-in (see
+    /// [Microbenchmark::opt_in]). Like the rest of kernels it's checked with
+    /// [reduction_sum_verify] before every benchmark.
+    SinglePass,
+}
+
+impl ReductionKernel {
+    /// Every kernel, from the most to the least portable.
+    pub const ALL: &'static [ReductionKernel] = &[
+        ReductionKernel::MultiPass,
+        ReductionKernel::AtomicCombine,
+        ReductionKernel::SinglePass,
+    ];
+
+    /// Element types the kernel can sum.
+    fn element_types(&self) -> &'static [ElementType] {
+        match self {
+            ReductionKernel::MultiPass => ElementType::ALL,
+            ReductionKernel::AtomicCombine | ReductionKernel::SinglePass => {
+                ATOMIC_ELEMENT_TYPES
+            }
+        }
+    }
+
+    /// Bytes of workgroup storage used by each workgroup of the kernel, its
+    /// sums plus the ticket of the single-pass kernel.
+    fn workgroup_storage_size(
+        &self,
+        element_type: ElementType,
+        workgroup_size: u32,
+    ) -> u64 {
+        let sums = workgroup_size as u64 * element_type.size() as u64;
+        match self {
+            ReductionKernel::MultiPass | ReductionKernel::AtomicCombine => sums,
+            ReductionKernel::SinglePass => sums + size_of::<u32>() as u64,
+        }
+    }
 
-/// 1MiB size buffer (of f32)
-const BENCHMARK_BUFFER_SIZE: usize = 262_144;
-const BENCHMARK_WARMUP_COUNT: usize = 2000;
-const BENCHMARK_ITERATIONS: usize = 20000;
+    /// Shader of the kernel for the element type.
+    fn shader(&self, element_type: ElementType) -> String {
+        let source = match self {
+            ReductionKernel::MultiPass => {
+                include_str!("reduction_sum_multi_pass.wgsl").to_string()
+            }
+            ReductionKernel::AtomicCombine => {
+                let atomic_add = match element_type.is_float() {
+                    true => include_str!("atomic_add_float.wgsl"),
+                    false => include_str!("atomic_add_integer.wgsl"),
+                };
+                include_str!("reduction_sum_atomic.wgsl")
+                    .replace("$atomic_add$", atomic_add)
+            }
+            ReductionKernel::SinglePass => {
+                include_str!("reduction_sum_single_pass.wgsl").to_string()
+            }
+        };
+
+        element_type.shader(&source)
+    }
+}
 
 /// Microbenchmark for a reduction sum operation. Sums all the elements of an
-/// array.
+/// array with the given kernel, the [MicrobenchmarkParameters::size] is its
+/// amount of elements and the [MicrobenchmarkParameters::element_type] their
+/// type.
 ///
 /// The workgroup size will dictate the size of workgroups used for the
 /// computation.
+///
+/// Before benchmarking, the kernel is checked to sum correctly with the
+/// workgroup size and amount of elements, see [reduction_sum_verify].
 pub async fn reduction_sum_benchmark(
+    gpu: &GPUContext,
+    kernel: ReductionKernel,
     workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
 ) -> Result<ReductionSumResults, BenchmarkError> {
-    let gpu = GPUContext::new(None).await?;
-    let buffers = Buffers::<BENCHMARK_BUFFER_SIZE>::new_with_random_input(
+    reduction_sum_verify(
+        gpu,
+        kernel,
         workgroup_size,
-        &gpu,
+        parameters.element_type,
+        parameters.size,
+    )
+    .await?;
+
+    let input = parameters.element_type.random(parameters.size);
+    let buffers = Buffers::new(
+        gpu,
+        kernel,
+        &input,
+        workgroup_size,
+        parameters.element_type,
     );
     let pipeline =
-        reduction_sum_pipeline(&gpu, &buffers, workgroup_size).await?;
+        reduction_sum_pipeline(gpu, kernel, &buffers, workgroup_size).await?;
 
     let results = Benchmark {
-        warmup_count: BENCHMARK_WARMUP_COUNT,
-        count: BENCHMARK_ITERATIONS,
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
         finalize_encoder_callback: None,
         workgroups_dispatch: workgroups_dispatch(
-            BENCHMARK_BUFFER_SIZE,
+            kernel,
+            parameters.size,
             workgroup_size,
         ),
-        dispatch_callback: None,
+        dispatch_callback: Some(&dispatch_callback(
+            &buffers.create_bind_groups(&pipeline),
+        )),
+        ..Default::default()
     }
     .run(pipeline)
     .await?;
 
-    Ok(ReductionSumResults(results))
+    Ok(ReductionSumResults {
+        results,
+        parameters,
+    })
+}
+
+/// Checks that the kernel sums `size` elements correctly with the workgroup
+/// size, which is required before benchmarking it since a kernel that gives
+/// wrong sums would give meaningless timings.
+///
+/// Sums ones and zeros of the element type, with few enough ones that every
+/// partial sum is an integer the type represents exactly, so the result must
+/// be exact. Returns a [BenchmarkError::IncorrectOutput] otherwise.
+pub async fn reduction_sum_verify(
+    gpu: &GPUContext,
+    kernel: ReductionKernel,
+    workgroup_size: u32,
+    element_type: ElementType,
+    size: usize,
+) -> Result<(), BenchmarkError> {
+    let max_exact: usize = match element_type {
+        ElementType::F32 => 1 << 24,
+        ElementType::F16 => 1 << 11,
+        ElementType::I32 => i32::MAX as usize,
+        ElementType::U32 => u32::MAX as usize,
+    };
+    // A one every `stride` elements, all of them when they fit
+    let stride = size.div_ceil(max_exact).max(1);
+    let input: Vec<f64> =
+        (0..size).map(|i| (i % stride == 0) as u32 as f64).collect();
+    let expected = size.div_ceil(stride) as f64;
+    let output =
+        reduce(gpu, kernel, workgroup_size, element_type, &input).await?;
+
+    match output == expected {
+        true => Ok(()),
+        false => Err(BenchmarkError::IncorrectOutput { output, expected }),
+    }
+}
+
+/// Accuracy of a single reduction sum of random data with the parameters of
+/// [reduction_sum_benchmark], against a sum computed on the CPU.
+pub async fn reduction_sum_accuracy(
+    gpu: &GPUContext,
+    kernel: ReductionKernel,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<Accuracy, BenchmarkError> {
+    let element_type = parameters.element_type;
+    let input = element_type.random(parameters.size);
+    let result =
+        reduce(gpu, kernel, workgroup_size, element_type, &input).await?;
+
+    Ok(Accuracy::measure(
+        element_type,
+        [(result, input.iter().sum())],
+    ))
+}
+
+/// Sums the input on the GPU once with the kernel.
+async fn reduce(
+    gpu: &GPUContext,
+    kernel: ReductionKernel,
+    workgroup_size: u32,
+    element_type: ElementType,
+    input: &[f64],
+) -> Result<f64, BenchmarkError> {
+    let buffers =
+        Buffers::new(gpu, kernel, input, workgroup_size, element_type);
+    let pipeline =
+        reduction_sum_pipeline(gpu, kernel, &buffers, workgroup_size).await?;
+
+    let result_size = element_type.buffer_size(1);
+    let staging_buffer = staging_buffer(gpu, result_size);
+    Benchmark {
+        warmup_count: 0,
+        count: 1,
+        workgroups_dispatch: workgroups_dispatch(
+            kernel,
+            input.len(),
+            workgroup_size,
+        ),
+        dispatch_callback: Some(&dispatch_callback(
+            &buffers.create_bind_groups(&pipeline),
+        )),
+        finalize_encoder_callback: Some(&|encoder| {
+            encoder.copy_buffer_to_buffer(
+                &buffers.result_buffer,
+                0,
+                &staging_buffer,
+                0,
+                result_size,
+            )
+        }),
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(element_type.decode(&read_staging_buffer(&staging_buffer).await)[0])
 }
 
 #[cfg(feature = "wasm")]
@@ -60,17 +276,16 @@ use wasm_bindgen::prelude::*;
 /// [reduction_sum_benchmark].
 ///
 /// Wraps a [BenchmarkResults] with some convenience methods.
-#[cfg_attr(
-    feature = "serde",
-    derive(serde::Serialize, serde::Deserialize),
-    serde(transparent)
-)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_eval]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-pub struct ReductionSumResults(
+pub struct ReductionSumResults {
+    /// Timings of the benchmark
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-    pub  BenchmarkResults,
-);
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl ReductionSumResults {
@@ -80,125 +295,330 @@ impl ReductionSumResults {
     /// would've been with padded out zeroes that don't affect the result,
     /// so I'm not counting those.
     pub fn flops(&self) -> f64 {
-        const NUM_FLOPS_PER_ITER: usize = BENCHMARK_BUFFER_SIZE - 1;
+        let num_flops_per_iter = self.parameters.size - 1;
 
-        (NUM_FLOPS_PER_ITER as f64 * self.0.count as f64)
-            / (self.0.total_time(TimeUnit::Second))
+        (num_flops_per_iter as f64 * self.results.count as f64)
+            / (self.results.total_time(TimeUnit::Second))
     }
 }
 
-/// GPU buffers needed for microbenchmark
-struct Buffers<const BUFFER_SIZE: usize> {
-    input_buffer: AsyncBuffer,
-    /// The final result will be in the [0] element, but the buffer is actually
-    /// of size:
-    ///
-    /// BUFFER_SIZE / workgroup_size (integer division rounded up)
-    /// TODO: Change
-    ///
-    /// The extra space is used to calculate intermediate results of the
-    /// reduction.
-    ///
-    /// The reason it's BUFFER_SIZE / workgroup_size is because in "each round"
-    /// of reduction the result array gets reduced by a factor of
-    /// workgroup_size
-    ///
-    /// For example, if workgroup_size is 4, the result gets reduced by a
-    /// factor of 4.
-    result_buffer: AsyncBuffer,
-    barriers_buffer: AsyncBuffer,
-    elements_left_buffer: AsyncBuffer,
-}
+/// Reduction sum microbenchmark with the given kernel, see
+/// [reduction_sum_benchmark].
+pub struct ReductionSum(pub ReductionKernel);
+
+impl Microbenchmark for ReductionSum {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            ReductionKernel::MultiPass => "reduction_sum",
+            ReductionKernel::AtomicCombine => "reduction_sum_atomic",
+            ReductionKernel::SinglePass => "reduction_sum_single_pass",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self.0 {
+            ReductionKernel::MultiPass => "Reduction",
+            ReductionKernel::AtomicCombine => "Atomic Reduction",
+            ReductionKernel::SinglePass => "Single-Pass Reduction",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.0 {
+            ReductionKernel::MultiPass => "This microbenchmark tests the performance of a tree reduction sum on a buffer of random elements, 262,144 of them by default, with a dispatch for each level of the tree.",
+            ReductionKernel::AtomicCombine => "This microbenchmark tests the performance of a reduction sum on a buffer of random elements, 262,144 of them by default, where every workgroup adds its sum to the result with atomics. Floats don't have atomic adds in WebGPU, so they're added with compare-exchange loops.",
+            ReductionKernel::SinglePass => "This microbenchmark tests the performance of a reduction sum on a buffer of random elements, 262,144 of them by default, in a single dispatch, where the last workgroup to finish sums the sums of the rest. WebGPU doesn't guarantee it sees them, so the sum is checked before running it and the benchmark fails if it's wrong on your GPU.",
+        }
+    }
+
+    fn element_types(&self) -> &'static [ElementType] { self.0.element_types() }
 
-impl<const BUFFER_SIZE: usize> Buffers<BUFFER_SIZE> {
-    fn new_with_random_input(workgroup_size: u32, gpu: &GPUContext) -> Self {
-        let mut input_data = vec![0_f32; BUFFER_SIZE];
+    fn workgroup_dimensions(&self) -> usize { 1 }
 
-        let mut rng = thread_rng();
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
 
-        rng.fill(input_data.as_mut_slice());
+    fn size_description(&self) -> &'static str { "Amount of elements to sum" }
 
-        Self::new_from_input(&input_data, workgroup_size, &gpu)
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
     }
 
-    fn new_from_input(
-        input_data: &[f32],
-        workgroup_size: u32,
+    fn opt_in(&self) -> bool { self.0 == ReductionKernel::SinglePass }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        let kernel = self.0;
+        kernel.workgroup_storage_size(parameters.element_type, workgroup_size.0)
+            <= limits.max_compute_workgroup_storage_size as u64
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroups_dispatch(kernel, size, workgroup_size.0),
+                parameters.element_type.buffer_size(size),
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(reduction_sum_benchmark(
+                gpu,
+                self.0,
+                workgroup_size.0,
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn accuracy<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> AccuracyFuture<'a> {
+        Box::pin(async move {
+            Ok(Some(
+                reduction_sum_accuracy(
+                    gpu,
+                    self.0,
+                    workgroup_size.0,
+                    parameters,
+                )
+                .await?,
+            ))
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = ReductionSumResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::flops(results.flops())]
+    }
+
+    fn arithmetic_intensity(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+    ) -> Option<f64> {
+        // reads every element once, the result is negligible
+        let n = parameters.size;
+        let bytes = n * parameters.element_type.size();
+        Some((n - 1) as f64 / bytes as f64)
+    }
+}
+
+/// One of the dispatches of [ReductionKernel::MultiPass], its fields match
+/// the `Pass` struct of the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pass {
+    source_offset: u32,
+    source_len: u32,
+    destination_offset: u32,
+    read_input: u32,
+}
+
+impl Pass {
+    /// Contents of the uniform buffer of the pass
+    fn uniform(&self) -> [u32; 4] {
+        [
+            self.source_offset,
+            self.source_len,
+            self.destination_offset,
+            self.read_input,
+        ]
+    }
+}
+
+/// The passes of [ReductionKernel::MultiPass] reducing `size` elements.
+///
+/// Each pass writes the sums of its workgroups right before the ones it
+/// reads, so the last one writes the final sum at offset 0.
+fn passes(size: usize, workgroup_size: u32) -> Vec<Pass> {
+    let mut source_lens = vec![size as u32];
+    loop {
+        let sums = source_lens[source_lens.len() - 1].div_ceil(workgroup_size);
+        if sums == 1 {
+            break;
+        }
+        source_lens.push(sums);
+    }
+
+    // The elements summed by each pass are the sums written by the previous
+    // one, which come after all the sums written by the passes after it
+    let sums_written =
+        |pass: usize| source_lens.get(pass + 1).copied().unwrap_or(1);
+    let destination_offset = |pass: usize| {
+        (pass + 1..source_lens.len()).map(sums_written).sum::<u32>()
+    };
+    (0..source_lens.len())
+        .map(|pass| Pass {
+            source_offset: match pass {
+                0 => 0,
+                _ => destination_offset(pass - 1),
+            },
+            source_len: source_lens[pass],
+            destination_offset: destination_offset(pass),
+            read_input: (pass == 0) as u32,
+        })
+        .collect()
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    input_buffer: AsyncBuffer,
+    /// The final sum will be in the [0] element. For
+    /// [ReductionKernel::MultiPass] the rest of the buffer holds the sums of
+    /// the workgroups of every pass but the last, see [passes].
+    result_buffer: AsyncBuffer,
+    /// Uniform of each dispatch, bound to `@group(1)` before it
+    dispatch_uniforms: Vec<AsyncBuffer>,
+    /// Sums of the workgroups and counter of the workgroups that have
+    /// finished of [ReductionKernel::SinglePass]
+    single_pass_buffers: Option<(AsyncBuffer, AsyncBuffer)>,
+    element_type: ElementType,
+}
+
+impl Buffers {
+    fn new(
         gpu: &GPUContext,
+        kernel: ReductionKernel,
+        input_data: &[f64],
+        workgroup_size: u32,
+        element_type: ElementType,
     ) -> Self {
         let input_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
             label: Some("Input Buffer"),
-            contents: bytemuck::cast_slice(input_data),
+            contents: &element_type.encode(input_data.iter().copied()),
             usage: BufferUsages::STORAGE,
         });
 
+        let passes = passes(input_data.len(), workgroup_size);
+        let result_len = match kernel {
+            ReductionKernel::MultiPass => {
+                passes[1..].iter().map(|pass| pass.source_len).sum::<u32>() + 1
+            }
+            ReductionKernel::AtomicCombine | ReductionKernel::SinglePass => 1,
+        };
         let result_buffer = gpu.create_buffer(&BufferDescriptor {
             label: Some("Result Buffer"),
-            // See `result_buffer` field docs for size explanation.
-            size: ((BUFFER_SIZE.div_ceil(workgroup_size as usize))
-                * std::mem::size_of::<f32>()) as u64,
+            size: element_type.buffer_size(result_len as usize),
             usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
             mapped_at_creation: false,
         });
 
-        let mut elements_per_pass: Vec<u32> = vec![];
-        let mut elements = BUFFER_SIZE as u32;
-        while elements > 1 {
-            elements = elements.div_ceil(workgroup_size);
-            elements_per_pass.push(elements);
-        }
-        let elements_per_pass = elements_per_pass;
-
-        println!("elements per pass: {:?}", elements_per_pass);
-
-        // See `result_buffer` field docs for size explanation.
-        let barriers_buffer_size: u32 =
-            elements_per_pass[1..].iter().sum::<u32>().max(1);
-        let barriers_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
-            label: Some("Barriers Buffer"),
-            contents: bytemuck::cast_slice(&vec![
-                0f32;
-                barriers_buffer_size as usize
-            ]),
-            usage: BufferUsages::STORAGE,
-        });
-
-        let elements_left_buffer =
+        let uniform = |contents: &[u8]| {
             gpu.create_buffer_init(&BufferInitDescriptor {
-                label: Some("Elements Left Buffer"),
-                contents: bytemuck::cast_slice(&elements_per_pass),
-                usage: BufferUsages::STORAGE,
+                label: Some("Dispatch Uniform"),
+                contents,
+                usage: BufferUsages::UNIFORM,
+            })
+        };
+        let dispatch_uniforms = match kernel {
+            ReductionKernel::MultiPass => passes
+                .iter()
+                .map(|pass| uniform(bytemuck::cast_slice(&pass.uniform())))
+                .collect(),
+            // Clear dispatch, then the reduction
+            ReductionKernel::AtomicCombine => [1u32, 0]
+                .iter()
+                .map(|clear| uniform(bytemuck::bytes_of(clear)))
+                .collect(),
+            ReductionKernel::SinglePass => vec![],
+        };
+
+        let single_pass_buffers =
+            (kernel == ReductionKernel::SinglePass).then(|| {
+                let workgroups =
+                    input_data.len().div_ceil(workgroup_size as usize);
+                let partials_buffer = gpu.create_buffer(&BufferDescriptor {
+                    label: Some("Partial Sums Buffer"),
+                    size: (workgroups * size_of::<u32>()) as u64,
+                    usage: BufferUsages::STORAGE,
+                    mapped_at_creation: false,
+                });
+                let finished_buffer =
+                    gpu.create_buffer_init(&BufferInitDescriptor {
+                        label: Some("Finished Workgroups Buffer"),
+                        contents: bytemuck::bytes_of(&0u32),
+                        usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                    });
+                (partials_buffer, finished_buffer)
             });
 
         Self {
             input_buffer,
             result_buffer,
-            barriers_buffer,
-            elements_left_buffer,
+            dispatch_uniforms,
+            single_pass_buffers,
+            element_type,
         }
     }
+
+    fn create_bind_groups(
+        &self,
+        pipeline: &BenchmarkComputePipeline,
+    ) -> Vec<BindGroup> {
+        self.dispatch_uniforms
+            .iter()
+            .map(|uniform_buffer| {
+                pipeline.create_bind_group(BindGroupParams {
+                    label: Some("Dispatch Uniform Bind Group"),
+                    group: 1,
+                    entries: HashMap::from([(
+                        0,
+                        uniform_buffer.as_entire_binding(),
+                    )]),
+                })
+            })
+            .collect()
+    }
 }
 
 /// Pipeline needed for microbenchmark
-async fn reduction_sum_pipeline<'a, const BUFFER_SIZE: usize>(
+async fn reduction_sum_pipeline<'a>(
     gpu: &'a GPUContext,
-    buffers: &'a Buffers<BUFFER_SIZE>,
+    kernel: ReductionKernel,
+    buffers: &'a Buffers,
     workgroup_size: u32,
 ) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    let mut bind_group_0 = HashMap::from([
+        (0, buffers.input_buffer.as_entire_binding()),
+        (1, buffers.result_buffer.as_entire_binding()),
+    ]);
+    if let Some((partials_buffer, finished_buffer)) =
+        &buffers.single_pass_buffers
+    {
+        bind_group_0.insert(2, partials_buffer.as_entire_binding());
+        bind_group_0.insert(3, finished_buffer.as_entire_binding());
+    }
+
     BenchmarkComputePipeline::new(PipelineParameters {
         shader: ShaderModuleDescriptor {
             label: Some("reduction sum shader"),
             source: ShaderSource::Wgsl(
-                include_str!("reduction_sum.wgsl").into(),
+                kernel.shader(buffers.element_type).into(),
             ),
         },
         entry_point: "main",
-        bind_group_0: HashMap::from([
-            (0, buffers.input_buffer.as_entire_binding()),
-            (1, buffers.result_buffer.as_entire_binding()),
-            (2, buffers.barriers_buffer.as_entire_binding()),
-            (3, buffers.elements_left_buffer.as_entire_binding()),
-        ]),
+        bind_group_0,
         gpu,
         workgroup_size: Some((workgroup_size, 1, 1)),
     })
@@ -206,147 +626,222 @@ async fn reduction_sum_pipeline<'a, const BUFFER_SIZE: usize>(
 }
 
 fn workgroups_dispatch(
+    kernel: ReductionKernel,
     buffer_size: usize,
     workgroup_size: u32,
 ) -> Vec<(u32, u32, u32)> {
-    vec![(
-        1 + (buffer_size.div_ceil(workgroup_size as usize)) as u32,
-        1,
-        1,
-    )]
+    let workgroups =
+        |len: usize| (len.div_ceil(workgroup_size as usize) as u32, 1, 1);
+
+    match kernel {
+        ReductionKernel::MultiPass => passes(buffer_size, workgroup_size)
+            .iter()
+            .map(|pass| workgroups(pass.source_len as usize))
+            .collect(),
+        ReductionKernel::AtomicCombine => {
+            vec![(1, 1, 1), workgroups(buffer_size)]
+        }
+        ReductionKernel::SinglePass => vec![workgroups(buffer_size)],
+    }
+}
+
+/// Sets the uniform of each dispatch, for the kernels that have them.
+fn dispatch_callback(
+    bind_groups: &[BindGroup],
+) -> impl Fn(usize, &mut ComputePass) + use<'_> {
+    |i, pass: &mut ComputePass| {
+        if let Some(bind_group) = bind_groups.get(i) {
+            pass.set_bind_group(1, bind_group, &[]);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use uwgpu::{
-        wgpu::{BufferDescriptor, BufferUsages, MapMode},
-        Benchmark, GPUContext,
-    };
+    use uwgpu::GPUContext;
 
     use super::*;
 
-    /// Verifies that the shader computes the reduction sum correctly.
+    /// Verifies that the passes of the multi-pass kernel read the sums written
+    /// by the previous pass and end up with the sum at offset 0.
+    #[test]
+    fn multi_pass_layout() {
+        // 1000 elements -> 125 -> 16 -> 2 -> 1
+        let layout = passes(1000, 8);
+        let field =
+            |f: fn(&Pass) -> u32| layout.iter().map(f).collect::<Vec<_>>();
+
+        assert_eq!(field(|pass| pass.source_len), [1000, 125, 16, 2]);
+        assert_eq!(field(|pass| pass.destination_offset), [19, 3, 1, 0]);
+        assert_eq!(field(|pass| pass.source_offset), [0, 19, 3, 1]);
+        assert_eq!(field(|pass| pass.read_input), [1, 0, 0, 0]);
+
+        assert_eq!(passes(5, 8).len(), 1);
+        assert_eq!(
+            workgroups_dispatch(ReductionKernel::MultiPass, 1000, 8),
+            [(125, 1, 1), (16, 1, 1), (2, 1, 1), (1, 1, 1)]
+        );
+    }
+
+    /// Verifies that every kernel computes the reduction sum correctly.
     #[tokio::test]
     async fn reduction_works() {
         const BUFFER_SIZE: usize = 1000;
 
-        // Incremental buffer [0, 1, 2, 3, 4, 5, 6, ... ]
-        let input: Vec<f32> = (1..=BUFFER_SIZE).map(|i| i as f32).collect();
-        let expected_result: f32 =
-            (BUFFER_SIZE * (BUFFER_SIZE + 1)) as f32 / 2.0;
+        // Incremental buffer [1, 2, 3, 4, 5, 6, ... ]
+        let input: Vec<f64> = (1..=BUFFER_SIZE).map(|i| i as f64).collect();
+        let expected_result = (BUFFER_SIZE * (BUFFER_SIZE + 1)) as f64 / 2.0;
         // arbitrary
         let workgroup_size = 8;
 
         let gpu = GPUContext::new(None).await.unwrap();
-        let buffers = Buffers::<BUFFER_SIZE>::new_from_input(
-            &input,
-            workgroup_size,
-            &gpu,
-        );
-        let pipeline = reduction_sum_pipeline(&gpu, &buffers, workgroup_size)
-            .await
-            .unwrap();
-
-        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
-            label: Some("Staging Buffer"),
-            size: std::mem::size_of::<f32>() as u64,
-            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        for &kernel in ReductionKernel::ALL {
+            let result =
+                reduce(&gpu, kernel, workgroup_size, ElementType::F32, &input)
+                    .await
+                    .unwrap();
 
-        let _results = Benchmark {
-            warmup_count: 0,
-            count: 1,
-            workgroups_dispatch: workgroups_dispatch(
-                BUFFER_SIZE,
-                workgroup_size,
-            ),
-            dispatch_callback: None,
-            finalize_encoder_callback: Some(&|encoder| {
-                encoder.copy_buffer_to_buffer(
-                    &buffers.result_buffer,
-                    0,
-                    &staging_buffer,
-                    0,
-                    std::mem::size_of::<f32>() as u64,
-                )
-            }),
+            assert_eq!(result, expected_result, "{kernel:?}");
         }
-        .run(pipeline)
-        .await
-        .unwrap();
+    }
+
+    /// Verifies that the integer instantiations of the kernels compute the
+    /// reduction sum correctly too.
+    #[tokio::test]
+    async fn reduction_works_with_integers() {
+        const BUFFER_SIZE: usize = 1000;
 
-        let staging_slice = staging_buffer.slice(..);
-        staging_slice.map_async(MapMode::Read).await.unwrap();
+        let input: Vec<f64> = (1..=BUFFER_SIZE).map(|i| i as f64).collect();
+        let expected_result = (BUFFER_SIZE * (BUFFER_SIZE + 1)) as f64 / 2.0;
+        let workgroup_size = 64;
 
-        let result_data: Vec<f32> = {
-            let result_data_raw: &[u8] = &*staging_slice.get_mapped_range();
-            bytemuck::cast_slice(&result_data_raw).to_vec()
-        };
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        for &kernel in ReductionKernel::ALL {
+            for element_type in [ElementType::I32, ElementType::U32] {
+                let result =
+                    reduce(&gpu, kernel, workgroup_size, element_type, &input)
+                        .await
+                        .unwrap();
+
+                assert_eq!(
+                    result, expected_result,
+                    "{kernel:?} {element_type}"
+                );
+            }
+        }
+    }
 
-        staging_buffer.unmap();
+    /// Verifies that every kernel passes the check done before benchmarking
+    /// it for each of its element types, with sizes needing from a single
+    /// workgroup to several passes and workgroup sizes that aren't powers of
+    /// two. The 100_000 elements don't fit exactly in a f16 sum.
+    #[tokio::test]
+    async fn reduction_verifies() {
+        let gpu = GPUContext::new(None).await.unwrap();
 
-        assert_eq!(result_data.len(), 1);
-        assert_eq!(result_data[0], expected_result);
+        for &kernel in ReductionKernel::ALL {
+            for &element_type in kernel.element_types() {
+                if !gpu.features().contains(element_type.required_features()) {
+                    continue;
+                }
+
+                for workgroup_size in [8, 12, 64] {
+                    for size in [1, 5, 1000, 100_000] {
+                        reduction_sum_verify(
+                            &gpu,
+                            kernel,
+                            workgroup_size,
+                            element_type,
+                            size,
+                        )
+                        .await
+                        .unwrap_or_else(|err| {
+                            panic!(
+                                "{kernel:?} {element_type} {workgroup_size} \
+                                 {size}: {err}"
+                            )
+                        });
+                    }
+                }
+            }
+        }
     }
 
-    /// Verifies that instantiating the reduction buffers with random inputs
-    /// creates appropiately sized buffers, essentially by not panicking
+    /// Verifies that every kernel sums correctly on the software adapter, so
+    /// their correctness doesn't depend on the hardware they were tested on.
     #[tokio::test]
-    async fn reduction_random_inputs() {
-        const BUFFER_SIZE: usize = 5;
+    async fn reduction_verifies_on_the_fallback_adapter() {
+        let gpu = GPUContext::fallback(None).await.unwrap();
+        let workgroup_size = 64;
+
+        for &kernel in ReductionKernel::ALL {
+            for &element_type in ATOMIC_ELEMENT_TYPES {
+                for size in [1, 1000, 100_000] {
+                    reduction_sum_verify(
+                        &gpu,
+                        kernel,
+                        workgroup_size,
+                        element_type,
+                        size,
+                    )
+                    .await
+                    .unwrap_or_else(|err| {
+                        panic!("{kernel:?} {element_type} {size}: {err}")
+                    });
+                }
+            }
+        }
+    }
 
-        // arbitrary
-        let workgroup_size = 8;
+    /// Verifies that the single-pass kernel resets its counter of finished
+    /// workgroups, so repeated iterations keep finding their last workgroup.
+    #[tokio::test]
+    async fn single_pass_reduction_resets_counter() {
+        let kernel = ReductionKernel::SinglePass;
+        let input = ElementType::F32.random(10_000);
+        let workgroup_size = 64;
 
         let gpu = GPUContext::new(None).await.unwrap();
-        let buffers =
-            Buffers::<BUFFER_SIZE>::new_with_random_input(workgroup_size, &gpu);
-        let pipeline = reduction_sum_pipeline(&gpu, &buffers, workgroup_size)
-            .await
-            .unwrap();
-
-        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
-            label: Some("Staging Buffer"),
-            size: std::mem::size_of::<f32>() as u64,
-            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let _results = Benchmark {
-            warmup_count: 0,
-            count: 1,
+        let buffers = Buffers::new(
+            &gpu,
+            kernel,
+            &input,
+            workgroup_size,
+            ElementType::F32,
+        );
+        let pipeline =
+            reduction_sum_pipeline(&gpu, kernel, &buffers, workgroup_size)
+                .await
+                .unwrap();
+        let (_, finished_buffer) =
+            buffers.single_pass_buffers.as_ref().unwrap();
+
+        let staging_buffer = staging_buffer(&gpu, size_of::<u32>() as u64);
+        Benchmark {
+            warmup_count: 3,
+            count: 5,
             workgroups_dispatch: workgroups_dispatch(
-                BUFFER_SIZE,
+                kernel,
+                input.len(),
                 workgroup_size,
             ),
-            dispatch_callback: None,
             finalize_encoder_callback: Some(&|encoder| {
                 encoder.copy_buffer_to_buffer(
-                    &buffers.result_buffer,
+                    finished_buffer,
                     0,
                     &staging_buffer,
                     0,
-                    std::mem::size_of::<f32>() as u64,
+                    size_of::<u32>() as u64,
                 )
             }),
+            ..Default::default()
         }
         .run(pipeline)
         .await
         .unwrap();
 
-        let staging_slice = staging_buffer.slice(..);
-        staging_slice.map_async(MapMode::Read).await.unwrap();
-
-        let result_data: Vec<f32> = {
-            let result_data_raw: &[u8] = &*staging_slice.get_mapped_range();
-            bytemuck::cast_slice(&result_data_raw).to_vec()
-        };
-
-        staging_buffer.unmap();
-
-        assert_eq!(result_data.len(), 1);
-        assert!(result_data[0].is_finite());
-        assert_ne!(result_data[0], 0.0);
+        let finished = read_staging_buffer(&staging_buffer).await;
+        assert_eq!(finished, 0u32.to_le_bytes());
     }
 }
@@ -0,0 +1,47 @@
+@group(0) @binding(0) var<storage, read> input: array<$element$>;
+
+// Declares the atomic `result` the workgroups add their sums to at binding 1,
+// and the `atomic_add` and `atomic_clear` functions that access it
+$atomic_add$
+
+// Whether this dispatch only clears `result`, which happens in a dispatch of
+// its own before each reduction so the iterations don't accumulate
+@group(1) @binding(0) var<uniform> clear: u32;
+
+var<workgroup> wg_reduce: array<$element$, $workgroup_x$>;
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>) {
+
+    if clear == 1u {
+        if global_id.x == 0u {
+            atomic_clear();
+        }
+        return;
+    }
+
+    if global_id.x < arrayLength(&input) {
+        wg_reduce[local_id.x] = input[global_id.x];
+    } else {
+        wg_reduce[local_id.x] = $element$(0); // Zero pad for out-of-bounds threads
+    }
+
+    workgroupBarrier();
+
+    // Halves the remaining elements each step, rounding up so the workgroup
+    // size doesn't need to be a power of two
+    var remaining: u32 = $workgroup_x$u;
+    while remaining > 1u {
+        let half = (remaining + 1u) / 2u;
+        if local_id.x < remaining - half {
+            wg_reduce[local_id.x] += wg_reduce[local_id.x + half];
+        }
+        remaining = half;
+        workgroupBarrier();
+    }
+
+    if local_id.x == 0u {
+        atomic_add(wg_reduce[0]);
+    }
+}
@@ -0,0 +1,57 @@
+@group(0) @binding(0) var<storage, read> input: array<$element$>;
+
+// Sums produced by the workgroups of every pass. Each pass writes its sums
+// right before the ones it reads, so the last pass writes the final sum into
+// the first element.
+@group(0) @binding(1) var<storage, read_write> result: array<$element$>;
+
+struct Pass {
+    // Where the elements summed by this pass start in `result`
+    source_offset: u32,
+    // Amount of elements summed by this pass
+    source_len: u32,
+    // Where the sums of the workgroups of this pass go in `result`
+    destination_offset: u32,
+    // Whether this is the first pass, which sums `input` instead of `result`
+    read_input: u32,
+}
+
+// Set before each dispatch, the passes only depend on each other through the
+// dispatches finishing so no forward-progress guarantees are needed.
+@group(1) @binding(0) var<uniform> reduction_pass: Pass;
+
+var<workgroup> wg_reduce: array<$element$, $workgroup_x$>;
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>) {
+
+    var value = $element$(0); // Zero pad for out-of-bounds threads
+    if global_id.x < reduction_pass.source_len {
+        if reduction_pass.read_input == 1u {
+            value = input[global_id.x];
+        } else {
+            value = result[reduction_pass.source_offset + global_id.x];
+        }
+    }
+    wg_reduce[local_id.x] = value;
+
+    workgroupBarrier();
+
+    // Halves the remaining elements each step, rounding up so the workgroup
+    // size doesn't need to be a power of two
+    var remaining: u32 = $workgroup_x$u;
+    while remaining > 1u {
+        let half = (remaining + 1u) / 2u;
+        if local_id.x < remaining - half {
+            wg_reduce[local_id.x] += wg_reduce[local_id.x + half];
+        }
+        remaining = half;
+        workgroupBarrier();
+    }
+
+    if local_id.x == 0u {
+        result[reduction_pass.destination_offset + workgroup_id.x] = wg_reduce[0];
+    }
+}
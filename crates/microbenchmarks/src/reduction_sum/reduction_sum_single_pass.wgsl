@@ -0,0 +1,77 @@
+@group(0) @binding(0) var<storage, read> input: array<$element$>;
+@group(0) @binding(1) var<storage, read_write> result: array<$element$>;
+
+// Sum of each workgroup as its bits, stored and loaded atomically so the last
+// workgroup doesn't read them from a stale cache
+@group(0) @binding(2) var<storage, read_write> partials: array<atomic<u32>>;
+
+// Amount of workgroups that have stored their sum in `partials`. Reset by the
+// last workgroup, so the next iteration starts from 0 again.
+@group(0) @binding(3) var<storage, read_write> finished: atomic<u32>;
+
+var<workgroup> wg_reduce: array<$element$, $workgroup_x$>;
+var<workgroup> wg_ticket: u32;
+
+// Single dispatch: the last workgroup to finish sums the sums of the rest.
+// No workgroup ever waits for another one, since WebGPU doesn't guarantee
+// they make progress concurrently, but WGSL's memory model doesn't guarantee
+// the last workgroup sees the other sums either. That's why this kernel has
+// to pass a correctness check before being benchmarked.
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>) {
+
+    if global_id.x < arrayLength(&input) {
+        wg_reduce[local_id.x] = input[global_id.x];
+    } else {
+        wg_reduce[local_id.x] = $element$(0); // Zero pad for out-of-bounds threads
+    }
+
+    workgroupBarrier();
+    reduce_workgroup(local_id.x);
+
+    if local_id.x == 0u {
+        atomicStore(&partials[workgroup_id.x], bitcast<u32>(wg_reduce[0]));
+    }
+
+    storageBarrier();
+
+    if local_id.x == 0u {
+        wg_ticket = atomicAdd(&finished, 1u);
+    }
+    let ticket = workgroupUniformLoad(&wg_ticket);
+    if ticket != num_workgroups.x - 1u {
+        return;
+    }
+
+    // Last workgroup: sum the sums of every workgroup
+    var sum = $element$(0);
+    for (var i = local_id.x; i < num_workgroups.x; i += $workgroup_x$u) {
+        sum += bitcast<$element$>(atomicLoad(&partials[i]));
+    }
+    wg_reduce[local_id.x] = sum;
+
+    workgroupBarrier();
+    reduce_workgroup(local_id.x);
+
+    if local_id.x == 0u {
+        result[0] = wg_reduce[0];
+        atomicStore(&finished, 0u);
+    }
+}
+
+// Sums `wg_reduce` into its first element. Halves the remaining elements each
+// step, rounding up so the workgroup size doesn't need to be a power of two.
+fn reduce_workgroup(local_index: u32) {
+    var remaining: u32 = $workgroup_x$u;
+    while remaining > 1u {
+        let half = (remaining + 1u) / 2u;
+        if local_index < remaining - half {
+            wg_reduce[local_index] += wg_reduce[local_index + half];
+        }
+        remaining = half;
+        workgroupBarrier();
+    }
+}
@@ -0,0 +1,297 @@
+//! Roofline model of a device from its measured peak FLOPS and bandwidth, see
+//! [Roofline]
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+use uwgpu::GPUContext;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    find_microbenchmark, fma_throughput::FmaThroughput,
+    memcpy::buffer_to_buffer::BufferToBuffer, BenchmarkError, ElementType,
+    Metric, MetricKind, Microbenchmark, MicrobenchmarkParameters, Sweep,
+    SweepResults,
+};
+
+/// Roofline model of a device: the performance a kernel can attain is bound
+/// by the peak FLOPS of the device or, for kernels that do few operations per
+/// byte, by the peak bandwidth times its arithmetic intensity.
+///
+/// The computational microbenchmarks are placed on it as [RooflinePoint]s, to
+/// see how close they get to what the device could attain.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+pub struct Roofline {
+    /// Floating point operations per second of the
+    /// [FMA throughput](crate::fma_throughput) microbenchmark
+    pub peak_flops: f64,
+    /// Bytes per second read and written by the
+    /// [buffer to buffer copy](crate::memcpy::buffer_to_buffer)
+    /// microbenchmark
+    pub peak_bandwidth: f64,
+    /// Microbenchmark runs placed on the roofline
+    pub points: Vec<RooflinePoint>,
+}
+
+/// A microbenchmark run placed on a [Roofline], see [Roofline::place].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+pub struct RooflinePoint {
+    /// The [Microbenchmark::name]
+    pub microbenchmark: String,
+    /// The [MicrobenchmarkParameters::element_type]
+    pub element_type: ElementType,
+    /// The [MicrobenchmarkParameters::size]
+    pub size: usize,
+    /// Workgroup size the microbenchmark ran with, with as many dimensions as
+    /// the [Microbenchmark::workgroup_dimensions]
+    pub workgroup_size: Vec<u32>,
+    /// See [Microbenchmark::arithmetic_intensity]
+    pub arithmetic_intensity: f64,
+    /// Floating point operations per second achieved by the run
+    pub flops: f64,
+    /// Floating point operations per second the roofline allows at the
+    /// arithmetic intensity, see [Roofline::attainable_flops]
+    pub attainable_flops: f64,
+    /// Percentage of the attainable FLOPS achieved
+    pub percent_of_roof: f64,
+    /// Whether the arithmetic intensity is below the
+    /// [Roofline::ridge_point], so the bandwidth is what bounds the run
+    pub memory_bound: bool,
+}
+
+/// An error building a [Roofline]
+#[derive(Debug, Clone, Error)]
+pub enum RooflineError {
+    /// None of the runs of the microbenchmark that measures one of the peaks
+    /// succeeded
+    #[error("the peak couldn't be measured, no run of {0} succeeded")]
+    MissingPeak(&'static str),
+    /// An error running the microbenchmark that measures one of the peaks
+    #[error("error measuring the peak: {0}")]
+    Benchmark(#[from] BenchmarkError),
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Roofline {
+    /// Roofline with the given peaks and no points.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(peak_flops: f64, peak_bandwidth: f64) -> Self {
+        Self {
+            peak_flops,
+            peak_bandwidth,
+            points: vec![],
+        }
+    }
+
+    /// Arithmetic intensity in FLOPs per byte at which the bandwidth stops
+    /// bounding the attainable FLOPS.
+    pub fn ridge_point(&self) -> f64 { self.peak_flops / self.peak_bandwidth }
+
+    /// Floating point operations per second attainable by a kernel with the
+    /// given arithmetic intensity in FLOPs per byte.
+    pub fn attainable_flops(&self, arithmetic_intensity: f64) -> f64 {
+        self.peak_flops
+            .min(arithmetic_intensity * self.peak_bandwidth)
+    }
+}
+
+impl Roofline {
+    /// Measures the peaks of the device by running the
+    /// [FMA throughput](crate::fma_throughput) and
+    /// [buffer to buffer copy](crate::memcpy::buffer_to_buffer)
+    /// microbenchmarks with their default workgroup sizes, see
+    /// [Roofline::from_sweep].
+    pub async fn measure(gpu: &GPUContext) -> Result<Self, RooflineError> {
+        let sweep = Sweep {
+            microbenchmarks: vec![&FmaThroughput, &BufferToBuffer],
+            ..Default::default()
+        };
+
+        Self::from_sweep(&sweep.run(gpu).await)
+    }
+
+    /// Roofline with the peaks given by the fastest runs of the
+    /// [FMA throughput](crate::fma_throughput) and
+    /// [buffer to buffer copy](crate::memcpy::buffer_to_buffer)
+    /// microbenchmarks in the results, with the rest of the runs placed on
+    /// it.
+    ///
+    /// The copy's bandwidth only counts the bytes copied, so the peak
+    /// bandwidth is twice as much since each of them is read and written.
+    /// Only the fastest workgroup size of each microbenchmark, element type
+    /// and size gets placed.
+    pub fn from_sweep(results: &SweepResults) -> Result<Self, RooflineError> {
+        let peak = |microbenchmark: &'static dyn Microbenchmark,
+                    kind: MetricKind| {
+            let mut peak: Option<f64> = None;
+            let mut error = None;
+            let runs = results
+                .runs
+                .iter()
+                .filter(|(key, _)| key.microbenchmark == microbenchmark.name());
+            for (_, outcome) in runs {
+                match outcome {
+                    Ok(run) => {
+                        if let Some(value) = metric_value(&run.metrics, kind) {
+                            peak = Some(peak.map_or(value, |p| p.max(value)));
+                        }
+                    }
+                    Err(err) => error = Some(err.clone()),
+                }
+            }
+
+            match (peak, error) {
+                (Some(peak), _) => Ok(peak),
+                (None, Some(err)) => Err(RooflineError::Benchmark(err)),
+                (None, None) => {
+                    Err(RooflineError::MissingPeak(microbenchmark.name()))
+                }
+            }
+        };
+
+        let mut roofline = Roofline::new(
+            peak(&FmaThroughput, MetricKind::Flops)?,
+            2.0 * peak(&BufferToBuffer, MetricKind::Bandwidth)?,
+        );
+
+        let mut fastest: BTreeMap<_, (f64, _, _)> = BTreeMap::new();
+        for (key, outcome) in &results.runs {
+            let Ok(run) = outcome else { continue };
+            let Some(flops) = metric_value(&run.metrics, MetricKind::Flops)
+            else {
+                continue;
+            };
+
+            let group = (key.microbenchmark, key.element_type, key.size);
+            match fastest.get(&group) {
+                Some(&(best, ..)) if best >= flops => {}
+                _ => {
+                    fastest.insert(group, (flops, key.workgroup_size, run));
+                }
+            }
+        }
+
+        for ((name, ..), (_, workgroup_size, run)) in fastest {
+            let microbenchmark = find_microbenchmark(name)
+                .expect("swept microbenchmarks are registered");
+            roofline.place(
+                microbenchmark,
+                workgroup_size,
+                &run.parameters,
+                &run.metrics,
+            );
+        }
+
+        Ok(roofline)
+    }
+
+    /// Places a run of the microbenchmark with the given metrics on the
+    /// roofline.
+    ///
+    /// Returns [None] without placing it if the microbenchmark has no
+    /// [Microbenchmark::arithmetic_intensity] or no FLOPS metric.
+    pub fn place(
+        &mut self,
+        microbenchmark: &dyn Microbenchmark,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+        metrics: &[Metric],
+    ) -> Option<&RooflinePoint> {
+        let arithmetic_intensity =
+            microbenchmark.arithmetic_intensity(parameters)?;
+        let flops = metric_value(metrics, MetricKind::Flops)?;
+        let attainable_flops = self.attainable_flops(arithmetic_intensity);
+        let (x, y, z) = workgroup_size;
+
+        self.points.push(RooflinePoint {
+            microbenchmark: microbenchmark.name().to_string(),
+            element_type: parameters.element_type,
+            size: parameters.size,
+            workgroup_size: [x, y, z][..microbenchmark.workgroup_dimensions()]
+                .to_vec(),
+            arithmetic_intensity,
+            flops,
+            attainable_flops,
+            percent_of_roof: 100.0 * flops / attainable_flops,
+            memory_bound: arithmetic_intensity < self.ridge_point(),
+        });
+
+        self.points.last()
+    }
+}
+
+/// Value of the first metric of the given kind
+fn metric_value(metrics: &[Metric], kind: MetricKind) -> Option<f64> {
+    metrics
+        .iter()
+        .find(|metric| metric.kind == kind)
+        .map(|metric| metric.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies the roof follows the bandwidth up to the ridge point and the
+    /// peak FLOPS past it.
+    #[test]
+    fn roof_is_bound_by_both_peaks() {
+        let roofline = Roofline::new(1e12, 1e11);
+
+        assert_eq!(roofline.ridge_point(), 10.0);
+        assert_eq!(roofline.attainable_flops(0.5), 5e10);
+        assert_eq!(roofline.attainable_flops(10.0), 1e12);
+        assert_eq!(roofline.attainable_flops(1000.0), 1e12);
+    }
+
+    /// Verifies the points get the microbenchmark's arithmetic intensity and
+    /// the percentage of the roof they reach.
+    #[test]
+    fn runs_are_placed_by_arithmetic_intensity() {
+        let mut roofline = Roofline::new(1e12, 1e11);
+        let matmul = find_microbenchmark("matmul").unwrap();
+        let reduction = find_microbenchmark("reduction_sum").unwrap();
+        let copy = find_microbenchmark("buffer_to_buffer").unwrap();
+
+        let parameters = MicrobenchmarkParameters::new(1024, 0, 1);
+        let point = roofline
+            .place(matmul, (8, 8, 1), &parameters, &[Metric::flops(5e11)])
+            .unwrap()
+            .clone();
+        // 1024² * 2047 FLOPs over 3 * 1024² * 4 bytes
+        assert_eq!(point.arithmetic_intensity, 2047.0 / 12.0);
+        assert_eq!(point.attainable_flops, 1e12);
+        assert_eq!(point.percent_of_roof, 50.0);
+        assert_eq!(point.workgroup_size, [8, 8]);
+        assert!(!point.memory_bound);
+
+        let parameters = MicrobenchmarkParameters::new(1 << 20, 0, 1);
+        let point = roofline
+            .place(reduction, (64, 1, 1), &parameters, &[Metric::flops(1e10)])
+            .unwrap();
+        assert!(point.memory_bound);
+        let expected = 100.0 * 1e10 / (point.arithmetic_intensity * 1e11);
+        assert!((point.percent_of_roof - expected).abs() < 1e-9);
+
+        assert!(roofline
+            .place(copy, (64, 1, 1), &parameters, &[Metric::bandwidth(1e11)])
+            .is_none());
+        assert_eq!(roofline.points.len(), 2);
+    }
+
+    /// Verifies the peaks are missing from results without their
+    /// microbenchmarks.
+    #[test]
+    fn peaks_are_required() {
+        assert!(matches!(
+            Roofline::from_sweep(&SweepResults::default()),
+            Err(RooflineError::MissingPeak("fma_throughput"))
+        ));
+    }
+}
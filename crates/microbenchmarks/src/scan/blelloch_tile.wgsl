@@ -0,0 +1,41 @@
+// Tile of 2 elements per invocation scanned by each workgroup
+var<workgroup> tile: array<$element$, $tile_elements$>;
+var<workgroup> tile_total: $element$;
+
+// Replaces `tile` with its exclusive scan with an up-sweep and a down-sweep
+// (Blelloch), returning the sum of the whole tile. Every invocation of the
+// workgroup must call it, after storing its 2 elements.
+fn scan_tile(local_index: u32) -> $element$ {
+    // Up-sweep: builds a tree of partial sums in place
+    var offset = 1u;
+    for (var d = $tile_elements$u / 2u; d > 0u; d /= 2u) {
+        workgroupBarrier();
+        if local_index < d {
+            let a = offset * (2u * local_index + 1u) - 1u;
+            let b = offset * (2u * local_index + 2u) - 1u;
+            tile[b] += tile[a];
+        }
+        offset *= 2u;
+    }
+
+    workgroupBarrier();
+    if local_index == 0u {
+        tile_total = tile[$tile_elements$u - 1u];
+        tile[$tile_elements$u - 1u] = $element$(0);
+    }
+
+    // Down-sweep: pushes the partial sums down the tree as prefixes
+    for (var d = 1u; d < $tile_elements$u; d *= 2u) {
+        offset /= 2u;
+        workgroupBarrier();
+        if local_index < d {
+            let a = offset * (2u * local_index + 1u) - 1u;
+            let b = offset * (2u * local_index + 2u) - 1u;
+            let left = tile[a];
+            tile[a] = tile[b];
+            tile[b] += left;
+        }
+    }
+
+    return workgroupUniformLoad(&tile_total);
+}
@@ -1,64 +1,348 @@
-//! Scan, also known as prefixed sum, microbenchmarks
+//! Scan, also known as prefixed sum, microbenchmarks, with a kernel for each
+//! way of computing it, see [ScanKernel]
 
 use std::collections::HashMap;
 
-use rand::{thread_rng, Rng};
 use uwgpu::{
     wgpu::{
-        util::BufferInitDescriptor, BindGroup, BufferUsages, ComputePass,
-        ShaderModuleDescriptor, ShaderSource,
+        util::BufferInitDescriptor, BindGroup, BufferDescriptor, BufferUsages,
+        ComputePass, Limits, ShaderModuleDescriptor, ShaderSource,
     },
     wgpu_async::AsyncBuffer,
     Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
-    GPUContext, PipelineParameters,
+    GPUContext, InputReset, PipelineParameters, ResetFrequency,
 };
 use uwgpu::{BindGroupParams, TimeUnit};
 
-use crate::BenchmarkError;
+use crate::{
+    accuracy::{read_staging_buffer, sample_indices, staging_buffer},
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, AccuracyFuture, Metric,
+        Microbenchmark, MicrobenchmarkParameters, RunFuture,
+    },
+    Accuracy, BenchmarkError, ElementType,
+};
 
 /// 1MiB size buffer (of f32)
-const BENCHMARK_BUFFER_SIZE: usize = 262_144;
-const BENCHMARK_WARMUP_COUNT: usize = 200;
-const BENCHMARK_ITERATIONS: usize = 2000;
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 262_144,
+    warmup_count: 200,
+    count: 2000,
+    element_type: ElementType::F32,
+};
+
+/// Element types of [ScanKernel::DecoupledLookBack], which shares the sums of
+/// the tiles through 32 bit atomics
+const ATOMIC_ELEMENT_TYPES: &[ElementType] =
+    &[ElementType::F32, ElementType::I32, ElementType::U32];
+
+/// Whether each element of the scan includes itself in its sum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScanMode {
+    /// Each element is the sum of itself and all the elements before it.
+    Inclusive,
+    /// Each element is the sum of all the elements before it, so the first
+    /// one is 0.
+    Exclusive,
+}
+
+/// The ways of computing the scan, each of them is a separate [Scan]
+/// microbenchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ScanKernel {
+    /// Naive inclusive scan in-place, with a dispatch per stride that adds
+    /// the last element of each block to the whole next block. It does
+    /// O(n log n) additions.
+    Sklansky,
+    /// Work-efficient scan doing O(n) additions. Each workgroup scans a tile
+    /// of 2 elements per invocation in workgroup memory with an up-sweep and
+    /// a down-sweep, the sums of the tiles get scanned the same way, and
+    /// then added to the tiles, with a dispatch for each level.
+    Blelloch(ScanMode),
+    /// Single dispatch scanning the tiles like [ScanKernel::Blelloch], where
+    /// each workgroup gets the sum of the tiles before it by looking back on
+    /// the sums they publish (chained scan with decoupled look-back).
+    ///
+    /// It relies on the sums being visible once their flags are, which
+    /// WebGPU doesn't guarantee, so it's opt-in (see
+    /// [Microbenchmark::opt_in]) and checked with [scan_verify] before every
+    /// benchmark, failing on the devices where it isn't correct.
+    DecoupledLookBack(ScanMode),
+}
+
+impl ScanKernel {
+    /// Every kernel, from the naive one to the single-pass ones.
+    pub const ALL: &'static [ScanKernel] = &[
+        ScanKernel::Sklansky,
+        ScanKernel::Blelloch(ScanMode::Inclusive),
+        ScanKernel::Blelloch(ScanMode::Exclusive),
+        ScanKernel::DecoupledLookBack(ScanMode::Inclusive),
+        ScanKernel::DecoupledLookBack(ScanMode::Exclusive),
+    ];
+
+    /// Whether the kernel computes an inclusive or exclusive scan.
+    pub fn mode(&self) -> ScanMode {
+        match self {
+            ScanKernel::Sklansky => ScanMode::Inclusive,
+            ScanKernel::Blelloch(mode)
+            | ScanKernel::DecoupledLookBack(mode) => *mode,
+        }
+    }
+
+    /// Element types the kernel can scan.
+    fn element_types(&self) -> &'static [ElementType] {
+        match self {
+            ScanKernel::Sklansky | ScanKernel::Blelloch(_) => ElementType::ALL,
+            ScanKernel::DecoupledLookBack(_) => ATOMIC_ELEMENT_TYPES,
+        }
+    }
+
+    /// Amount of elements scanned by each workgroup of the kernels working
+    /// on tiles, 2 per invocation.
+    fn tile_elements(workgroup_size: u32) -> u32 { 2 * workgroup_size }
+
+    /// Bytes of workgroup storage used by each workgroup of the kernel, the
+    /// tile of `blelloch_tile.wgsl` and its total plus the look-back state.
+    fn workgroup_storage_size(
+        &self,
+        element_type: ElementType,
+        workgroup_size: u32,
+    ) -> u64 {
+        let tile = Self::tile_elements(workgroup_size) as u64 + 1;
+        let element_size = element_type.size() as u64;
+        match self {
+            ScanKernel::Sklansky => 0,
+            ScanKernel::Blelloch(_) => tile * element_size,
+            ScanKernel::DecoupledLookBack(_) => {
+                (tile + workgroup_size as u64 + 1) * element_size
+                    + 2 * size_of::<u32>() as u64
+            }
+        }
+    }
+
+    /// Shader of the kernel for the element type and workgroup size.
+    fn shader(&self, element_type: ElementType, workgroup_size: u32) -> String {
+        let source = match self {
+            ScanKernel::Sklansky => include_str!("scan.wgsl"),
+            ScanKernel::Blelloch(_) => include_str!("scan_blelloch.wgsl"),
+            ScanKernel::DecoupledLookBack(_) => {
+                include_str!("scan_decoupled_look_back.wgsl")
+            }
+        }
+        .replace("$scan_tile$", include_str!("blelloch_tile.wgsl"))
+        .replace(
+            "$tile_elements$",
+            &Self::tile_elements(workgroup_size).to_string(),
+        )
+        .replace(
+            "$inclusive$",
+            &(self.mode() == ScanMode::Inclusive).to_string(),
+        );
+
+        element_type.shader(&source)
+    }
+}
 
-/// Microbenchmark for a scan operation.
+/// Microbenchmark for a scan operation with the given kernel, the
+/// [MicrobenchmarkParameters::size] is the amount of elements scanned and the
+/// [MicrobenchmarkParameters::element_type] their type.
 ///
 /// The workgroup size will dictate the size of workgroups used for the
 /// computation.
 ///
-/// ## Panic
+/// [ScanKernel::DecoupledLookBack] is checked to scan correctly with the
+/// workgroup size and amount of elements before benchmarking it, see
+/// [scan_verify].
 ///
-/// This function panics if the workgroup size is not a power of 2, because it
-/// hasn't really been considered how to handle such cases.
+/// Fails with [BenchmarkError::WorkgroupSizeNotPowerOfTwo] if the workgroup
+/// size is not a power of 2, because it hasn't really been considered how to
+/// handle such cases.
 pub async fn scan_benchmark(
+    gpu: &GPUContext,
+    kernel: ScanKernel,
     workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
 ) -> Result<ScanResults, BenchmarkError> {
-    assert!(workgroup_size.is_power_of_two());
+    check_workgroup_size(workgroup_size)?;
 
-    let gpu = GPUContext::new(None).await?;
-    let buffers = Buffers::<BENCHMARK_BUFFER_SIZE>::new_with_random_input(
+    if let ScanKernel::DecoupledLookBack(_) = kernel {
+        scan_verify(gpu, kernel, workgroup_size, parameters.size).await?;
+    }
+
+    let input = parameters.element_type.random(parameters.size);
+    let buffers = Buffers::new(
+        gpu,
+        kernel,
+        &input,
         workgroup_size,
-        &gpu,
+        parameters.element_type,
     );
-    let pipeline = scan_pipeline(&gpu, &buffers, workgroup_size).await?;
+    let pipeline = scan_pipeline(gpu, kernel, &buffers, workgroup_size).await?;
 
     let results = Benchmark {
-        warmup_count: BENCHMARK_WARMUP_COUNT,
-        count: BENCHMARK_ITERATIONS,
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
         finalize_encoder_callback: None,
         workgroups_dispatch: workgroup_dispatches(
-            buffers.strides.len(),
-            BENCHMARK_BUFFER_SIZE,
+            kernel,
+            parameters.size,
             workgroup_size,
         ),
         dispatch_callback: Some(&dispatch_callback(
             &buffers.create_bind_groups(&pipeline),
         )),
+        // The Sklansky scan is done in-place, so without restoring the input
+        // each iteration would keep scanning the previous one's output
+        input_resets: buffers
+            .pristine_buffer
+            .iter()
+            .map(|pristine_buffer| InputReset {
+                source: pristine_buffer,
+                destination: &buffers.data_buffer,
+                frequency: ResetFrequency::PerIteration,
+            })
+            .collect(),
+        ..Default::default()
     }
     .run(pipeline)
     .await?;
 
-    Ok(ScanResults(results))
+    Ok(ScanResults {
+        results,
+        parameters,
+        kernel,
+    })
+}
+
+/// Checks that the kernel scans `size` elements correctly with the workgroup
+/// size, required before benchmarking [ScanKernel::DecoupledLookBack] since
+/// a kernel that gives wrong scans would give meaningless timings.
+///
+/// Scans [ElementType::U32] elements, so the result must be exact. Returns a
+/// [BenchmarkError::IncorrectOutput] with the first wrong element otherwise.
+///
+/// Like [scan_benchmark], fails if the workgroup size is not a power of 2.
+pub async fn scan_verify(
+    gpu: &GPUContext,
+    kernel: ScanKernel,
+    workgroup_size: u32,
+    size: usize,
+) -> Result<(), BenchmarkError> {
+    check_workgroup_size(workgroup_size)?;
+
+    let input: Vec<f64> = (0..size).map(|i| (i % 16) as f64).collect();
+    let output =
+        scan(gpu, kernel, workgroup_size, ElementType::U32, &input).await?;
+
+    // Wrapping like the sums on the GPU would for huge sizes
+    let reference = reference_scan(kernel.mode(), &input)
+        .into_iter()
+        .map(|sum| sum % 2f64.powi(32));
+    match output
+        .into_iter()
+        .zip(reference)
+        .find(|(output, expected)| output != expected)
+    {
+        None => Ok(()),
+        Some((output, expected)) => {
+            Err(BenchmarkError::IncorrectOutput { output, expected })
+        }
+    }
+}
+
+/// Accuracy of a single scan of random data with the parameters of
+/// [scan_benchmark], against a scan computed on the CPU.
+///
+/// Like [scan_benchmark], fails if the workgroup size is not a power of 2.
+pub async fn scan_accuracy(
+    gpu: &GPUContext,
+    kernel: ScanKernel,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<Accuracy, BenchmarkError> {
+    check_workgroup_size(workgroup_size)?;
+
+    let element_type = parameters.element_type;
+    let input = element_type.random(parameters.size);
+    let result =
+        scan(gpu, kernel, workgroup_size, element_type, &input).await?;
+    let reference = reference_scan(kernel.mode(), &input);
+
+    Ok(Accuracy::measure(
+        element_type,
+        sample_indices(input.len())
+            .into_iter()
+            .map(|i| (result[i], reference[i])),
+    ))
+}
+
+/// Fails with [BenchmarkError::WorkgroupSizeNotPowerOfTwo] if the kernels
+/// can't work with the workgroup size.
+fn check_workgroup_size(workgroup_size: u32) -> Result<(), BenchmarkError> {
+    if !workgroup_size.is_power_of_two() {
+        return Err(BenchmarkError::WorkgroupSizeNotPowerOfTwo(workgroup_size));
+    }
+
+    Ok(())
+}
+
+/// Scans the input on the GPU once with the kernel.
+async fn scan(
+    gpu: &GPUContext,
+    kernel: ScanKernel,
+    workgroup_size: u32,
+    element_type: ElementType,
+    input: &[f64],
+) -> Result<Vec<f64>, BenchmarkError> {
+    let buffers =
+        Buffers::new(gpu, kernel, input, workgroup_size, element_type);
+    let pipeline = scan_pipeline(gpu, kernel, &buffers, workgroup_size).await?;
+
+    let result_size = element_type.buffer_size(input.len());
+    let staging_buffer = staging_buffer(gpu, result_size);
+    Benchmark {
+        warmup_count: 0,
+        count: 1,
+        workgroups_dispatch: workgroup_dispatches(
+            kernel,
+            input.len(),
+            workgroup_size,
+        ),
+        dispatch_callback: Some(&dispatch_callback(
+            &buffers.create_bind_groups(&pipeline),
+        )),
+        finalize_encoder_callback: Some(&|encoder| {
+            encoder.copy_buffer_to_buffer(
+                &buffers.data_buffer,
+                0,
+                &staging_buffer,
+                0,
+                result_size,
+            )
+        }),
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(element_type.decode(&read_staging_buffer(&staging_buffer).await))
+}
+
+/// Scan of the input computed on the CPU.
+fn reference_scan(mode: ScanMode, input: &[f64]) -> Vec<f64> {
+    input
+        .iter()
+        .scan(0.0, |sum, element| {
+            let exclusive = *sum;
+            *sum += element;
+            Some(match mode {
+                ScanMode::Inclusive => *sum,
+                ScanMode::Exclusive => exclusive,
+            })
+        })
+        .collect()
 }
 
 #[cfg(feature = "wasm")]
@@ -68,17 +352,19 @@ use wasm_bindgen::prelude::*;
 /// [scan_benchmark].
 ///
 /// Wraps a [BenchmarkResults] with some convenience methods.
-#[cfg_attr(
-    feature = "serde",
-    derive(serde::Serialize, serde::Deserialize),
-    serde(transparent)
-)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_eval]
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
-pub struct ScanResults(
+pub struct ScanResults {
+    /// Timings of the benchmark
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
-    pub  BenchmarkResults,
-);
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// Kernel the benchmark ran with
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub kernel: ScanKernel,
+}
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl ScanResults {
@@ -88,70 +374,393 @@ impl ScanResults {
     /// would've been with padded out zeroes that don't affect the result,
     /// so I'm not counting those.
     pub fn flops(&self) -> f64 {
-        const NUM_PASSES: usize =
-            (BENCHMARK_BUFFER_SIZE * 2 - 1).ilog2() as usize;
-        const NUM_FLOPS_PER_ITER: usize =
-            NUM_PASSES * (BENCHMARK_BUFFER_SIZE / 2);
-
-        (NUM_FLOPS_PER_ITER as f64 * self.0.count as f64)
-            / (self.0.total_time(TimeUnit::Second))
+        (flops_per_iteration(self.kernel, self.parameters.size) as f64
+            * self.results.count as f64)
+            / (self.results.total_time(TimeUnit::Second))
     }
 }
 
-/// GPU buffers needed for microbenchmark
-struct Buffers<const BUFFER_SIZE: usize> {
-    /// Buffer for scan operation done in-place
-    data_buffer: AsyncBuffer,
-    /// For each pass that the scan has to do, a stride is given.
-    strides: Vec<AsyncBuffer>,
+/// Scan microbenchmark with the given kernel, see [scan_benchmark].
+pub struct Scan(pub ScanKernel);
+
+impl Microbenchmark for Scan {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            ScanKernel::Sklansky => "scan",
+            ScanKernel::Blelloch(ScanMode::Inclusive) => "scan_blelloch",
+            ScanKernel::Blelloch(ScanMode::Exclusive) => {
+                "scan_blelloch_exclusive"
+            }
+            ScanKernel::DecoupledLookBack(ScanMode::Inclusive) => {
+                "scan_decoupled_look_back"
+            }
+            ScanKernel::DecoupledLookBack(ScanMode::Exclusive) => {
+                "scan_decoupled_look_back_exclusive"
+            }
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self.0 {
+            ScanKernel::Sklansky => "Scan",
+            ScanKernel::Blelloch(ScanMode::Inclusive) => "Blelloch Scan",
+            ScanKernel::Blelloch(ScanMode::Exclusive) => {
+                "Exclusive Blelloch Scan"
+            }
+            ScanKernel::DecoupledLookBack(ScanMode::Inclusive) => {
+                "Decoupled Look-Back Scan"
+            }
+            ScanKernel::DecoupledLookBack(ScanMode::Exclusive) => {
+                "Exclusive Decoupled Look-Back Scan"
+            }
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.0 {
+            ScanKernel::Sklansky => "This microbenchmark tests the performance of a naive multi-pass scan using the Sklansky technique over a 1MB buffer of random data.",
+            ScanKernel::Blelloch(ScanMode::Inclusive) => "This microbenchmark tests the performance of a work-efficient inclusive scan over a 1MB buffer of random data, scanning tiles in workgroup memory with an up-sweep and a down-sweep (Blelloch) and a dispatch for each level of tiles.",
+            ScanKernel::Blelloch(ScanMode::Exclusive) => "This microbenchmark tests the performance of a work-efficient exclusive scan over a 1MB buffer of random data, scanning tiles in workgroup memory with an up-sweep and a down-sweep (Blelloch) and a dispatch for each level of tiles.",
+            ScanKernel::DecoupledLookBack(ScanMode::Inclusive) => "This microbenchmark tests the performance of a single-pass inclusive scan over a 1MB buffer of random data, where each workgroup looks back on the sums published by the previous ones. WebGPU doesn't guarantee it sees them, so the scan is checked before running it and the benchmark fails if it's wrong on your GPU.",
+            ScanKernel::DecoupledLookBack(ScanMode::Exclusive) => "This microbenchmark tests the performance of a single-pass exclusive scan over a 1MB buffer of random data, where each workgroup looks back on the sums published by the previous ones. WebGPU doesn't guarantee it sees them, so the scan is checked before running it and the benchmark fails if it's wrong on your GPU.",
+        }
+    }
+
+    fn element_types(&self) -> &'static [ElementType] { self.0.element_types() }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str { "Amount of elements to scan" }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn opt_in(&self) -> bool {
+        matches!(self.0, ScanKernel::DecoupledLookBack(_))
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        let kernel = self.0;
+        let largest_buffer_size = match kernel {
+            ScanKernel::Sklansky => {
+                size.next_multiple_of(workgroup_size.0 as usize)
+            }
+            ScanKernel::Blelloch(_) => {
+                levels(size, workgroup_size.0).iter().sum::<u32>() as usize
+            }
+            ScanKernel::DecoupledLookBack(_) => size,
+        };
+
+        // See the panic section of [scan_benchmark]
+        workgroup_size.0.is_power_of_two()
+            && kernel.workgroup_storage_size(
+                parameters.element_type,
+                workgroup_size.0,
+            ) <= limits.max_compute_workgroup_storage_size as u64
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroup_dispatches(kernel, size, workgroup_size.0),
+                parameters.element_type.buffer_size(largest_buffer_size),
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(scan_benchmark(gpu, self.0, workgroup_size.0, parameters)
+                .await?
+                .results)
+        })
+    }
+
+    fn accuracy<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> AccuracyFuture<'a> {
+        Box::pin(async move {
+            Ok(Some(
+                scan_accuracy(gpu, self.0, workgroup_size.0, parameters)
+                    .await?,
+            ))
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = ScanResults {
+            results: results.clone(),
+            parameters: *parameters,
+            kernel: self.0,
+        };
+        vec![Metric::flops(results.flops())]
+    }
+
+    fn arithmetic_intensity(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+    ) -> Option<f64> {
+        // reads the input and writes the result
+        let n = parameters.size;
+        let bytes = 2 * n * parameters.element_type.size();
+        Some(flops_per_iteration(self.0, n) as f64 / bytes as f64)
+    }
 }
 
-impl<const BUFFER_SIZE: usize> Buffers<BUFFER_SIZE> {
-    fn new_with_random_input(workgroup_size: u32, gpu: &GPUContext) -> Self {
-        let mut input_data = vec![0_f32; BUFFER_SIZE];
+/// Amount of floating point operations done by each iteration of the
+/// microbenchmark with a `buffer_size` elements long input.
+///
+/// For the work-efficient kernels these are the additions of the up-sweep
+/// and down-sweep, of the prefixes of the tiles and, for inclusive scans, of
+/// each element to its exclusive prefix. The additions done for the sums of
+/// the tiles are a tile's worth fewer, so they aren't counted.
+fn flops_per_iteration(kernel: ScanKernel, buffer_size: usize) -> usize {
+    match kernel {
+        ScanKernel::Sklansky => {
+            let num_passes = (buffer_size * 2 - 1).ilog2() as usize;
+            num_passes * (buffer_size / 2)
+        }
+        ScanKernel::Blelloch(mode) | ScanKernel::DecoupledLookBack(mode) => {
+            let sweeps = 2 * (buffer_size - 1);
+            let prefixes = buffer_size;
+            let inclusive = match mode {
+                ScanMode::Inclusive => buffer_size,
+                ScanMode::Exclusive => 0,
+            };
+            sweeps + prefixes + inclusive
+        }
+    }
+}
 
-        let mut rng = thread_rng();
+/// Amount of elements of each level of [ScanKernel::Blelloch] scanning
+/// `size` elements: the input, the sums of its tiles, the sums of their
+/// tiles, and so on until a level fits in a single tile.
+fn levels(size: usize, workgroup_size: u32) -> Vec<u32> {
+    let tile = ScanKernel::tile_elements(workgroup_size);
+    let mut levels = vec![size as u32];
+    while levels[levels.len() - 1] > tile {
+        levels.push(levels[levels.len() - 1].div_ceil(tile));
+    }
+    levels
+}
 
-        rng.fill(input_data.as_mut_slice());
+/// One of the dispatches of [ScanKernel::Blelloch], its fields match the
+/// `Pass` struct of the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pass {
+    add: u32,
+    offset: u32,
+    len: u32,
+    sums_offset: u32,
+    read_input: u32,
+    write_sums: u32,
+}
 
-        Self::new_from_input(&input_data, workgroup_size, &gpu)
+impl Pass {
+    /// Contents of the uniform buffer of the pass
+    fn uniform(&self) -> [u32; 6] {
+        [
+            self.add,
+            self.offset,
+            self.len,
+            self.sums_offset,
+            self.read_input,
+            self.write_sums,
+        ]
     }
+}
 
-    fn new_from_input(
-        input_data: &[f32],
-        workgroup_size: u32,
+/// The passes of [ScanKernel::Blelloch] scanning `size` elements: a scan
+/// pass for each of the [levels] from the bottom up, then an add pass for
+/// each level but the top one from the top down.
+fn passes(size: usize, workgroup_size: u32) -> Vec<Pass> {
+    let levels = levels(size, workgroup_size);
+    let offset = |level: usize| levels[..level].iter().sum::<u32>();
+    let top = levels.len() - 1;
+
+    let scans = (0..=top).map(|level| Pass {
+        add: 0,
+        offset: offset(level),
+        len: levels[level],
+        sums_offset: offset(level + 1),
+        read_input: (level == 0) as u32,
+        write_sums: (level < top) as u32,
+    });
+    let adds = (0..top).rev().map(|level| Pass {
+        add: 1,
+        offset: offset(level),
+        len: levels[level],
+        sums_offset: offset(level + 1),
+        read_input: 0,
+        write_sums: 0,
+    });
+
+    scans.chain(adds).collect()
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    /// Buffer where the scan ends up, in its first elements. The Sklansky
+    /// scan is done in it in-place, and the Blelloch scan keeps every level
+    /// in it, see [levels].
+    data_buffer: AsyncBuffer,
+    /// Input of the kernels that don't scan in-place
+    input_buffer: Option<AsyncBuffer>,
+    /// Copy of the original contents of `data_buffer` for the Sklansky scan,
+    /// used to restore it between iterations.
+    pristine_buffer: Option<AsyncBuffer>,
+    /// Uniform of each dispatch, bound to `@group(1)` before it. The stride
+    /// of each pass of the Sklansky scan, or each [Pass] of the Blelloch scan.
+    dispatch_uniforms: Vec<AsyncBuffer>,
+    /// Flags, aggregates, inclusive prefixes and counter of the tiles of
+    /// [ScanKernel::DecoupledLookBack]
+    tile_state_buffers: Option<[AsyncBuffer; 4]>,
+    element_type: ElementType,
+}
+
+impl Buffers {
+    fn new(
         gpu: &GPUContext,
+        kernel: ScanKernel,
+        input_data: &[f64],
+        workgroup_size: u32,
+        element_type: ElementType,
     ) -> Self {
-        assert_eq!(input_data.len(), BUFFER_SIZE);
+        let uniform = |contents: &[u8]| {
+            gpu.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Dispatch Uniform"),
+                usage: BufferUsages::UNIFORM,
+                contents,
+            })
+        };
+
+        match kernel {
+            ScanKernel::Sklansky => Self::new_sklansky(
+                gpu,
+                input_data,
+                workgroup_size,
+                element_type,
+            ),
+            ScanKernel::Blelloch(_) => {
+                let data_len = levels(input_data.len(), workgroup_size)
+                    .iter()
+                    .sum::<u32>();
+                Self {
+                    data_buffer: gpu.create_buffer(&BufferDescriptor {
+                        label: Some("Data Buffer"),
+                        size: element_type.buffer_size(data_len as usize),
+                        usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                        mapped_at_creation: false,
+                    }),
+                    input_buffer: Some(Self::input_buffer(
+                        gpu,
+                        input_data,
+                        element_type,
+                    )),
+                    pristine_buffer: None,
+                    dispatch_uniforms: passes(input_data.len(), workgroup_size)
+                        .iter()
+                        .map(|pass| {
+                            uniform(bytemuck::cast_slice(&pass.uniform()))
+                        })
+                        .collect(),
+                    tile_state_buffers: None,
+                    element_type,
+                }
+            }
+            ScanKernel::DecoupledLookBack(_) => {
+                let tiles = input_data.len().div_ceil(
+                    ScanKernel::tile_elements(workgroup_size) as usize,
+                );
+                let tile_state = |label, len: usize| {
+                    gpu.create_buffer_init(&BufferInitDescriptor {
+                        label: Some(label),
+                        contents: bytemuck::cast_slice(&vec![0u32; len]),
+                        usage: BufferUsages::STORAGE,
+                    })
+                };
 
-        let label = Some("Data Buffer");
-        let usage = BufferUsages::STORAGE | BufferUsages::COPY_SRC;
+                Self {
+                    data_buffer: gpu.create_buffer(&BufferDescriptor {
+                        label: Some("Output Buffer"),
+                        size: element_type.buffer_size(input_data.len()),
+                        usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+                        mapped_at_creation: false,
+                    }),
+                    input_buffer: Some(Self::input_buffer(
+                        gpu,
+                        input_data,
+                        element_type,
+                    )),
+                    pristine_buffer: None,
+                    dispatch_uniforms: vec![],
+                    tile_state_buffers: Some([
+                        tile_state("Tile Flags Buffer", tiles),
+                        tile_state("Tile Aggregates Buffer", tiles),
+                        tile_state("Tile Prefixes Buffer", tiles),
+                        tile_state("Tile Counter Buffer", 1),
+                    ]),
+                    element_type,
+                }
+            }
+        }
+    }
 
+    fn new_sklansky(
+        gpu: &GPUContext,
+        input_data: &[f64],
+        workgroup_size: u32,
+        element_type: ElementType,
+    ) -> Self {
         let rem = input_data.len() % workgroup_size as usize;
-        let data_buffer = if rem != 0 {
+        let contents: Vec<f64> = if rem != 0 {
             // 0 pad
             let mut data_vec = input_data.to_vec();
             data_vec.resize(
                 input_data.len() + (workgroup_size as usize - rem),
                 0.0,
             );
-            gpu.create_buffer_init(&BufferInitDescriptor {
-                label,
-                usage,
-                contents: bytemuck::cast_slice(&data_vec),
-            })
+            data_vec
         } else {
-            gpu.create_buffer_init(&BufferInitDescriptor {
-                label,
-                usage,
-                contents: bytemuck::cast_slice(input_data),
-            })
+            input_data.to_vec()
         };
+        let contents = element_type.encode(contents);
 
-        let passes_needed = (input_data.len() * 2 - 1).ilog2();
-        let strides = (1..=passes_needed).map(|i| 2u32.pow(i));
+        let data_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Data Buffer"),
+            usage: BufferUsages::STORAGE
+                | BufferUsages::COPY_SRC
+                | BufferUsages::COPY_DST,
+            contents: &contents,
+        });
+
+        let pristine_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Pristine Data Buffer"),
+            usage: BufferUsages::COPY_SRC,
+            contents: &contents,
+        });
 
-        let stride_buffers = strides
+        let stride_buffers = sklansky_strides(input_data.len())
             .map(|stride: u32| {
                 gpu.create_buffer_init(&BufferInitDescriptor {
                     label: Some("Stride Uniform"),
@@ -163,23 +772,39 @@ impl<const BUFFER_SIZE: usize> Buffers<BUFFER_SIZE> {
 
         Self {
             data_buffer,
-            strides: stride_buffers,
+            input_buffer: None,
+            pristine_buffer: Some(pristine_buffer),
+            dispatch_uniforms: stride_buffers,
+            tile_state_buffers: None,
+            element_type,
         }
     }
 
+    fn input_buffer(
+        gpu: &GPUContext,
+        input_data: &[f64],
+        element_type: ElementType,
+    ) -> AsyncBuffer {
+        gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Input Buffer"),
+            usage: BufferUsages::STORAGE,
+            contents: &element_type.encode(input_data.iter().copied()),
+        })
+    }
+
     fn create_bind_groups(
         &self,
         pipeline: &BenchmarkComputePipeline,
     ) -> Vec<BindGroup> {
-        self.strides
+        self.dispatch_uniforms
             .iter()
-            .map(|stride_buffer| {
+            .map(|uniform_buffer| {
                 pipeline.create_bind_group(BindGroupParams {
-                    label: Some("Stride Uniform Bind Group"),
+                    label: Some("Dispatch Uniform Bind Group"),
                     group: 1,
                     entries: HashMap::from([(
                         0,
-                        stride_buffer.as_entire_binding(),
+                        uniform_buffer.as_entire_binding(),
                     )]),
                 })
             })
@@ -187,22 +812,45 @@ impl<const BUFFER_SIZE: usize> Buffers<BUFFER_SIZE> {
     }
 }
 
+/// Strides of the passes of the Sklansky scan of `buffer_size` elements
+fn sklansky_strides(buffer_size: usize) -> impl Iterator<Item = u32> {
+    let passes_needed = (buffer_size * 2 - 1).ilog2();
+    (1..=passes_needed).map(|i| 2u32.pow(i))
+}
+
 /// Pipeline needed for microbenchmark
-async fn scan_pipeline<'a, const BUFFER_SIZE: usize>(
+async fn scan_pipeline<'a>(
     gpu: &'a GPUContext,
-    buffers: &'a Buffers<BUFFER_SIZE>,
+    kernel: ScanKernel,
+    buffers: &'a Buffers,
     workgroup_size: u32,
 ) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    let bind_group_0 = match &buffers.input_buffer {
+        // In-place
+        None => HashMap::from([(0, buffers.data_buffer.as_entire_binding())]),
+        Some(input_buffer) => {
+            let mut bind_group_0 = HashMap::from([
+                (0, input_buffer.as_entire_binding()),
+                (1, buffers.data_buffer.as_entire_binding()),
+            ]);
+            for (binding, buffer) in
+                (2..).zip(buffers.tile_state_buffers.iter().flatten())
+            {
+                bind_group_0.insert(binding, buffer.as_entire_binding());
+            }
+            bind_group_0
+        }
+    };
+
     BenchmarkComputePipeline::new(PipelineParameters {
         shader: ShaderModuleDescriptor {
             label: Some("scan shader"),
-            source: ShaderSource::Wgsl(include_str!("scan.wgsl").into()),
+            source: ShaderSource::Wgsl(
+                kernel.shader(buffers.element_type, workgroup_size).into(),
+            ),
         },
         entry_point: "main",
-        bind_group_0: HashMap::from([(
-            0,
-            buffers.data_buffer.as_entire_binding(),
-        )]),
+        bind_group_0,
         gpu,
         workgroup_size: Some((workgroup_size, 1, 1)),
     })
@@ -210,165 +858,199 @@ async fn scan_pipeline<'a, const BUFFER_SIZE: usize>(
 }
 
 fn workgroup_dispatches(
-    num_passes: usize,
+    kernel: ScanKernel,
     buffer_size: usize,
     workgroup_size: u32,
 ) -> Vec<(u32, u32, u32)> {
-    std::iter::repeat_n(
-        (
-            1 + (buffer_size.div_ceil(workgroup_size as usize)) as u32,
-            1,
-            1,
-        ),
-        num_passes,
-    )
-    .collect()
+    let tiles = |len: usize| {
+        let tile = ScanKernel::tile_elements(workgroup_size) as usize;
+        (len.div_ceil(tile) as u32, 1, 1)
+    };
+
+    match kernel {
+        ScanKernel::Sklansky => std::iter::repeat_n(
+            (
+                1 + (buffer_size.div_ceil(workgroup_size as usize)) as u32,
+                1,
+                1,
+            ),
+            sklansky_strides(buffer_size).count(),
+        )
+        .collect(),
+        ScanKernel::Blelloch(_) => passes(buffer_size, workgroup_size)
+            .iter()
+            .map(|pass| tiles(pass.len as usize))
+            .collect(),
+        ScanKernel::DecoupledLookBack(_) => vec![tiles(buffer_size)],
+    }
 }
 
+/// Sets the uniform of each dispatch, for the kernels that have them.
 fn dispatch_callback(
     bind_groups: &[BindGroup],
 ) -> impl Fn(usize, &mut ComputePass) + use<'_> {
     |i, pass: &mut ComputePass| {
-        pass.set_bind_group(1, &bind_groups[i], &[]);
+        if let Some(bind_group) = bind_groups.get(i) {
+            pass.set_bind_group(1, bind_group, &[]);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use uwgpu::{
-        wgpu::{BufferDescriptor, BufferUsages, MapMode},
-        Benchmark, GPUContext,
-    };
+    use uwgpu::GPUContext;
 
-    use super::{
-        dispatch_callback, scan_pipeline, workgroup_dispatches, Buffers,
-    };
+    use super::*;
+
+    /// Verifies workgroup sizes that aren't a power of 2 are rejected instead
+    /// of panicking.
+    #[test]
+    fn non_power_of_two_workgroups_are_rejected() {
+        assert!(check_workgroup_size(64).is_ok());
+        assert!(matches!(
+            check_workgroup_size(48),
+            Err(BenchmarkError::WorkgroupSizeNotPowerOfTwo(48))
+        ));
+    }
 
-    /// Verifies that the shader computes the scan correctly.
+    /// Verifies that the levels of the Blelloch scan get scanned from the
+    /// bottom up, and their prefixes added from the top down.
+    #[test]
+    fn blelloch_passes() {
+        // 1000 elements -> 63 tiles of 16 -> 4 tiles
+        let passes = passes(1000, 8);
+        let field =
+            |f: fn(&Pass) -> u32| passes.iter().map(f).collect::<Vec<_>>();
+
+        assert_eq!(field(|pass| pass.add), [0, 0, 0, 1, 1]);
+        assert_eq!(field(|pass| pass.len), [1000, 63, 4, 63, 1000]);
+        assert_eq!(field(|pass| pass.offset), [0, 1000, 1063, 1000, 0]);
+        assert_eq!(
+            field(|pass| pass.sums_offset),
+            [1000, 1063, 1067, 1063, 1000]
+        );
+        assert_eq!(field(|pass| pass.write_sums), [1, 1, 0, 0, 0]);
+        assert_eq!(field(|pass| pass.read_input), [1, 0, 0, 0, 0]);
+        assert_eq!(
+            workgroup_dispatches(
+                ScanKernel::Blelloch(ScanMode::Inclusive),
+                1000,
+                8
+            ),
+            [(63, 1, 1), (4, 1, 1), (1, 1, 1), (4, 1, 1), (63, 1, 1)]
+        );
+    }
+
+    /// Verifies that every kernel computes the scan correctly.
     #[tokio::test]
     async fn scan_works() {
         const BUFFER_SIZE: usize = 100;
 
         // Incremental buffer [1, 2, 3, 4, 5, 6, ... ]
-        let input: Vec<f32> = (1..=BUFFER_SIZE).map(|i| i as f32).collect();
-        let expected_result: Vec<f32> = input
-            .iter()
-            .scan(0.0, |acc, n| {
-                *acc += *n;
-                Some(*acc)
-            })
-            .collect();
+        let input: Vec<f64> = (1..=BUFFER_SIZE).map(|i| i as f64).collect();
         let workgroup_size = 8;
 
         let gpu = GPUContext::new(None).await.unwrap();
-        let buffers = Buffers::<BUFFER_SIZE>::new_from_input(
-            &input,
-            workgroup_size,
-            &gpu,
-        );
-        let pipeline =
-            scan_pipeline(&gpu, &buffers, workgroup_size).await.unwrap();
-
-        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
-            label: Some("Staging Buffer"),
-            size: (BUFFER_SIZE * std::mem::size_of::<f32>()) as u64,
-            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        for &kernel in ScanKernel::ALL {
+            let result =
+                scan(&gpu, kernel, workgroup_size, ElementType::F32, &input)
+                    .await
+                    .unwrap();
 
-        let _results = Benchmark {
-            warmup_count: 0,
-            count: 1,
-            workgroups_dispatch: workgroup_dispatches(
-                buffers.strides.len(),
-                BUFFER_SIZE,
-                workgroup_size,
-            ),
-            dispatch_callback: Some(&dispatch_callback(
-                &buffers.create_bind_groups(&pipeline),
-            )),
-            finalize_encoder_callback: Some(&|encoder| {
-                encoder.copy_buffer_to_buffer(
-                    &buffers.data_buffer,
-                    0,
-                    &staging_buffer,
-                    0,
-                    (BUFFER_SIZE * std::mem::size_of::<f32>()) as u64,
-                )
-            }),
+            assert_eq!(
+                result,
+                reference_scan(kernel.mode(), &input),
+                "{kernel:?}"
+            );
         }
-        .run(pipeline)
-        .await
-        .unwrap();
+    }
 
-        let staging_slice = staging_buffer.slice(..);
-        staging_slice.map_async(MapMode::Read).await.unwrap();
+    /// Verifies that the kernels scan random inputs into finite values
+    #[tokio::test]
+    async fn scan_random_inputs() {
+        const BUFFER_SIZE: usize = 50;
 
-        let result_data: Vec<f32> = {
-            let result_data_raw: &[u8] = &*staging_slice.get_mapped_range();
-            bytemuck::cast_slice(&result_data_raw).to_vec()
-        };
+        let workgroup_size = 8;
+        let input = ElementType::F32.random(BUFFER_SIZE);
+        let gpu = GPUContext::new(None).await.unwrap();
 
-        staging_buffer.unmap();
+        for &kernel in ScanKernel::ALL {
+            let result =
+                scan(&gpu, kernel, workgroup_size, ElementType::F32, &input)
+                    .await
+                    .unwrap();
 
-        assert_eq!(result_data, expected_result);
+            assert_eq!(result.len(), BUFFER_SIZE);
+            assert!(result.iter().all(|f| f.is_finite()), "{kernel:?}");
+        }
     }
 
-    /// Verifies that instantiating the scan buffers with random inputs
-    /// creates appropiately sized buffers, essentially by not panicking
+    /// Verifies that every kernel scans integers exactly, with sizes needing
+    /// from a single tile to several levels of them.
     #[tokio::test]
-    async fn scan_random_inputs() {
-        const BUFFER_SIZE: usize = 50;
+    async fn scan_verifies() {
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        for &kernel in ScanKernel::ALL {
+            for workgroup_size in [8, 64] {
+                for size in [1, 5, 1000, 100_000] {
+                    scan_verify(&gpu, kernel, workgroup_size, size)
+                        .await
+                        .unwrap_or_else(|err| {
+                            panic!("{kernel:?} {workgroup_size} {size}: {err}")
+                        });
+                }
+            }
+        }
+    }
+
+    /// Verifies that the decoupled look-back scan stays correct across
+    /// iterations, which reuse the flags of the tiles with a new epoch.
+    #[tokio::test]
+    async fn decoupled_look_back_repeats() {
+        let kernel = ScanKernel::DecoupledLookBack(ScanMode::Inclusive);
+        let input: Vec<f64> = (0..10_000).map(|i| (i % 16) as f64).collect();
+        let workgroup_size = 64;
 
-        let workgroup_size = 8;
         let gpu = GPUContext::new(None).await.unwrap();
-        let buffers =
-            Buffers::<BUFFER_SIZE>::new_with_random_input(workgroup_size, &gpu);
-        let pipeline =
-            scan_pipeline(&gpu, &buffers, workgroup_size).await.unwrap();
-
-        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
-            label: Some("Staging Buffer"),
-            size: (BUFFER_SIZE * std::mem::size_of::<f32>()) as u64,
-            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let buffers = Buffers::new(
+            &gpu,
+            kernel,
+            &input,
+            workgroup_size,
+            ElementType::U32,
+        );
+        let pipeline = scan_pipeline(&gpu, kernel, &buffers, workgroup_size)
+            .await
+            .unwrap();
 
-        let _results = Benchmark {
-            warmup_count: 0,
-            count: 1,
+        let result_size = ElementType::U32.buffer_size(input.len());
+        let staging_buffer = staging_buffer(&gpu, result_size);
+        Benchmark {
+            warmup_count: 3,
+            count: 5,
             workgroups_dispatch: workgroup_dispatches(
-                buffers.strides.len(),
-                BUFFER_SIZE,
+                kernel,
+                input.len(),
                 workgroup_size,
             ),
-            dispatch_callback: Some(&dispatch_callback(
-                &buffers.create_bind_groups(&pipeline),
-            )),
             finalize_encoder_callback: Some(&|encoder| {
                 encoder.copy_buffer_to_buffer(
                     &buffers.data_buffer,
                     0,
                     &staging_buffer,
                     0,
-                    (BUFFER_SIZE * std::mem::size_of::<f32>()) as u64,
+                    result_size,
                 )
             }),
+            ..Default::default()
         }
         .run(pipeline)
         .await
         .unwrap();
 
-        let staging_slice = staging_buffer.slice(..);
-        staging_slice.map_async(MapMode::Read).await.unwrap();
-
-        let result_data: Vec<f32> = {
-            let result_data_raw: &[u8] = &*staging_slice.get_mapped_range();
-            bytemuck::cast_slice(&result_data_raw).to_vec()
-        };
-
-        staging_buffer.unmap();
-
-        assert!(result_data.iter().all(|f| f.is_normal()));
+        let result = ElementType::U32
+            .decode(&read_staging_buffer(&staging_buffer).await);
+        assert_eq!(result, reference_scan(ScanMode::Inclusive, &input));
     }
 }
@@ -1,4 +1,4 @@
-@group(0) @binding(0) var<storage, read_write> data: array<f32>;
+@group(0) @binding(0) var<storage, read_write> data: array<$element$>;
 
 @group(1) @binding(0) var<uniform> stride: u32;
 
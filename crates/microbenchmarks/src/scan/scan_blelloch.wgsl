@@ -0,0 +1,80 @@
+const INCLUSIVE: bool = $inclusive$;
+
+@group(0) @binding(0) var<storage, read> input: array<$element$>;
+
+// Every level of the scan: the scanned input followed by the scanned sums of
+// its tiles, the scanned sums of their tiles, and so on until a level fits in
+// a single tile.
+@group(0) @binding(1) var<storage, read_write> data: array<$element$>;
+
+struct Pass {
+    // Whether this pass adds the scanned sums of the tiles of the level
+    // above to this level, instead of scanning the tiles of this level
+    add: u32,
+    // Where this level starts in `data`
+    offset: u32,
+    // Amount of elements of this level
+    len: u32,
+    // Where the sums of the tiles of this level start in `data`
+    sums_offset: u32,
+    // Whether this is the first level, which is read from `input`
+    read_input: u32,
+    // Whether the sums of the tiles are written, which the top level skips
+    write_sums: u32,
+}
+
+// Set before each dispatch: first a scan pass for each level from the bottom
+// up, then an add pass for each level but the top from the top down
+@group(1) @binding(0) var<uniform> scan_pass: Pass;
+
+$scan_tile$
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_id) local_id: vec3<u32>) {
+
+    let first = workgroup_id.x * $tile_elements$u + 2u * local_id.x;
+
+    if scan_pass.add == 1u {
+        let prefix = data[scan_pass.sums_offset + workgroup_id.x];
+        for (var k = 0u; k < 2u; k++) {
+            if first + k < scan_pass.len {
+                data[scan_pass.offset + first + k] += prefix;
+            }
+        }
+        return;
+    }
+
+    var elements: array<$element$, 2>;
+    for (var k = 0u; k < 2u; k++) {
+        var element = $element$(0); // Zero pad for out-of-bounds threads
+        if first + k < scan_pass.len {
+            if scan_pass.read_input == 1u {
+                element = input[first + k];
+            } else {
+                element = data[scan_pass.offset + first + k];
+            }
+        }
+        elements[k] = element;
+        tile[2u * local_id.x + k] = element;
+    }
+
+    let total = scan_tile(local_id.x);
+
+    // The levels above are always exclusive, so their sums are the prefixes
+    // of the tiles below
+    let inclusive = INCLUSIVE && scan_pass.read_input == 1u;
+    for (var k = 0u; k < 2u; k++) {
+        if first + k < scan_pass.len {
+            var scanned = tile[2u * local_id.x + k];
+            if inclusive {
+                scanned += elements[k];
+            }
+            data[scan_pass.offset + first + k] = scanned;
+        }
+    }
+
+    if scan_pass.write_sums == 1u && local_id.x == 0u {
+        data[scan_pass.sums_offset + workgroup_id.x] = total;
+    }
+}
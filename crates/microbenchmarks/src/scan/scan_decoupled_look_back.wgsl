@@ -0,0 +1,152 @@
+const INCLUSIVE: bool = $inclusive$;
+
+// Times the flag of the previous tile is polled before computing its sum
+// from the input instead
+const MAX_SPINS: u32 = 256u;
+
+// States of the flag of a tile, ORed with the iteration (epoch) shifted left
+// by 2 so flags from previous iterations don't count as ready
+const FLAG_AGGREGATE: u32 = 1u;
+const FLAG_PREFIX: u32 = 2u;
+
+@group(0) @binding(0) var<storage, read> input: array<$element$>;
+@group(0) @binding(1) var<storage, read_write> output: array<$element$>;
+
+// Flag, sum (aggregate) and inclusive prefix of each tile, the sums as their
+// bits so they can be stored and loaded atomically
+@group(0) @binding(2) var<storage, read_write> flags: array<atomic<u32>>;
+@group(0) @binding(3) var<storage, read_write> aggregates: array<atomic<u32>>;
+@group(0) @binding(4) var<storage, read_write> prefixes: array<atomic<u32>>;
+
+// Tiles are handed out in the order workgroups start, so the tiles a
+// workgroup looks back on have always started. Never reset: every iteration
+// takes the next `num_workgroups` values, which gives its epoch.
+@group(0) @binding(5) var<storage, read_write> tile_counter: atomic<u32>;
+
+$scan_tile$
+
+// Sums of the tile being reduced from the input when its flag isn't ready
+var<workgroup> fallback: array<$element$, $workgroup_x$>;
+
+var<workgroup> wg_tile: u32;
+var<workgroup> wg_flag: u32;
+var<workgroup> wg_value: $element$;
+
+// Single dispatch: each workgroup scans its tile, publishes its sum, and
+// looks back on the tiles before it until one has published its inclusive
+// prefix.
+//
+// WebGPU doesn't guarantee the workgroups of the previous tiles make
+// progress while a workgroup waits for them, so it only polls their flags a
+// bounded amount of times before reducing their tile itself. It also doesn't
+// guarantee a flag being visible implies its sum is, so this kernel has to
+// pass a correctness check before being benchmarked.
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(local_invocation_id) local_id: vec3<u32>,
+    @builtin(num_workgroups) num_workgroups: vec3<u32>) {
+
+    if local_id.x == 0u {
+        wg_tile = atomicAdd(&tile_counter, 1u);
+    }
+    let ticket = workgroupUniformLoad(&wg_tile);
+    let tile_id = ticket % num_workgroups.x;
+    let epoch = (ticket / num_workgroups.x) << 2u;
+
+    let first = tile_id * $tile_elements$u + 2u * local_id.x;
+    var elements: array<$element$, 2>;
+    for (var k = 0u; k < 2u; k++) {
+        var element = $element$(0); // Zero pad for out-of-bounds threads
+        if first + k < arrayLength(&input) {
+            element = input[first + k];
+        }
+        elements[k] = element;
+        tile[2u * local_id.x + k] = element;
+    }
+
+    let total = scan_tile(local_id.x);
+
+    var prefix = $element$(0);
+    if tile_id == 0u {
+        publish(tile_id, local_id.x, epoch | FLAG_PREFIX, total);
+    } else {
+        publish(tile_id, local_id.x, epoch | FLAG_AGGREGATE, total);
+
+        var look_back = tile_id - 1u;
+        loop {
+            workgroupBarrier();
+            if local_id.x == 0u {
+                var flag = 0u;
+                for (var spins = 0u; spins < MAX_SPINS; spins++) {
+                    flag = atomicLoad(&flags[look_back]);
+                    if (flag & ~3u) == epoch && (flag & 3u) != 0u {
+                        break;
+                    }
+                    flag = 0u;
+                }
+                if (flag & 3u) == FLAG_PREFIX {
+                    wg_value = bitcast<$element$>(atomicLoad(&prefixes[look_back]));
+                } else if (flag & 3u) == FLAG_AGGREGATE {
+                    wg_value = bitcast<$element$>(atomicLoad(&aggregates[look_back]));
+                }
+                wg_flag = flag & 3u;
+            }
+            let state = workgroupUniformLoad(&wg_flag);
+
+            if state == 0u {
+                // Not ready, reduce the tile from the input instead
+                let fallback_first = look_back * $tile_elements$u + 2u * local_id.x;
+                var sum = $element$(0);
+                for (var k = 0u; k < 2u; k++) {
+                    if fallback_first + k < arrayLength(&input) {
+                        sum += input[fallback_first + k];
+                    }
+                }
+                fallback[local_id.x] = sum;
+                for (var d = $workgroup_x$u / 2u; d > 0u; d /= 2u) {
+                    workgroupBarrier();
+                    if local_id.x < d {
+                        fallback[local_id.x] += fallback[local_id.x + d];
+                    }
+                }
+                workgroupBarrier();
+                prefix += fallback[0];
+            } else {
+                prefix += wg_value;
+            }
+
+            // The first tile's sum is its inclusive prefix
+            if state == FLAG_PREFIX || look_back == 0u {
+                break;
+            }
+            look_back -= 1u;
+        }
+
+        publish(tile_id, local_id.x, epoch | FLAG_PREFIX, prefix + total);
+    }
+
+    for (var k = 0u; k < 2u; k++) {
+        if first + k < arrayLength(&input) {
+            var scanned = tile[2u * local_id.x + k] + prefix;
+            if INCLUSIVE {
+                scanned += elements[k];
+            }
+            output[first + k] = scanned;
+        }
+    }
+}
+
+// Stores the aggregate or inclusive prefix of the tile, and then its flag.
+// Every invocation of the workgroup must call it.
+fn publish(tile_id: u32, local_index: u32, flag: u32, value: $element$) {
+    if local_index == 0u {
+        if (flag & 3u) == FLAG_PREFIX {
+            atomicStore(&prefixes[tile_id], bitcast<u32>(value));
+        } else {
+            atomicStore(&aggregates[tile_id], bitcast<u32>(value));
+        }
+    }
+    storageBarrier();
+    if local_index == 0u {
+        atomicStore(&flags[tile_id], flag);
+    }
+}
@@ -0,0 +1,45 @@
+// Keys padded to a power of two with the largest key, sorted in-place
+@group(0) @binding(0) var<storage, read_write> keys: array<u32>;
+// Sorted along with the keys when PAIRS is true
+@group(0) @binding(1) var<storage, read_write> values: array<u32>;
+
+const PAIRS: bool = $pairs$;
+
+struct Stage {
+    // Size of the bitonic sequences being merged
+    block: u32,
+    // Distance between the compared keys
+    distance: u32,
+    // Amount of keys, a power of two
+    len: u32,
+}
+
+// Set before each dispatch: a stage for each distance of each block size
+@group(1) @binding(0) var<uniform> stage: Stage;
+
+// Each invocation compares and swaps a pair of keys `distance` apart, in
+// ascending order in the even blocks and descending in the odd ones
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let pair = global_id.x;
+    if pair >= stage.len / 2u {
+        return;
+    }
+
+    let low = pair & (stage.distance - 1u);
+    let left = ((pair - low) << 1u) + low;
+    let right = left + stage.distance;
+    let ascending = (left & stage.block) == 0u;
+
+    let left_key = keys[left];
+    let right_key = keys[right];
+    if (left_key > right_key) == ascending {
+        keys[left] = right_key;
+        keys[right] = left_key;
+        if PAIRS {
+            let left_value = values[left];
+            values[left] = values[right];
+            values[right] = left_value;
+        }
+    }
+}
@@ -0,0 +1,798 @@
+//! Sorting microbenchmarks of u32 keys, optionally with a value along with
+//! each key, with a kernel for each way of sorting them, see [SortKernel]
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+use uwgpu::{
+    wgpu::{
+        util::BufferInitDescriptor, BindGroup, BufferDescriptor, BufferUsages,
+        ComputePass, Limits, ShaderModuleDescriptor, ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, BindGroupParams,
+    CreatePipelineError, GPUContext, InputReset, PipelineParameters,
+    ResetFrequency, TimeUnit,
+};
+
+use crate::{
+    accuracy::{read_staging_buffer, staging_buffer},
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// 1Mi keys, 4MiB
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 1_048_576,
+    warmup_count: 10,
+    count: 100,
+    element_type: ElementType::U32,
+};
+
+/// Bits of each digit of [SortKernel::Radix]
+const DIGIT_BITS: u32 = 4;
+
+/// Values each digit of [SortKernel::Radix] can take
+const RADIX: u32 = 1 << DIGIT_BITS;
+
+/// Digits of each key, a pass of [SortKernel::Radix] for each
+const DIGITS: u32 = u32::BITS / DIGIT_BITS;
+
+/// The ways of sorting, each of them is a separate [Sort] microbenchmark for
+/// each [SortElements].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortKernel {
+    /// Bitonic sort in-place, with a dispatch for each merge stage that
+    /// compares and swaps every pair of keys a distance apart. The keys are
+    /// padded to a power of two, and it does O(n log² n) comparisons.
+    Bitonic,
+    /// Least significant digit radix sort, with a pass for each 4 bit digit
+    /// made of a dispatch that counts the digits of each tile of keys, a
+    /// dispatch that scans the counts, and a dispatch that scatters the keys
+    /// to where their digit and tile start. It does O(n) work.
+    Radix,
+}
+
+/// What gets sorted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SortElements {
+    /// Only the keys
+    Keys,
+    /// The keys with a u32 value each, which gets moved along with its key
+    Pairs,
+}
+
+/// Microbenchmark for sorting random u32 keys with the given kernel, the
+/// [MicrobenchmarkParameters::size] is the amount of keys sorted.
+///
+/// The workgroup size will dictate the size of workgroups used for the
+/// computation, [SortKernel::Radix] sorts tiles of a key per invocation so it
+/// needs at least 16 invocations per workgroup.
+///
+/// The output of the last iteration is read back and checked to be sorted,
+/// with each value along with its key for [SortElements::Pairs], returning a
+/// [BenchmarkError::IncorrectOutput] with the first wrong key otherwise.
+pub async fn sort_benchmark(
+    gpu: &GPUContext,
+    kernel: SortKernel,
+    elements: SortElements,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<SortResults, BenchmarkError> {
+    let mut rng = thread_rng();
+    let keys: Vec<u32> = (0..parameters.size).map(|_| rng.gen()).collect();
+
+    let buffers = Buffers::new(gpu, kernel, elements, &keys, workgroup_size);
+    let pipeline =
+        sort_pipeline(gpu, kernel, elements, &buffers, workgroup_size).await?;
+
+    let keys_size = (keys.len() * size_of::<u32>()) as u64;
+    let keys_staging_buffer = staging_buffer(gpu, keys_size);
+    let values_staging_buffer = staging_buffer(gpu, buffers.values_size);
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: Some(&|encoder| {
+            encoder.copy_buffer_to_buffer(
+                &buffers.keys_buffer,
+                0,
+                &keys_staging_buffer,
+                0,
+                keys_size,
+            );
+            encoder.copy_buffer_to_buffer(
+                &buffers.values_buffer,
+                0,
+                &values_staging_buffer,
+                0,
+                buffers.values_size,
+            );
+        }),
+        workgroups_dispatch: workgroup_dispatches(
+            kernel,
+            keys.len(),
+            workgroup_size,
+        ),
+        dispatch_callback: Some(&dispatch_callback(
+            &buffers.create_bind_groups(&pipeline),
+        )),
+        // The keys and values are sorted in-place, so without restoring them
+        // every iteration but the first would sort sorted keys, along with
+        // values already moved by the previous iterations
+        input_resets: buffers.input_resets(),
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    let sorted_keys: Vec<u32> =
+        bytemuck::cast_slice(&read_staging_buffer(&keys_staging_buffer).await)
+            .to_vec();
+    let sorted_values: Vec<u32> = bytemuck::cast_slice(
+        &read_staging_buffer(&values_staging_buffer).await,
+    )
+    .to_vec();
+    verify_sorted(elements, &keys, &sorted_keys, &sorted_values)?;
+
+    Ok(SortResults {
+        results,
+        parameters,
+        kernel,
+        elements,
+    })
+}
+
+/// Checks the sorted keys against the keys sorted on the CPU, and for
+/// [SortElements::Pairs] that each value, the index of its key in the input,
+/// was moved along with its key. The values past the end of the input are
+/// those of the padding, whose keys are the largest key.
+fn verify_sorted(
+    elements: SortElements,
+    input: &[u32],
+    keys: &[u32],
+    values: &[u32],
+) -> Result<(), BenchmarkError> {
+    let mut reference = input.to_vec();
+    reference.sort_unstable();
+
+    let wrong_key = keys[..input.len()]
+        .iter()
+        .zip(&reference)
+        .find(|(key, expected)| key != expected);
+    if let Some((&output, &expected)) = wrong_key {
+        return Err(BenchmarkError::IncorrectOutput {
+            output: output as f64,
+            expected: expected as f64,
+        });
+    }
+
+    if elements == SortElements::Pairs {
+        let wrong_value = keys[..input.len()]
+            .iter()
+            .zip(values)
+            .map(|(&key, &value)| {
+                (*input.get(value as usize).unwrap_or(&u32::MAX), key)
+            })
+            .find(|(output, expected)| output != expected);
+        if let Some((output, expected)) = wrong_value {
+            return Err(BenchmarkError::IncorrectOutput {
+                output: output as f64,
+                expected: expected as f64,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the sort microbenchmarks. See [sort_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_eval]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct SortResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// Kernel the benchmark ran with
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub kernel: SortKernel,
+    /// What the benchmark sorted
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub elements: SortElements,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SortResults {
+    /// Get the amount of keys sorted per second, not counting the padding of
+    /// the bitonic sort
+    pub fn keys(&self) -> f64 {
+        (self.parameters.size as f64 * self.results.count as f64)
+            / self.results.total_time(TimeUnit::Second)
+    }
+}
+
+/// Sort microbenchmark with the given kernel, see [sort_benchmark].
+pub struct Sort(pub SortKernel, pub SortElements);
+
+impl Microbenchmark for Sort {
+    fn name(&self) -> &'static str {
+        match (self.0, self.1) {
+            (SortKernel::Bitonic, SortElements::Keys) => "bitonic_sort",
+            (SortKernel::Bitonic, SortElements::Pairs) => "bitonic_sort_pairs",
+            (SortKernel::Radix, SortElements::Keys) => "radix_sort",
+            (SortKernel::Radix, SortElements::Pairs) => "radix_sort_pairs",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match (self.0, self.1) {
+            (SortKernel::Bitonic, SortElements::Keys) => "Bitonic Sort",
+            (SortKernel::Bitonic, SortElements::Pairs) => {
+                "Bitonic Sort (Key-Value Pairs)"
+            }
+            (SortKernel::Radix, SortElements::Keys) => "Radix Sort",
+            (SortKernel::Radix, SortElements::Pairs) => {
+                "Radix Sort (Key-Value Pairs)"
+            }
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.0 {
+            SortKernel::Bitonic => "This microbenchmark tests the performance of sorting random u32 keys in your GPU with a bitonic sort, a sorting network that compares and swaps every pair of keys a distance apart in a dispatch for each stage of its merges.",
+            SortKernel::Radix => "This microbenchmark tests the performance of sorting random u32 keys in your GPU with a least significant digit radix sort, which for each 4 bit digit counts the digits of each tile of keys, scans the counts and scatters the keys to where their digit starts.",
+        }
+    }
+
+    fn element_types(&self) -> &'static [ElementType] { &[ElementType::U32] }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str { "Amount of keys to sort" }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        let fits_kernel = match self.0 {
+            SortKernel::Bitonic => true,
+            SortKernel::Radix => {
+                let workgroup_storage = (3 * workgroup_size.0 as u64
+                    + 2 * RADIX as u64)
+                    * size_of::<u32>() as u64;
+
+                workgroup_size.0 >= RADIX
+                    && workgroup_storage
+                        <= limits.max_compute_workgroup_storage_size as u64
+            }
+        };
+
+        // The keys padded to a power of two must fit in a u32
+        size > 0
+            && size <= u32::MAX as usize / 2 + 1
+            && fits_kernel
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroup_dispatches(self.0, size, workgroup_size.0),
+                largest_buffer_size(self.0, size, workgroup_size.0),
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(sort_benchmark(
+                gpu,
+                self.0,
+                self.1,
+                workgroup_size.0,
+                parameters,
+            )
+            .await?
+            .results)
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = SortResults {
+            results: results.clone(),
+            parameters: *parameters,
+            kernel: self.0,
+            elements: self.1,
+        };
+        vec![Metric::keys(results.keys())]
+    }
+}
+
+/// Amount of keys in the buffers of the kernel sorting `size` keys, padded
+/// to a power of two for [SortKernel::Bitonic].
+fn padded_len(kernel: SortKernel, size: usize) -> usize {
+    match kernel {
+        SortKernel::Bitonic => size.next_power_of_two(),
+        SortKernel::Radix => size,
+    }
+}
+
+/// Amount of tiles of a key per invocation of [SortKernel::Radix].
+fn tiles(size: usize, workgroup_size: u32) -> u32 {
+    size.div_ceil(workgroup_size as usize) as u32
+}
+
+/// Size in bytes of the largest buffer of the kernel: the keys, or the counts
+/// of the digits of every tile of [SortKernel::Radix] if they're bigger.
+fn largest_buffer_size(
+    kernel: SortKernel,
+    size: usize,
+    workgroup_size: u32,
+) -> u64 {
+    let keys = padded_len(kernel, size) as u64;
+    let counts = match kernel {
+        SortKernel::Bitonic => 0,
+        SortKernel::Radix => RADIX as u64 * tiles(size, workgroup_size) as u64,
+    };
+    keys.max(counts) * size_of::<u32>() as u64
+}
+
+/// One of the dispatches of [SortKernel::Bitonic], its fields match the
+/// `Stage` struct of the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stage {
+    block: u32,
+    distance: u32,
+    len: u32,
+}
+
+/// The stages of [SortKernel::Bitonic] sorting `len` keys, a power of two:
+/// for each size of the blocks being merged, a stage for each distance from
+/// half the block down to 1.
+fn bitonic_stages(len: u32) -> Vec<Stage> {
+    std::iter::successors(Some(2), |block| Some(block * 2))
+        .take_while(|block| *block <= len)
+        .flat_map(|block| {
+            std::iter::successors(Some(block / 2), |distance| {
+                Some(distance / 2)
+            })
+            .take_while(|distance| *distance > 0)
+            .map(move |distance| Stage {
+                block,
+                distance,
+                len,
+            })
+        })
+        .collect()
+}
+
+/// Phase of a dispatch of [SortKernel::Radix], matching the constants of the
+/// shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+enum Phase {
+    Histogram = 0,
+    Scan = 1,
+    Scatter = 2,
+}
+
+/// One of the dispatches of [SortKernel::Radix], its fields match the `Pass`
+/// struct of the shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Pass {
+    phase: Phase,
+    shift: u32,
+    len: u32,
+}
+
+/// The passes of [SortKernel::Radix] sorting `len` keys: a histogram, scan
+/// and scatter for each digit, from the least significant one.
+fn radix_passes(len: u32) -> Vec<Pass> {
+    (0..DIGITS)
+        .flat_map(|digit| {
+            [Phase::Histogram, Phase::Scan, Phase::Scatter].map(|phase| Pass {
+                phase,
+                shift: digit * DIGIT_BITS,
+                len,
+            })
+        })
+        .collect()
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    /// Keys, where they end up sorted. The bitonic sort works on them
+    /// in-place, and the radix sort scatters them back and forth with
+    /// `scratch_buffers`, an even amount of times.
+    keys_buffer: AsyncBuffer,
+    /// Values, the index of their key in the input. A single element that
+    /// isn't used for [SortElements::Keys].
+    values_buffer: AsyncBuffer,
+    /// Size in bytes of `values_buffer`
+    values_size: u64,
+    /// Copy of the original keys, used to restore them between iterations.
+    pristine_keys_buffer: AsyncBuffer,
+    /// Copy of the original values for [SortElements::Pairs], used to
+    /// restore them between iterations.
+    pristine_values_buffer: Option<AsyncBuffer>,
+    /// Keys and values scattered to by every other pass of
+    /// [SortKernel::Radix], and the counts of the digits of each tile.
+    radix_buffers: Option<[AsyncBuffer; 3]>,
+    /// Uniform of each dispatch, each [Stage] of the bitonic sort, or each
+    /// [Pass] of the radix sort.
+    dispatch_uniforms: Vec<AsyncBuffer>,
+}
+
+impl Buffers {
+    fn new(
+        gpu: &GPUContext,
+        kernel: SortKernel,
+        elements: SortElements,
+        keys: &[u32],
+        workgroup_size: u32,
+    ) -> Self {
+        let len = padded_len(kernel, keys.len());
+        let uniform = |contents: &[u8]| {
+            gpu.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Dispatch Uniform"),
+                usage: BufferUsages::UNIFORM,
+                contents,
+            })
+        };
+        let scratch_buffer = |label, size| {
+            gpu.create_buffer(&BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            })
+        };
+
+        // Padded with the largest key so the padding sorts last
+        let mut padded_keys = keys.to_vec();
+        padded_keys.resize(len, u32::MAX);
+
+        let keys_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Keys Buffer"),
+            usage: BufferUsages::STORAGE
+                | BufferUsages::COPY_SRC
+                | BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(&padded_keys),
+        });
+
+        let pristine_keys_buffer =
+            gpu.create_buffer_init(&BufferInitDescriptor {
+                label: Some("Pristine Keys Buffer"),
+                usage: BufferUsages::COPY_SRC,
+                contents: bytemuck::cast_slice(&padded_keys),
+            });
+
+        let values: Vec<u32> = match elements {
+            SortElements::Keys => vec![0],
+            SortElements::Pairs => (0..len as u32).collect(),
+        };
+        let values_size = (values.len() * size_of::<u32>()) as u64;
+        let values_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Values Buffer"),
+            usage: BufferUsages::STORAGE
+                | BufferUsages::COPY_SRC
+                | BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(&values),
+        });
+
+        let pristine_values_buffer =
+            (elements == SortElements::Pairs).then(|| {
+                gpu.create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Pristine Values Buffer"),
+                    usage: BufferUsages::COPY_SRC,
+                    contents: bytemuck::cast_slice(&values),
+                })
+            });
+
+        let (radix_buffers, dispatch_uniforms) = match kernel {
+            SortKernel::Bitonic => (
+                None,
+                bitonic_stages(len as u32)
+                    .iter()
+                    .map(|stage| {
+                        uniform(bytemuck::cast_slice(&[
+                            stage.block,
+                            stage.distance,
+                            stage.len,
+                        ]))
+                    })
+                    .collect(),
+            ),
+            SortKernel::Radix => (
+                Some([
+                    scratch_buffer(
+                        "Scratch Keys Buffer",
+                        (len * size_of::<u32>()) as u64,
+                    ),
+                    scratch_buffer("Scratch Values Buffer", values_size),
+                    scratch_buffer(
+                        "Counts Buffer",
+                        (RADIX * tiles(len, workgroup_size)) as u64
+                            * size_of::<u32>() as u64,
+                    ),
+                ]),
+                radix_passes(len as u32)
+                    .iter()
+                    .map(|pass| {
+                        uniform(bytemuck::cast_slice(&[
+                            pass.phase as u32,
+                            pass.shift,
+                            pass.len,
+                        ]))
+                    })
+                    .collect(),
+            ),
+        };
+
+        Self {
+            keys_buffer,
+            values_buffer,
+            values_size,
+            pristine_keys_buffer,
+            pristine_values_buffer,
+            radix_buffers,
+            dispatch_uniforms,
+        }
+    }
+
+    /// Restores the keys, and the values if they're sorted, before every
+    /// iteration.
+    fn input_resets(&self) -> Vec<InputReset<'_>> {
+        let keys = (&self.pristine_keys_buffer, &self.keys_buffer);
+        let values = self
+            .pristine_values_buffer
+            .as_ref()
+            .map(|pristine_values| (pristine_values, &self.values_buffer));
+
+        std::iter::once(keys)
+            .chain(values)
+            .map(|(source, destination)| InputReset {
+                source,
+                destination,
+                frequency: ResetFrequency::PerIteration,
+            })
+            .collect()
+    }
+
+    /// Bind group of each dispatch: its uniform, and for the radix sort the
+    /// buffers it reads and writes, which swap with every digit.
+    fn create_bind_groups(
+        &self,
+        pipeline: &BenchmarkComputePipeline,
+    ) -> Vec<BindGroup> {
+        self.dispatch_uniforms
+            .iter()
+            .enumerate()
+            .map(|(i, uniform_buffer)| {
+                let mut entries =
+                    HashMap::from([(0, uniform_buffer.as_entire_binding())]);
+
+                if let Some([scratch_keys, scratch_values, _]) =
+                    &self.radix_buffers
+                {
+                    let digit = i / 3;
+                    let (keys, values) = (
+                        [&self.keys_buffer, scratch_keys],
+                        [&self.values_buffer, scratch_values],
+                    );
+                    entries.extend([
+                        (1, keys[digit % 2].as_entire_binding()),
+                        (2, keys[(digit + 1) % 2].as_entire_binding()),
+                        (3, values[digit % 2].as_entire_binding()),
+                        (4, values[(digit + 1) % 2].as_entire_binding()),
+                    ]);
+                }
+
+                pipeline.create_bind_group(BindGroupParams {
+                    label: Some("Dispatch Bind Group"),
+                    group: 1,
+                    entries,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Pipeline needed for microbenchmark
+async fn sort_pipeline<'a>(
+    gpu: &'a GPUContext,
+    kernel: SortKernel,
+    elements: SortElements,
+    buffers: &'a Buffers,
+    workgroup_size: u32,
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    let (shader, bind_group_0) = match (kernel, &buffers.radix_buffers) {
+        (SortKernel::Radix, Some([_, _, counts_buffer])) => (
+            include_str!("radix_sort.wgsl"),
+            HashMap::from([(0, counts_buffer.as_entire_binding())]),
+        ),
+        _ => (
+            include_str!("bitonic_sort.wgsl"),
+            HashMap::from([
+                (0, buffers.keys_buffer.as_entire_binding()),
+                (1, buffers.values_buffer.as_entire_binding()),
+            ]),
+        ),
+    };
+
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("sort shader"),
+            source: ShaderSource::Wgsl(
+                shader
+                    .replace(
+                        "$pairs$",
+                        &(elements == SortElements::Pairs).to_string(),
+                    )
+                    .replace("$radix$", &RADIX.to_string())
+                    .replace("$digit_bits$", &DIGIT_BITS.to_string())
+                    .into(),
+            ),
+        },
+        entry_point: "main",
+        bind_group_0,
+        gpu,
+        workgroup_size: Some((workgroup_size, 1, 1)),
+    })
+    .await
+}
+
+fn workgroup_dispatches(
+    kernel: SortKernel,
+    size: usize,
+    workgroup_size: u32,
+) -> Vec<(u32, u32, u32)> {
+    let len = padded_len(kernel, size);
+
+    match kernel {
+        SortKernel::Bitonic => {
+            let pairs = (len / 2).div_ceil(workgroup_size as usize) as u32;
+            vec![(pairs, 1, 1); bitonic_stages(len as u32).len()]
+        }
+        SortKernel::Radix => radix_passes(len as u32)
+            .iter()
+            .map(|pass| match pass.phase {
+                Phase::Scan => (1, 1, 1),
+                Phase::Histogram | Phase::Scatter => {
+                    (tiles(len, workgroup_size), 1, 1)
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Sets the bind group of each dispatch.
+fn dispatch_callback(
+    bind_groups: &[BindGroup],
+) -> impl Fn(usize, &mut ComputePass) + use<'_> {
+    |i, pass: &mut ComputePass| {
+        pass.set_bind_group(1, &bind_groups[i], &[]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::GPUContext;
+
+    use super::*;
+
+    /// Verifies that the bitonic stages sort any keys, by running them on
+    /// the CPU.
+    #[test]
+    fn bitonic_stages_sort() {
+        let mut rng = thread_rng();
+        for len in [1, 2, 16, 1024] {
+            let mut keys: Vec<u32> = (0..len).map(|_| rng.gen()).collect();
+            let mut expected = keys.clone();
+            expected.sort_unstable();
+
+            for stage in bitonic_stages(len) {
+                for left in 0..len as usize {
+                    let right = left ^ stage.distance as usize;
+                    let ascending = left & stage.block as usize == 0;
+                    if right > left && (keys[left] > keys[right]) == ascending {
+                        keys.swap(left, right);
+                    }
+                }
+            }
+            assert_eq!(keys, expected, "{len} keys");
+        }
+    }
+
+    /// Verifies that wrongly sorted keys or values are caught.
+    #[test]
+    fn unsorted_output_is_caught() {
+        let input = [3, 1, 2];
+        let keys = [1, 2, 3];
+
+        assert!(
+            verify_sorted(SortElements::Pairs, &input, &keys, &[1, 2, 0])
+                .is_ok()
+        );
+        assert!(verify_sorted(SortElements::Keys, &input, &[1, 3, 2], &[0])
+            .is_err());
+        assert!(
+            verify_sorted(SortElements::Pairs, &input, &keys, &[2, 1, 0])
+                .is_err()
+        );
+    }
+
+    /// Verifies that every kernel sorts keys and pairs, with sizes that
+    /// aren't a power of two or a multiple of the workgroup size.
+    #[tokio::test]
+    async fn sort_works() {
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        for kernel in [SortKernel::Bitonic, SortKernel::Radix] {
+            for elements in [SortElements::Keys, SortElements::Pairs] {
+                for size in [5, 1000, 65_536] {
+                    let parameters = MicrobenchmarkParameters {
+                        size,
+                        warmup_count: 1,
+                        count: 2,
+                        element_type: ElementType::U32,
+                    };
+                    sort_benchmark(&gpu, kernel, elements, 64, parameters)
+                        .await
+                        .unwrap_or_else(|err| {
+                            panic!("{kernel:?} {elements:?} {size}: {err}")
+                        });
+                }
+            }
+        }
+    }
+
+    /// Verifies that the values are restored along with the keys, so every
+    /// iteration sorts the original pairs instead of the values moved by the
+    /// iterations before it.
+    #[tokio::test]
+    async fn pairs_are_restored_between_iterations() {
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        for kernel in [SortKernel::Bitonic, SortKernel::Radix] {
+            let parameters = MicrobenchmarkParameters {
+                size: 1000,
+                warmup_count: 3,
+                count: 5,
+                element_type: ElementType::U32,
+            };
+            sort_benchmark(&gpu, kernel, SortElements::Pairs, 64, parameters)
+                .await
+                .unwrap_or_else(|err| panic!("{kernel:?}: {err}"));
+        }
+    }
+}
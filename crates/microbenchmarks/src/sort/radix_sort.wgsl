@@ -0,0 +1,164 @@
+// Count of each digit in each tile, digit-major so that their exclusive scan
+// is where each tile scatters each digit to
+@group(0) @binding(0) var<storage, read_write> counts: array<u32>;
+
+const PAIRS: bool = $pairs$;
+const RADIX: u32 = $radix$u;
+const DIGIT_BITS: u32 = $digit_bits$u;
+const WORKGROUP_SIZE: u32 = $workgroup_x$u;
+
+const HISTOGRAM: u32 = 0u;
+const SCAN: u32 = 1u;
+const SCATTER: u32 = 2u;
+
+struct Pass {
+    // HISTOGRAM, SCAN or SCATTER
+    phase: u32,
+    // Bit where the digit of this pass starts
+    shift: u32,
+    // Amount of keys
+    len: u32,
+}
+
+// Set before each dispatch, along with the buffers the keys and values are
+// read from and written to, which swap with every digit
+@group(1) @binding(0) var<uniform> sort_pass: Pass;
+@group(1) @binding(1) var<storage, read> source_keys: array<u32>;
+@group(1) @binding(2) var<storage, read_write> destination_keys: array<u32>;
+@group(1) @binding(3) var<storage, read> source_values: array<u32>;
+@group(1) @binding(4) var<storage, read_write> destination_values: array<u32>;
+
+var<workgroup> histogram: array<atomic<u32>, $radix$>;
+var<workgroup> sums: array<u32, $workgroup_x$>;
+var<workgroup> tile_keys: array<u32, $workgroup_x$>;
+var<workgroup> tile_values: array<u32, $workgroup_x$>;
+var<workgroup> digit_starts: array<u32, $radix$>;
+
+// Inclusive scan of `sums` in-place, with `value` as this invocation's element
+fn scan_sums(local: u32, value: u32) -> u32 {
+    sums[local] = value;
+    workgroupBarrier();
+    for (var offset = 1u; offset < WORKGROUP_SIZE; offset <<= 1u) {
+        var previous = 0u;
+        if local >= offset {
+            previous = sums[local - offset];
+        }
+        workgroupBarrier();
+        sums[local] += previous;
+        workgroupBarrier();
+    }
+    return sums[local];
+}
+
+fn digit(key: u32) -> u32 {
+    return (key >> sort_pass.shift) & (RADIX - 1u);
+}
+
+// Each workgroup counts the digits of its tile of keys, one per invocation
+fn count_digits(local: u32, tile: u32, tiles: u32) {
+    if local < RADIX {
+        atomicStore(&histogram[local], 0u);
+    }
+    workgroupBarrier();
+
+    let index = tile * WORKGROUP_SIZE + local;
+    if index < sort_pass.len {
+        atomicAdd(&histogram[digit(source_keys[index])], 1u);
+    }
+    workgroupBarrier();
+
+    if local < RADIX {
+        counts[local * tiles + tile] = atomicLoad(&histogram[local]);
+    }
+}
+
+// A single workgroup scans the counts, each invocation summing a chunk of
+// them before the sums of the chunks get scanned
+fn scan_counts(local: u32, tiles: u32) {
+    let len = RADIX * tiles;
+    let chunk = (len + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+    let start = min(local * chunk, len);
+    let end = min(start + chunk, len);
+
+    var sum = 0u;
+    for (var i = start; i < end; i++) {
+        sum += counts[i];
+    }
+
+    var prefix = scan_sums(local, sum) - sum;
+    for (var i = start; i < end; i++) {
+        let count = counts[i];
+        counts[i] = prefix;
+        prefix += count;
+    }
+}
+
+// Each workgroup sorts its tile by the digit with a stable split for each of
+// its bits, then writes every key after the keys of the same digit of the
+// tiles before it
+fn scatter(local: u32, tile: u32, tiles: u32) {
+    let index = tile * WORKGROUP_SIZE + local;
+    let in_bounds = index < sort_pass.len;
+
+    // The padding sorts after every key of the tile
+    var key = 0xffffffffu;
+    var value = 0u;
+    if in_bounds {
+        key = source_keys[index];
+        if PAIRS {
+            value = source_values[index];
+        }
+    }
+
+    for (var bit = 0u; bit < DIGIT_BITS; bit++) {
+        let zero = 1u - ((key >> (sort_pass.shift + bit)) & 1u);
+        let zeros_before = scan_sums(local, zero) - zero;
+        let zeros = sums[WORKGROUP_SIZE - 1u];
+        workgroupBarrier();
+
+        // Zeros keep their order before the ones, which keep theirs
+        var position = zeros_before;
+        if zero == 0u {
+            position = zeros + local - zeros_before;
+        }
+        tile_keys[position] = key;
+        tile_values[position] = value;
+        workgroupBarrier();
+
+        key = tile_keys[local];
+        value = tile_values[local];
+        workgroupBarrier();
+    }
+
+    // The tile is sorted by the digit, so each digit starts where it differs
+    // from the key before it
+    let key_digit = digit(key);
+    if local == 0u || digit(tile_keys[local - 1u]) != key_digit {
+        digit_starts[key_digit] = local;
+    }
+    workgroupBarrier();
+
+    let tile_len = min(WORKGROUP_SIZE, sort_pass.len - tile * WORKGROUP_SIZE);
+    if local < tile_len {
+        let rank = local - digit_starts[key_digit];
+        let destination = counts[key_digit * tiles + tile] + rank;
+        destination_keys[destination] = key;
+        if PAIRS {
+            destination_values[destination] = value;
+        }
+    }
+}
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>) {
+    let tiles = (sort_pass.len + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+
+    if sort_pass.phase == HISTOGRAM {
+        count_digits(local, workgroup_id.x, tiles);
+    } else if sort_pass.phase == SCAN {
+        scan_counts(local, tiles);
+    } else {
+        scatter(local, workgroup_id.x, tiles);
+    }
+}
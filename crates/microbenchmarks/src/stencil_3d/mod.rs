@@ -0,0 +1,499 @@
+//! 3D stencil microbenchmark
+
+use std::collections::HashMap;
+
+use rand::{thread_rng, Rng};
+use uwgpu::{
+    wgpu::{
+        util::BufferInitDescriptor, BindGroup, BufferDescriptor, BufferUsages,
+        Limits, ShaderModuleDescriptor, ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, BindGroupParams,
+    CreatePipelineError, GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    accuracy::{read_staging_buffer, sample_indices, staging_buffer},
+    microbenchmark::{
+        default_workgroups_3d, fits_limits, AccuracyFuture, Metric,
+        Microbenchmark, MicrobenchmarkParameters, RunFuture,
+    },
+    Accuracy, BenchmarkError, ElementType,
+};
+
+/// 256x256x256 grid
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 256,
+    warmup_count: 50,
+    count: 500,
+    element_type: ElementType::F32,
+};
+
+/// Weights of the stencil, the same ones as in the shader
+const CENTER_WEIGHT: f32 = 0.4;
+const NEIGHBOR_WEIGHT: f32 = 0.1;
+
+/// Microbenchmark for a 7-point stencil over a 3D grid.
+///
+/// Each cell of a cubic grid of random data is replaced by a weighted sum of
+/// itself and its 6 face neighbors, the [MicrobenchmarkParameters::size] is
+/// the width, height and depth of the grid.
+///
+/// Each iteration is a time step that reads the previous one's output, by
+/// swapping the input and result grids between iterations.
+pub async fn stencil_3d_benchmark(
+    gpu: &GPUContext,
+    workgroup_size: (u32, u32, u32),
+    parameters: MicrobenchmarkParameters,
+) -> Result<Stencil3dResults, BenchmarkError> {
+    let buffers = Buffers::new_with_random_inputs(parameters.size, gpu);
+    let pipeline = stencil_3d_pipeline(gpu, &buffers, workgroup_size).await?;
+    let swapped_bind_group = buffers.create_swapped_bind_group(&pipeline);
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: workgroups_dispatch(
+            parameters.size,
+            workgroup_size,
+        ),
+        dispatch_callback: None,
+        ping_pong_bind_group: Some(&swapped_bind_group),
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(Stencil3dResults {
+        results,
+        parameters,
+    })
+}
+
+/// Accuracy of a single application of the stencil to random data with the
+/// parameters of [stencil_3d_benchmark], against a reference computed on the
+/// CPU.
+pub async fn stencil_3d_accuracy(
+    gpu: &GPUContext,
+    workgroup_size: (u32, u32, u32),
+    parameters: MicrobenchmarkParameters,
+) -> Result<Accuracy, BenchmarkError> {
+    let grid_dims = parameters.size;
+    let mut input_grid = vec![0_f32; grid_dims.pow(3)];
+    thread_rng().fill(input_grid.as_mut_slice());
+    let buffers = Buffers::new_from_input(&input_grid, gpu);
+    let pipeline = stencil_3d_pipeline(gpu, &buffers, workgroup_size).await?;
+
+    let result_size = std::mem::size_of_val(input_grid.as_slice()) as u64;
+    let staging_buffer = staging_buffer(gpu, result_size);
+    Benchmark {
+        warmup_count: 0,
+        count: 1,
+        workgroups_dispatch: workgroups_dispatch(grid_dims, workgroup_size),
+        finalize_encoder_callback: Some(&|encoder| {
+            encoder.copy_buffer_to_buffer(
+                &buffers.result_buffer,
+                0,
+                &staging_buffer,
+                0,
+                result_size,
+            )
+        }),
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+    let result =
+        ElementType::F32.decode(&read_staging_buffer(&staging_buffer).await);
+
+    Ok(Accuracy::measure(
+        ElementType::F32,
+        sample_indices(input_grid.len()).into_iter().map(|i| {
+            (result[i], stencil_3d_reference(&input_grid, grid_dims, i))
+        }),
+    ))
+}
+
+/// The cell `i` of the grid after applying the stencil like the shader does,
+/// with the neighbors outside of the grid counting as 0.
+fn stencil_3d_reference(input_grid: &[f32], grid_dims: usize, i: usize) -> f64 {
+    let (x, y, z) = (
+        i % grid_dims,
+        (i / grid_dims) % grid_dims,
+        i / grid_dims.pow(2),
+    );
+    let (row, plane) = (grid_dims, grid_dims.pow(2));
+    let cell = |i: usize| input_grid[i] as f64;
+
+    let neighbors = [
+        (x > 0).then(|| cell(i - 1)),
+        (x + 1 < grid_dims).then(|| cell(i + 1)),
+        (y > 0).then(|| cell(i - row)),
+        (y + 1 < grid_dims).then(|| cell(i + row)),
+        (z > 0).then(|| cell(i - plane)),
+        (z + 1 < grid_dims).then(|| cell(i + plane)),
+    ];
+
+    CENTER_WEIGHT as f64 * cell(i)
+        + NEIGHBOR_WEIGHT as f64 * neighbors.into_iter().flatten().sum::<f64>()
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the 3D stencil microbenchmark. See [stencil_3d_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_eval]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct Stencil3dResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl Stencil3dResults {
+    /// Get the amount of FLOPS (floating point operations per second)
+    pub fn flops(&self) -> f64 {
+        (flops_per_iteration(self.parameters.size) as f64
+            * self.results.count as f64)
+            / (self.results.total_time(TimeUnit::Second))
+    }
+}
+
+/// 3D stencil microbenchmark, see [stencil_3d_benchmark].
+pub struct Stencil3d;
+
+impl Microbenchmark for Stencil3d {
+    fn name(&self) -> &'static str { "stencil_3d" }
+
+    fn title(&self) -> &'static str { "3D Stencil" }
+
+    fn description(&self) -> &'static str {
+        "This microbenchmark tests the performance of a naive 7-point stencil over a 256x256x256 grid, using 3D workgroups."
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 3 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_3d()
+    }
+
+    fn size_description(&self) -> &'static str {
+        "Dimensions of the cubic grid"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let size = parameters.size;
+        fits_limits(
+            limits,
+            workgroup_size,
+            || workgroups_dispatch(size, workgroup_size),
+            (size * size * size * size_of::<f32>()) as u64,
+        )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(stencil_3d_benchmark(gpu, workgroup_size, parameters)
+                .await?
+                .results)
+        })
+    }
+
+    fn accuracy<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> AccuracyFuture<'a> {
+        Box::pin(async move {
+            Ok(Some(
+                stencil_3d_accuracy(gpu, workgroup_size, parameters).await?,
+            ))
+        })
+    }
+
+    fn metrics(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        let results = Stencil3dResults {
+            results: results.clone(),
+            parameters: *parameters,
+        };
+        vec![Metric::flops(results.flops())]
+    }
+
+    fn arithmetic_intensity(
+        &self,
+        parameters: &MicrobenchmarkParameters,
+    ) -> Option<f64> {
+        // reads the input grid and writes the result
+        let n = parameters.size;
+        let bytes = 2 * n.pow(3) * size_of::<f32>();
+        Some(flops_per_iteration(n) as f64 / bytes as f64)
+    }
+}
+
+/// Amount of floating point operations done by each iteration of the
+/// microbenchmark with a `grid_dims` x `grid_dims` x `grid_dims` grid.
+fn flops_per_iteration(grid_dims: usize) -> usize {
+    // every cell: 2 muls + 1 sum to weight itself and its neighbors
+    let flops_weights = grid_dims.pow(3) * 3;
+    // 1 sum per neighbor inside the grid, along each axis every row of
+    // cells has (grid_dims - 1) pairs of neighbors
+    let flops_neighbors = 3 * 2 * grid_dims.pow(2) * (grid_dims - 1);
+
+    flops_weights + flops_neighbors
+}
+
+/// GPU buffers needed for microbenchmark
+///
+/// Grids are assumed to be cubic.
+struct Buffers {
+    input_grid_buffer: AsyncBuffer,
+    result_buffer: AsyncBuffer,
+    grid_size_buffer: AsyncBuffer,
+}
+
+impl Buffers {
+    fn new_with_random_inputs(grid_dims: usize, gpu: &GPUContext) -> Self {
+        let mut input_grid = vec![0_f32; grid_dims.pow(3)];
+
+        thread_rng().fill(input_grid.as_mut_slice());
+
+        Self::new_from_input(&input_grid, gpu)
+    }
+
+    /// The grid's dimensions are taken from the cubic root of the amount of
+    /// elements of the input.
+    fn new_from_input(input_grid_data: &[f32], gpu: &GPUContext) -> Self {
+        let grid_dims = (input_grid_data.len() as f64).cbrt().round() as usize;
+        assert_eq!(input_grid_data.len(), grid_dims.pow(3));
+
+        let input_grid_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Input Grid Buffer"),
+            contents: bytemuck::cast_slice(input_grid_data),
+            usage: BufferUsages::STORAGE,
+        });
+
+        let result_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Result Buffer"),
+            size: std::mem::size_of_val(input_grid_data) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let grid_size_buffer = gpu.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Grid Size Buffer"),
+            contents: bytemuck::cast_slice(&[grid_dims as u32]),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        Self {
+            input_grid_buffer,
+            result_buffer,
+            grid_size_buffer,
+        }
+    }
+
+    /// Bind group with the input and result grids swapped, so the stencil
+    /// reads the result grid and writes to the input grid.
+    fn create_swapped_bind_group(
+        &self,
+        pipeline: &BenchmarkComputePipeline,
+    ) -> BindGroup {
+        pipeline.create_bind_group(BindGroupParams {
+            label: Some("Swapped Grids Bind Group"),
+            group: 0,
+            entries: HashMap::from([
+                (0, self.result_buffer.as_entire_binding()),
+                (1, self.input_grid_buffer.as_entire_binding()),
+                (2, self.grid_size_buffer.as_entire_binding()),
+            ]),
+        })
+    }
+}
+
+/// Pipeline needed for microbenchmark
+async fn stencil_3d_pipeline<'a>(
+    gpu: &'a GPUContext,
+    buffers: &'a Buffers,
+    workgroup_size: (u32, u32, u32),
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("3D stencil shader"),
+            source: ShaderSource::Wgsl(include_str!("stencil_3d.wgsl").into()),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([
+            (0, buffers.input_grid_buffer.as_entire_binding()),
+            (1, buffers.result_buffer.as_entire_binding()),
+            (2, buffers.grid_size_buffer.as_entire_binding()),
+        ]),
+        gpu,
+        workgroup_size: Some(workgroup_size),
+    })
+    .await
+}
+
+fn workgroups_dispatch(
+    grid_dims: usize,
+    workgroup_size: (u32, u32, u32),
+) -> Vec<(u32, u32, u32)> {
+    vec![(
+        1 + (grid_dims / (workgroup_size.0 as usize)) as u32,
+        1 + (grid_dims / (workgroup_size.1 as usize)) as u32,
+        1 + (grid_dims / (workgroup_size.2 as usize)) as u32,
+    )]
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::{
+        estimate_shader_cost,
+        wgpu::{BufferDescriptor, BufferUsages, MapMode},
+        Benchmark, GPUContext, ShaderCostParameters,
+    };
+
+    use super::*;
+
+    /// Cross-checks [flops_per_iteration] against the estimate from analyzing
+    /// the shader.
+    #[test]
+    fn flops_match_shader_estimate() {
+        let grid_dims = DEFAULT_PARAMETERS.size;
+        let cost = estimate_shader_cost(&ShaderCostParameters {
+            shader: ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(
+                    include_str!("stencil_3d.wgsl").into(),
+                ),
+            },
+            entry_point: "main",
+            workgroup_size: Some((4, 4, 4)),
+            uniforms: HashMap::from([((0, 2), grid_dims as i64)]),
+        })
+        .unwrap()
+        .for_invocations(grid_dims.pow(3) as u64);
+
+        // The estimate assumes every cell has its 6 neighbors, including the
+        // ones at the faces of the grid
+        assert_eq!(cost.unresolved_loops, 0);
+        let relative_error =
+            (cost.flops - flops_per_iteration(grid_dims) as f64).abs()
+                / cost.flops;
+        assert!(relative_error < 0.01, "relative error: {relative_error}");
+    }
+
+    /// Verifies that the 3D workgroups cover the whole grid and that the
+    /// stencil is computed correctly, including at the faces of the grid.
+    #[tokio::test]
+    async fn stencil_3d_works() {
+        const GRID_DIMS: usize = 9;
+        const GRID_SIZE: usize = GRID_DIMS * GRID_DIMS * GRID_DIMS;
+
+        let input_grid: Vec<f32> = (0..GRID_SIZE).map(|i| i as f32).collect();
+
+        let cell = |x: isize, y: isize, z: isize| {
+            let dims = 0..GRID_DIMS as isize;
+            match dims.contains(&x) && dims.contains(&y) && dims.contains(&z) {
+                true => {
+                    input_grid[(z as usize * GRID_DIMS + y as usize)
+                        * GRID_DIMS
+                        + x as usize]
+                }
+                false => 0.0,
+            }
+        };
+        let expected_result: Vec<f32> = (0..GRID_SIZE as isize)
+            .map(|i| {
+                let dims = GRID_DIMS as isize;
+                let (x, y, z) =
+                    (i % dims, (i / dims) % dims, i / (dims * dims));
+                let neighbors = cell(x - 1, y, z)
+                    + cell(x + 1, y, z)
+                    + cell(x, y - 1, z)
+                    + cell(x, y + 1, z)
+                    + cell(x, y, z - 1)
+                    + cell(x, y, z + 1);
+
+                0.4 * cell(x, y, z) + 0.1 * neighbors
+            })
+            .collect();
+
+        let workgroup_size = (4, 2, 4);
+        let gpu = GPUContext::new(None).await.unwrap();
+        let buffers = Buffers::new_from_input(&input_grid, &gpu);
+        let pipeline = stencil_3d_pipeline(&gpu, &buffers, workgroup_size)
+            .await
+            .unwrap();
+
+        let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Staging Buffer"),
+            size: (GRID_SIZE * std::mem::size_of::<f32>()) as u64,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let _results = Benchmark {
+            warmup_count: 0,
+            count: 1,
+            workgroups_dispatch: workgroups_dispatch(GRID_DIMS, workgroup_size),
+            dispatch_callback: None,
+            finalize_encoder_callback: Some(&|encoder| {
+                encoder.copy_buffer_to_buffer(
+                    &buffers.result_buffer,
+                    0,
+                    &staging_buffer,
+                    0,
+                    (GRID_SIZE * std::mem::size_of::<f32>()) as u64,
+                )
+            }),
+            ..Default::default()
+        }
+        .run(pipeline)
+        .await
+        .unwrap();
+
+        let staging_slice = staging_buffer.slice(..);
+        staging_slice.map_async(MapMode::Read).await.unwrap();
+
+        let result_data: Vec<f32> = {
+            let result_data_raw: &[u8] = &staging_slice.get_mapped_range();
+            bytemuck::cast_slice(result_data_raw).to_vec()
+        };
+
+        staging_buffer.unmap();
+
+        for (result, expected) in result_data.iter().zip(&expected_result) {
+            assert!(
+                (result - expected).abs() <= expected.abs() * 1e-5,
+                "{result} != {expected}"
+            );
+        }
+    }
+}
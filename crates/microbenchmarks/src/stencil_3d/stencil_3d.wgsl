@@ -0,0 +1,44 @@
+@group(0) @binding(0) var<storage, read> inputGrid: array<f32>;
+@group(0) @binding(1) var<storage, read_write> result: array<f32>;
+@group(0) @binding(2) var<uniform> gridSize: u32;
+
+const CENTER_WEIGHT: f32 = 0.4;
+const NEIGHBOR_WEIGHT: f32 = 0.1;
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x: u32 = global_id.x;
+    let y: u32 = global_id.y;
+    let z: u32 = global_id.z;
+
+    if x >= gridSize || y >= gridSize || z >= gridSize {
+        return;
+    }
+
+    let row = gridSize;
+    let plane = gridSize * gridSize;
+    let i = z * plane + y * row + x;
+
+    // Neighbors outside of the grid count as 0
+    var sum: f32 = 0.0;
+    if x > 0u {
+        sum += inputGrid[i - 1u];
+    }
+    if x + 1u < gridSize {
+        sum += inputGrid[i + 1u];
+    }
+    if y > 0u {
+        sum += inputGrid[i - row];
+    }
+    if y + 1u < gridSize {
+        sum += inputGrid[i + row];
+    }
+    if z > 0u {
+        sum += inputGrid[i - plane];
+    }
+    if z + 1u < gridSize {
+        sum += inputGrid[i + plane];
+    }
+
+    result[i] = CENTER_WEIGHT * inputGrid[i] + NEIGHBOR_WEIGHT * sum;
+}
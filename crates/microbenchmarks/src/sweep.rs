@@ -0,0 +1,481 @@
+//! Running microbenchmarks over combinations of workgroup and problem sizes,
+//! see [Sweep]
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::RangeInclusive,
+};
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use uwgpu::{
+    wgpu::{Features, Limits},
+    BenchmarkResults, GPUContext,
+};
+
+use crate::{
+    microbenchmarks, Accuracy, BenchmarkError, ElementType, Metric,
+    Microbenchmark, MicrobenchmarkParameters,
+};
+
+/// A sweep over the cartesian product of microbenchmarks, element types,
+/// workgroup sizes and problem sizes.
+///
+/// The combinations the device can't run are skipped and the rest are ran in
+/// a random order on the same [GPUContext], so that slow drifts of the
+/// device's performance (such as thermal throttling) don't consistently favor
+/// the combinations that ran first.
+pub struct Sweep {
+    /// Microbenchmarks to run
+    pub microbenchmarks: Vec<&'static dyn Microbenchmark>,
+    /// Workgroup sizes to run each microbenchmark with
+    pub workgroups: WorkgroupSweep,
+    /// Problem sizes to run each microbenchmark with, see
+    /// [MicrobenchmarkParameters::size]. The microbenchmark's
+    /// [Microbenchmark::default_sizes] are used if empty.
+    pub sizes: Vec<usize>,
+    /// Element types to run each microbenchmark with, see
+    /// [MicrobenchmarkParameters::element_type]. The microbenchmark's
+    /// default element type is used if empty.
+    pub element_types: Vec<ElementType>,
+    /// Overrides the microbenchmarks' default warmup count
+    pub warmup_count: Option<usize>,
+    /// Overrides the microbenchmarks' default amount of measured iterations
+    pub count: Option<usize>,
+    /// Seed of the order the combinations are ran in, a random one is used if
+    /// not given.
+    pub seed: Option<u64>,
+}
+
+/// The workgroup sizes a [Sweep] runs each microbenchmark with
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum WorkgroupSweep {
+    /// The microbenchmark's [Microbenchmark::default_workgroups]
+    #[default]
+    Default,
+    /// The given workgroup sizes, the dimensions past the microbenchmark's
+    /// [Microbenchmark::workgroup_dimensions] are set to 1
+    List(Vec<(u32, u32, u32)>),
+    /// The cartesian product of the values of each dimension, the dimensions
+    /// past the microbenchmark's [Microbenchmark::workgroup_dimensions] are
+    /// ignored and set to 1. See [powers_of_two] for building ranges of
+    /// values.
+    Product([Vec<u32>; 3]),
+}
+
+/// One combination of parameters of a [Sweep]
+#[derive(Clone, Copy)]
+pub struct SweepCase {
+    /// Microbenchmark to run
+    pub microbenchmark: &'static dyn Microbenchmark,
+    /// Workgroup size to run it with
+    pub workgroup_size: (u32, u32, u32),
+    /// Problem size and iteration counts to run it with
+    pub parameters: MicrobenchmarkParameters,
+}
+
+/// Identifies a [SweepCase] in the [SweepResults]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SweepKey {
+    /// The [Microbenchmark::name]
+    pub microbenchmark: &'static str,
+    /// The [MicrobenchmarkParameters::element_type]
+    pub element_type: ElementType,
+    /// The [MicrobenchmarkParameters::size]
+    pub size: usize,
+    /// The workgroup size
+    pub workgroup_size: (u32, u32, u32),
+}
+
+/// Outcome of a successful [SweepCase]
+#[derive(Debug, Clone)]
+pub struct SweepRun {
+    /// Parameters the microbenchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// Results of the run
+    pub results: BenchmarkResults,
+    /// Metrics computed from the results, see [Microbenchmark::metrics]
+    pub metrics: Vec<Metric>,
+    /// Accuracy of the output, see [Microbenchmark::accuracy]
+    pub accuracy: Option<Accuracy>,
+}
+
+/// Results of [Sweep::run], sorted by their parameters regardless of the
+/// order they ran in.
+#[derive(Debug, Default)]
+pub struct SweepResults {
+    /// Outcome of each combination that ran
+    pub runs: BTreeMap<SweepKey, Result<SweepRun, BenchmarkError>>,
+    /// Combinations that were skipped because the device's limits or features
+    /// don't allow them, see [Microbenchmark::supports] and
+    /// [Microbenchmark::supports_element_type]
+    pub unsupported: Vec<SweepKey>,
+}
+
+impl Default for Sweep {
+    /// Sweeps every microbenchmark with its default workgroups and
+    /// parameters, except the [Microbenchmark::opt_in] ones
+    fn default() -> Self {
+        Self {
+            microbenchmarks: microbenchmarks()
+                .filter(|microbenchmark| !microbenchmark.opt_in())
+                .collect(),
+            workgroups: WorkgroupSweep::Default,
+            sizes: vec![],
+            element_types: vec![],
+            warmup_count: None,
+            count: None,
+            seed: None,
+        }
+    }
+}
+
+impl Sweep {
+    /// Runs every supported combination on the given GPU, followed by a
+    /// check of its accuracy.
+    ///
+    /// A combination failing doesn't stop the rest from running, its error is
+    /// kept in the [SweepResults].
+    pub async fn run(&self, gpu: &GPUContext) -> SweepResults {
+        let limits = gpu.limits();
+        let features = gpu.features();
+        let mut sweep_results = SweepResults {
+            unsupported: self
+                .combinations()
+                .filter(|case| !case.is_supported(&limits, features))
+                .map(|case| case.key())
+                .collect(),
+            ..Default::default()
+        };
+
+        for case in self.cases(&limits, features) {
+            let outcome = async {
+                let microbenchmark = case.microbenchmark;
+                let results = microbenchmark
+                    .run(gpu, case.workgroup_size, case.parameters)
+                    .await?;
+                let accuracy = microbenchmark
+                    .accuracy(gpu, case.workgroup_size, case.parameters)
+                    .await?;
+
+                Ok(SweepRun {
+                    metrics: microbenchmark.metrics(&case.parameters, &results),
+                    parameters: case.parameters,
+                    results,
+                    accuracy,
+                })
+            }
+            .await;
+
+            sweep_results.runs.insert(case.key(), outcome);
+        }
+
+        sweep_results
+    }
+
+    /// The combinations supported by a device with the given limits and
+    /// features, in the order they should run.
+    pub fn cases(&self, limits: &Limits, features: Features) -> Vec<SweepCase> {
+        let mut cases: Vec<SweepCase> = self
+            .combinations()
+            .filter(|case| case.is_supported(limits, features))
+            .collect();
+
+        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        cases.shuffle(&mut StdRng::seed_from_u64(seed));
+
+        cases
+    }
+
+    /// Every combination of the sweep in a deterministic order, including the
+    /// unsupported ones.
+    fn combinations(&self) -> impl Iterator<Item = SweepCase> + '_ {
+        self.microbenchmarks
+            .iter()
+            .flat_map(move |&microbenchmark| {
+                let defaults = microbenchmark.default_parameters();
+                let element_types = match self.element_types.is_empty() {
+                    true => vec![defaults.element_type],
+                    false => self.element_types.clone(),
+                };
+                let sizes = match self.sizes.is_empty() {
+                    true => microbenchmark.default_sizes(),
+                    false => self.sizes.clone(),
+                };
+                let workgroups = self.workgroups.sizes(microbenchmark);
+
+                element_types.into_iter().flat_map(move |element_type| {
+                    let workgroups = workgroups.clone();
+
+                    sizes.clone().into_iter().flat_map(move |size| {
+                        let parameters = MicrobenchmarkParameters {
+                            size,
+                            warmup_count: self
+                                .warmup_count
+                                .unwrap_or(defaults.warmup_count),
+                            count: self.count.unwrap_or(defaults.count),
+                            element_type,
+                        };
+
+                        workgroups.clone().into_iter().map(
+                            move |workgroup_size| SweepCase {
+                                microbenchmark,
+                                workgroup_size,
+                                parameters,
+                            },
+                        )
+                    })
+                })
+            })
+    }
+}
+
+impl WorkgroupSweep {
+    /// The workgroup sizes to run the microbenchmark with, without
+    /// duplicates.
+    fn sizes(
+        &self,
+        microbenchmark: &dyn Microbenchmark,
+    ) -> Vec<(u32, u32, u32)> {
+        let dims = microbenchmark.workgroup_dimensions();
+        let truncate = |(x, y, z): (u32, u32, u32)| {
+            let size = [x, y, z];
+            let size = |dim: usize| if dim < dims { size[dim] } else { 1 };
+            (size(0), size(1), size(2))
+        };
+
+        let mut sizes: Vec<(u32, u32, u32)> = match self {
+            WorkgroupSweep::Default => microbenchmark.default_workgroups(),
+            WorkgroupSweep::List(sizes) => {
+                sizes.iter().copied().map(truncate).collect()
+            }
+            WorkgroupSweep::Product([xs, ys, zs]) => {
+                let values = |dim: usize, values: &Vec<u32>| match dim < dims {
+                    true => values.clone(),
+                    false => vec![1],
+                };
+
+                values(0, xs)
+                    .into_iter()
+                    .flat_map(|x| {
+                        values(1, ys).into_iter().flat_map(move |y| {
+                            values(2, zs).into_iter().map(move |z| (x, y, z))
+                        })
+                    })
+                    .collect()
+            }
+        };
+
+        let mut seen = HashSet::new();
+        sizes.retain(|size| seen.insert(*size));
+        sizes
+    }
+}
+
+impl SweepCase {
+    /// The key of the case in the [SweepResults]
+    pub fn key(&self) -> SweepKey {
+        SweepKey {
+            microbenchmark: self.microbenchmark.name(),
+            element_type: self.parameters.element_type,
+            size: self.parameters.size,
+            workgroup_size: self.workgroup_size,
+        }
+    }
+
+    fn is_supported(&self, limits: &Limits, features: Features) -> bool {
+        self.microbenchmark
+            .supports_element_type(self.parameters.element_type, features)
+            && self.microbenchmark.supports(
+                limits,
+                self.workgroup_size,
+                &self.parameters,
+            )
+    }
+}
+
+/// The powers of two within the range, useful for building the values of a
+/// [WorkgroupSweep::Product] or the [Sweep::sizes].
+pub fn powers_of_two(range: RangeInclusive<u32>) -> Vec<u32> {
+    (0..u32::BITS)
+        .map(|exponent| 1 << exponent)
+        .filter(|value| range.contains(value))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_microbenchmark;
+
+    /// Verifies the cartesian product skips the dimensions a microbenchmark
+    /// doesn't have and the combinations past the device's limits.
+    #[test]
+    fn combinations_are_filtered_by_limits() {
+        let sweep = Sweep {
+            microbenchmarks: vec![
+                find_microbenchmark("matmul").unwrap(),
+                find_microbenchmark("scan").unwrap(),
+            ],
+            workgroups: WorkgroupSweep::Product([
+                vec![1, 3, 16, 512],
+                vec![1, 16, 32],
+                vec![2, 4],
+            ]),
+            sizes: vec![64, 128],
+            seed: Some(0),
+            ..Default::default()
+        };
+        let limits = Limits::downlevel_defaults();
+
+        let cases = sweep.cases(&limits, Features::empty());
+
+        // matmul: every (x, y) pair under 256 invocations, with x <= 256
+        // scan: the powers of two under 256 invocations (1 and 16)
+        let matmul = [(1, 1), (1, 16), (1, 32), (3, 1), (3, 16), (3, 32)].len()
+            + [(16, 1), (16, 16)].len();
+        let scan = 2;
+        assert_eq!(cases.len(), (matmul + scan) * 2);
+        assert_eq!(sweep.combinations().count(), (4 * 3 + 4) * 2);
+
+        for case in &cases {
+            let (x, y, z) = case.workgroup_size;
+            assert!(x * y * z <= limits.max_compute_invocations_per_workgroup);
+            assert_eq!(z, 1);
+            if case.microbenchmark.name() == "scan" {
+                assert_eq!(y, 1);
+            }
+        }
+    }
+
+    /// Verifies the z dimension is only swept for the 3D microbenchmarks and
+    /// that it's checked against the device's limits.
+    #[test]
+    fn z_dimension_is_swept_in_3d() {
+        let sweep = Sweep {
+            microbenchmarks: vec![
+                find_microbenchmark("stencil_3d").unwrap(),
+                find_microbenchmark("convolution").unwrap(),
+            ],
+            workgroups: WorkgroupSweep::Product([
+                vec![4, 8],
+                vec![4, 8],
+                vec![1, 4, 128],
+            ]),
+            sizes: vec![64],
+            seed: Some(0),
+            ..Default::default()
+        };
+        let limits = Limits {
+            max_compute_workgroup_size_z: 64,
+            ..Limits::downlevel_defaults()
+        };
+
+        let cases = sweep.cases(&limits, Features::empty());
+        let zs = |name: &str| -> HashSet<u32> {
+            cases
+                .iter()
+                .filter(|case| case.microbenchmark.name() == name)
+                .map(|case| case.workgroup_size.2)
+                .collect()
+        };
+
+        assert_eq!(zs("stencil_3d"), HashSet::from([1, 4]));
+        assert_eq!(zs("convolution"), HashSet::from([1]));
+        assert_eq!(cases.len(), 2 * 2 * 2 + 2 * 2);
+    }
+
+    /// Verifies the order only depends on the seed and that the defaults of
+    /// each microbenchmark fill in the missing parameters and sizes.
+    #[test]
+    fn order_is_seeded() {
+        let sweep = |seed| Sweep {
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let keys = |seed| -> Vec<SweepKey> {
+            sweep(seed)
+                .cases(&Limits::default(), Features::empty())
+                .iter()
+                .map(SweepCase::key)
+                .collect()
+        };
+
+        assert_eq!(keys(7), keys(7));
+        assert_ne!(keys(7), keys(8));
+
+        let mut sorted = keys(7);
+        sorted.sort();
+        let mut combinations: Vec<SweepKey> = sweep(7)
+            .combinations()
+            .filter(|case| {
+                case.is_supported(&Limits::default(), Features::empty())
+            })
+            .map(|case| case.key())
+            .collect();
+        combinations.sort();
+        assert_eq!(sorted, combinations);
+
+        for case in sweep(7).cases(&Limits::default(), Features::empty()) {
+            let microbenchmark = case.microbenchmark;
+            assert!(microbenchmark
+                .default_sizes()
+                .contains(&case.parameters.size));
+            assert_eq!(
+                case.parameters,
+                MicrobenchmarkParameters {
+                    size: case.parameters.size,
+                    ..microbenchmark.default_parameters()
+                }
+            );
+        }
+    }
+
+    /// Verifies the element types are swept for the microbenchmarks that
+    /// support them, and only if the device has the features they need.
+    #[test]
+    fn element_types_are_filtered_by_features() {
+        let sweep = Sweep {
+            microbenchmarks: vec![
+                find_microbenchmark("reduction_sum").unwrap(),
+                find_microbenchmark("buffer_to_buffer").unwrap(),
+            ],
+            workgroups: WorkgroupSweep::List(vec![(64, 1, 1)]),
+            element_types: ElementType::ALL.to_vec(),
+            seed: Some(0),
+            ..Default::default()
+        };
+        let limits = Limits::default();
+        let keys = |features: Features| -> Vec<SweepKey> {
+            let mut keys: Vec<SweepKey> = sweep
+                .cases(&limits, features)
+                .iter()
+                .map(SweepCase::key)
+                .collect();
+            keys.sort();
+            keys
+        };
+
+        assert_eq!(
+            keys(Features::empty())
+                .iter()
+                .map(|key| (key.microbenchmark, key.element_type))
+                .collect::<Vec<_>>(),
+            [
+                ("buffer_to_buffer", ElementType::F32),
+                ("reduction_sum", ElementType::F32),
+                ("reduction_sum", ElementType::I32),
+                ("reduction_sum", ElementType::U32),
+            ]
+        );
+        assert!(keys(Features::SHADER_F16)
+            .iter()
+            .any(|key| key.element_type == ElementType::F16));
+        assert_eq!(sweep.combinations().count(), 8);
+    }
+
+    #[test]
+    fn powers_of_two_in_range() {
+        assert_eq!(powers_of_two(3..=64), vec![4, 8, 16, 32, 64]);
+        assert_eq!(powers_of_two(1..=1), vec![1]);
+    }
+}
@@ -0,0 +1,414 @@
+//! Searching for the fastest workgroup size of a microbenchmark, see [Tuner]
+
+use std::future::Future;
+
+use thiserror::Error;
+use uwgpu::{wgpu::Limits, GPUContext, TimeUnit};
+
+use crate::{
+    powers_of_two, BenchmarkError, ElementType, Microbenchmark,
+    MicrobenchmarkParameters,
+};
+
+/// Auto-tuner of the workgroup size of a microbenchmark using successive
+/// halving.
+///
+/// Every workgroup shape with power of two dimensions that the device
+/// supports is a candidate. Each round runs every remaining candidate a few
+/// times with short runs and keeps the fastest half, the runs of the next
+/// round being twice as long. So most of the time is spent measuring the
+/// candidates that are competitive.
+pub struct Tuner {
+    /// Microbenchmark to tune
+    pub microbenchmark: &'static dyn Microbenchmark,
+    /// Problem size to tune for, see [MicrobenchmarkParameters::size]
+    pub size: usize,
+    /// Element type to tune for, see [MicrobenchmarkParameters::element_type]
+    pub element_type: ElementType,
+    /// Warmup iterations before each run
+    pub warmup_count: usize,
+    /// Measured iterations of each run in the first round, doubled every
+    /// round
+    pub initial_count: usize,
+    /// Runs of each candidate per round, the time per iteration of each run
+    /// is one sample of the candidate's confidence bounds
+    pub runs_per_round: usize,
+}
+
+/// A candidate workgroup size along with the measurements taken of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TunedWorkgroup {
+    /// The workgroup size
+    pub workgroup_size: (u32, u32, u32),
+    /// Rounds of the successive halving it took part in
+    pub rounds: usize,
+    /// Amount of runs measured
+    pub samples: usize,
+    /// Mean time per iteration of the runs in nanoseconds
+    pub mean_ns: f64,
+    /// Lower end of the 95% confidence interval of the mean, in nanoseconds
+    pub lower_ns: f64,
+    /// Upper end of the 95% confidence interval of the mean, in nanoseconds
+    pub upper_ns: f64,
+}
+
+/// Results of [Tuner::tune]
+#[derive(Debug, Clone)]
+pub struct TuneResults {
+    /// Every candidate that could run, from best to worst. The candidates
+    /// that made it to later rounds rank above the ones eliminated earlier.
+    pub ranking: Vec<TunedWorkgroup>,
+    /// Candidates that failed to run along with the reason
+    pub failed: Vec<((u32, u32, u32), BenchmarkError)>,
+}
+
+/// An error trying to tune a microbenchmark with [Tuner::tune]
+#[derive(Debug, Clone, Error)]
+pub enum TuneError {
+    /// None of the workgroup sizes fit the device's limits
+    #[error("no workgroup size is supported by the device's limits")]
+    NoSupportedWorkgroups,
+    /// The microbenchmark or the device don't support the element type, see
+    /// [Microbenchmark::supports_element_type]
+    #[error("the element type {0} isn't supported")]
+    UnsupportedElementType(ElementType),
+    /// Every candidate failed to run, this is the error of the last one
+    #[error("every workgroup size failed to run: {0}")]
+    AllFailed(BenchmarkError),
+}
+
+impl Tuner {
+    /// Tuner for the microbenchmark with its default problem size, where the
+    /// runs of the first round are a 16th of the microbenchmark's default
+    /// runs.
+    pub fn new(microbenchmark: &'static dyn Microbenchmark) -> Self {
+        let defaults = microbenchmark.default_parameters();
+
+        Self {
+            microbenchmark,
+            size: defaults.size,
+            element_type: defaults.element_type,
+            warmup_count: (defaults.warmup_count / 16).max(1),
+            initial_count: (defaults.count / 16).max(1),
+            runs_per_round: 3,
+        }
+    }
+
+    /// Searches for the workgroup size with the lowest time per iteration.
+    pub async fn tune(
+        &self,
+        gpu: &GPUContext,
+    ) -> Result<TuneResults, TuneError> {
+        if !self
+            .microbenchmark
+            .supports_element_type(self.element_type, gpu.features())
+        {
+            return Err(TuneError::UnsupportedElementType(self.element_type));
+        }
+
+        let candidates = self.candidates(&gpu.limits());
+
+        successive_halving(
+            candidates,
+            self.initial_count,
+            self.runs_per_round,
+            |workgroup_size, count| async move {
+                let parameters = MicrobenchmarkParameters {
+                    size: self.size,
+                    warmup_count: self.warmup_count,
+                    count,
+                    element_type: self.element_type,
+                };
+                let results = self
+                    .microbenchmark
+                    .run(gpu, workgroup_size, parameters)
+                    .await?;
+
+                Ok(results.time_per_iteration(TimeUnit::Nano))
+            },
+        )
+        .await
+    }
+
+    /// Every workgroup shape with power of two dimensions that a device with
+    /// the given limits supports, see [Microbenchmark::supports]. The
+    /// dimensions past the microbenchmark's
+    /// [Microbenchmark::workgroup_dimensions] are 1.
+    pub fn candidates(&self, limits: &Limits) -> Vec<(u32, u32, u32)> {
+        let dims = self.microbenchmark.workgroup_dimensions();
+        let max_sizes = [
+            limits.max_compute_workgroup_size_x,
+            limits.max_compute_workgroup_size_y,
+            limits.max_compute_workgroup_size_z,
+        ];
+        let values = |dim: usize| match dim < dims {
+            true => powers_of_two(1..=max_sizes[dim]),
+            false => vec![1],
+        };
+        let parameters = MicrobenchmarkParameters {
+            size: self.size,
+            warmup_count: self.warmup_count,
+            count: self.initial_count,
+            element_type: self.element_type,
+        };
+
+        values(0)
+            .into_iter()
+            .flat_map(|x| {
+                values(1).into_iter().flat_map(move |y| {
+                    values(2).into_iter().map(move |z| (x, y, z))
+                })
+            })
+            .filter(|&workgroup_size| {
+                self.microbenchmark.supports(
+                    limits,
+                    workgroup_size,
+                    &parameters,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Successive halving over the candidates, where `measure` runs a candidate
+/// with the given amount of iterations and returns its time per iteration.
+async fn successive_halving<F, Fut>(
+    candidates: Vec<(u32, u32, u32)>,
+    initial_count: usize,
+    runs_per_round: usize,
+    mut measure: F,
+) -> Result<TuneResults, TuneError>
+where
+    F: FnMut((u32, u32, u32), usize) -> Fut,
+    Fut: Future<Output = Result<f64, BenchmarkError>>,
+{
+    if candidates.is_empty() {
+        return Err(TuneError::NoSupportedWorkgroups);
+    }
+
+    let mut remaining: Vec<Candidate> = candidates
+        .into_iter()
+        .map(|workgroup_size| Candidate {
+            workgroup_size,
+            rounds: 0,
+            samples: vec![],
+        })
+        .collect();
+    let mut eliminated: Vec<Vec<Candidate>> = vec![];
+    let mut failed = vec![];
+    let mut count = initial_count.max(1);
+
+    loop {
+        let mut measured = vec![];
+        for mut candidate in remaining {
+            let mut error = None;
+            for _ in 0..runs_per_round.max(1) {
+                match measure(candidate.workgroup_size, count).await {
+                    Ok(time) => candidate.samples.push(time),
+                    Err(err) => {
+                        error = Some(err);
+                        break;
+                    }
+                }
+            }
+
+            match error {
+                Some(err) => failed.push((candidate.workgroup_size, err)),
+                None => {
+                    candidate.rounds += 1;
+                    measured.push(candidate);
+                }
+            }
+        }
+
+        measured.sort_by(|a, b| a.mean().total_cmp(&b.mean()));
+
+        if measured.len() <= 1 {
+            remaining = measured;
+            break;
+        }
+
+        let kept = measured.len().div_ceil(2);
+        eliminated.push(measured.split_off(kept));
+        remaining = measured;
+        count *= 2;
+    }
+
+    let ranking: Vec<TunedWorkgroup> = remaining
+        .into_iter()
+        .chain(eliminated.into_iter().rev().flatten())
+        .map(|candidate| candidate.summary())
+        .collect();
+
+    if ranking.is_empty() {
+        let (_, err) = failed.pop().expect("every candidate failed");
+        return Err(TuneError::AllFailed(err));
+    }
+
+    Ok(TuneResults { ranking, failed })
+}
+
+impl TuneResults {
+    /// The fastest workgroup size found
+    pub fn best(&self) -> &TunedWorkgroup { &self.ranking[0] }
+
+    /// Whether the confidence interval of the best workgroup size doesn't
+    /// overlap with the one of the runner-up, meaning it's very likely to
+    /// actually be faster and not a product of noise.
+    pub fn is_conclusive(&self) -> bool {
+        match self.ranking.get(1) {
+            Some(runner_up) => self.best().upper_ns < runner_up.lower_ns,
+            None => true,
+        }
+    }
+}
+
+/// A candidate workgroup size during the successive halving
+struct Candidate {
+    workgroup_size: (u32, u32, u32),
+    rounds: usize,
+    /// Time per iteration of each run
+    samples: Vec<f64>,
+}
+
+impl Candidate {
+    fn mean(&self) -> f64 {
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+
+    /// The mean with its 95% confidence interval, using the Student's t
+    /// distribution since there are few samples.
+    fn summary(&self) -> TunedWorkgroup {
+        let n = self.samples.len();
+        let mean = self.mean();
+        let half_width = match n {
+            0 | 1 => f64::INFINITY,
+            _ => {
+                let variance = self
+                    .samples
+                    .iter()
+                    .map(|sample| (sample - mean).powi(2))
+                    .sum::<f64>()
+                    / (n - 1) as f64;
+
+                t_critical_95(n - 1) * (variance / n as f64).sqrt()
+            }
+        };
+
+        TunedWorkgroup {
+            workgroup_size: self.workgroup_size,
+            rounds: self.rounds,
+            samples: n,
+            mean_ns: mean,
+            lower_ns: mean - half_width,
+            upper_ns: mean + half_width,
+        }
+    }
+}
+
+/// Two-sided 95% critical value of the Student's t distribution with the
+/// given degrees of freedom.
+fn t_critical_95(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+        2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+        2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+    ];
+
+    match degrees_of_freedom {
+        0 => f64::INFINITY,
+        df if df <= TABLE.len() => TABLE[df - 1],
+        _ => 1.960,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use uwgpu::GetGPUContextError;
+
+    use super::*;
+    use crate::find_microbenchmark;
+
+    /// Verifies the candidates respect the workgroup size limits, for both 1D
+    /// and 2D microbenchmarks.
+    #[test]
+    fn candidates_respect_limits() {
+        let limits = Limits::downlevel_defaults();
+
+        // Small enough for a workgroup size of 1 to not dispatch too many
+        // workgroups
+        let scan = Tuner {
+            size: 1024,
+            ..Tuner::new(find_microbenchmark("scan").unwrap())
+        };
+        assert_eq!(
+            scan.candidates(&limits),
+            powers_of_two(1..=256)
+                .into_iter()
+                .map(|x| (x, 1, 1))
+                .collect::<Vec<_>>()
+        );
+
+        let matmul = Tuner::new(find_microbenchmark("matmul").unwrap());
+        let candidates = matmul.candidates(&limits);
+        // Pairs of exponents adding up to at most 8
+        assert_eq!(candidates.len(), (1..=9).sum::<usize>());
+        assert!(candidates.iter().all(|&(x, y, z)| {
+            z == 1 && x * y <= limits.max_compute_invocations_per_workgroup
+        }));
+        assert!(candidates.contains(&(1, 256, 1)));
+        assert!(candidates.contains(&(16, 16, 1)));
+    }
+
+    /// Verifies the halving finds the fastest candidate with a fake
+    /// measurement, spending more runs on the best candidates and leaving out
+    /// the ones that fail.
+    #[tokio::test]
+    async fn halving_finds_fastest() {
+        let candidates: Vec<(u32, u32, u32)> = powers_of_two(1..=256)
+            .into_iter()
+            .map(|x| (x, 1, 1))
+            .collect();
+        // Fastest at 64 with some deterministic jitter, 256 fails to run
+        let run = RefCell::new(0);
+        let measure = |(x, ..): (u32, u32, u32), _count: usize| {
+            *run.borrow_mut() += 1;
+            let jitter = (*run.borrow() % 3) as f64;
+            async move {
+                match x {
+                    256 => Err(BenchmarkError::GPUContext(
+                        GetGPUContextError::NoAdapter,
+                    )),
+                    _ => Ok(100.0
+                        + (x.ilog2() as f64 - 6.0).abs() * 10.0
+                        + jitter),
+                }
+            }
+        };
+
+        let results = successive_halving(candidates, 10, 3, measure)
+            .await
+            .unwrap();
+
+        let best = results.best();
+        assert_eq!(best.workgroup_size, (64, 1, 1));
+        assert!(best.lower_ns <= 101.0 && 101.0 <= best.upper_ns);
+        assert_eq!(results.ranking.len(), 8);
+        assert_eq!(results.failed.len(), 1);
+        assert!(results
+            .ranking
+            .windows(2)
+            .all(|pair| pair[0].rounds >= pair[1].rounds));
+        assert!(best.samples > results.ranking.last().unwrap().samples);
+    }
+
+    #[tokio::test]
+    async fn no_candidates_is_an_error() {
+        let result =
+            successive_halving(vec![], 10, 3, |_, _| async { Ok(1.0) }).await;
+
+        assert!(matches!(result, Err(TuneError::NoSupportedWorkgroups)));
+    }
+}
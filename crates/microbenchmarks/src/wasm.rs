@@ -3,19 +3,11 @@
 
 use wasm_bindgen::prelude::*;
 
-use crate::convolution::{convolution_benchmark, ConvolutionResults};
-use crate::matmul::{matmul_benchmark, MatmulResults};
-use crate::memcpy::buffer_to_buffer::{
-    buffer_to_buffer_benchmark, BufferToBufferResults,
+use crate::{
+    find_microbenchmark, microbenchmarks, Accuracy, Metric, Microbenchmark,
+    MicrobenchmarkParameters, Roofline, RooflinePoint,
 };
-use crate::memcpy::buffer_to_texture::{
-    buffer_to_texture_benchmark, BufferToTextureResults,
-};
-use crate::memcpy::texture_to_texture::{
-    texture_to_texture_benchmark, TextureToTextureResults,
-};
-use crate::reduction_sum::{reduction_sum_benchmark, ReductionSumResults};
-use crate::scan::{scan_benchmark, ScanResults};
+use uwgpu::{BenchmarkResults, GPUContext};
 
 #[wasm_bindgen(start)]
 /// Entrypoint to instantiate the WASM module.
@@ -23,70 +15,163 @@ pub async fn setup() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 }
 
-/// WASM compatible version of [matmul_benchmark]
+/// Names of every microbenchmark, see [microbenchmarks]
 #[wasm_bindgen]
-pub async fn wasm_matmul_benchmark(
-    workgroup_size_x: u32,
-    workgroup_size_y: u32,
-) -> Result<MatmulResults, JsError> {
-    Ok(matmul_benchmark((workgroup_size_x, workgroup_size_y)).await?)
+pub fn wasm_microbenchmark_names() -> Vec<String> {
+    microbenchmarks()
+        .map(|microbenchmark| microbenchmark.name().to_string())
+        .collect()
 }
 
-/// WASM compatible version of [convolution_benchmark]
-#[wasm_bindgen]
-pub async fn wasm_convolution_benchmark(
-    workgroup_size_x: u32,
-    workgroup_size_y: u32,
-) -> Result<ConvolutionResults, JsError> {
-    Ok(convolution_benchmark((workgroup_size_x, workgroup_size_y)).await?)
+/// Results of [wasm_run_microbenchmark]
+#[wasm_bindgen(getter_with_clone)]
+pub struct MicrobenchmarkRun {
+    /// Results of the benchmark
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// Metrics computed from the results, see [Microbenchmark::metrics]
+    pub metrics: Vec<Metric>,
+    /// Accuracy of the output, see [Microbenchmark::accuracy]
+    pub accuracy: Option<Accuracy>,
 }
 
-/// WASM compatible version of [reduction_sum_benchmark]
+/// Runs the microbenchmark with the given [Microbenchmark::name], the
+/// workgroup size can have from 1 to 3 dimensions.
+///
+/// Runs with the [Microbenchmark::default_parameters] if no parameters are
+/// given, and fails if the device doesn't support them, see
+/// [check_supported].
 #[wasm_bindgen]
-pub async fn wasm_reduction_sum_benchmark(
-    workgroup_size: u32,
-) -> Result<ReductionSumResults, JsError> {
-    Ok(reduction_sum_benchmark(workgroup_size).await?)
+pub async fn wasm_run_microbenchmark(
+    name: String,
+    workgroup_size: Vec<u32>,
+    parameters: Option<MicrobenchmarkParameters>,
+) -> Result<MicrobenchmarkRun, JsError> {
+    let (microbenchmark, workgroup_size) =
+        find_with_workgroup(&name, &workgroup_size)?;
+
+    let parameters =
+        parameters.unwrap_or_else(|| microbenchmark.default_parameters());
+    let gpu = GPUContext::new(None).await?;
+    check_supported(microbenchmark, &gpu, workgroup_size, &parameters)?;
+    let results = microbenchmark.run(&gpu, workgroup_size, parameters).await?;
+    let accuracy = microbenchmark
+        .accuracy(&gpu, workgroup_size, parameters)
+        .await?;
+
+    Ok(MicrobenchmarkRun {
+        metrics: microbenchmark.metrics(&parameters, &results),
+        results,
+        parameters,
+        accuracy,
+    })
 }
 
-/// WASM compatible version of [scan_benchmark]
+/// Checks the accuracy of the microbenchmark with the given
+/// [Microbenchmark::name], see [Microbenchmark::accuracy]. The workgroup size
+/// can have from 1 to 3 dimensions.
+///
+/// Runs with the [Microbenchmark::default_parameters] if no parameters are
+/// given, and fails if the device doesn't support them, see
+/// [check_supported].
 #[wasm_bindgen]
-pub async fn wasm_scan_benchmark(
-    workgroup_size: u32,
-) -> Result<ScanResults, JsError> {
-    Ok(scan_benchmark(workgroup_size).await?)
+pub async fn wasm_microbenchmark_accuracy(
+    name: String,
+    workgroup_size: Vec<u32>,
+    parameters: Option<MicrobenchmarkParameters>,
+) -> Result<Option<Accuracy>, JsError> {
+    let (microbenchmark, workgroup_size) =
+        find_with_workgroup(&name, &workgroup_size)?;
+
+    let parameters =
+        parameters.unwrap_or_else(|| microbenchmark.default_parameters());
+    let gpu = GPUContext::new(None).await?;
+    check_supported(microbenchmark, &gpu, workgroup_size, &parameters)?;
+
+    Ok(microbenchmark
+        .accuracy(&gpu, workgroup_size, parameters)
+        .await?)
 }
 
+/// Measures the peaks of the device's roofline, see [Roofline::measure]
 #[wasm_bindgen]
-/// WASM compatible version of [buffer_to_buffer_benchmark]
-pub async fn wasm_buffer_to_buffer_benchmark(
-    workgroup_size: u32,
-) -> Result<BufferToBufferResults, JsError> {
-    Ok(buffer_to_buffer_benchmark(workgroup_size).await?)
+pub async fn wasm_measure_roofline() -> Result<Roofline, JsError> {
+    let gpu = GPUContext::new(None).await?;
+    Ok(Roofline::measure(&gpu).await?)
 }
 
+/// Places a run of the microbenchmark with the given [Microbenchmark::name]
+/// on the roofline, see [Roofline::place]. The workgroup size can have from 1
+/// to 3 dimensions.
 #[wasm_bindgen]
-/// WASM compatible version of [buffer_to_texture_benchmark]
-pub async fn wasm_buffer_to_texture_benchmark(
-    workgroup_size_x: u32,
-    workgroup_size_y: u32,
-) -> Result<BufferToTextureResults, JsError> {
-    Ok(
-        buffer_to_texture_benchmark((workgroup_size_x, workgroup_size_y))
-            .await?,
-    )
+pub fn wasm_place_on_roofline(
+    roofline: &mut Roofline,
+    name: String,
+    workgroup_size: Vec<u32>,
+    run: &MicrobenchmarkRun,
+) -> Result<Option<RooflinePoint>, JsError> {
+    let (microbenchmark, workgroup_size) =
+        find_with_workgroup(&name, &workgroup_size)?;
+
+    Ok(roofline
+        .place(
+            microbenchmark,
+            workgroup_size,
+            &run.parameters,
+            &run.metrics,
+        )
+        .cloned())
 }
 
-#[wasm_bindgen]
-/// WASM compatible version of [texture_to_texture_benchmark]
-pub async fn wasm_texture_to_texture_benchmark(
-    workgroup_size_x: u32,
-    workgroup_size_y: u32,
-) -> Result<TextureToTextureResults, JsError> {
-    Ok(
-        texture_to_texture_benchmark((workgroup_size_x, workgroup_size_y))
-            .await?,
-    )
+/// Checks that the device supports the element type of the parameters, see
+/// [Microbenchmark::supports_element_type], and that the microbenchmark fits
+/// its limits with them, see [Microbenchmark::supports].
+fn check_supported(
+    microbenchmark: &dyn Microbenchmark,
+    gpu: &GPUContext,
+    workgroup_size: (u32, u32, u32),
+    parameters: &MicrobenchmarkParameters,
+) -> Result<(), JsError> {
+    if !microbenchmark
+        .supports_element_type(parameters.element_type, gpu.features())
+    {
+        return Err(JsError::new(&format!(
+            "{} doesn't support {} elements on this device",
+            microbenchmark.name(),
+            parameters.element_type
+        )));
+    }
+
+    if !microbenchmark.supports(&gpu.limits(), workgroup_size, parameters) {
+        return Err(JsError::new(&format!(
+            "{} doesn't support workgroup size {:?} with size {} on this \
+             device",
+            microbenchmark.name(),
+            workgroup_size,
+            parameters.size
+        )));
+    }
+
+    Ok(())
+}
+
+/// The microbenchmark with the given name along with the workgroup size of 1
+/// to 3 dimensions given by JS.
+fn find_with_workgroup(
+    name: &str,
+    workgroup_size: &[u32],
+) -> Result<(&'static dyn Microbenchmark, (u32, u32, u32)), JsError> {
+    let microbenchmark = find_microbenchmark(name).ok_or_else(|| {
+        JsError::new(&format!("unknown microbenchmark \"{}\"", name))
+    })?;
+
+    if workgroup_size.is_empty() || workgroup_size.len() > 3 {
+        return Err(JsError::new("workgroup size must have 1 to 3 dimensions"));
+    }
+    let size = |dim: usize| workgroup_size.get(dim).copied().unwrap_or(1);
+
+    Ok((microbenchmark, (size(0), size(1), size(2))))
 }
 
 /// Shadow println! when compiling to WASM
@@ -0,0 +1,362 @@
+//! Workgroup memory microbenchmark for the bandwidth of `var<workgroup>`
+//! accesses, with strides between the invocations that show the penalty of
+//! bank conflicts
+
+use std::collections::HashMap;
+
+use uwgpu::{
+    wgpu::{
+        BufferDescriptor, BufferUsages, Limits, ShaderModuleDescriptor,
+        ShaderSource,
+    },
+    wgpu_async::AsyncBuffer,
+    Benchmark, BenchmarkComputePipeline, BenchmarkResults, CreatePipelineError,
+    GPUContext, PipelineParameters, TimeUnit,
+};
+
+use crate::{
+    microbenchmark::{
+        default_workgroups_1d, fits_limits, Metric, Microbenchmark,
+        MicrobenchmarkParameters, RunFuture,
+    },
+    BenchmarkError, ElementType,
+};
+
+/// Neighbouring invocations on neighbouring elements
+const DEFAULT_PARAMETERS: MicrobenchmarkParameters = MicrobenchmarkParameters {
+    size: 1,
+    warmup_count: 10,
+    count: 100,
+    element_type: ElementType::F32,
+};
+
+/// Invocations dispatched, workgroup sizes that don't divide it round it up
+/// to whole workgroups
+const INVOCATIONS: u32 = 262_144;
+
+/// Iterations of reads and writes of each invocation
+const ITERATIONS: u32 = 16;
+
+/// Reads of each invocation in every iteration, between the barriers
+const READS: u32 = 16;
+
+/// Strides of the default sweep: powers of two up to 32 elements, which
+/// usually has every invocation of a warp on the same bank, and 33 which is
+/// back to having each on its own bank.
+const STRIDES: [usize; 7] = [1, 2, 4, 8, 16, 32, 33];
+
+/// Microbenchmark for measuring the bandwidth of workgroup memory, the
+/// [MicrobenchmarkParameters::size] is the stride in u32 elements between the
+/// accesses of neighbouring invocations.
+///
+/// Each workgroup uses `workgroup_size * stride` u32 elements of workgroup
+/// storage, see [workgroup_storage_size]. The microbenchmark isn't supported
+/// when that's more than the device's
+/// [Limits::max_compute_workgroup_storage_size].
+///
+/// Workgroup memory is split into banks and the accesses that fall on the
+/// same bank in the same cycle are serialized, so strides that are a multiple
+/// of the amount of banks are the slowest while odd strides are as fast as
+/// contiguous accesses.
+pub async fn workgroup_memory_benchmark(
+    gpu: &GPUContext,
+    workgroup_size: u32,
+    parameters: MicrobenchmarkParameters,
+) -> Result<WorkgroupMemoryResults, BenchmarkError> {
+    let stride = parameters.size as u32;
+    let buffers = Buffers::new(workgroup_size, gpu);
+    let pipeline =
+        workgroup_memory_pipeline(gpu, &buffers, stride, workgroup_size)
+            .await?;
+
+    let results = Benchmark {
+        warmup_count: parameters.warmup_count,
+        count: parameters.count,
+        finalize_encoder_callback: None,
+        workgroups_dispatch: workgroups_dispatch(workgroup_size),
+        dispatch_callback: None,
+        ..Default::default()
+    }
+    .run(pipeline)
+    .await?;
+
+    Ok(WorkgroupMemoryResults {
+        results,
+        parameters,
+        workgroup_size,
+    })
+}
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Results from the workgroup memory microbenchmark. See
+/// [workgroup_memory_benchmark].
+///
+/// Wraps a [BenchmarkResults] with some convenience methods.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_eval]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub struct WorkgroupMemoryResults {
+    /// Timings of the benchmark
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub results: BenchmarkResults,
+    /// Parameters the benchmark ran with
+    pub parameters: MicrobenchmarkParameters,
+    /// Workgroup size the benchmark ran with
+    pub workgroup_size: u32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl WorkgroupMemoryResults {
+    /// Get the bandwidth of workgroup memory in bytes per second, counting
+    /// both the reads and the writes
+    pub fn bandwidth(&self) -> f64 { bandwidth(&self.results) }
+
+    /// Get the bytes of workgroup storage used by each workgroup
+    pub fn workgroup_storage_bytes(&self) -> u64 {
+        workgroup_storage_size(self.workgroup_size, self.parameters.size as u32)
+    }
+}
+
+/// Workgroup memory microbenchmark, see [workgroup_memory_benchmark].
+pub struct WorkgroupMemory;
+
+impl Microbenchmark for WorkgroupMemory {
+    fn name(&self) -> &'static str { "workgroup_memory" }
+
+    fn title(&self) -> &'static str { "Workgroup Memory Bandwidth" }
+
+    fn description(&self) -> &'static str {
+        "This microbenchmark tests the bandwidth of workgroup (shared) memory in your GPU, with the accesses of neighbouring invocations a configurable stride apart. Strides that land many invocations on the same memory bank show the penalty of bank conflicts, while contiguous or odd strides show the peak bandwidth."
+    }
+
+    fn workgroup_dimensions(&self) -> usize { 1 }
+
+    fn default_workgroups(&self) -> Vec<(u32, u32, u32)> {
+        default_workgroups_1d()
+    }
+
+    fn size_description(&self) -> &'static str {
+        "Stride in u32 elements between the accesses of neighbouring \
+         invocations"
+    }
+
+    fn default_parameters(&self) -> MicrobenchmarkParameters {
+        DEFAULT_PARAMETERS
+    }
+
+    fn default_sizes(&self) -> Vec<usize> { STRIDES.to_vec() }
+
+    fn supports(
+        &self,
+        limits: &Limits,
+        workgroup_size: (u32, u32, u32),
+        parameters: &MicrobenchmarkParameters,
+    ) -> bool {
+        let stride = parameters.size as u32;
+
+        stride > 0
+            && workgroup_storage_size(workgroup_size.0, stride)
+                <= limits.max_compute_workgroup_storage_size as u64
+            && fits_limits(
+                limits,
+                workgroup_size,
+                || workgroups_dispatch(workgroup_size.0),
+                result_size(workgroup_size.0),
+            )
+    }
+
+    fn run<'a>(
+        &'a self,
+        gpu: &'a GPUContext,
+        workgroup_size: (u32, u32, u32),
+        parameters: MicrobenchmarkParameters,
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            Ok(
+                workgroup_memory_benchmark(gpu, workgroup_size.0, parameters)
+                    .await?
+                    .results,
+            )
+        })
+    }
+
+    fn metrics(
+        &self,
+        _parameters: &MicrobenchmarkParameters,
+        results: &BenchmarkResults,
+    ) -> Vec<Metric> {
+        vec![Metric::bandwidth(bandwidth(results))]
+    }
+}
+
+/// Bytes of workgroup storage used by each workgroup with a stride of
+/// `stride` elements.
+pub fn workgroup_storage_size(workgroup_size: u32, stride: u32) -> u64 {
+    workgroup_size as u64 * stride as u64 * size_of::<u32>() as u64
+}
+
+/// Bandwidth in bytes per second of the reads and writes of [INVOCATIONS]
+/// invocations.
+fn bandwidth(results: &BenchmarkResults) -> f64 {
+    let bytes = INVOCATIONS as usize
+        * ITERATIONS as usize
+        * (READS as usize + 1)
+        * size_of::<u32>();
+
+    (bytes * results.count) as f64 / results.total_time(TimeUnit::Second)
+}
+
+fn workgroups_dispatch(workgroup_size: u32) -> Vec<(u32, u32, u32)> {
+    vec![(INVOCATIONS.div_ceil(workgroup_size), 1, 1)]
+}
+
+/// Size in bytes of the result buffer, one u32 per invocation dispatched.
+fn result_size(workgroup_size: u32) -> u64 {
+    INVOCATIONS.div_ceil(workgroup_size) as u64
+        * workgroup_size as u64
+        * size_of::<u32>() as u64
+}
+
+/// GPU buffers needed for microbenchmark
+struct Buffers {
+    result_buffer: AsyncBuffer,
+}
+
+impl Buffers {
+    fn new(workgroup_size: u32, gpu: &GPUContext) -> Self {
+        let result_buffer = gpu.create_buffer(&BufferDescriptor {
+            label: Some("Result Buffer"),
+            size: result_size(workgroup_size),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        Self { result_buffer }
+    }
+}
+
+/// Pipeline needed for microbenchmark
+async fn workgroup_memory_pipeline<'a>(
+    gpu: &'a GPUContext,
+    buffers: &'a Buffers,
+    stride: u32,
+    workgroup_size: u32,
+) -> Result<BenchmarkComputePipeline<'a>, CreatePipelineError> {
+    BenchmarkComputePipeline::new(PipelineParameters {
+        shader: ShaderModuleDescriptor {
+            label: Some("workgroup memory shader"),
+            source: ShaderSource::Wgsl(
+                include_str!("workgroup_memory.wgsl")
+                    .replace("$iterations$", &ITERATIONS.to_string())
+                    .replace("$reads$", &READS.to_string())
+                    .replace("$stride$", &stride.to_string())
+                    .replace("$tile$", &(workgroup_size * stride).to_string())
+                    .into(),
+            ),
+        },
+        entry_point: "main",
+        bind_group_0: HashMap::from([(
+            0,
+            buffers.result_buffer.as_entire_binding(),
+        )]),
+        gpu,
+        workgroup_size: Some((workgroup_size, 1, 1)),
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use uwgpu::{
+        wgpu::{BufferDescriptor, BufferUsages, MapMode},
+        Benchmark, GPUContext,
+    };
+
+    use super::*;
+
+    /// Runs the reads and writes of `workgroup_memory.wgsl` on the CPU for
+    /// the workgroup starting at invocation `first`.
+    fn expected_workgroup(first: u32, workgroup_size: u32) -> Vec<u32> {
+        let mut values: Vec<u32> = (first..first + workgroup_size).collect();
+
+        for i in 0..ITERATIONS {
+            values = (0..workgroup_size)
+                .map(|local| {
+                    (1..=READS).fold(values[local as usize], |value, j| {
+                        let neighbour = (local + i + j) % workgroup_size;
+                        value.wrapping_add(values[neighbour as usize])
+                    })
+                })
+                .collect();
+        }
+        values
+    }
+
+    /// Verifies that every invocation sums the elements of its neighbours,
+    /// for strides with and without bank conflicts.
+    #[tokio::test]
+    async fn workgroup_memory_works() {
+        let gpu = GPUContext::new(None).await.unwrap();
+        let workgroup_size = 64;
+
+        let expected_result: Vec<u32> = (0..INVOCATIONS / workgroup_size)
+            .flat_map(|workgroup| {
+                expected_workgroup(workgroup * workgroup_size, workgroup_size)
+            })
+            .collect();
+
+        for stride in [1, 32, 33] {
+            let buffers = Buffers::new(workgroup_size, &gpu);
+            let pipeline = workgroup_memory_pipeline(
+                &gpu,
+                &buffers,
+                stride,
+                workgroup_size,
+            )
+            .await
+            .unwrap();
+
+            let size = result_size(workgroup_size);
+            let staging_buffer = gpu.create_buffer(&BufferDescriptor {
+                label: Some("Staging Buffer"),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            Benchmark {
+                warmup_count: 0,
+                count: 1,
+                finalize_encoder_callback: Some(&|encoder| {
+                    encoder.copy_buffer_to_buffer(
+                        &buffers.result_buffer,
+                        0,
+                        &staging_buffer,
+                        0,
+                        size,
+                    )
+                }),
+                workgroups_dispatch: workgroups_dispatch(workgroup_size),
+                dispatch_callback: None,
+                ..Default::default()
+            }
+            .run(pipeline)
+            .await
+            .unwrap();
+
+            let staging_slice = staging_buffer.slice(..);
+            staging_slice.map_async(MapMode::Read).await.unwrap();
+
+            let result_data: Vec<u32> = {
+                let result_data_raw = staging_slice.get_mapped_range();
+                bytemuck::cast_slice(&result_data_raw).to_vec()
+            };
+
+            staging_buffer.unmap();
+
+            assert_eq!(result_data, expected_result, "stride {stride}");
+        }
+    }
+}
@@ -0,0 +1,33 @@
+@group(0) @binding(0) var<storage, read_write> result: array<u32>;
+
+const ITERATIONS: u32 = $iterations$u;
+const READS: u32 = $reads$u;
+const STRIDE: u32 = $stride$u;
+
+// Workgroup size * stride elements, local invocation i owns element i * STRIDE
+// so the accesses of neighbouring invocations are STRIDE elements apart
+var<workgroup> tile: array<u32, $tile$>;
+
+// Every iteration each invocation reads the elements of the READS invocations
+// after it, then writes its own. The elements are written by other
+// invocations so none of the reads can be kept in registers
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32) {
+    var value = global_id.x;
+    tile[local_index * STRIDE] = value;
+    workgroupBarrier();
+
+    for (var i = 0u; i < ITERATIONS; i++) {
+        for (var j = 1u; j <= READS; j++) {
+            let neighbour = (local_index + i + j) % $workgroup_x$u;
+            value += tile[neighbour * STRIDE];
+        }
+        workgroupBarrier();
+        tile[local_index * STRIDE] = value;
+        workgroupBarrier();
+    }
+
+    // The sums are the result so none of the accesses is dead code
+    result[global_id.x] = value;
+}
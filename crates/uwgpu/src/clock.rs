@@ -0,0 +1,56 @@
+//! Host-side wall-clock used to complement the GPU timestamp queries, see
+//! [HostTimer]
+
+/// Measures elapsed wall-clock time on the host (CPU) side.
+///
+/// Uses [std::time::Instant] natively and `performance.now()` when compiling
+/// to WASM, where [std::time::Instant] isn't available. Works both on the main
+/// thread and in Web Workers.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct HostTimer {
+    /// Start of the measurement in nanoseconds, relative to an arbitrary
+    /// epoch.
+    start: f64,
+}
+
+impl HostTimer {
+    /// Start measuring from the current moment.
+    pub(crate) fn start() -> Self { Self { start: now() } }
+
+    /// Nanoseconds elapsed since this timer was started.
+    pub(crate) fn elapsed(&self) -> f64 { now() - self.start }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "wasm")] {
+        use wasm_bindgen::JsCast;
+        use web_sys::{Window, WorkerGlobalScope};
+
+        /// Current time in nanoseconds, according to `performance.now()` of
+        /// the global scope, which is a [Window] on the main thread and a
+        /// [WorkerGlobalScope] in Web Workers.
+        fn now() -> f64 {
+            let global = js_sys::global();
+            let performance = if let Some(window) = global.dyn_ref::<Window>() {
+                window.performance()
+            } else if let Some(worker) = global.dyn_ref::<WorkerGlobalScope>() {
+                worker.performance()
+            } else {
+                None
+            }
+            .expect("performance.now() should be available");
+
+            performance.now() * 1_000_000.0
+        }
+    } else {
+        use std::{sync::OnceLock, time::Instant};
+
+        /// Arbitrary epoch that host timestamps are measured against.
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+        /// Current time in nanoseconds since [EPOCH].
+        fn now() -> f64 {
+            EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as f64
+        }
+    }
+}
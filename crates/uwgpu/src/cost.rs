@@ -0,0 +1,764 @@
+//! Static estimation of the arithmetic operations and global memory traffic of
+//! a compute shader, see [estimate_shader_cost]
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Add,
+};
+
+use naga::{
+    proc::GlobalCtx,
+    valid::{FunctionInfo, ModuleInfo},
+    AddressSpace, Arena, BinaryOperator, Block, Expression, Function, Handle,
+    Literal, LocalVariable, MathFunction, Module, ScalarKind, Statement,
+    TypeInner,
+};
+use wgpu::ShaderModuleDescriptor;
+
+use crate::{
+    reflection::{ParsedShader, ShaderAnalysisError},
+    BenchmarkResults,
+};
+
+/// Loops whose simulated trip count goes over this are considered unresolved.
+const MAX_SIMULATED_TRIP_COUNT: u64 = 1 << 24;
+
+/// Parameters for [estimate_shader_cost].
+#[derive(Clone)]
+pub struct ShaderCostParameters<'a> {
+    /// Compute shader to analyze, only WGSL sources are supported.
+    pub shader: ShaderModuleDescriptor<'a>,
+
+    /// Entry point of the compute shader.
+    pub entry_point: &'a str,
+
+    /// If [Some], the `$workgroup$` placeholders will be replaced the same way
+    /// as in the [PipelineParameters](crate::PipelineParameters)
+    /// `workgroup_size` field.
+    pub workgroup_size: Option<(u32, u32, u32)>,
+
+    /// Known values of the shader's scalar integer uniforms, keyed by their
+    /// `(group, binding)`.
+    ///
+    /// Used to compute the trip count of loops bounded by a uniform, like
+    /// `for (var k = 0u; k < matrixSize; k++)`.
+    pub uniforms: HashMap<(u32, u32), i64>,
+}
+
+/// Estimated cost of executing a compute shader.
+///
+/// As returned by [estimate_shader_cost] it's the cost of a single invocation,
+/// [ShaderCost::for_invocations] can be used to get the cost of a whole
+/// benchmark iteration.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShaderCost {
+    /// Floating point operations.
+    ///
+    /// Each float add, sub, mul, div, mod and math built-in counts as one
+    /// operation per component, except for `fma` which counts as 2 and
+    /// `dot` which counts as `2n - 1`.
+    pub flops: f64,
+
+    /// Bytes loaded from storage buffers.
+    pub bytes_read: f64,
+
+    /// Bytes stored to storage buffers. Atomic operations count both as a
+    /// read and a write.
+    pub bytes_written: f64,
+
+    /// Amount of loops whose trip count couldn't be determined, each one got
+    /// counted as a single iteration.
+    pub unresolved_loops: usize,
+}
+
+impl ShaderCost {
+    /// Total bytes of global memory traffic.
+    pub fn bytes(&self) -> f64 { self.bytes_read + self.bytes_written }
+
+    /// Floating point operations per byte of global memory traffic.
+    pub fn arithmetic_intensity(&self) -> f64 { self.flops / self.bytes() }
+
+    /// Cost of running the given amount of invocations.
+    pub fn for_invocations(&self, invocations: u64) -> Self {
+        self.scaled(invocations as f64)
+    }
+
+    /// FLOPS (floating point operations per second) achieved by the
+    /// benchmark, assuming `self` is the cost of one of its iterations.
+    pub fn achieved_flops(&self, results: &BenchmarkResults) -> f64 {
+        (self.flops * results.count as f64)
+            / results.total_time(crate::TimeUnit::Second)
+    }
+
+    /// Bandwidth in bytes per second achieved by the benchmark, assuming
+    /// `self` is the cost of one of its iterations.
+    pub fn achieved_bandwidth(&self, results: &BenchmarkResults) -> f64 {
+        (self.bytes() * results.count as f64)
+            / results.total_time(crate::TimeUnit::Second)
+    }
+
+    fn scaled(&self, factor: f64) -> Self {
+        Self {
+            flops: self.flops * factor,
+            bytes_read: self.bytes_read * factor,
+            bytes_written: self.bytes_written * factor,
+            unresolved_loops: self.unresolved_loops,
+        }
+    }
+
+    /// Component-wise max, used for branches.
+    fn max(self, other: Self) -> Self {
+        Self {
+            flops: self.flops.max(other.flops),
+            bytes_read: self.bytes_read.max(other.bytes_read),
+            bytes_written: self.bytes_written.max(other.bytes_written),
+            unresolved_loops: self.unresolved_loops + other.unresolved_loops,
+        }
+    }
+}
+
+impl Add for ShaderCost {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            flops: self.flops + other.flops,
+            bytes_read: self.bytes_read + other.bytes_read,
+            bytes_written: self.bytes_written + other.bytes_written,
+            unresolved_loops: self.unresolved_loops + other.unresolved_loops,
+        }
+    }
+}
+
+/// Estimates the cost of a single invocation of a compute shader by walking
+/// its [naga] IR.
+///
+/// The estimate is an upper bound on the path taken: both sides of a branch
+/// get analyzed and the most expensive one is kept, and early returns are
+/// ignored. Loop trip counts are simulated when the loop variable's start
+/// value, bound and step are known from literals, constants or the given
+/// `uniforms`.
+pub fn estimate_shader_cost(
+    params: &ShaderCostParameters,
+) -> Result<ShaderCost, ShaderAnalysisError> {
+    let ParsedShader {
+        module,
+        info,
+        entry_point,
+    } = ParsedShader::new(
+        &params.shader,
+        params.entry_point,
+        params.workgroup_size,
+    )?;
+
+    let analyzer = Analyzer {
+        module: &module,
+        info: &info,
+        uniforms: &params.uniforms,
+    };
+
+    Ok(analyzer.function(
+        &module.entry_points[entry_point].function,
+        info.get_entry_point(entry_point),
+    ))
+}
+
+/// Walks the functions of a validated module.
+struct Analyzer<'a> {
+    module: &'a Module,
+    info: &'a ModuleInfo,
+    uniforms: &'a HashMap<(u32, u32), i64>,
+}
+
+/// State of the walk through a single function.
+struct FunctionWalk<'a> {
+    function: &'a Function,
+    function_info: &'a FunctionInfo,
+    /// Local variables with a currently known integer value
+    locals: HashMap<Handle<LocalVariable>, i64>,
+}
+
+impl Analyzer<'_> {
+    fn ctx(&self) -> GlobalCtx<'_> { self.module.to_ctx() }
+
+    fn function(
+        &self,
+        function: &Function,
+        function_info: &FunctionInfo,
+    ) -> ShaderCost {
+        let mut walk = FunctionWalk {
+            function,
+            function_info,
+            locals: HashMap::new(),
+        };
+
+        for (handle, local) in function.local_variables.iter() {
+            if let Some(value) = local.init.and_then(|init| {
+                self.eval(&function.expressions, init, &walk.locals)
+            }) {
+                walk.locals.insert(handle, value);
+            }
+        }
+
+        self.block(&mut walk, &function.body)
+    }
+
+    fn block(&self, walk: &mut FunctionWalk, block: &Block) -> ShaderCost {
+        block
+            .iter()
+            .map(|statement| self.statement(walk, statement))
+            .fold(ShaderCost::default(), Add::add)
+    }
+
+    fn statement(
+        &self,
+        walk: &mut FunctionWalk,
+        statement: &Statement,
+    ) -> ShaderCost {
+        match statement {
+            Statement::Emit(range) => range
+                .clone()
+                .map(|expression| self.expression(walk, expression))
+                .fold(ShaderCost::default(), Add::add),
+            Statement::Block(block) => self.block(walk, block),
+            Statement::If { accept, reject, .. } => {
+                self.branches(walk, [accept, reject])
+            }
+            Statement::Switch { cases, .. } => {
+                self.branches(walk, cases.iter().map(|case| &case.body))
+            }
+            Statement::Loop {
+                body, continuing, ..
+            } => self.loop_(walk, body, continuing),
+            Statement::Store { pointer, value } => {
+                if let Some(local) = self.local_root(walk.function, *pointer) {
+                    match self.eval(
+                        &walk.function.expressions,
+                        *value,
+                        &walk.locals,
+                    ) {
+                        Some(value) => walk.locals.insert(local, value),
+                        None => walk.locals.remove(&local),
+                    };
+                }
+
+                ShaderCost {
+                    bytes_written: self.storage_bytes(walk, *pointer, *value),
+                    ..Default::default()
+                }
+            }
+            Statement::Atomic { pointer, value, .. } => {
+                let bytes = self.storage_bytes(walk, *pointer, *value);
+                ShaderCost {
+                    bytes_read: bytes,
+                    bytes_written: bytes,
+                    ..Default::default()
+                }
+            }
+            Statement::Call { function, .. } => self.function(
+                &self.module.functions[*function],
+                &self.info[*function],
+            ),
+            _ => ShaderCost::default(),
+        }
+    }
+
+    /// Cost of the most expensive of the given branches.
+    fn branches<'b>(
+        &self,
+        walk: &mut FunctionWalk,
+        branches: impl IntoIterator<Item = &'b Block>,
+    ) -> ShaderCost {
+        let before = walk.locals.clone();
+        let mut after: Option<HashMap<_, _>> = None;
+        let mut cost = ShaderCost::default();
+
+        for branch in branches {
+            walk.locals = before.clone();
+            cost = cost.max(self.block(walk, branch));
+
+            // Only keep the values that all the branches agree on
+            after = Some(match after {
+                None => walk.locals.clone(),
+                Some(mut after) => {
+                    after.retain(|local, value| {
+                        walk.locals.get(local) == Some(value)
+                    });
+                    after
+                }
+            });
+        }
+
+        walk.locals = after.unwrap_or(before);
+        cost
+    }
+
+    fn loop_(
+        &self,
+        walk: &mut FunctionWalk,
+        body: &Block,
+        continuing: &Block,
+    ) -> ShaderCost {
+        let trip_count = self.trip_count(walk, body, continuing);
+
+        // Values stored within the loop change between iterations
+        let mut stored = HashSet::new();
+        self.stored_locals(walk.function, body, &mut stored);
+        self.stored_locals(walk.function, continuing, &mut stored);
+        walk.locals.retain(|local, _| !stored.contains(local));
+
+        let iteration = self.block(walk, body) + self.block(walk, continuing);
+        walk.locals.retain(|local, _| !stored.contains(local));
+
+        match trip_count {
+            Some(trip_count) => iteration.scaled(trip_count as f64),
+            None => ShaderCost {
+                unresolved_loops: iteration.unresolved_loops + 1,
+                ..iteration
+            },
+        }
+    }
+
+    /// Simulates the loop variable to find the trip count of loops shaped like
+    /// `for (var i = start; i < end; i = i + step)`, which naga represents as
+    /// a loop starting with `if i < end {} else { break; }`.
+    fn trip_count(
+        &self,
+        walk: &FunctionWalk,
+        body: &Block,
+        continuing: &Block,
+    ) -> Option<u64> {
+        let expressions = &walk.function.expressions;
+
+        let (condition, break_when) = body
+            .iter()
+            .find(|statement| !matches!(statement, Statement::Emit(_)))
+            .and_then(|statement| match statement {
+                Statement::If {
+                    condition,
+                    accept,
+                    reject,
+                } => match (accept.first(), reject.first()) {
+                    (None, Some(Statement::Break)) => Some((*condition, false)),
+                    (Some(Statement::Break), None) => Some((*condition, true)),
+                    _ => None,
+                },
+                _ => None,
+            })?;
+
+        let Expression::Binary { op, left, right } = expressions[condition]
+        else {
+            return None;
+        };
+
+        // The loop variable can be on either side of the comparison
+        let (local, bound, op) = match (
+            self.loaded_local(walk.function, left),
+            self.loaded_local(walk.function, right),
+        ) {
+            (Some(local), None) => (local, right, op),
+            (None, Some(local)) => (local, left, flip_comparison(op)?),
+            _ => return None,
+        };
+        let bound = self.eval(expressions, bound, &walk.locals)?;
+        let start = *walk.locals.get(&local)?;
+
+        let mut updates = vec![];
+        self.local_updates(walk.function, local, body, &mut updates);
+        self.local_updates(walk.function, local, continuing, &mut updates);
+        let [update] = updates[..] else {
+            return None;
+        };
+        let Expression::Binary {
+            op: update_op,
+            left,
+            right,
+        } = expressions[update]
+        else {
+            return None;
+        };
+        let step = match self.loaded_local(walk.function, left) {
+            Some(loaded) if loaded == local => right,
+            _ => return None,
+        };
+        let step = self.eval(expressions, step, &walk.locals)?;
+
+        let mut value = start;
+        let mut trip_count = 0;
+        while compare(op, value, bound)? != break_when {
+            let next = apply(update_op, value, step)?;
+            if next == value || trip_count >= MAX_SIMULATED_TRIP_COUNT {
+                return None;
+            }
+
+            value = next;
+            trip_count += 1;
+        }
+
+        Some(trip_count)
+    }
+
+    /// Cost of evaluating a single expression, not including its operands.
+    fn expression(
+        &self,
+        walk: &FunctionWalk,
+        expression: Handle<Expression>,
+    ) -> ShaderCost {
+        let ty = walk.function_info[expression]
+            .ty
+            .inner_with(&self.module.types);
+
+        match walk.function.expressions[expression] {
+            Expression::Load { pointer } if self.is_storage(walk, pointer) => {
+                ShaderCost {
+                    bytes_read: ty.size(self.ctx()) as f64,
+                    ..Default::default()
+                }
+            }
+            Expression::Binary {
+                op:
+                    BinaryOperator::Add
+                    | BinaryOperator::Subtract
+                    | BinaryOperator::Multiply
+                    | BinaryOperator::Divide
+                    | BinaryOperator::Modulo,
+                ..
+            } => ShaderCost {
+                flops: float_components(ty),
+                ..Default::default()
+            },
+            Expression::Math { fun, arg, .. } => {
+                let flops = match fun {
+                    MathFunction::Fma => 2.0 * float_components(ty),
+                    MathFunction::Dot => {
+                        let arg_ty = walk.function_info[arg]
+                            .ty
+                            .inner_with(&self.module.types);
+                        (2.0 * float_components(arg_ty) - 1.0).max(0.0)
+                    }
+                    _ => float_components(ty),
+                };
+                ShaderCost {
+                    flops,
+                    ..Default::default()
+                }
+            }
+            _ => ShaderCost::default(),
+        }
+    }
+
+    /// Bytes moved by writing `value` through `pointer`, if it points to a
+    /// storage buffer.
+    fn storage_bytes(
+        &self,
+        walk: &FunctionWalk,
+        pointer: Handle<Expression>,
+        value: Handle<Expression>,
+    ) -> f64 {
+        if self.is_storage(walk, pointer) {
+            walk.function_info[value]
+                .ty
+                .inner_with(&self.module.types)
+                .size(self.ctx()) as f64
+        } else {
+            0.0
+        }
+    }
+
+    fn is_storage(
+        &self,
+        walk: &FunctionWalk,
+        pointer: Handle<Expression>,
+    ) -> bool {
+        matches!(
+            root(&walk.function.expressions, pointer),
+            Expression::GlobalVariable(global)
+                if matches!(
+                    self.module.global_variables[*global].space,
+                    AddressSpace::Storage { .. }
+                )
+        )
+    }
+
+    fn local_root(
+        &self,
+        function: &Function,
+        pointer: Handle<Expression>,
+    ) -> Option<Handle<LocalVariable>> {
+        match root(&function.expressions, pointer) {
+            Expression::LocalVariable(local) => Some(*local),
+            _ => None,
+        }
+    }
+
+    /// The local variable loaded by the expression, if it's a plain load of
+    /// one.
+    fn loaded_local(
+        &self,
+        function: &Function,
+        expression: Handle<Expression>,
+    ) -> Option<Handle<LocalVariable>> {
+        match function.expressions[expression] {
+            Expression::Load { pointer } => {
+                match function.expressions[pointer] {
+                    Expression::LocalVariable(local) => Some(local),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Collects every local variable stored to within the block.
+    fn stored_locals(
+        &self,
+        function: &Function,
+        block: &Block,
+        stored: &mut HashSet<Handle<LocalVariable>>,
+    ) {
+        for_each_statement(block, &mut |statement| {
+            if let Statement::Store { pointer, .. } = statement {
+                if let Some(local) = self.local_root(function, *pointer) {
+                    stored.insert(local);
+                }
+            }
+        });
+    }
+
+    /// Collects the values stored to the given local variable within the
+    /// block.
+    fn local_updates(
+        &self,
+        function: &Function,
+        local: Handle<LocalVariable>,
+        block: &Block,
+        updates: &mut Vec<Handle<Expression>>,
+    ) {
+        for_each_statement(block, &mut |statement| {
+            if let Statement::Store { pointer, value } = statement {
+                if self.local_root(function, *pointer) == Some(local) {
+                    updates.push(*value);
+                }
+            }
+        });
+    }
+
+    /// Evaluates an integer expression made of literals, constants, known
+    /// uniforms and known local variables.
+    fn eval(
+        &self,
+        expressions: &Arena<Expression>,
+        expression: Handle<Expression>,
+        locals: &HashMap<Handle<LocalVariable>, i64>,
+    ) -> Option<i64> {
+        match expressions[expression] {
+            Expression::Literal(literal) => match literal {
+                Literal::U32(value) => Some(value as i64),
+                Literal::I32(value) => Some(value as i64),
+                Literal::U64(value) => value.try_into().ok(),
+                Literal::I64(value) | Literal::AbstractInt(value) => {
+                    Some(value)
+                }
+                _ => None,
+            },
+            Expression::ZeroValue(_) => Some(0),
+            Expression::Constant(constant) => self.eval(
+                &self.module.global_expressions,
+                self.module.constants[constant].init,
+                &HashMap::new(),
+            ),
+            Expression::As { expr, .. } => self.eval(expressions, expr, locals),
+            Expression::Binary { op, left, right } => apply(
+                op,
+                self.eval(expressions, left, locals)?,
+                self.eval(expressions, right, locals)?,
+            ),
+            Expression::Load { pointer } => match expressions[pointer] {
+                Expression::LocalVariable(local) => locals.get(&local).copied(),
+                Expression::GlobalVariable(global) => {
+                    let global = &self.module.global_variables[global];
+                    let binding = global.binding.as_ref()?;
+                    (global.space == AddressSpace::Uniform)
+                        .then(|| {
+                            self.uniforms.get(&(binding.group, binding.binding))
+                        })
+                        .flatten()
+                        .copied()
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Follows the accesses of a pointer expression back to the variable it
+/// points into.
+fn root(
+    expressions: &Arena<Expression>,
+    mut pointer: Handle<Expression>,
+) -> &Expression {
+    loop {
+        match &expressions[pointer] {
+            Expression::Access { base, .. }
+            | Expression::AccessIndex { base, .. } => pointer = *base,
+            expression => return expression,
+        }
+    }
+}
+
+/// Calls `f` on every statement of the block, including nested ones.
+fn for_each_statement(block: &Block, f: &mut impl FnMut(&Statement)) {
+    for statement in block.iter() {
+        f(statement);
+        match statement {
+            Statement::Block(block) => for_each_statement(block, f),
+            Statement::If { accept, reject, .. } => {
+                for_each_statement(accept, f);
+                for_each_statement(reject, f);
+            }
+            Statement::Switch { cases, .. } => {
+                for case in cases {
+                    for_each_statement(&case.body, f);
+                }
+            }
+            Statement::Loop {
+                body, continuing, ..
+            } => {
+                for_each_statement(body, f);
+                for_each_statement(continuing, f);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Amount of float components of a type, 0 if it isn't a float type.
+fn float_components(ty: &TypeInner) -> f64 {
+    let is_float =
+        |kind| matches!(kind, ScalarKind::Float | ScalarKind::AbstractFloat);
+
+    match *ty {
+        TypeInner::Scalar(scalar) if is_float(scalar.kind) => 1.0,
+        TypeInner::Vector { size, scalar } if is_float(scalar.kind) => {
+            size as u8 as f64
+        }
+        TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } if is_float(scalar.kind) => (columns as u8 * rows as u8) as f64,
+        _ => 0.0,
+    }
+}
+
+/// The comparison that gives the same result with its operands swapped.
+fn flip_comparison(op: BinaryOperator) -> Option<BinaryOperator> {
+    Some(match op {
+        BinaryOperator::Less => BinaryOperator::Greater,
+        BinaryOperator::LessEqual => BinaryOperator::GreaterEqual,
+        BinaryOperator::Greater => BinaryOperator::Less,
+        BinaryOperator::GreaterEqual => BinaryOperator::LessEqual,
+        BinaryOperator::NotEqual => BinaryOperator::NotEqual,
+        _ => return None,
+    })
+}
+
+fn compare(op: BinaryOperator, left: i64, right: i64) -> Option<bool> {
+    Some(match op {
+        BinaryOperator::Less => left < right,
+        BinaryOperator::LessEqual => left <= right,
+        BinaryOperator::Greater => left > right,
+        BinaryOperator::GreaterEqual => left >= right,
+        BinaryOperator::NotEqual => left != right,
+        _ => return None,
+    })
+}
+
+fn apply(op: BinaryOperator, left: i64, right: i64) -> Option<i64> {
+    match op {
+        BinaryOperator::Add => left.checked_add(right),
+        BinaryOperator::Subtract => left.checked_sub(right),
+        BinaryOperator::Multiply => left.checked_mul(right),
+        BinaryOperator::Divide => left.checked_div(right),
+        BinaryOperator::Modulo => left.checked_rem(right),
+        BinaryOperator::ShiftLeft => left.checked_shl(right.try_into().ok()?),
+        BinaryOperator::ShiftRight => left.checked_shr(right.try_into().ok()?),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::ShaderSource;
+
+    use super::*;
+
+    fn estimate(source: &str, uniforms: &[((u32, u32), i64)]) -> ShaderCost {
+        estimate_shader_cost(&ShaderCostParameters {
+            shader: ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(source.into()),
+            },
+            entry_point: "main",
+            workgroup_size: Some((64, 1, 1)),
+            uniforms: uniforms.iter().copied().collect(),
+        })
+        .unwrap()
+    }
+
+    /// Verifies loop trip counts get resolved from uniforms, literals and the
+    /// workgroup size placeholders.
+    #[test]
+    fn loops_are_resolved() {
+        let source = r#"
+            @group(0) @binding(0) var<storage, read_write> data: array<f32>;
+            @group(0) @binding(1) var<uniform> n: u32;
+
+            @compute @workgroup_size($workgroup$)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                var sum = 0.0;
+                for (var i = 0u; i < n; i++) {
+                    sum += data[i] * 2.0;
+                }
+                var step = $workgroup_x$u / 2u;
+                while step > 0 {
+                    sum += 1.0;
+                    step = step / 2;
+                }
+                data[id.x] = sum;
+            }
+        "#;
+
+        let cost = estimate(source, &[((0, 1), 10)]);
+        // 10 iterations * (mul + add) + log2(64) adds
+        assert_eq!(cost.flops, (10 * 2 + 6) as f64);
+        assert_eq!(cost.bytes_read, (10 * 4) as f64);
+        assert_eq!(cost.bytes_written, 4.0);
+        assert_eq!(cost.unresolved_loops, 0);
+        assert_eq!(cost.arithmetic_intensity(), 26.0 / 44.0);
+    }
+
+    /// Verifies loops with unknown bounds get counted once and reported.
+    #[test]
+    fn unknown_loops_are_reported() {
+        let source = r#"
+            @group(0) @binding(0) var<storage, read_write> data: array<vec4<f32>>;
+
+            @compute @workgroup_size($workgroup$)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                for (var i = 0u; i < arrayLength(&data); i++) {
+                    data[i] = fma(data[i], data[i], data[i]);
+                }
+            }
+        "#;
+
+        let cost = estimate(source, &[]);
+        assert_eq!(cost.flops, 8.0);
+        assert_eq!(cost.bytes_read, 3.0 * 16.0);
+        assert_eq!(cost.bytes_written, 16.0);
+        assert_eq!(cost.unresolved_loops, 1);
+        assert_eq!(cost.for_invocations(10).bytes(), 640.0);
+    }
+}
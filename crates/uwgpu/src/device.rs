@@ -0,0 +1,302 @@
+//! Encoding and submission of the [Benchmark] commands to the GPU, see
+//! [PipelineDevice]
+
+use std::{mem::size_of, ops::Deref};
+
+use wgpu::{
+    BindGroup, CommandBuffer, CommandEncoderDescriptor, ComputePassDescriptor,
+    ComputePassTimestampWrites, MapMode, QuerySet, QueryType, Queue,
+};
+use wgpu_async::{AsyncBuffer, AsyncDevice};
+
+use crate::{
+    schedule::{BenchmarkDevice, BindGroupSlot, PlannedPass, TimestampPlan},
+    AdapterInfo, Benchmark, BenchmarkComputePipeline, MapTimestampResultError,
+};
+
+/// Runs a [Benchmark] on the GPU context of a [BenchmarkComputePipeline].
+pub(crate) struct PipelineDevice<'a> {
+    benchmark: &'a Benchmark<'a>,
+    pipeline: &'a BenchmarkComputePipeline<'a>,
+    plan: TimestampPlan,
+    timestamp_query: TimestampQuery,
+}
+
+impl<'a> PipelineDevice<'a> {
+    /// Creates the timestamp queries needed for the given plan.
+    pub(crate) fn new(
+        benchmark: &'a Benchmark<'a>,
+        pipeline: &'a BenchmarkComputePipeline<'a>,
+        plan: TimestampPlan,
+    ) -> Self {
+        Self {
+            benchmark,
+            pipeline,
+            plan,
+            timestamp_query: TimestampQuery::new(&pipeline.gpu.device, &plan),
+        }
+    }
+
+    /// Chromium will panick if we don't deref to the regular Queue
+    /// for some reason.
+    fn queue(&self) -> &Queue { self.pipeline.gpu.queue.deref() }
+
+    /// The bind group for `@group(0)` in the given slot, either the
+    /// pipeline's or the `ping_pong_bind_group`.
+    fn bind_group_0(&self, slot: BindGroupSlot) -> &BindGroup {
+        match slot {
+            BindGroupSlot::Pipeline => &self.pipeline.bind_group_0,
+            BindGroupSlot::PingPong => self
+                .benchmark
+                .ping_pong_bind_group
+                .expect("only planned with a ping-pong bind group"),
+        }
+    }
+
+    /// Warmup compute pass
+    fn warmup_pass(&self) -> CommandBuffer {
+        let mut encoder =
+            self.pipeline.gpu.device.create_command_encoder(
+                &CommandEncoderDescriptor { label: None },
+            );
+
+        let mut warmup_pass =
+            encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+
+        warmup_pass.set_pipeline(&self.pipeline.pipeline);
+        warmup_pass.set_bind_group(0, &self.pipeline.bind_group_0, &[]);
+
+        for iteration in 0..self.benchmark.warmup_count {
+            if self.benchmark.ping_pong_bind_group.is_some() {
+                warmup_pass.set_bind_group(
+                    0,
+                    self.bind_group_0(BindGroupSlot::of_iteration(iteration)),
+                    &[],
+                );
+            }
+
+            for (i, dispatch) in
+                self.benchmark.workgroups_dispatch.iter().enumerate()
+            {
+                if let Some(callback) = self.benchmark.dispatch_callback {
+                    callback(i, &mut warmup_pass);
+                }
+
+                warmup_pass
+                    .dispatch_workgroups(dispatch.0, dispatch.1, dispatch.2);
+            }
+        }
+
+        drop(warmup_pass); // has to be dropped before finishing commands
+
+        encoder.finish()
+    }
+
+    /// Command buffer with the given compute passes of timed iterations.
+    fn benchmark_command_buffer(
+        &self,
+        passes: &[PlannedPass],
+    ) -> CommandBuffer {
+        let mut encoder =
+            self.pipeline.gpu.device.create_command_encoder(
+                &CommandEncoderDescriptor { label: None },
+            );
+
+        for pass in passes {
+            // Restore the input before the pass so it doesn't get timed
+            if pass.reset_input {
+                for input_reset in &self.benchmark.input_resets {
+                    encoder.copy_buffer_to_buffer(
+                        input_reset.source,
+                        0,
+                        input_reset.destination,
+                        0,
+                        input_reset.source.size(),
+                    );
+                }
+            }
+
+            let timestamp_writes =
+                (pass.beginning_of_pass_write_index.is_some()
+                    || pass.end_of_pass_write_index.is_some())
+                .then(|| ComputePassTimestampWrites {
+                    query_set: &self.timestamp_query.query_sets[pass.query_set],
+                    beginning_of_pass_write_index: pass
+                        .beginning_of_pass_write_index,
+                    end_of_pass_write_index: pass.end_of_pass_write_index,
+                });
+
+            let mut bench_pass =
+                encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: None,
+                    timestamp_writes,
+                });
+
+            bench_pass.set_pipeline(&self.pipeline.pipeline);
+            bench_pass.set_bind_group(0, &self.pipeline.bind_group_0, &[]);
+
+            for iteration in pass.iterations.clone() {
+                if let Some(slot) = pass.bind_group(iteration) {
+                    bench_pass.set_bind_group(0, self.bind_group_0(slot), &[]);
+                }
+
+                for (i, dispatch) in
+                    self.benchmark.workgroups_dispatch.iter().enumerate()
+                {
+                    if let Some(callback) = self.benchmark.dispatch_callback {
+                        callback(i, &mut bench_pass);
+                    }
+
+                    bench_pass.dispatch_workgroups(
+                        dispatch.0, dispatch.1, dispatch.2,
+                    );
+                }
+            }
+
+            // Already gets dropped but leaving here as a reminder in case I
+            // change stuff around
+            // drop(bench_pass) // must be dropped before encoding more commands
+        }
+
+        encoder.finish()
+    }
+
+    /// Pass for resolving the timestamp query compute pass + end callback
+    /// commands
+    fn timestamp_pass(&self) -> CommandBuffer {
+        let TimestampQuery {
+            query_sets,
+            query_buf,
+            query_staging_buf,
+        } = &self.timestamp_query;
+
+        let mut encoder =
+            self.pipeline.gpu.device.create_command_encoder(
+                &CommandEncoderDescriptor { label: None },
+            );
+
+        if let Some(callback) = self.benchmark.finalize_encoder_callback {
+            callback(&mut encoder)
+        }
+
+        for resolve in self.plan.resolves() {
+            encoder.resolve_query_set(
+                &query_sets[resolve.set],
+                resolve.queries.clone(),
+                query_buf,
+                resolve.offset,
+            );
+            encoder.copy_buffer_to_buffer(
+                query_buf,
+                resolve.offset,
+                query_staging_buf,
+                resolve.offset,
+                (resolve.queries.len() * size_of::<u64>()) as u64,
+            );
+        }
+
+        encoder.finish()
+    }
+}
+
+impl BenchmarkDevice for PipelineDevice<'_> {
+    fn submit_warmup(&mut self) { self.queue().submit([self.warmup_pass()]); }
+
+    fn submit_passes(&mut self, passes: &[PlannedPass]) {
+        self.queue().submit([self.benchmark_command_buffer(passes)]);
+    }
+
+    async fn submitted_work_done(&self) {
+        self.pipeline.gpu.submitted_work_done().await
+    }
+
+    async fn read_timestamps(
+        &mut self,
+    ) -> Result<Box<[u64]>, MapTimestampResultError> {
+        self.queue().submit([self.timestamp_pass()]);
+        self.timestamp_query.get_timestamp_result().await
+    }
+
+    fn timestamp_period(&self) -> f32 {
+        self.pipeline.gpu.queue.get_timestamp_period()
+    }
+
+    fn adapter_info(&self) -> AdapterInfo {
+        self.pipeline.gpu.adapter_info.clone().into()
+    }
+}
+
+/// Utility struct for passing around the query sets and buffers needed to add
+/// timestamp queries to the [Benchmark] compute passes.
+struct TimestampQuery {
+    /// The timestamp query sets
+    query_sets: Vec<QuerySet>,
+    /// Buffer where the timestamp queries gets resolved.
+    /// Flags: COPY_SRC | QUERY_RESOLVE,
+    query_buf: wgpu::Buffer,
+    /// Mappable buffer to read the queries results.
+    /// Flags: COPY_SRC | QUERY_RESOLVE,
+    query_staging_buf: AsyncBuffer,
+}
+
+impl TimestampQuery {
+    /// Creates the query sets and buffers laid out by the given plan.
+    fn new(device: &AsyncDevice, plan: &TimestampPlan) -> Self {
+        let query_sets: Vec<QuerySet> = plan
+            .query_set_sizes()
+            .into_iter()
+            .map(|count| {
+                device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("Timestamp Query Set"),
+                    count,
+                    ty: QueryType::Timestamp,
+                })
+            })
+            .collect();
+
+        let query_buf = (**device).create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: plan.buffer_size(),
+            usage: wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::QUERY_RESOLVE,
+            mapped_at_creation: false,
+        });
+        let query_staging_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: plan.buffer_size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query_sets,
+            query_buf,
+            query_staging_buf,
+        }
+    }
+
+    async fn get_timestamp_result(
+        &self,
+    ) -> Result<Box<[u64]>, MapTimestampResultError> {
+        let timestamp_query_slice = self.query_staging_buf.slice(..);
+
+        timestamp_query_slice
+            .map_async(MapMode::Read)
+            .await
+            .map_err(|_| MapTimestampResultError)?;
+
+        let ts_data: Box<[u64]> = {
+            let ts_data_raw: &[u8] = &timestamp_query_slice.get_mapped_range();
+            bytemuck::cast_slice(ts_data_raw)
+                .to_vec()
+                .into_boxed_slice()
+        };
+
+        self.query_staging_buf.unmap();
+
+        Ok(ts_data)
+    }
+}
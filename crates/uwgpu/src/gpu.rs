@@ -5,13 +5,25 @@ use std::sync::Arc;
 use thiserror::Error;
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt, TextureDataOrder},
-    AdapterInfo, Backends, BufferDescriptor, DeviceDescriptor,
+    AdapterInfo, Backend, Backends, BufferDescriptor, DeviceDescriptor,
     DeviceLostReason, Features, Instance, InstanceDescriptor, Limits,
     MemoryHints, PowerPreference, RequestAdapterOptions, RequestDeviceError,
     Texture, TextureDescriptor,
 };
 use wgpu_async::{AsyncBuffer, AsyncDevice, AsyncQueue};
 
+/// Features requested whenever the adapter supports them, on top of the
+/// required ones.
+///
+/// [Features::SHADER_F16] is only requested in the browser, since natively
+/// the WGSL shaders get parsed by naga which doesn't support `f16` yet.
+fn optional_features(adapter_info: &AdapterInfo) -> Features {
+    match adapter_info.backend {
+        Backend::BrowserWebGpu => Features::SHADER_F16,
+        _ => Features::empty(),
+    }
+}
+
 /// Represents a handle on a GPU device
 pub struct GPUContext {
     pub(crate) adapter_info: AdapterInfo,
@@ -23,6 +35,22 @@ impl GPUContext {
     /// Instantiate a new [GPUContext]
     pub async fn new(
         required_features: Option<Features>,
+    ) -> Result<Self, GetGPUContextError> {
+        Self::request(required_features, false).await
+    }
+
+    /// Instantiate a new [GPUContext] on the fallback adapter, a software
+    /// implementation of the API like lavapipe or WARP, useful for checking
+    /// the correctness of shaders independently of the hardware.
+    pub async fn fallback(
+        required_features: Option<Features>,
+    ) -> Result<Self, GetGPUContextError> {
+        Self::request(required_features, true).await
+    }
+
+    async fn request(
+        required_features: Option<Features>,
+        force_fallback_adapter: bool,
     ) -> Result<Self, GetGPUContextError> {
         let instance = Instance::new(InstanceDescriptor {
             backends: Backends::PRIMARY,
@@ -33,7 +61,7 @@ impl GPUContext {
             .request_adapter(&RequestAdapterOptions {
                 power_preference: PowerPreference::HighPerformance,
                 compatible_surface: None,
-                force_fallback_adapter: false,
+                force_fallback_adapter,
             })
             .await
         else {
@@ -63,7 +91,8 @@ impl GPUContext {
                 &DeviceDescriptor {
                     label: None,
                     required_features: Features::TIMESTAMP_QUERY
-                        | required_features.unwrap_or(Features::empty()),
+                        | required_features.unwrap_or(Features::empty())
+                        | (features & optional_features(&adapter_info)),
                     required_limits: Limits::default(),
                     memory_hints: MemoryHints::Performance,
                 },
@@ -94,6 +123,14 @@ impl GPUContext {
         self.device.set_device_lost_callback(callback)
     }
 
+    /// The limits of the device, this is just a wrapper for
+    /// [Device::limits](wgpu::Device::limits)
+    pub fn limits(&self) -> Limits { self.device.limits() }
+
+    /// The features enabled on the device, this is just a wrapper for
+    /// [Device::features](wgpu::Device::features)
+    pub fn features(&self) -> Features { self.device.features() }
+
     /// Creates a [Buffer], this is just a wrapper for
     /// [AsyncDevice::create_buffer]
     pub fn create_buffer(&self, desc: &BufferDescriptor) -> AsyncBuffer {
@@ -125,6 +162,16 @@ impl GPUContext {
         self.device
             .create_texture_with_data(&self.queue, desc, order, data)
     }
+
+    /// Resolves once all the work submitted to the queue up to this point has
+    /// finished executing.
+    pub(crate) async fn submitted_work_done(&self) {
+        self.device
+            .do_async(|callback| {
+                self.queue.on_submitted_work_done(move || callback(()))
+            })
+            .await
+    }
 }
 
 /// An error when trying to get a GPU context with [GPUContext::new]
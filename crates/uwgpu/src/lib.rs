@@ -8,15 +8,10 @@
 pub use wgpu;
 pub use wgpu_async;
 
-use std::{mem::size_of, ops::Deref};
+use std::ops::Range;
 
 use thiserror::Error;
-use wgpu::{
-    CommandBuffer, CommandEncoder, CommandEncoderDescriptor, ComputePass,
-    ComputePassDescriptor, ComputePassTimestampWrites, MapMode, QuerySet,
-    QueryType, Queue,
-};
-use wgpu_async::{AsyncBuffer, AsyncDevice};
+use wgpu::{BindGroup, Buffer, CommandEncoder, ComputePass};
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -37,12 +32,29 @@ mod wasm_print {
 }
 
 mod adapter_info;
+mod clock;
+mod cost;
+mod device;
 mod gpu;
 mod pipeline;
+mod reflection;
+mod schedule;
+#[cfg(feature = "spec")]
+mod spec;
+
+use clock::HostTimer;
+use device::PipelineDevice;
+use schedule::{
+    reduce_timestamps, BenchmarkDevice, PlannedPass, TimestampPlan,
+};
 
 pub use adapter_info::{AdapterInfo, Backend, DeviceType};
+pub use cost::*;
 pub use gpu::*;
 pub use pipeline::*;
+pub use reflection::*;
+#[cfg(feature = "spec")]
+pub use spec::*;
 
 /// According to https://www.w3.org/TR/webgpu/#timestamp, timestamp queries can
 /// return negative time deltas in rare circumstances. To mitigate this effect,
@@ -53,15 +65,27 @@ const MAX_COUNT_BETWEEN_QUERIES: usize = 50;
 
 /// This type represents the parameters for running a benchmark.
 ///
-/// The benchmark will run 2 compute passes in the following order:
+/// The benchmark will run 2 phases in the following order:
 ///
 /// 1. If `warmup_count` is >0, it will run a compute pass executing the shader
 ///    `warmup_count` times.
 ///
-/// 2. After that it'll run the actual benchmark compute pass which will be
-///    timed. The time taken will be returned in the [BenchmarkResults]
-///    `total_time_spent` field.
-#[derive(Clone)]
+/// 2. After that it'll run the actual benchmark iterations which will be timed.
+///    The iterations are grouped in batches, each batch is timed with its own
+///    pair of timestamp queries. The time taken will be returned in the
+///    [BenchmarkResults] `total_time_spent` field.
+///
+/// How the timed iterations get split into command buffers and compute passes
+/// can be configured through the `submission_mode` and `pass_mode` fields.
+///
+/// For shaders that modify their own input, the input can be restored before
+/// each iteration or batch with `input_resets`, or the iterations can alternate
+/// between two bind groups with `ping_pong_bind_group`.
+///
+/// Besides the GPU timestamps, the end-to-end latency of each batch is measured
+/// with the host's wall-clock. To do so the host waits for each batch to
+/// finish executing before encoding the next one.
+#[derive(Clone, Default)]
 pub struct Benchmark<'a> {
     /// The number of warm-up iterations to run before starting the actual
     /// benchmarking process.
@@ -95,12 +119,124 @@ pub struct Benchmark<'a> {
     /// to a buffer that has the [MAP_READ](wgpu::BufferUsages::MAP_READ)
     /// usage flag set.
     ///
-    /// The callback will be called after benchmark compute pass. Right before
-    /// calling `encoder.finish()`.
+    /// The callback will be called after all the benchmark compute passes
+    /// have been submitted, so the commands it encodes don't count towards
+    /// the host-side timing of the benchmark.
     ///
     /// Will NOT be run after the warmup pass, only after the actual benchmark
     /// passes.
     pub finalize_encoder_callback: Option<&'a dyn Fn(&mut CommandEncoder)>,
+
+    /// How the timed iterations get split into command buffers submitted to
+    /// the queue. See [SubmissionMode].
+    pub submission_mode: SubmissionMode,
+
+    /// How the timed iterations get split into compute passes. See
+    /// [PassMode].
+    pub pass_mode: PassMode,
+
+    /// Input buffers that will be restored from a pristine copy of them
+    /// during the timed iterations, for example the keys and the values of a
+    /// sort. See [InputReset].
+    pub input_resets: Vec<InputReset<'a>>,
+
+    /// If [Some], the iterations will alternate between the pipeline's
+    /// `@group(0)` bind group (even iterations) and this bind group (odd
+    /// iterations), including the warmup iterations.
+    ///
+    /// This allows iterative kernels to read the previous iteration's output
+    /// and write to the previous iteration's input, for example by swapping
+    /// the input and output buffers.
+    ///
+    /// Can be created with
+    /// [BenchmarkComputePipeline::create_bind_group] for group 0.
+    pub ping_pong_bind_group: Option<&'a BindGroup>,
+}
+
+/// Restores an input buffer from a pristine copy of it with a
+/// `copy_buffer_to_buffer` command, which is encoded outside of the timed
+/// compute passes.
+///
+/// Useful for kernels that work in-place, since otherwise each iteration would
+/// use the previous iteration's output as its input.
+#[derive(Clone, Copy)]
+pub struct InputReset<'a> {
+    /// Buffer holding the pristine input. Must have the
+    /// [COPY_SRC](wgpu::BufferUsages::COPY_SRC) usage flag set.
+    pub source: &'a Buffer,
+
+    /// Buffer that gets restored, it must be at least as big as `source`.
+    /// Must have the [COPY_DST](wgpu::BufferUsages::COPY_DST) usage flag set.
+    pub destination: &'a Buffer,
+
+    /// How often the input gets restored.
+    pub frequency: ResetFrequency,
+}
+
+/// How often an [InputReset] restores the input buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub enum ResetFrequency {
+    /// Restore the input before each iteration.
+    ///
+    /// Because the copy can't happen within the timed compute passes, this
+    /// makes each iteration its own batch timed with its own pair of
+    /// timestamp queries.
+    PerIteration,
+
+    /// Restore the input before each batch. The iterations within a batch
+    /// will still use the previous iteration's output as their input.
+    PerBatch,
+}
+
+/// Controls how the timed iterations of a [Benchmark] get encoded into
+/// command buffers and submitted to the queue.
+///
+/// Comparing the results of the different modes allows quantifying the
+/// overhead of each `queue.submit` call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub enum SubmissionMode {
+    /// Encode all the batches into a single command buffer, submitted with a
+    /// single `queue.submit` call.
+    #[default]
+    Single,
+
+    /// Encode each batch into its own command buffer, each one submitted with
+    /// its own `queue.submit` call.
+    PerBatch,
+
+    /// Encode each iteration into its own command buffer, each one submitted
+    /// with its own `queue.submit` call.
+    ///
+    /// Because a compute pass can't span more than one command buffer, this
+    /// implies [PassMode::PerIteration].
+    PerIteration,
+}
+
+/// Controls how the timed iterations of a [Benchmark] get split into compute
+/// passes.
+///
+/// Comparing the results of the different modes allows quantifying the
+/// overhead of each compute pass.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+pub enum PassMode {
+    /// Encode each batch into a single compute pass.
+    #[default]
+    PerBatch,
+
+    /// Encode each iteration into its own compute pass.
+    ///
+    /// The timestamps of a batch get written at the beginning of its first
+    /// pass and at the end of its last pass, so the GPU time includes the
+    /// overhead between the passes.
+    PerIteration,
 }
 
 /// Results from executing a benchmark with [Benchmark::run].
@@ -122,6 +258,40 @@ pub struct BenchmarkResults {
     /// Total time spent executing the benchmark. Stored in nanoseconds.
     pub total_time_spent: f64,
 
+    /// Total end-to-end latency of the timed iterations measured with the
+    /// host's wall-clock. Stored in nanoseconds.
+    ///
+    /// Unlike `total_time_spent`, this includes the CPU-side cost of encoding
+    /// and submitting the work, as well as waiting for the GPU to finish
+    /// executing it. It's the sum of `host_batch_times`.
+    pub host_time_spent: f64,
+
+    /// End-to-end latency of each batch of iterations measured with the
+    /// host's wall-clock, from the moment the batch starts being encoded until
+    /// the GPU finishes executing it. Stored in nanoseconds.
+    ///
+    /// With [SubmissionMode::Single] all the batches get executed by a single
+    /// submission, so a single latency covering all of them is reported.
+    #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
+    pub host_batch_times: Vec<f64>,
+
+    /// Total iterations covered by the host measurements, this is always the
+    /// `count` field in the [Benchmark] that was ran, since the host clock
+    /// doesn't suffer from invalid measurements.
+    pub host_count: usize,
+
+    /// Latency of resolving and reading back the timestamp query results to
+    /// the host, measured with the host's wall-clock. Also includes any
+    /// commands encoded by the [Benchmark] `finalize_encoder_callback`. Stored
+    /// in nanoseconds.
+    pub host_readback_time: f64,
+
+    /// Amount of `queue.submit` calls used for the timed iterations.
+    pub submission_count: usize,
+
+    /// Amount of compute passes used for the timed iterations.
+    pub pass_count: usize,
+
     /// Information about the adapter used in the benchmark.
     #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
     pub adapter_info: AdapterInfo,
@@ -138,6 +308,33 @@ impl BenchmarkResults {
     pub fn time_per_iteration(&self, unit: TimeUnit) -> f64 {
         nano_to_unit(self.total_time_spent, unit) / (self.count as f64)
     }
+
+    /// Get the total wall-clock time measured on the host in the time unit
+    /// given.
+    pub fn host_total_time(&self, unit: TimeUnit) -> f64 {
+        nano_to_unit(self.host_time_spent, unit)
+    }
+
+    /// Get the wall-clock time measured on the host per `queue.submit` call
+    /// in the time unit given.
+    pub fn host_time_per_submission(&self, unit: TimeUnit) -> f64 {
+        nano_to_unit(self.host_time_spent, unit)
+            / (self.submission_count as f64)
+    }
+
+    /// Get the wall-clock time measured on the host per iteration in the time
+    /// unit given.
+    pub fn host_time_per_iteration(&self, unit: TimeUnit) -> f64 {
+        nano_to_unit(self.host_time_spent, unit) / (self.host_count as f64)
+    }
+
+    /// Get the CPU-side overhead per iteration in the time unit given.
+    ///
+    /// Calculated by subtracting the GPU time per iteration from the host
+    /// time per iteration.
+    pub fn host_overhead_per_iteration(&self, unit: TimeUnit) -> f64 {
+        self.host_time_per_iteration(unit) - self.time_per_iteration(unit)
+    }
 }
 
 impl Benchmark<'_> {
@@ -148,341 +345,159 @@ impl Benchmark<'_> {
         &self,
         pipeline: BenchmarkComputePipeline<'a>,
     ) -> Result<BenchmarkResults, MapTimestampResultError> {
-        let timestamp_query =
-            TimestampQuery::new(&pipeline.gpu.device, self.count);
-
-        let warmup_command_buf = self.warmup_pass(&pipeline);
-        let benchmark_command_buf =
-            self.benchmark_passes(&pipeline, &timestamp_query);
-        let resolve_timestamp_pass =
-            self.timestamp_pass(&pipeline, &timestamp_query);
-
-        // Chromium will panick if we don't deref to the regular Queue
-        // for some reason.
-        let queue: &Queue = pipeline.gpu.queue.deref();
-        queue.submit([
-            warmup_command_buf,
-            benchmark_command_buf,
-            resolve_timestamp_pass,
-        ]);
-
-        let ts_data = timestamp_query.get_timestamp_result().await?;
-        let ts_period = pipeline.gpu.queue.get_timestamp_period() as f64;
-
-        // println!("ts_data: {:?}", ts_data);
-
-        // Accumulator inside fold is (total_time, real_count)
-        let (total_time_spent, real_count) = ts_data
-            .chunks(2)
-            .enumerate()
-            .fold((0, 0), |(total_time, real_count), (i, times)| {
-                let start_time = times[0];
-                let end_time = times[1];
-                let (time, is_invalid) = end_time.overflowing_sub(start_time);
-
-                if is_invalid {
-                    // println!(
-                    //     "overflow! start: {}, end: {}",
-                    //     start_time, end_time
-                    // );
-                    (total_time, real_count)
-                } else {
-                    // println!(
-                    //     "GOOD!  time: {}\t total_time: {}",
-                    //     time,
-                    //     total_time + time
-                    // );
-                    // println!(" start: {}, end: {}", start_time, end_time);
-                    let is_last_result = ((i + 1) * 2) == ts_data.len();
-                    let additional_count = if is_last_result {
-                        let residue = self.count % MAX_COUNT_BETWEEN_QUERIES;
-                        if residue == 0 {
-                            MAX_COUNT_BETWEEN_QUERIES
-                        } else {
-                            residue
-                        }
-                    } else {
-                        MAX_COUNT_BETWEEN_QUERIES
-                    };
-                    (total_time + time, real_count + additional_count)
-                }
-            });
-
-        // println!(
-        //     "Total time: {}\t Real count: {}\nTotal time as f64:
-        // {}\nts_period: {}",     total_time_spent, real_count,
-        // total_time_spent as f64, ts_period );
-        let total_time_spent = (total_time_spent as f64) * ts_period;
+        let plan = TimestampPlan::new(self.batch_count());
+        let mut device = PipelineDevice::new(self, &pipeline, plan);
 
-        Ok(BenchmarkResults {
-            count: real_count,
-            total_time_spent,
-            adapter_info: pipeline.gpu.adapter_info.clone().into(),
-        })
+        self.run_on(&mut device, &plan).await
     }
 
-    /// Warmup compute pass
-    fn warmup_pass(
+    /// Runs the benchmark on the given device, with its timestamp queries laid
+    /// out according to `plan`.
+    async fn run_on(
         &self,
-        pipeline: &BenchmarkComputePipeline,
-    ) -> CommandBuffer {
-        let mut encoder = pipeline
-            .gpu
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
-
-        let mut warmup_pass =
-            encoder.begin_compute_pass(&ComputePassDescriptor {
-                label: None,
-                timestamp_writes: None,
-            });
-
-        warmup_pass.set_pipeline(&pipeline.pipeline);
-        warmup_pass.set_bind_group(0, &pipeline.bind_group_0, &[]);
-
-        for _ in 0..self.warmup_count {
-            for (i, dispatch) in self.workgroups_dispatch.iter().enumerate() {
-                if let Some(callback) = self.dispatch_callback {
-                    callback(i, &mut warmup_pass);
-                }
-
-                warmup_pass
-                    .dispatch_workgroups(dispatch.0, dispatch.1, dispatch.2);
+        device: &mut impl BenchmarkDevice,
+        plan: &TimestampPlan,
+    ) -> Result<BenchmarkResults, MapTimestampResultError> {
+        // Wait for the warmup so it doesn't count towards the host timing
+        device.submit_warmup();
+        device.submitted_work_done().await;
+
+        let submissions = self.submissions();
+        let submission_count = submissions.len();
+        let mut pass_count = 0;
+        let mut host_batch_times = vec![];
+        let mut host_timer = HostTimer::start();
+        for iterations in submissions {
+            let ends_batch = iterations.end % self.batch_size() == 0
+                || iterations.end == self.count;
+
+            let passes = self.planned_passes(iterations, plan);
+            pass_count += passes.len();
+            device.submit_passes(&passes);
+
+            // Wait for each batch to finish so its end-to-end latency can be
+            // measured
+            if ends_batch {
+                device.submitted_work_done().await;
+                host_batch_times.push(host_timer.elapsed());
+                host_timer = HostTimer::start();
             }
         }
 
-        drop(warmup_pass); // has to be dropped before finishing commands
+        let readback_timer = HostTimer::start();
+        let ts_data = device.read_timestamps().await?;
+        let host_readback_time = readback_timer.elapsed();
+        let ts_period = device.timestamp_period() as f64;
 
-        encoder.finish()
-    }
+        let totals = reduce_timestamps(&ts_data, self.count, self.batch_size());
 
-    /// Benchmark compute passes + end callback commands
-    fn benchmark_passes(
-        &self,
-        pipeline: &BenchmarkComputePipeline,
-        timestamp_query: &TimestampQuery,
-    ) -> CommandBuffer {
-        let passes_num = self.count / MAX_COUNT_BETWEEN_QUERIES;
-
-        let mut encoder = pipeline
-            .gpu
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
-
-        for pass in 0..=passes_num {
-            let (query_set, begin_index, end_index) =
-                timestamp_query.get_query_set(pass);
-
-            let amount = {
-                if pass < passes_num {
-                    MAX_COUNT_BETWEEN_QUERIES
-                } else {
-                    self.count % MAX_COUNT_BETWEEN_QUERIES
-                }
-            };
-
-            // Can happen if the count == MAX_COUNT_BETWEEN_QUERIES
-            if amount == 0 {
-                break;
-            }
-
-            let mut bench_pass =
-                encoder.begin_compute_pass(&ComputePassDescriptor {
-                    label: None,
-                    timestamp_writes: Some(ComputePassTimestampWrites {
-                        query_set: &query_set,
-                        beginning_of_pass_write_index: Some(begin_index),
-                        end_of_pass_write_index: Some(end_index),
-                    }),
-                });
-
-            bench_pass.set_pipeline(&pipeline.pipeline);
-            bench_pass.set_bind_group(0, &pipeline.bind_group_0, &[]);
-
-            // println!("Adding pass i={} with {} runs", pass, amount);
-
-            for _ in 0..amount {
-                for (i, dispatch) in self.workgroups_dispatch.iter().enumerate()
-                {
-                    if let Some(callback) = self.dispatch_callback {
-                        callback(i, &mut bench_pass);
-                    }
-
-                    bench_pass.dispatch_workgroups(
-                        dispatch.0, dispatch.1, dispatch.2,
-                    );
-                }
-            }
+        Ok(BenchmarkResults {
+            count: totals.count,
+            total_time_spent: (totals.ticks as f64) * ts_period,
+            host_time_spent: host_batch_times.iter().sum(),
+            host_batch_times,
+            host_count: self.count,
+            host_readback_time,
+            submission_count,
+            pass_count,
+            adapter_info: device.adapter_info(),
+        })
+    }
 
-            // Already gets dropped but leaving here as a reminder in case I
-            // change stuff around
-            // drop(bench_pass) // must be dropped before encoding more commands
+    /// Max amount of iterations timed by each pair of timestamp queries.
+    fn batch_size(&self) -> usize {
+        let per_iteration = self
+            .input_resets
+            .iter()
+            .any(|reset| reset.frequency == ResetFrequency::PerIteration);
+
+        if per_iteration {
+            1
+        } else {
+            MAX_COUNT_BETWEEN_QUERIES
         }
+    }
 
-        if let Some(callback) = self.finalize_encoder_callback {
-            callback(&mut encoder)
+    /// Amount of batches the timed iterations get split into.
+    fn batch_count(&self) -> usize { self.count.div_ceil(self.batch_size()) }
+
+    /// The timed iterations that get encoded into each command buffer
+    /// submitted to the queue, depending on the [SubmissionMode].
+    fn submissions(&self) -> Vec<Range<usize>> {
+        match self.submission_mode {
+            SubmissionMode::Single => std::iter::once(0..self.count).collect(),
+            SubmissionMode::PerBatch => (0..self.count)
+                .step_by(self.batch_size())
+                .map(|start| start..(start + self.batch_size()).min(self.count))
+                .collect(),
+            SubmissionMode::PerIteration => {
+                (0..self.count).map(|i| i..(i + 1)).collect()
+            }
         }
-
-        encoder.finish()
     }
 
-    /// Pass for resolving the timestamp query compute pass
-    fn timestamp_pass(
-        &self,
-        pipeline: &BenchmarkComputePipeline,
-        timestamp_query: &TimestampQuery,
-    ) -> CommandBuffer {
-        let TimestampQuery {
-            query_sets,
-            query_buf,
-            query_staging_buf,
-        } = timestamp_query;
-
-        let mut encoder = pipeline
-            .gpu
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor { label: None });
-
-        let num_runs = (self.count + MAX_COUNT_BETWEEN_QUERIES - 1)
-            / MAX_COUNT_BETWEEN_QUERIES;
-        for (i, query_set) in query_sets.iter().enumerate() {
-            let offset = (i * (wgpu::QUERY_SET_MAX_QUERIES as usize)) as u64;
-            let amount = {
-                if i < (query_sets.len() - 1) {
-                    wgpu::QUERY_SET_MAX_QUERIES as usize
-                } else {
-                    (num_runs * 2) % (wgpu::QUERY_SET_MAX_QUERIES as usize)
-                }
+    /// Splits the iterations of a command buffer into the iterations that get
+    /// encoded into each compute pass, depending on the [PassMode].
+    ///
+    /// A compute pass never spans more than one batch.
+    fn passes(&self, iterations: Range<usize>) -> Vec<Range<usize>> {
+        let pass_per_iteration = self.pass_mode == PassMode::PerIteration
+            || self.submission_mode == SubmissionMode::PerIteration;
+
+        let mut passes = vec![];
+        let mut start = iterations.start;
+        while start < iterations.end {
+            let end = if pass_per_iteration {
+                start + 1
+            } else {
+                let batch_end =
+                    (start / self.batch_size() + 1) * self.batch_size();
+                batch_end.min(iterations.end)
             };
 
-            // println!(
-            //     "Resolving query set i={} with offset {} and amount {}",
-            //     i, offset, amount
-            // );
-
-            encoder.resolve_query_set(
-                &query_set,
-                0..(amount as u32),
-                &query_buf,
-                offset,
-            );
-            encoder.copy_buffer_to_buffer(
-                &query_buf,
-                offset,
-                &query_staging_buf,
-                offset,
-                (amount * size_of::<u64>()) as u64,
-            );
+            passes.push(start..end);
+            start = end;
         }
 
-        encoder.finish()
+        passes
     }
-}
-
-/// Utility struct for passing around the query sets and buffers needed to add
-/// timestamp queries to the [Benchmark] compute passes.
-struct TimestampQuery {
-    /// The timestamp query sets
-    query_sets: Vec<QuerySet>,
-    /// Buffer where the timestamp queries gets resolved.
-    /// Flags: COPY_SRC | QUERY_RESOLVE,
-    query_buf: wgpu::Buffer,
-    /// Mappable buffer to read the queries results.
-    /// Flags: COPY_SRC | QUERY_RESOLVE,
-    query_staging_buf: AsyncBuffer,
-}
-
-impl TimestampQuery {
-    fn new(device: &AsyncDevice, count: usize) -> Self {
-        let num_runs =
-            (count + MAX_COUNT_BETWEEN_QUERIES - 1) / MAX_COUNT_BETWEEN_QUERIES;
-
-        let number_of_full_query_sets =
-            (num_runs * 2) / (wgpu::QUERY_SET_MAX_QUERIES as usize);
-
-        let remainder =
-            ((num_runs * 2) % (wgpu::QUERY_SET_MAX_QUERIES as usize)) as u32;
 
-        let total_count = number_of_full_query_sets + remainder as usize;
-
-        // println!(
-        //         "Creating {} full query sets with maybe a remainder set with
-        // {} elements", number_of_full_query_sets, remainder,     );
-
-        let mut query_sets: Vec<QuerySet> = (0..number_of_full_query_sets)
-            .map(|_| {
-                device.create_query_set(&wgpu::QuerySetDescriptor {
-                    label: Some("Timestamp Query Set"),
-                    count: wgpu::QUERY_SET_MAX_QUERIES,
-                    ty: QueryType::Timestamp,
-                })
+    /// The compute passes for the given range of timed iterations, along with
+    /// the timestamp queries they write and whether they restore the input.
+    fn planned_passes(
+        &self,
+        iterations: Range<usize>,
+        plan: &TimestampPlan,
+    ) -> Vec<PlannedPass> {
+        self.passes(iterations)
+            .into_iter()
+            .map(|pass| {
+                let batch = pass.start / self.batch_size();
+                let batch_start = batch * self.batch_size();
+                let batch_end =
+                    (batch_start + self.batch_size()).min(self.count);
+                let slot = plan.slot(batch);
+
+                // Batches are a single iteration if any input is restored per
+                // iteration, so restoring at the start of each batch covers
+                // both frequencies
+                let reset_input =
+                    !self.input_resets.is_empty() && pass.start == batch_start;
+
+                // The batch's timestamps get written at the beginning of its
+                // first pass and at the end of its last pass
+                PlannedPass {
+                    reset_input,
+                    query_set: slot.set,
+                    beginning_of_pass_write_index: (pass.start == batch_start)
+                        .then_some(slot.begin),
+                    end_of_pass_write_index: (pass.end == batch_end)
+                        .then_some(slot.end),
+                    ping_pong: self.ping_pong_bind_group.is_some(),
+                    iterations: pass,
+                }
             })
-            .collect();
-
-        if remainder > 0 {
-            query_sets.push(device.create_query_set(
-                &wgpu::QuerySetDescriptor {
-                    label: Some("Timestamp Query Set"),
-                    count: remainder,
-                    ty: QueryType::Timestamp,
-                },
-            ))
-        }
-
-        let query_buf = (**device).create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: (total_count * size_of::<u64>()) as u64,
-            usage: wgpu::BufferUsages::COPY_SRC
-                | wgpu::BufferUsages::QUERY_RESOLVE,
-            mapped_at_creation: false,
-        });
-        let query_staging_buf = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: (total_count * size_of::<u64>()) as u64,
-            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        Self {
-            query_sets,
-            query_buf,
-            query_staging_buf,
-        }
-    }
-
-    /// Returns the appropiate query set for the given compute pass, then the
-    /// 2 indexes returned are the ones that should be passed to
-    /// `beginning_of_pass_write_index` and `end_of_pass_write_index`
-    /// of the [ComputePassTimestampWrites]
-    fn get_query_set(&self, pass: usize) -> (&QuerySet, u32, u32) {
-        let query_set_i = (pass * 2) / (wgpu::QUERY_SET_MAX_QUERIES as usize);
-        let start_index =
-            ((pass * 2) % (wgpu::QUERY_SET_MAX_QUERIES as usize)) as u32;
-        let end_index = start_index + 1;
-
-        (&self.query_sets[query_set_i], start_index, end_index)
-    }
-
-    async fn get_timestamp_result<'a>(
-        &'a self,
-    ) -> Result<Box<[u64]>, MapTimestampResultError> {
-        let timestamp_query_slice = self.query_staging_buf.slice(..);
-
-        timestamp_query_slice
-            .map_async(MapMode::Read)
-            .await
-            .map_err(|_| MapTimestampResultError)?;
-
-        let ts_data: Box<[u64]> = {
-            let ts_data_raw: &[u8] = &*timestamp_query_slice.get_mapped_range();
-            bytemuck::cast_slice(&ts_data_raw)
-                .to_vec()
-                .into_boxed_slice()
-        };
-
-        self.query_staging_buf.unmap();
-
-        Ok(ts_data)
+            .collect()
     }
 }
 
@@ -521,3 +536,292 @@ fn nano_to_unit(nanoseconds: f64, unit: TimeUnit) -> f64 {
         TimeUnit::Nano => nanoseconds,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::{
+        schedule::{BindGroupSlot, QuerySlot},
+        Backend, DeviceType,
+    };
+
+    /// Device that doesn't execute anything, it writes scripted timestamps to
+    /// its query sets in the order the submitted passes request them.
+    struct FakeDevice {
+        plan: TimestampPlan,
+        /// Timestamps to write next, in order
+        script: VecDeque<u64>,
+        /// Contents of each query set, [None] if the query was never written
+        query_sets: Vec<Vec<Option<u64>>>,
+        /// Bind groups set for each timed iteration, in submission order
+        bind_groups: Vec<(usize, BindGroupSlot)>,
+    }
+
+    impl FakeDevice {
+        fn new(plan: TimestampPlan, script: Vec<u64>) -> Self {
+            Self {
+                plan,
+                script: script.into(),
+                query_sets: plan
+                    .query_set_sizes()
+                    .into_iter()
+                    .map(|size| vec![None; size as usize])
+                    .collect(),
+                bind_groups: vec![],
+            }
+        }
+
+        fn write(&mut self, set: usize, index: u32) {
+            let query = &mut self.query_sets[set][index as usize];
+            assert!(query.is_none(), "query {index} of set {set} rewritten");
+            *query = Some(self.script.pop_front().expect("script ran out"));
+        }
+    }
+
+    impl BenchmarkDevice for FakeDevice {
+        fn submit_warmup(&mut self) {}
+
+        fn submit_passes(&mut self, passes: &[PlannedPass]) {
+            for pass in passes {
+                if let Some(index) = pass.beginning_of_pass_write_index {
+                    self.write(pass.query_set, index);
+                }
+                for iteration in pass.iterations.clone() {
+                    if let Some(slot) = pass.bind_group(iteration) {
+                        self.bind_groups.push((iteration, slot));
+                    }
+                }
+                if let Some(index) = pass.end_of_pass_write_index {
+                    self.write(pass.query_set, index);
+                }
+            }
+        }
+
+        async fn submitted_work_done(&self) {}
+
+        async fn read_timestamps(
+            &mut self,
+        ) -> Result<Box<[u64]>, MapTimestampResultError> {
+            let mut buffer = vec![0; self.plan.query_count()];
+            for resolve in self.plan.resolves() {
+                let start = resolve.offset as usize / size_of::<u64>();
+                for (i, query) in resolve.queries.enumerate() {
+                    buffer[start + i] = self.query_sets[resolve.set]
+                        [query as usize]
+                        .expect("resolved a query that was never written");
+                }
+            }
+
+            Ok(buffer.into_boxed_slice())
+        }
+
+        fn timestamp_period(&self) -> f32 { 2.0 }
+
+        fn adapter_info(&self) -> AdapterInfo {
+            AdapterInfo {
+                name: "Fake".to_owned(),
+                vendor: 0,
+                device: 0,
+                device_type: DeviceType::Other,
+                driver: String::new(),
+                driver_info: String::new(),
+                backend: Backend::Empty,
+            }
+        }
+    }
+
+    /// Runs the benchmark on a [FakeDevice] where every batch takes the
+    /// given amount of ticks, except for the batches in `negative` which get
+    /// an end timestamp before their start.
+    async fn run_scripted(
+        benchmark: &Benchmark<'_>,
+        ticks: u64,
+        negative: &[usize],
+    ) -> BenchmarkResults {
+        let plan = TimestampPlan::new(benchmark.batch_count());
+        let script = (0..benchmark.batch_count())
+            .flat_map(|batch| {
+                let start = 1_000 + batch as u64 * 2 * ticks;
+                if negative.contains(&batch) {
+                    [start, start - 1]
+                } else {
+                    [start, start + ticks]
+                }
+            })
+            .collect();
+
+        let mut device = FakeDevice::new(plan, script);
+        let results = benchmark.run_on(&mut device, &plan).await.unwrap();
+        assert!(device.script.is_empty(), "not every batch was timed");
+
+        results
+    }
+
+    /// Verifies every batch gets timed and reduced correctly around the
+    /// [wgpu::QUERY_SET_MAX_QUERIES] boundaries, for every submission and pass
+    /// mode.
+    #[tokio::test]
+    async fn scripted_timestamps_around_query_set_limits() {
+        let half_set = wgpu::QUERY_SET_MAX_QUERIES as usize / 2;
+
+        for batches in [1, half_set - 1, half_set, half_set + 1, half_set * 2] {
+            for (submission_mode, pass_mode) in [
+                (SubmissionMode::Single, PassMode::PerBatch),
+                (SubmissionMode::PerBatch, PassMode::PerBatch),
+                (SubmissionMode::Single, PassMode::PerIteration),
+            ] {
+                let benchmark = Benchmark {
+                    count: batches * MAX_COUNT_BETWEEN_QUERIES - 7,
+                    submission_mode,
+                    pass_mode,
+                    ..Default::default()
+                };
+
+                let results = run_scripted(&benchmark, 10, &[]).await;
+                assert_eq!(results.count, benchmark.count);
+                assert_eq!(results.total_time_spent, (batches * 10 * 2) as f64);
+            }
+        }
+    }
+
+    /// Verifies batches with negative deltas get left out of the results,
+    /// including the last, partial, batch.
+    #[tokio::test]
+    async fn scripted_negative_deltas_are_ignored() {
+        let benchmark = Benchmark {
+            count: MAX_COUNT_BETWEEN_QUERIES * 3 + 5,
+            ..Default::default()
+        };
+
+        let results = run_scripted(&benchmark, 10, &[1]).await;
+        assert_eq!(results.count, MAX_COUNT_BETWEEN_QUERIES * 2 + 5);
+        assert_eq!(results.total_time_spent, (3 * 10 * 2) as f64);
+
+        let results = run_scripted(&benchmark, 10, &[0, 3]).await;
+        assert_eq!(results.count, MAX_COUNT_BETWEEN_QUERIES * 2);
+        assert_eq!(results.total_time_spent, (2 * 10 * 2) as f64);
+    }
+
+    /// Verifies the timed iterations alternate between the pipeline's and the
+    /// ping-pong bind group across passes and submissions, for every
+    /// submission and pass mode, and that no bind group gets set without one.
+    #[test]
+    fn ping_pong_alternates_across_passes() {
+        for (submission_mode, pass_mode) in [
+            (SubmissionMode::Single, PassMode::PerBatch),
+            (SubmissionMode::PerBatch, PassMode::PerBatch),
+            (SubmissionMode::Single, PassMode::PerIteration),
+            (SubmissionMode::PerIteration, PassMode::PerBatch),
+        ] {
+            let benchmark = Benchmark {
+                count: MAX_COUNT_BETWEEN_QUERIES * 2 + 3,
+                submission_mode,
+                pass_mode,
+                ..Default::default()
+            };
+            let plan = TimestampPlan::new(benchmark.batch_count());
+            let mut device = FakeDevice::new(plan, vec![0; plan.query_count()]);
+            let mut ping_pong_device =
+                FakeDevice::new(plan, vec![0; plan.query_count()]);
+
+            for iterations in benchmark.submissions() {
+                let mut passes = benchmark.planned_passes(iterations, &plan);
+                device.submit_passes(&passes);
+
+                // Planned like for a benchmark with a ping-pong bind group,
+                // which needs a GPU to be created
+                for pass in &mut passes {
+                    pass.ping_pong = true;
+                }
+                ping_pong_device.submit_passes(&passes);
+            }
+
+            assert!(device.bind_groups.is_empty());
+            let iterations: Vec<usize> = ping_pong_device
+                .bind_groups
+                .iter()
+                .map(|(iteration, _)| *iteration)
+                .collect();
+            assert_eq!(iterations, (0..benchmark.count).collect::<Vec<_>>());
+            assert_eq!(
+                ping_pong_device.bind_groups[0].1,
+                BindGroupSlot::Pipeline
+            );
+            assert!(ping_pong_device
+                .bind_groups
+                .windows(2)
+                .all(|pair| pair[0].1 != pair[1].1));
+        }
+    }
+
+    /// Verifies a batch's pair of queries always lands in the same query set
+    /// as the passes that write it.
+    #[test]
+    fn planned_passes_write_their_batch_slot() {
+        let half_set = wgpu::QUERY_SET_MAX_QUERIES as usize / 2;
+        let benchmark = Benchmark {
+            count: MAX_COUNT_BETWEEN_QUERIES * (half_set + 1),
+            pass_mode: PassMode::PerIteration,
+            ..Default::default()
+        };
+        let plan = TimestampPlan::new(benchmark.batch_count());
+
+        let passes = benchmark.planned_passes(0..benchmark.count, &plan);
+        let last_batch = &passes[passes.len() - MAX_COUNT_BETWEEN_QUERIES..];
+        assert_eq!(
+            plan.slot(half_set),
+            QuerySlot {
+                set: 1,
+                begin: 0,
+                end: 1
+            }
+        );
+        assert!(last_batch.iter().all(|pass| pass.query_set == 1));
+        assert_eq!(last_batch[0].beginning_of_pass_write_index, Some(0));
+        assert_eq!(last_batch.last().unwrap().end_of_pass_write_index, Some(1));
+        assert!(passes.iter().all(|pass| !pass.reset_input));
+    }
+
+    /// Verifies that the timed iterations get split into the expected command
+    /// buffers and compute passes, without passes ever spanning more than one
+    /// batch.
+    #[test]
+    fn submission_and_pass_modes_split_iterations() {
+        let count = MAX_COUNT_BETWEEN_QUERIES * 2 + 3;
+        let mut benchmark = Benchmark {
+            count,
+            ..Default::default()
+        };
+
+        let submissions = benchmark.submissions();
+        assert_eq!(submissions.len(), 1);
+        assert_eq!(submissions[0], 0..count);
+        assert_eq!(
+            benchmark.passes(submissions[0].clone()),
+            vec![
+                0..MAX_COUNT_BETWEEN_QUERIES,
+                MAX_COUNT_BETWEEN_QUERIES..(MAX_COUNT_BETWEEN_QUERIES * 2),
+                (MAX_COUNT_BETWEEN_QUERIES * 2)..count,
+            ]
+        );
+
+        benchmark.submission_mode = SubmissionMode::PerBatch;
+        let submissions = benchmark.submissions();
+        assert_eq!(submissions.len(), 3);
+        assert_eq!(submissions[2], (MAX_COUNT_BETWEEN_QUERIES * 2)..count);
+        assert_eq!(benchmark.passes(submissions[2].clone()).len(), 1);
+
+        benchmark.pass_mode = PassMode::PerIteration;
+        assert_eq!(benchmark.passes(submissions[2].clone()).len(), 3);
+
+        benchmark.submission_mode = SubmissionMode::PerIteration;
+        benchmark.pass_mode = PassMode::PerBatch;
+        let submissions = benchmark.submissions();
+        assert_eq!(submissions.len(), count);
+        assert!(submissions
+            .into_iter()
+            .all(|iterations| benchmark.passes(iterations).len() == 1));
+    }
+}
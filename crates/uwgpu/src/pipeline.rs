@@ -44,7 +44,10 @@ pub struct PipelineParameters<'a, 'b> {
     /// group, so for example if the shader uses the same buffer for input
     /// and output (by overriding it), it will keep overriding the same
     /// buffer over and over, effectively using last iteration's output as its
-    /// next iteration's input.
+    /// next iteration's input. To avoid that, see the [Benchmark]
+    /// `input_resets` and `ping_pong_bind_group` fields.
+    ///
+    /// [Benchmark]: crate::Benchmark
     pub bind_group_0: HashMap<u32, BindingResource<'b>>,
 
     /// GPU context that is to be used for creating this pipeline.
@@ -185,7 +188,7 @@ async fn check_shader_compilation_errors(
 
 /// Inspects the shader source to replace the expected $workgroup$ variable with
 /// the workgroup size given
-fn replace_shader_workgroup_variable<'a>(
+pub(crate) fn replace_shader_workgroup_variable<'a>(
     shader: &'a ShaderModuleDescriptor,
     wg_size: &(u32, u32, u32),
 ) -> ShaderModuleDescriptor<'a> {
@@ -0,0 +1,890 @@
+//! Reflection of the resources bound by a compute shader and automatic
+//! creation of them, see [reflect_bindings] and [ShaderResources]
+
+use std::collections::{BTreeMap, HashMap};
+
+use naga::{
+    valid::{Capabilities, ModuleInfo, ValidationFlags, Validator},
+    AddressSpace, ArraySize, EntryPoint, ImageClass, ImageDimension, Module,
+    ScalarKind, StorageAccess, StorageFormat, TypeInner,
+};
+use thiserror::Error;
+use wgpu::{
+    util::BufferInitDescriptor, BindingResource, BufferUsages, Extent3d,
+    Sampler, SamplerDescriptor, ShaderModuleDescriptor, ShaderSource,
+    StorageTextureAccess, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension,
+};
+use wgpu_async::AsyncBuffer;
+
+use crate::{pipeline::replace_shader_workgroup_variable, GPUContext};
+
+/// A resource bound by a shader's entry point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderBinding {
+    /// The `n` of the `@group(n)` attribute.
+    pub group: u32,
+    /// The `n` of the `@binding(n)` attribute.
+    pub binding: u32,
+    /// Name of the variable in the shader.
+    pub name: Option<String>,
+    /// What kind of resource is bound.
+    pub kind: BindingKind,
+}
+
+/// The kind of resource bound to a [ShaderBinding].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BindingKind {
+    /// `var<uniform>`
+    Uniform(BufferLayout),
+    /// `var<storage, read>` or `var<storage, read_write>`
+    Storage {
+        /// Whether the shader can only read the buffer.
+        read_only: bool,
+        /// Layout of the buffer contents.
+        layout: BufferLayout,
+    },
+    /// A sampled or depth texture.
+    Texture {
+        /// Dimension of the texture view.
+        dimension: TextureViewDimension,
+        /// Type of the texels' components, [ElementType::Other] for depth
+        /// textures.
+        sample_type: ElementType,
+        /// Whether the texture is multisampled.
+        multisampled: bool,
+    },
+    /// A storage texture.
+    StorageTexture {
+        /// Dimension of the texture view.
+        dimension: TextureViewDimension,
+        /// Format of the texture.
+        format: TextureFormat,
+        /// How the shader can access the texture.
+        access: StorageTextureAccess,
+    },
+    /// A sampler.
+    Sampler {
+        /// Whether it's a comparison sampler.
+        comparison: bool,
+    },
+}
+
+/// Layout of a buffer bound by a shader, seen as an array of elements.
+///
+/// Scalars and structs are seen as an array with a single element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BufferLayout {
+    /// Scalar type of the element's components.
+    pub element: ElementType,
+    /// Amount of scalar components in each element, for example 4 for a
+    /// `vec4<f32>`.
+    pub components: u32,
+    /// Size in bytes of each element, including padding.
+    pub stride: u64,
+    /// Bytes before the first element, for arrays that are the last member
+    /// of a struct.
+    pub offset: u64,
+    /// Amount of elements, [None] if it's a runtime-sized array.
+    pub len: Option<usize>,
+}
+
+impl BufferLayout {
+    /// Size in bytes of a buffer holding `len` elements.
+    ///
+    /// `len` is ignored if the buffer isn't a runtime-sized array.
+    pub fn size(&self, len: usize) -> u64 {
+        self.offset + self.len.unwrap_or(len) as u64 * self.stride
+    }
+}
+
+/// Scalar type of the components of a bound resource.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementType {
+    /// `f32`
+    F32,
+    /// `f16`
+    F16,
+    /// `i32` or `atomic<i32>`
+    I32,
+    /// `u32` or `atomic<u32>`
+    U32,
+    /// Anything else, such as structs or matrices.
+    Other,
+}
+
+impl ElementType {
+    /// Size in bytes of a single component.
+    fn size(&self) -> Option<usize> {
+        match self {
+            Self::F32 | Self::I32 | Self::U32 => Some(4),
+            Self::F16 => Some(2),
+            Self::Other => None,
+        }
+    }
+
+    /// Encodes the value as this type, [None] if it can't be encoded.
+    fn encode(&self, value: f64) -> Option<Vec<u8>> {
+        match self {
+            Self::F32 => Some((value as f32).to_le_bytes().to_vec()),
+            Self::I32 => Some((value as i32).to_le_bytes().to_vec()),
+            Self::U32 => Some((value as u32).to_le_bytes().to_vec()),
+            Self::F16 | Self::Other => None,
+        }
+    }
+}
+
+/// Reflects the resources used by the shader's entry point, sorted by their
+/// `(group, binding)`.
+///
+/// If `workgroup_size` is [Some], the `$workgroup$` placeholders will be
+/// replaced the same way as in the [PipelineParameters] `workgroup_size`
+/// field.
+///
+/// [PipelineParameters]: crate::PipelineParameters
+pub fn reflect_bindings(
+    shader: &ShaderModuleDescriptor,
+    entry_point: &str,
+    workgroup_size: Option<(u32, u32, u32)>,
+) -> Result<Vec<ShaderBinding>, ShaderAnalysisError> {
+    let parsed = ParsedShader::new(shader, entry_point, workgroup_size)?;
+    let module = &parsed.module;
+    let uses = parsed.info.get_entry_point(parsed.entry_point);
+
+    let mut bindings: Vec<ShaderBinding> = module
+        .global_variables
+        .iter()
+        .filter(|(handle, _)| !uses[*handle].is_empty())
+        .filter_map(|(_, global)| {
+            let binding = global.binding.as_ref()?;
+            Some((global, binding))
+        })
+        .map(|(global, binding)| {
+            Ok(ShaderBinding {
+                group: binding.group,
+                binding: binding.binding,
+                name: global.name.clone(),
+                kind: binding_kind(module, global.space, global.ty).ok_or(
+                    ShaderAnalysisError::UnsupportedBinding(
+                        binding.group,
+                        binding.binding,
+                    ),
+                )?,
+            })
+        })
+        .collect::<Result<_, _>>()?;
+
+    bindings.sort_by_key(|binding| (binding.group, binding.binding));
+    Ok(bindings)
+}
+
+/// How to initialize the resource of a binding when creating
+/// [ShaderResources].
+pub enum BindingInit<'a> {
+    /// Buffer with `len` zeroed elements.
+    Zeroed {
+        /// Amount of elements, ignored if the binding isn't a runtime-sized
+        /// array.
+        len: usize,
+    },
+    /// Buffer with `len` elements, where each scalar component is generated
+    /// from its index.
+    ///
+    /// For example for an `array<vec2<f32>>` the generator gets called with
+    /// the indexes `0..len * 2`. The values are converted to the binding's
+    /// [ElementType].
+    Generated {
+        /// Amount of elements, ignored if the binding isn't a runtime-sized
+        /// array.
+        len: usize,
+        /// Generates the value of the component with the given index.
+        generator: Box<dyn Fn(usize) -> f64 + 'a>,
+    },
+    /// Buffer with the given contents.
+    Bytes(&'a [u8]),
+    /// Zeroed texture of the given size.
+    Texture {
+        /// Size of the texture, for array textures the layers go in
+        /// `depth_or_array_layers`.
+        size: Extent3d,
+    },
+}
+
+/// The resources bound by a shader, created from its [ShaderBinding]s.
+pub struct ShaderResources {
+    resources: BTreeMap<(u32, u32), Resource>,
+}
+
+enum Resource {
+    Buffer(AsyncBuffer),
+    Texture(Texture, TextureView),
+    Sampler(Sampler),
+}
+
+impl ShaderResources {
+    /// Creates a resource for each of the bindings, as specified in `inits`
+    /// which is keyed by `(group, binding)`.
+    ///
+    /// Bindings without an init get zeroed, except for runtime-sized arrays
+    /// and textures whose size is unknown. Samplers get created with the
+    /// default [SamplerDescriptor] and don't need an init.
+    ///
+    /// Buffers get created with the [COPY_SRC](BufferUsages::COPY_SRC) and
+    /// [COPY_DST](BufferUsages::COPY_DST) usage flags so they can be read back
+    /// or restored.
+    pub fn new(
+        gpu: &GPUContext,
+        bindings: &[ShaderBinding],
+        mut inits: HashMap<(u32, u32), BindingInit>,
+    ) -> Result<Self, CreateShaderResourcesError> {
+        let mut resources = BTreeMap::new();
+
+        for binding in bindings {
+            let key = (binding.group, binding.binding);
+            let init = inits.remove(&key);
+            let label = binding.name.as_deref();
+
+            let resource = match (&binding.kind, init) {
+                (BindingKind::Uniform(layout), init) => {
+                    Resource::Buffer(create_buffer(
+                        gpu,
+                        label,
+                        layout,
+                        BufferUsages::UNIFORM,
+                        init,
+                        key,
+                    )?)
+                }
+                (BindingKind::Storage { layout, .. }, init) => {
+                    Resource::Buffer(create_buffer(
+                        gpu,
+                        label,
+                        layout,
+                        BufferUsages::STORAGE,
+                        init,
+                        key,
+                    )?)
+                }
+                (
+                    BindingKind::Texture {
+                        dimension,
+                        sample_type,
+                        multisampled,
+                    },
+                    Some(BindingInit::Texture { size }),
+                ) => {
+                    let format = match sample_type {
+                        ElementType::F32 | ElementType::F16 => {
+                            TextureFormat::Rgba8Unorm
+                        }
+                        ElementType::I32 => TextureFormat::R32Sint,
+                        ElementType::U32 => TextureFormat::R32Uint,
+                        ElementType::Other => TextureFormat::Depth32Float,
+                    };
+                    let sample_count = if *multisampled { 4 } else { 1 };
+                    create_texture(
+                        gpu,
+                        label,
+                        *dimension,
+                        format,
+                        size,
+                        sample_count,
+                        TextureUsages::TEXTURE_BINDING,
+                    )
+                }
+                (
+                    BindingKind::StorageTexture {
+                        dimension, format, ..
+                    },
+                    Some(BindingInit::Texture { size }),
+                ) => create_texture(
+                    gpu,
+                    label,
+                    *dimension,
+                    *format,
+                    size,
+                    1,
+                    TextureUsages::STORAGE_BINDING,
+                ),
+                (BindingKind::Sampler { comparison }, None) => {
+                    Resource::Sampler(
+                        gpu.device.create_sampler(&SamplerDescriptor {
+                            label,
+                            compare: comparison
+                                .then_some(wgpu::CompareFunction::Less),
+                            ..Default::default()
+                        }),
+                    )
+                }
+                (_, Some(_)) => {
+                    return Err(CreateShaderResourcesError::WrongInit(
+                        key.0, key.1,
+                    ))
+                }
+                (_, None) => {
+                    return Err(CreateShaderResourcesError::MissingInit(
+                        key.0, key.1,
+                    ))
+                }
+            };
+
+            resources.insert(key, resource);
+        }
+
+        if let Some((group, binding)) = inits.into_keys().next() {
+            return Err(CreateShaderResourcesError::UnknownBinding(
+                group, binding,
+            ));
+        }
+
+        Ok(Self { resources })
+    }
+
+    /// The buffer bound at `(group, binding)`.
+    pub fn buffer(&self, group: u32, binding: u32) -> Option<&AsyncBuffer> {
+        match self.resources.get(&(group, binding))? {
+            Resource::Buffer(buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    /// The texture bound at `(group, binding)`.
+    pub fn texture(&self, group: u32, binding: u32) -> Option<&Texture> {
+        match self.resources.get(&(group, binding))? {
+            Resource::Texture(texture, _) => Some(texture),
+            _ => None,
+        }
+    }
+
+    /// The entries of the given group, ready to be used for the
+    /// [PipelineParameters] `bind_group_0` field or the [BindGroupParams]
+    /// `entries` field.
+    ///
+    /// [PipelineParameters]: crate::PipelineParameters
+    /// [BindGroupParams]: crate::BindGroupParams
+    pub fn bind_group_entries(
+        &self,
+        group: u32,
+    ) -> HashMap<u32, BindingResource<'_>> {
+        self.resources
+            .range((group, 0)..=(group, u32::MAX))
+            .map(|((_, binding), resource)| {
+                let resource = match resource {
+                    Resource::Buffer(buffer) => buffer.as_entire_binding(),
+                    Resource::Texture(_, view) => {
+                        BindingResource::TextureView(view)
+                    }
+                    Resource::Sampler(sampler) => {
+                        BindingResource::Sampler(sampler)
+                    }
+                };
+                (*binding, resource)
+            })
+            .collect()
+    }
+}
+
+/// Error analyzing a shader with [reflect_bindings] or
+/// [estimate_shader_cost](crate::estimate_shader_cost).
+#[derive(Debug, Clone, Error)]
+pub enum ShaderAnalysisError {
+    /// Only WGSL shaders can be analyzed
+    #[error("only WGSL shaders can be analyzed")]
+    UnsupportedShaderSource,
+    /// Error parsing the shader
+    #[error("error parsing shader: {0}")]
+    Parse(String),
+    /// Error validating the shader
+    #[error("error validating shader: {0}")]
+    Validation(String),
+    /// The shader has no entry point with the given name
+    #[error("shader has no entry point named \"{0}\"")]
+    MissingEntryPoint(String),
+    /// The type of the binding at `(group, binding)` isn't supported
+    #[error("unsupported type for @group({0}) @binding({1})")]
+    UnsupportedBinding(u32, u32),
+}
+
+/// Error creating [ShaderResources]
+#[derive(Debug, Clone, Error)]
+pub enum CreateShaderResourcesError {
+    /// The binding at `(group, binding)` needs a size to be created
+    #[error("@group({0}) @binding({1}) needs an init specifying its size")]
+    MissingInit(u32, u32),
+    /// The init given doesn't fit the binding at `(group, binding)`
+    #[error("init doesn't match the type of @group({0}) @binding({1})")]
+    WrongInit(u32, u32),
+    /// An init was given for a binding the shader doesn't use
+    #[error("shader doesn't use @group({0}) @binding({1})")]
+    UnknownBinding(u32, u32),
+    /// The bytes given don't fill the buffer at `(group, binding)`
+    #[error("wrong amount of bytes for @group({0}) @binding({1})")]
+    WrongSize(u32, u32),
+}
+
+/// A validated shader module, with the entry point to analyze.
+pub(crate) struct ParsedShader {
+    pub(crate) module: Module,
+    pub(crate) info: ModuleInfo,
+    /// Index of the entry point in the module
+    pub(crate) entry_point: usize,
+}
+
+impl ParsedShader {
+    pub(crate) fn new(
+        shader: &ShaderModuleDescriptor,
+        entry_point: &str,
+        workgroup_size: Option<(u32, u32, u32)>,
+    ) -> Result<Self, ShaderAnalysisError> {
+        let ShaderSource::Wgsl(_) = &shader.source else {
+            return Err(ShaderAnalysisError::UnsupportedShaderSource);
+        };
+
+        let shader = match workgroup_size {
+            Some(workgroup_size) => {
+                replace_shader_workgroup_variable(shader, &workgroup_size)
+            }
+            None => shader.clone(),
+        };
+        let ShaderSource::Wgsl(source) = shader.source else {
+            unreachable!(
+                "the workgroup size replacement keeps the source as WGSL"
+            )
+        };
+
+        let module = naga::front::wgsl::parse_str(&source).map_err(|err| {
+            ShaderAnalysisError::Parse(err.emit_to_string(&source))
+        })?;
+        let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+            .validate(&module)
+            .map_err(|err| ShaderAnalysisError::Validation(err.to_string()))?;
+
+        let entry_point = module
+            .entry_points
+            .iter()
+            .position(|EntryPoint { name, .. }| name == entry_point)
+            .ok_or_else(|| {
+                ShaderAnalysisError::MissingEntryPoint(entry_point.to_owned())
+            })?;
+
+        Ok(Self {
+            module,
+            info,
+            entry_point,
+        })
+    }
+}
+
+fn binding_kind(
+    module: &Module,
+    space: AddressSpace,
+    ty: naga::Handle<naga::Type>,
+) -> Option<BindingKind> {
+    match (space, &module.types[ty].inner) {
+        (AddressSpace::Uniform, _) => {
+            Some(BindingKind::Uniform(buffer_layout(module, ty)))
+        }
+        (AddressSpace::Storage { access }, _) => Some(BindingKind::Storage {
+            read_only: !access.contains(StorageAccess::STORE),
+            layout: buffer_layout(module, ty),
+        }),
+        (
+            AddressSpace::Handle,
+            TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            },
+        ) => {
+            let dimension = view_dimension(*dim, *arrayed)?;
+            Some(match *class {
+                ImageClass::Sampled { kind, multi } => BindingKind::Texture {
+                    dimension,
+                    sample_type: element_type(kind, 4),
+                    multisampled: multi,
+                },
+                ImageClass::Depth { multi } => BindingKind::Texture {
+                    dimension,
+                    sample_type: ElementType::Other,
+                    multisampled: multi,
+                },
+                ImageClass::Storage { format, access } => {
+                    BindingKind::StorageTexture {
+                        dimension,
+                        format: texture_format(format)?,
+                        access: match (
+                            access.contains(StorageAccess::LOAD),
+                            access.contains(StorageAccess::STORE),
+                        ) {
+                            (true, true) => StorageTextureAccess::ReadWrite,
+                            (true, false) => StorageTextureAccess::ReadOnly,
+                            _ => StorageTextureAccess::WriteOnly,
+                        },
+                    }
+                }
+            })
+        }
+        (AddressSpace::Handle, TypeInner::Sampler { comparison }) => {
+            Some(BindingKind::Sampler {
+                comparison: *comparison,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn buffer_layout(
+    module: &Module,
+    ty: naga::Handle<naga::Type>,
+) -> BufferLayout {
+    let ctx = module.to_ctx();
+    let inner = &module.types[ty].inner;
+
+    match inner {
+        TypeInner::Array { base, size, stride } => {
+            let (element, components) = components(&module.types[*base].inner);
+            BufferLayout {
+                element,
+                components,
+                stride: *stride as u64,
+                offset: 0,
+                len: match size {
+                    ArraySize::Constant(len) => Some(len.get() as usize),
+                    ArraySize::Dynamic => None,
+                },
+            }
+        }
+        // Arrays at the end of structs, the rest of the members are left as a
+        // header before the first element
+        TypeInner::Struct { members, .. }
+            if matches!(
+                members.last().map(|member| &module.types[member.ty].inner),
+                Some(TypeInner::Array {
+                    size: ArraySize::Dynamic,
+                    ..
+                })
+            ) =>
+        {
+            let last = members.last().expect("matched above");
+            BufferLayout {
+                offset: last.offset as u64,
+                ..buffer_layout(module, last.ty)
+            }
+        }
+        _ => {
+            let (element, components) = components(inner);
+            BufferLayout {
+                element,
+                components,
+                stride: inner.size(ctx) as u64,
+                offset: 0,
+                len: Some(1),
+            }
+        }
+    }
+}
+
+/// Element type and amount of components of scalars, vectors and atomics.
+fn components(inner: &TypeInner) -> (ElementType, u32) {
+    match *inner {
+        TypeInner::Scalar(scalar) | TypeInner::Atomic(scalar) => {
+            (element_type(scalar.kind, scalar.width), 1)
+        }
+        TypeInner::Vector { size, scalar } => {
+            (element_type(scalar.kind, scalar.width), size as u32)
+        }
+        _ => (ElementType::Other, 1),
+    }
+}
+
+fn element_type(kind: ScalarKind, width: u8) -> ElementType {
+    match (kind, width) {
+        (ScalarKind::Float, 4) => ElementType::F32,
+        (ScalarKind::Float, 2) => ElementType::F16,
+        (ScalarKind::Sint, 4) => ElementType::I32,
+        (ScalarKind::Uint, 4) => ElementType::U32,
+        _ => ElementType::Other,
+    }
+}
+
+fn view_dimension(
+    dim: ImageDimension,
+    arrayed: bool,
+) -> Option<TextureViewDimension> {
+    Some(match (dim, arrayed) {
+        (ImageDimension::D1, false) => TextureViewDimension::D1,
+        (ImageDimension::D2, false) => TextureViewDimension::D2,
+        (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+        (ImageDimension::D3, false) => TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+        _ => return None,
+    })
+}
+
+/// The texture formats that can be used by storage textures in WGSL.
+fn texture_format(format: StorageFormat) -> Option<TextureFormat> {
+    Some(match format {
+        StorageFormat::Rgba8Unorm => TextureFormat::Rgba8Unorm,
+        StorageFormat::Rgba8Snorm => TextureFormat::Rgba8Snorm,
+        StorageFormat::Rgba8Uint => TextureFormat::Rgba8Uint,
+        StorageFormat::Rgba8Sint => TextureFormat::Rgba8Sint,
+        StorageFormat::Bgra8Unorm => TextureFormat::Bgra8Unorm,
+        StorageFormat::Rgba16Uint => TextureFormat::Rgba16Uint,
+        StorageFormat::Rgba16Sint => TextureFormat::Rgba16Sint,
+        StorageFormat::Rgba16Float => TextureFormat::Rgba16Float,
+        StorageFormat::R32Uint => TextureFormat::R32Uint,
+        StorageFormat::R32Sint => TextureFormat::R32Sint,
+        StorageFormat::R32Float => TextureFormat::R32Float,
+        StorageFormat::Rg32Uint => TextureFormat::Rg32Uint,
+        StorageFormat::Rg32Sint => TextureFormat::Rg32Sint,
+        StorageFormat::Rg32Float => TextureFormat::Rg32Float,
+        StorageFormat::Rgba32Uint => TextureFormat::Rgba32Uint,
+        StorageFormat::Rgba32Sint => TextureFormat::Rgba32Sint,
+        StorageFormat::Rgba32Float => TextureFormat::Rgba32Float,
+        _ => return None,
+    })
+}
+
+/// Contents of a buffer with the given layout and init, [None] if it should
+/// be zeroed.
+fn buffer_contents(
+    layout: &BufferLayout,
+    init: Option<BindingInit>,
+    key: (u32, u32),
+) -> Result<(u64, Option<Vec<u8>>), CreateShaderResourcesError> {
+    let (group, binding) = key;
+
+    match init {
+        None if layout.len.is_none() => {
+            Err(CreateShaderResourcesError::MissingInit(group, binding))
+        }
+        None => Ok((layout.size(0), None)),
+        Some(BindingInit::Zeroed { len }) => Ok((layout.size(len), None)),
+        Some(BindingInit::Bytes(bytes)) => {
+            let len = (bytes.len() as u64).saturating_sub(layout.offset)
+                / layout.stride;
+            if layout.size(len as usize) != bytes.len() as u64 {
+                return Err(CreateShaderResourcesError::WrongSize(
+                    group, binding,
+                ));
+            }
+
+            Ok((bytes.len() as u64, Some(bytes.to_vec())))
+        }
+        Some(BindingInit::Generated { len, generator }) => {
+            let component_size = layout
+                .element
+                .size()
+                .ok_or(CreateShaderResourcesError::WrongInit(group, binding))?;
+            let len = layout.len.unwrap_or(len);
+            let size = layout.size(len);
+
+            let mut contents = vec![0; size as usize];
+            for i in 0..len * layout.components as usize {
+                let element = i / layout.components as usize;
+                let component = i % layout.components as usize;
+                let start = layout.offset as usize
+                    + element * layout.stride as usize
+                    + component * component_size;
+
+                let bytes = layout.element.encode(generator(i)).ok_or(
+                    CreateShaderResourcesError::WrongInit(group, binding),
+                )?;
+                contents[start..start + component_size].copy_from_slice(&bytes);
+            }
+
+            Ok((size, Some(contents)))
+        }
+        Some(BindingInit::Texture { .. }) => {
+            Err(CreateShaderResourcesError::WrongInit(group, binding))
+        }
+    }
+}
+
+fn create_buffer(
+    gpu: &GPUContext,
+    label: Option<&str>,
+    layout: &BufferLayout,
+    usage: BufferUsages,
+    init: Option<BindingInit>,
+    key: (u32, u32),
+) -> Result<AsyncBuffer, CreateShaderResourcesError> {
+    let usage = usage | BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
+    let (size, contents) = buffer_contents(layout, init, key)?;
+
+    Ok(gpu.create_buffer_init(&BufferInitDescriptor {
+        label,
+        contents: &contents.unwrap_or_else(|| vec![0; size as usize]),
+        usage,
+    }))
+}
+
+fn create_texture(
+    gpu: &GPUContext,
+    label: Option<&str>,
+    dimension: TextureViewDimension,
+    format: TextureFormat,
+    size: Extent3d,
+    sample_count: u32,
+    usage: TextureUsages,
+) -> Resource {
+    let texture = gpu.create_texture(&TextureDescriptor {
+        label,
+        size,
+        mip_level_count: 1,
+        sample_count,
+        dimension: match dimension {
+            TextureViewDimension::D1 => TextureDimension::D1,
+            TextureViewDimension::D3 => TextureDimension::D3,
+            _ => TextureDimension::D2,
+        },
+        format,
+        usage: usage | TextureUsages::COPY_SRC | TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&TextureViewDescriptor {
+        dimension: Some(dimension),
+        ..Default::default()
+    });
+
+    Resource::Texture(texture, view)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies the bindings get reflected with their layouts, and that
+    /// unused bindings get skipped.
+    #[test]
+    fn bindings_are_reflected() {
+        let source = r#"
+            struct Header { len: u32, data: array<vec3<f32>> }
+
+            @group(0) @binding(0) var<storage, read> input: array<f32>;
+            @group(0) @binding(1) var<storage, read_write> output: Header;
+            @group(0) @binding(2) var<uniform> size: u32;
+            @group(0) @binding(3) var<uniform> unused: u32;
+            @group(1) @binding(0) var image: texture_storage_2d<r32float, write>;
+            var<workgroup> shared_data: array<f32, $workgroup_x$>;
+
+            @compute @workgroup_size($workgroup$)
+            fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+                shared_data[id.x] = input[id.x];
+                output.data[id.x] = vec3(shared_data[id.x] * f32(size));
+                textureStore(image, id.xy, vec4(1.0));
+            }
+        "#;
+
+        let bindings = reflect_bindings(
+            &ShaderModuleDescriptor {
+                label: None,
+                source: ShaderSource::Wgsl(source.into()),
+            },
+            "main",
+            Some((64, 1, 1)),
+        )
+        .unwrap();
+
+        let kinds: Vec<_> = bindings
+            .iter()
+            .map(|binding| (binding.group, binding.binding, &binding.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (
+                    0,
+                    0,
+                    &BindingKind::Storage {
+                        read_only: true,
+                        layout: BufferLayout {
+                            element: ElementType::F32,
+                            components: 1,
+                            stride: 4,
+                            offset: 0,
+                            len: None,
+                        }
+                    }
+                ),
+                (
+                    0,
+                    1,
+                    &BindingKind::Storage {
+                        read_only: false,
+                        layout: BufferLayout {
+                            element: ElementType::F32,
+                            components: 3,
+                            stride: 16,
+                            offset: 16,
+                            len: None,
+                        }
+                    }
+                ),
+                (
+                    0,
+                    2,
+                    &BindingKind::Uniform(BufferLayout {
+                        element: ElementType::U32,
+                        components: 1,
+                        stride: 4,
+                        offset: 0,
+                        len: Some(1),
+                    })
+                ),
+                (
+                    1,
+                    0,
+                    &BindingKind::StorageTexture {
+                        dimension: TextureViewDimension::D2,
+                        format: TextureFormat::R32Float,
+                        access: StorageTextureAccess::WriteOnly,
+                    }
+                ),
+            ]
+        );
+        assert_eq!(bindings[1].name.as_deref(), Some("output"));
+    }
+
+    /// Verifies generated buffer contents respect the stride and offset of
+    /// the layout.
+    #[test]
+    fn generated_contents_follow_layout() {
+        let layout = BufferLayout {
+            element: ElementType::U32,
+            components: 3,
+            stride: 16,
+            offset: 4,
+            len: None,
+        };
+
+        let (size, contents) = buffer_contents(
+            &layout,
+            Some(BindingInit::Generated {
+                len: 2,
+                generator: Box::new(|i| i as f64 + 1.0),
+            }),
+            (0, 0),
+        )
+        .unwrap();
+        let contents: Vec<u32> = contents
+            .unwrap()
+            .chunks(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+
+        assert_eq!(size, 36);
+        assert_eq!(contents, vec![0, 1, 2, 3, 0, 4, 5, 6, 0]);
+
+        assert!(matches!(
+            buffer_contents(&layout, None, (0, 0)),
+            Err(CreateShaderResourcesError::MissingInit(0, 0))
+        ));
+        assert!(matches!(
+            buffer_contents(&layout, Some(BindingInit::Bytes(&[0; 8])), (0, 0)),
+            Err(CreateShaderResourcesError::WrongSize(0, 0))
+        ));
+    }
+}
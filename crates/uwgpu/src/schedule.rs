@@ -0,0 +1,303 @@
+//! Planning of the compute passes and timestamp queries of a
+//! [Benchmark](crate::Benchmark), and reduction of the timestamps they produce,
+//! see [TimestampPlan] and [reduce_timestamps].
+//!
+//! Nothing in here talks to the GPU, instead [Benchmark::run] goes through the
+//! [BenchmarkDevice] trait so that the scheduling can be tested with a fake
+//! device.
+//!
+//! [Benchmark::run]: crate::Benchmark::run
+
+use std::{mem::size_of, ops::Range};
+
+use crate::{AdapterInfo, MapTimestampResultError};
+
+/// Max amount of queries in a single query set.
+const QUERIES_PER_SET: usize = wgpu::QUERY_SET_MAX_QUERIES as usize;
+
+/// Layout of the timestamp queries needed to time a number of batches.
+///
+/// Each batch gets a pair of queries, one written at its start and one at its
+/// end. Query sets can't hold more than [wgpu::QUERY_SET_MAX_QUERIES] queries,
+/// so the queries are split into as many full query sets as needed plus a
+/// remainder set. All of them get resolved into a single buffer, in batch
+/// order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TimestampPlan {
+    batches: usize,
+}
+
+/// Location of a batch's pair of timestamp queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct QuerySlot {
+    /// Index of the query set.
+    pub(crate) set: usize,
+    /// Index of the query written at the start of the batch.
+    pub(crate) begin: u32,
+    /// Index of the query written at the end of the batch.
+    pub(crate) end: u32,
+}
+
+/// How a query set gets resolved into the query buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct QueryResolve {
+    /// Index of the query set.
+    pub(crate) set: usize,
+    /// Queries of the set to resolve.
+    pub(crate) queries: Range<u32>,
+    /// Offset in bytes into the query buffer.
+    pub(crate) offset: u64,
+}
+
+impl TimestampPlan {
+    /// Plan for timing the given amount of batches.
+    pub(crate) fn new(batches: usize) -> Self { Self { batches } }
+
+    /// Total amount of queries.
+    pub(crate) fn query_count(&self) -> usize { self.batches * 2 }
+
+    /// The amount of queries in each query set.
+    pub(crate) fn query_set_sizes(&self) -> Vec<u32> {
+        let full_sets = self.query_count() / QUERIES_PER_SET;
+        let remainder = self.query_count() % QUERIES_PER_SET;
+
+        let mut sizes = vec![QUERIES_PER_SET as u32; full_sets];
+        if remainder > 0 {
+            sizes.push(remainder as u32);
+        }
+
+        sizes
+    }
+
+    /// Size in bytes of the buffer the queries get resolved into.
+    pub(crate) fn buffer_size(&self) -> u64 {
+        (self.query_count() * size_of::<u64>()) as u64
+    }
+
+    /// Queries used to time the given batch.
+    pub(crate) fn slot(&self, batch: usize) -> QuerySlot {
+        debug_assert!(batch < self.batches);
+
+        // Query sets hold an even amount of queries, so a batch's pair never
+        // gets split across two sets
+        let begin = batch * 2;
+        QuerySlot {
+            set: begin / QUERIES_PER_SET,
+            begin: (begin % QUERIES_PER_SET) as u32,
+            end: (begin % QUERIES_PER_SET) as u32 + 1,
+        }
+    }
+
+    /// How each query set gets resolved into the query buffer.
+    pub(crate) fn resolves(&self) -> Vec<QueryResolve> {
+        self.query_set_sizes()
+            .into_iter()
+            .enumerate()
+            .map(|(set, size)| QueryResolve {
+                set,
+                queries: 0..size,
+                offset: (set * QUERIES_PER_SET * size_of::<u64>()) as u64,
+            })
+            .collect()
+    }
+}
+
+/// A compute pass of timed iterations, ready to be encoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PlannedPass {
+    /// The iterations encoded in the pass.
+    pub(crate) iterations: Range<usize>,
+    /// Whether the inputs have to be restored before the pass, see
+    /// [InputReset](crate::InputReset).
+    pub(crate) reset_input: bool,
+    /// Index of the query set the pass's timestamps get written to.
+    pub(crate) query_set: usize,
+    /// Query written at the beginning of the pass, if it starts a batch.
+    pub(crate) beginning_of_pass_write_index: Option<u32>,
+    /// Query written at the end of the pass, if it ends a batch.
+    pub(crate) end_of_pass_write_index: Option<u32>,
+    /// Whether the iterations alternate between the pipeline's and the
+    /// ping-pong bind group, see
+    /// [Benchmark::ping_pong_bind_group](crate::Benchmark::ping_pong_bind_group).
+    pub(crate) ping_pong: bool,
+}
+
+impl PlannedPass {
+    /// The `@group(0)` bind group the given iteration has to be encoded with,
+    /// [None] if every iteration uses the pipeline's one.
+    pub(crate) fn bind_group(&self, iteration: usize) -> Option<BindGroupSlot> {
+        self.ping_pong
+            .then(|| BindGroupSlot::of_iteration(iteration))
+    }
+}
+
+/// Which `@group(0)` bind group an iteration gets encoded with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BindGroupSlot {
+    /// The pipeline's bind group, used by the even iterations.
+    Pipeline,
+    /// The ping-pong bind group, used by the odd iterations.
+    PingPong,
+}
+
+impl BindGroupSlot {
+    /// The bind group of the given iteration when alternating between both.
+    ///
+    /// The warmup and the timed iterations are counted separately, so both
+    /// start with the pipeline's bind group.
+    pub(crate) fn of_iteration(iteration: usize) -> Self {
+        match iteration % 2 {
+            0 => Self::Pipeline,
+            _ => Self::PingPong,
+        }
+    }
+}
+
+/// Sum of the time spent by the batches with valid timestamps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct TimestampTotals {
+    /// Time spent, in ticks of the timestamp period.
+    pub(crate) ticks: u64,
+    /// Amount of iterations ran by the valid batches.
+    pub(crate) count: usize,
+}
+
+/// Adds up the time between each batch's pair of resolved timestamps.
+///
+/// `count` is the total amount of timed iterations, split into batches of
+/// `batch_size` iterations with the last batch possibly being smaller.
+///
+/// Batches whose end timestamp comes before their start timestamp are ignored,
+/// since timestamp queries can return negative deltas in rare circumstances.
+pub(crate) fn reduce_timestamps(
+    timestamps: &[u64],
+    count: usize,
+    batch_size: usize,
+) -> TimestampTotals {
+    timestamps
+        .chunks_exact(2)
+        .enumerate()
+        .filter_map(|(batch, times)| {
+            let time = times[1].checked_sub(times[0])?;
+            let iterations = batch_size.min(count - batch * batch_size);
+            Some((time, iterations))
+        })
+        .fold(
+            TimestampTotals { ticks: 0, count: 0 },
+            |totals, (time, iterations)| TimestampTotals {
+                ticks: totals.ticks + time,
+                count: totals.count + iterations,
+            },
+        )
+}
+
+/// GPU operations needed by [Benchmark::run](crate::Benchmark::run).
+pub(crate) trait BenchmarkDevice {
+    /// Submits the warmup iterations.
+    fn submit_warmup(&mut self);
+
+    /// Submits a command buffer with the given compute passes.
+    fn submit_passes(&mut self, passes: &[PlannedPass]);
+
+    /// Resolves once all the submitted work has finished executing.
+    async fn submitted_work_done(&self);
+
+    /// Submits the finalize callback commands and resolves the timestamp
+    /// queries, then reads them back in the layout given by the
+    /// [TimestampPlan].
+    async fn read_timestamps(
+        &mut self,
+    ) -> Result<Box<[u64]>, MapTimestampResultError>;
+
+    /// Nanoseconds per timestamp tick.
+    fn timestamp_period(&self) -> f32;
+
+    /// Information about the adapter the benchmark runs on.
+    fn adapter_info(&self) -> AdapterInfo;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verifies the query sets get split at [wgpu::QUERY_SET_MAX_QUERIES],
+    /// including when the queries are an exact multiple of it.
+    #[test]
+    fn query_sets_split_at_max_queries() {
+        let half_set = QUERIES_PER_SET / 2;
+
+        assert!(TimestampPlan::new(0).query_set_sizes().is_empty());
+        assert_eq!(TimestampPlan::new(3).query_set_sizes(), vec![6]);
+        assert_eq!(
+            TimestampPlan::new(half_set).query_set_sizes(),
+            vec![QUERIES_PER_SET as u32]
+        );
+        assert_eq!(
+            TimestampPlan::new(half_set + 1).query_set_sizes(),
+            vec![QUERIES_PER_SET as u32, 2]
+        );
+        assert_eq!(
+            TimestampPlan::new(half_set * 2).query_set_sizes(),
+            vec![QUERIES_PER_SET as u32; 2]
+        );
+    }
+
+    /// Verifies consecutive batches get consecutive queries, and that the
+    /// resolved queries fill the query buffer without gaps or overlaps.
+    #[test]
+    fn slots_and_resolves_are_contiguous() {
+        let half_set = QUERIES_PER_SET / 2;
+        for batches in
+            [1, 7, half_set - 1, half_set, half_set + 1, half_set * 2]
+        {
+            let plan = TimestampPlan::new(batches);
+
+            let resolved: Vec<(usize, u32)> = plan
+                .resolves()
+                .into_iter()
+                .flat_map(|resolve| {
+                    let first = resolve.offset as usize / size_of::<u64>();
+                    assert_eq!(first, resolve.set * QUERIES_PER_SET);
+                    resolve.queries.map(move |query| (resolve.set, query))
+                })
+                .collect();
+            assert_eq!(resolved.len(), plan.query_count());
+            assert_eq!(
+                plan.buffer_size() as usize,
+                resolved.len() * size_of::<u64>()
+            );
+
+            for batch in 0..batches {
+                let slot = plan.slot(batch);
+                assert_eq!(resolved[batch * 2], (slot.set, slot.begin));
+                assert_eq!(resolved[batch * 2 + 1], (slot.set, slot.end));
+            }
+        }
+    }
+
+    /// Verifies negative deltas get ignored along with their iterations, and
+    /// that the last batch only counts its remaining iterations.
+    #[test]
+    fn reduce_ignores_negative_deltas() {
+        let timestamps = [100, 110, 200, 190, 300, 305];
+
+        assert_eq!(
+            reduce_timestamps(&timestamps, 23, 10),
+            TimestampTotals {
+                ticks: 15,
+                count: 13
+            }
+        );
+        assert_eq!(
+            reduce_timestamps(&timestamps, 30, 10),
+            TimestampTotals {
+                ticks: 15,
+                count: 20
+            }
+        );
+        assert_eq!(
+            reduce_timestamps(&[5, 5], 1, 1),
+            TimestampTotals { ticks: 0, count: 1 }
+        );
+    }
+}
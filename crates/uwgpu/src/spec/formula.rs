@@ -0,0 +1,434 @@
+//! Arithmetic formulas used by [BenchmarkSpec](super::BenchmarkSpec) for
+//! sizes, dispatch grids, init patterns and metrics, see [Formula]
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+/// An arithmetic expression over named variables.
+///
+/// Supports numbers, variables, the `+ - * / %` operators, parentheses and
+/// the functions `ceil`, `floor`, `round`, `abs`, `sqrt`, `log2`, `min`,
+/// `max` and `pow`. For example `ceil(n / wx)`.
+///
+/// Can be deserialized from either a string or a plain number.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "FormulaRepr")]
+pub struct Formula {
+    source: String,
+    expr: Expr,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FormulaRepr {
+    Number(f64),
+    Text(String),
+}
+
+impl TryFrom<FormulaRepr> for Formula {
+    type Error = ParseFormulaError;
+
+    fn try_from(repr: FormulaRepr) -> Result<Self, Self::Error> {
+        match repr {
+            FormulaRepr::Number(value) => Ok(Self {
+                source: value.to_string(),
+                expr: Expr::Number(value),
+            }),
+            FormulaRepr::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl FromStr for Formula {
+    type Err = ParseFormulaError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let expr = parser.expression()?;
+
+        match parser.tokens.get(parser.position) {
+            None => Ok(Self {
+                source: source.to_string(),
+                expr,
+            }),
+            Some(token) => {
+                Err(ParseFormulaError::Unexpected(token.to_string()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Formula {
+    /// Evaluates the formula with the given variables.
+    pub fn eval(
+        &self,
+        variables: &HashMap<String, f64>,
+    ) -> Result<f64, EvalFormulaError> {
+        self.expr.eval(variables)
+    }
+
+    /// Evaluates the formula, checking the result is a non-negative integer
+    /// such as a size or an amount of workgroups.
+    pub fn eval_integer(
+        &self,
+        variables: &HashMap<String, f64>,
+    ) -> Result<u64, EvalFormulaError> {
+        let value = self.eval(variables)?;
+
+        if value >= 0.0 && value.fract() == 0.0 && value <= u64::MAX as f64 {
+            Ok(value as u64)
+        } else {
+            Err(EvalFormulaError::NotAnInteger(self.source.clone(), value))
+        }
+    }
+}
+
+/// Error parsing a [Formula]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ParseFormulaError {
+    /// The formula contains a character that isn't part of the syntax
+    #[error("unexpected character '{0}'")]
+    UnexpectedCharacter(char),
+    /// The formula contains a token where it isn't expected
+    #[error("unexpected \"{0}\"")]
+    Unexpected(String),
+    /// The formula ends before it's complete
+    #[error("unexpected end of formula")]
+    UnexpectedEnd,
+    /// The formula calls a function that doesn't exist
+    #[error("unknown function \"{0}\"")]
+    UnknownFunction(String),
+    /// A function is called with the wrong amount of arguments
+    #[error("function \"{0}\" takes {1} arguments")]
+    WrongArgumentCount(String, usize),
+}
+
+/// Error evaluating a [Formula]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum EvalFormulaError {
+    /// The formula uses a variable that wasn't given
+    #[error("unknown variable \"{0}\"")]
+    UnknownVariable(String),
+    /// The formula was expected to give a non-negative integer
+    #[error("formula \"{0}\" gives {1}, which isn't a non-negative integer")]
+    NotAnInteger(String, f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Number(f64),
+    Variable(String),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Ceil,
+    Floor,
+    Round,
+    Abs,
+    Sqrt,
+    Log2,
+    Min,
+    Max,
+    Pow,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ceil" => Self::Ceil,
+            "floor" => Self::Floor,
+            "round" => Self::Round,
+            "abs" => Self::Abs,
+            "sqrt" => Self::Sqrt,
+            "log2" => Self::Log2,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "pow" => Self::Pow,
+            _ => return None,
+        })
+    }
+
+    fn arguments(&self) -> usize {
+        match self {
+            Self::Min | Self::Max | Self::Pow => 2,
+            _ => 1,
+        }
+    }
+
+    fn apply(&self, args: &[f64]) -> f64 {
+        match self {
+            Self::Ceil => args[0].ceil(),
+            Self::Floor => args[0].floor(),
+            Self::Round => args[0].round(),
+            Self::Abs => args[0].abs(),
+            Self::Sqrt => args[0].sqrt(),
+            Self::Log2 => args[0].log2(),
+            Self::Min => args[0].min(args[1]),
+            Self::Max => args[0].max(args[1]),
+            Self::Pow => args[0].powf(args[1]),
+        }
+    }
+}
+
+impl Expr {
+    fn eval(
+        &self,
+        variables: &HashMap<String, f64>,
+    ) -> Result<f64, EvalFormulaError> {
+        Ok(match self {
+            Expr::Number(value) => *value,
+            Expr::Variable(name) => *variables.get(name).ok_or_else(|| {
+                EvalFormulaError::UnknownVariable(name.clone())
+            })?,
+            Expr::Negate(expr) => -expr.eval(variables)?,
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(variables)?, rhs.eval(variables)?);
+                match op {
+                    BinaryOp::Add => lhs + rhs,
+                    BinaryOp::Subtract => lhs - rhs,
+                    BinaryOp::Multiply => lhs * rhs,
+                    BinaryOp::Divide => lhs / rhs,
+                    BinaryOp::Remainder => lhs % rhs,
+                }
+            }
+            Expr::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(variables))
+                    .collect::<Result<Vec<f64>, _>>()?;
+                function.apply(&args)
+            }
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Symbol(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Identifier(name) => f.write_str(name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ParseFormulaError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        let mut take_while = |predicate: fn(char) -> bool| {
+            while let Some((i, c)) = chars.next_if(|(_, c)| predicate(*c)) {
+                end = i + c.len_utf8();
+            }
+            &source[start..end]
+        };
+
+        match c {
+            c if c.is_whitespace() => {}
+            '0'..='9' | '.' => {
+                let number = take_while(|c| c.is_ascii_digit() || c == '.');
+                tokens.push(Token::Number(number.parse().map_err(|_| {
+                    ParseFormulaError::Unexpected(number.to_string())
+                })?));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let name = take_while(|c| c.is_alphanumeric() || c == '_');
+                tokens.push(Token::Identifier(name.to_string()));
+            }
+            '+' | '-' | '*' | '/' | '%' | '(' | ')' | ',' => {
+                tokens.push(Token::Symbol(c))
+            }
+            c => return Err(ParseFormulaError::UnexpectedCharacter(c)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser, the usual precedence rules apply.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Result<Token, ParseFormulaError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or(ParseFormulaError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn next_symbol_if(&mut self, symbols: &[char]) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(Token::Symbol(symbol)) if symbols.contains(symbol) => {
+                self.position += 1;
+                Some(*symbol)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), ParseFormulaError> {
+        match self.next()? {
+            Token::Symbol(s) if s == symbol => Ok(()),
+            token => Err(ParseFormulaError::Unexpected(token.to_string())),
+        }
+    }
+
+    /// `term (('+' | '-') term)*`
+    fn expression(&mut self) -> Result<Expr, ParseFormulaError> {
+        let mut expr = self.term()?;
+        while let Some(symbol) = self.next_symbol_if(&['+', '-']) {
+            let op = match symbol {
+                '+' => BinaryOp::Add,
+                _ => BinaryOp::Subtract,
+            };
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.term()?));
+        }
+        Ok(expr)
+    }
+
+    /// `factor (('*' | '/' | '%') factor)*`
+    fn term(&mut self) -> Result<Expr, ParseFormulaError> {
+        let mut expr = self.factor()?;
+        while let Some(symbol) = self.next_symbol_if(&['*', '/', '%']) {
+            let op = match symbol {
+                '*' => BinaryOp::Multiply,
+                '/' => BinaryOp::Divide,
+                _ => BinaryOp::Remainder,
+            };
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.factor()?));
+        }
+        Ok(expr)
+    }
+
+    /// `'-' factor | number | variable | function '(' args ')' | '('
+    /// expression ')'`
+    fn factor(&mut self) -> Result<Expr, ParseFormulaError> {
+        match self.next()? {
+            Token::Symbol('-') => Ok(Expr::Negate(Box::new(self.factor()?))),
+            Token::Symbol('(') => {
+                let expr = self.expression()?;
+                self.expect_symbol(')')?;
+                Ok(expr)
+            }
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Identifier(name) => {
+                if self.next_symbol_if(&['(']).is_none() {
+                    return Ok(Expr::Variable(name));
+                }
+
+                let function = Function::from_name(&name).ok_or_else(|| {
+                    ParseFormulaError::UnknownFunction(name.clone())
+                })?;
+
+                let mut args = vec![self.expression()?];
+                while self.next_symbol_if(&[',']).is_some() {
+                    args.push(self.expression()?);
+                }
+                self.expect_symbol(')')?;
+
+                if args.len() != function.arguments() {
+                    return Err(ParseFormulaError::WrongArgumentCount(
+                        name,
+                        function.arguments(),
+                    ));
+                }
+
+                Ok(Expr::Call(function, args))
+            }
+            token => Err(ParseFormulaError::Unexpected(token.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, variables: &[(&str, f64)]) -> f64 {
+        let variables = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect();
+        source.parse::<Formula>().unwrap().eval(&variables).unwrap()
+    }
+
+    /// Verifies operator precedence, functions and variables.
+    #[test]
+    fn formulas_evaluate() {
+        assert_eq!(eval("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(eval("10 - 4 - 3", &[]), 3.0);
+        assert_eq!(eval("-2 * -3 + 7 % 4", &[]), 9.0);
+        assert_eq!(eval("ceil(n / wx)", &[("n", 1000.0), ("wx", 64.0)]), 16.0);
+        assert_eq!(eval("max(pow(2, 10), n_2)", &[("n_2", 5.0)]), 1024.0);
+        assert_eq!(eval("2.5 * e", &[("e", 2.0)]), 5.0);
+    }
+
+    /// Verifies malformed formulas get rejected.
+    #[test]
+    fn malformed_formulas_fail() {
+        let parse = |source: &str| source.parse::<Formula>().unwrap_err();
+
+        assert_eq!(parse("1 +"), ParseFormulaError::UnexpectedEnd);
+        assert_eq!(parse("(1 + 2"), ParseFormulaError::UnexpectedEnd);
+        assert_eq!(parse("1 2"), ParseFormulaError::Unexpected("2".into()));
+        assert_eq!(parse("2 ^ 3"), ParseFormulaError::UnexpectedCharacter('^'));
+        assert_eq!(
+            parse("foo(1)"),
+            ParseFormulaError::UnknownFunction("foo".into())
+        );
+        assert_eq!(
+            parse("min(1)"),
+            ParseFormulaError::WrongArgumentCount("min".into(), 2)
+        );
+
+        let formula: Formula = "n / 3".parse().unwrap();
+        assert_eq!(
+            formula.eval(&HashMap::new()),
+            Err(EvalFormulaError::UnknownVariable("n".into()))
+        );
+        assert!(matches!(
+            formula.eval_integer(&HashMap::from([("n".into(), 10.0)])),
+            Err(EvalFormulaError::NotAnInteger(..))
+        ));
+    }
+}
@@ -0,0 +1,662 @@
+//! Declarative benchmark specifications, which allow defining a benchmark in a
+//! TOML or JSON file instead of writing Rust, see [BenchmarkSpec]
+//!
+//! A spec for a SAXPY kernel could look like:
+//!
+//! ```toml
+//! name = "SAXPY"
+//! shader = "saxpy.wgsl"
+//! warmup_count = 100
+//! count = 1000
+//! workgroup_sizes = [[64, 1, 1], [256, 1, 1]]
+//! dispatch = [["ceil(n / wx)", 1, 1]]
+//!
+//! [params]
+//! n = 1048576
+//!
+//! [[bindings]]
+//! binding = 0
+//! len = "n"
+//! init = "random"
+//! usage = "read_only_storage"
+//!
+//! [[bindings]]
+//! binding = 1
+//! len = "n"
+//! init = { formula = "i % 7" }
+//! usage = "storage"
+//!
+//! [metrics]
+//! gflops = "2 * n / time / 1000000000"
+//! ```
+
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use thiserror::Error;
+use wgpu::{Extent3d, ShaderModuleDescriptor, ShaderSource};
+
+use crate::{
+    reflect_bindings, Benchmark, BenchmarkComputePipeline, BenchmarkResults,
+    BindingInit, BindingKind, CreatePipelineError, CreateShaderResourcesError,
+    GPUContext, MapTimestampResultError, PassMode, PipelineParameters,
+    ShaderAnalysisError, ShaderResources, SubmissionMode, TimeUnit,
+};
+
+mod formula;
+
+pub use formula::{EvalFormulaError, Formula, ParseFormulaError};
+
+/// A benchmark defined declaratively, usually loaded from a file with
+/// [BenchmarkSpec::load] or from a directory of them with
+/// [BenchmarkSpec::load_dir].
+///
+/// Sizes, dispatch grids, init patterns and metrics are given as [Formula]s
+/// which can use the following variables:
+/// - Every entry of `params`.
+/// - `wx`, `wy` and `wz`: the workgroup size being benchmarked.
+/// - `i`: the index of the component being generated, only in
+///   [InitPattern::Formula].
+/// - `time` and `host_time`: seconds per iteration measured with the GPU
+///   timestamps and the host's wall-clock respectively, only in `metrics`.
+///
+/// The shader's `@group(0)` resources are created from its reflected bindings
+/// (see [ShaderResources]), so only the bindings that need a size or specific
+/// contents have to be given in `bindings`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BenchmarkSpec {
+    /// Name of the benchmark.
+    pub name: String,
+
+    /// Path to the WGSL shader, relative to the spec file.
+    pub shader: PathBuf,
+
+    /// Entry point of the compute shader, `main` by default.
+    #[serde(default = "default_entry_point")]
+    pub entry_point: String,
+
+    /// The number of warm-up iterations, see [Benchmark].
+    #[serde(default)]
+    pub warmup_count: usize,
+
+    /// The number of timed iterations, see [Benchmark].
+    pub count: usize,
+
+    /// How the timed iterations get split into command buffers, given as
+    /// `single`, `per_batch` or `per_iteration`. See [SubmissionMode].
+    #[serde(default)]
+    pub submission_mode: SubmissionMode,
+
+    /// How the timed iterations get split into compute passes, given as
+    /// `per_batch` or `per_iteration`. See [PassMode].
+    #[serde(default)]
+    pub pass_mode: PassMode,
+
+    /// The workgroup sizes to benchmark, as `[x, y, z]`.
+    pub workgroup_sizes: Vec<[u32; 3]>,
+
+    /// The grids of workgroups dispatched by each iteration, as `[x, y, z]`.
+    pub dispatch: Vec<[Formula; 3]>,
+
+    /// Named values that can be used by every formula.
+    #[serde(default)]
+    pub params: BTreeMap<String, f64>,
+
+    /// How to create the bindings of `@group(0)`.
+    #[serde(default)]
+    pub bindings: Vec<BindingSpec>,
+
+    /// Named formulas computed from each run's results, such as GFLOPS or
+    /// bandwidth.
+    #[serde(default)]
+    pub metrics: BTreeMap<String, Formula>,
+
+    /// Contents of the shader file, read when loading the spec.
+    #[serde(skip)]
+    shader_source: String,
+}
+
+/// How to create the resource bound to `@group(0) @binding(n)`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BindingSpec {
+    /// The `n` of the `@binding(n)` attribute.
+    pub binding: u32,
+
+    /// Amount of elements of a runtime-sized array. Ignored for bindings with
+    /// a fixed size.
+    pub len: Option<Formula>,
+
+    /// Size of a texture as `[width, height, depth_or_array_layers]`.
+    pub texture_size: Option<[Formula; 3]>,
+
+    /// Contents of a buffer, zeroed by default.
+    #[serde(default)]
+    pub init: InitPattern,
+
+    /// If given, the binding's usage in the shader is checked against it.
+    pub usage: Option<BindingUsage>,
+}
+
+/// Contents of a buffer created from a [BindingSpec].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InitPattern {
+    /// All zeroes.
+    #[default]
+    Zeroed,
+    /// Pseudo-random values in `[0, 1)`, the same in every run.
+    Random,
+    /// Each component is computed from its index `i`.
+    Formula(Formula),
+}
+
+/// How a shader uses a binding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingUsage {
+    /// `var<uniform>`
+    Uniform,
+    /// `var<storage, read_write>`
+    Storage,
+    /// `var<storage, read>`
+    ReadOnlyStorage,
+    /// A sampled or depth texture.
+    Texture,
+    /// A storage texture.
+    StorageTexture,
+}
+
+fn default_entry_point() -> String { "main".to_string() }
+
+impl BenchmarkSpec {
+    /// Loads a spec from a `.toml` or `.json` file.
+    pub fn load(path: &Path) -> Result<Self, SpecError> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| SpecError::Read(path.to_path_buf(), err))?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&text, base_dir),
+            Some("json") => Self::from_json(&text, base_dir),
+            _ => Err(SpecError::UnknownFormat(path.to_path_buf())),
+        }
+    }
+
+    /// Loads every `.toml` and `.json` spec in the directory, sorted by file
+    /// name.
+    pub fn load_dir(dir: &Path) -> Result<Vec<Self>, SpecError> {
+        let read_error = |err| SpecError::Read(dir.to_path_buf(), err);
+
+        let mut paths = fs::read_dir(dir)
+            .map_err(read_error)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<PathBuf>, _>>()
+            .map_err(read_error)?;
+        paths.retain(|path| {
+            matches!(
+                path.extension().and_then(|extension| extension.to_str()),
+                Some("toml" | "json")
+            )
+        });
+        paths.sort();
+
+        paths.iter().map(|path| Self::load(path)).collect()
+    }
+
+    /// Parses a TOML spec, reading its shader relative to `base_dir`.
+    pub fn from_toml(text: &str, base_dir: &Path) -> Result<Self, SpecError> {
+        let spec: Self = toml::from_str(text)
+            .map_err(|err| SpecError::Parse(err.to_string()))?;
+        spec.with_shader_from(base_dir)
+    }
+
+    /// Parses a JSON spec, reading its shader relative to `base_dir`.
+    pub fn from_json(text: &str, base_dir: &Path) -> Result<Self, SpecError> {
+        let spec: Self = serde_json::from_str(text)
+            .map_err(|err| SpecError::Parse(err.to_string()))?;
+        spec.with_shader_from(base_dir)
+    }
+
+    fn with_shader_from(mut self, base_dir: &Path) -> Result<Self, SpecError> {
+        let path = base_dir.join(&self.shader);
+        self.shader_source = fs::read_to_string(&path)
+            .map_err(|err| SpecError::Read(path.clone(), err))?;
+        self.shader = path;
+        Ok(self)
+    }
+
+    /// The shader module, with its workgroup placeholders still in place.
+    pub fn shader(&self) -> ShaderModuleDescriptor<'_> {
+        ShaderModuleDescriptor {
+            label: Some(&self.name),
+            source: ShaderSource::Wgsl(Cow::Borrowed(&self.shader_source)),
+        }
+    }
+
+    /// Creates the resources needed to run the benchmark with the given
+    /// workgroup size.
+    pub fn instantiate(
+        &self,
+        gpu: &GPUContext,
+        workgroup_size: [u32; 3],
+    ) -> Result<SpecInstance<'_>, SpecError> {
+        let variables = self.variables(workgroup_size);
+        let bindings = reflect_bindings(
+            &self.shader(),
+            &self.entry_point,
+            Some(workgroup_size.into()),
+        )?;
+
+        let mut inits = HashMap::new();
+        for binding_spec in &self.bindings {
+            let key = (0, binding_spec.binding);
+            let binding = bindings
+                .iter()
+                .find(|binding| (binding.group, binding.binding) == key)
+                .ok_or(CreateShaderResourcesError::UnknownBinding(
+                    key.0, key.1,
+                ))?;
+
+            if let Some(usage) = binding_spec.usage {
+                if Some(usage) != BindingUsage::of(&binding.kind) {
+                    return Err(SpecError::UsageMismatch(key.0, key.1, usage));
+                }
+            }
+
+            let runtime_sized = match &binding.kind {
+                BindingKind::Uniform(layout)
+                | BindingKind::Storage { layout, .. } => layout.len.is_none(),
+                _ => false,
+            };
+            let len = match &binding_spec.len {
+                Some(len) => eval_integer(len, &variables)? as usize,
+                None if runtime_sized => {
+                    return Err(SpecError::MissingLen(key.0, key.1))
+                }
+                None => 0,
+            };
+
+            let init = match (&binding_spec.texture_size, &binding_spec.init) {
+                (Some([width, height, depth]), _) => BindingInit::Texture {
+                    size: Extent3d {
+                        width: eval_integer(width, &variables)? as u32,
+                        height: eval_integer(height, &variables)? as u32,
+                        depth_or_array_layers: eval_integer(depth, &variables)?
+                            as u32,
+                    },
+                },
+                (None, InitPattern::Zeroed) => BindingInit::Zeroed { len },
+                (None, InitPattern::Random) => BindingInit::Generated {
+                    len,
+                    generator: Box::new(move |i| random(key, i)),
+                },
+                (None, InitPattern::Formula(formula)) => {
+                    // Check the formula evaluates before generating with it
+                    let mut variables = variables.clone();
+                    variables.insert("i".to_string(), 0.0);
+                    eval(formula, &variables)?;
+
+                    let variables = RefCell::new(variables);
+                    BindingInit::Generated {
+                        len,
+                        generator: Box::new(move |i| {
+                            let mut variables = variables.borrow_mut();
+                            variables.insert("i".to_string(), i as f64);
+                            formula.eval(&variables).unwrap_or(f64::NAN)
+                        }),
+                    }
+                }
+            };
+
+            inits.insert(key, init);
+        }
+
+        let workgroups_dispatch = self
+            .dispatch
+            .iter()
+            .map(|[x, y, z]| {
+                Ok((
+                    eval_integer(x, &variables)? as u32,
+                    eval_integer(y, &variables)? as u32,
+                    eval_integer(z, &variables)? as u32,
+                ))
+            })
+            .collect::<Result<Vec<_>, SpecError>>()?;
+
+        Ok(SpecInstance {
+            spec: self,
+            workgroup_size,
+            variables,
+            resources: ShaderResources::new(gpu, &bindings, inits)?,
+            workgroups_dispatch,
+        })
+    }
+
+    /// Runs the benchmark with the given workgroup size.
+    pub async fn run(
+        &self,
+        gpu: &GPUContext,
+        workgroup_size: [u32; 3],
+    ) -> Result<SpecResults, SpecError> {
+        let instance = self.instantiate(gpu, workgroup_size)?;
+        let pipeline =
+            BenchmarkComputePipeline::new(instance.pipeline_parameters(gpu))
+                .await?;
+        let results = instance.benchmark().run(pipeline).await?;
+
+        Ok(SpecResults {
+            name: self.name.clone(),
+            workgroup_size,
+            metrics: instance.metrics(&results)?,
+            results,
+        })
+    }
+
+    /// Variables available to every formula.
+    fn variables(&self, workgroup_size: [u32; 3]) -> HashMap<String, f64> {
+        let mut variables: HashMap<String, f64> = self
+            .params
+            .iter()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect();
+
+        for (name, size) in ["wx", "wy", "wz"].into_iter().zip(workgroup_size) {
+            variables.insert(name.to_string(), size as f64);
+        }
+
+        variables
+    }
+}
+
+/// The resources of a [BenchmarkSpec] created for a workgroup size, ready to
+/// create its [PipelineParameters] and [Benchmark].
+pub struct SpecInstance<'a> {
+    spec: &'a BenchmarkSpec,
+    workgroup_size: [u32; 3],
+    variables: HashMap<String, f64>,
+    resources: ShaderResources,
+    workgroups_dispatch: Vec<(u32, u32, u32)>,
+}
+
+impl<'a> SpecInstance<'a> {
+    /// Parameters for creating the benchmark's pipeline.
+    pub fn pipeline_parameters<'b>(
+        &'b self,
+        gpu: &'b GPUContext,
+    ) -> PipelineParameters<'b, 'b> {
+        PipelineParameters {
+            shader: self.spec.shader(),
+            entry_point: &self.spec.entry_point,
+            bind_group_0: self.resources.bind_group_entries(0),
+            gpu,
+            workgroup_size: Some(self.workgroup_size.into()),
+        }
+    }
+
+    /// The benchmark to run on the pipeline.
+    pub fn benchmark(&self) -> Benchmark<'a> {
+        Benchmark {
+            warmup_count: self.spec.warmup_count,
+            count: self.spec.count,
+            workgroups_dispatch: self.workgroups_dispatch.clone(),
+            submission_mode: self.spec.submission_mode,
+            pass_mode: self.spec.pass_mode,
+            ..Default::default()
+        }
+    }
+
+    /// The resources bound by the shader.
+    pub fn resources(&self) -> &ShaderResources { &self.resources }
+
+    /// Evaluates the spec's metrics with the given results, in the same order
+    /// as the spec's `metrics`.
+    pub fn metrics(
+        &self,
+        results: &BenchmarkResults,
+    ) -> Result<Vec<(String, f64)>, SpecError> {
+        let mut variables = self.variables.clone();
+        variables.insert(
+            "time".to_string(),
+            results.time_per_iteration(TimeUnit::Second),
+        );
+        variables.insert(
+            "host_time".to_string(),
+            results.host_time_per_iteration(TimeUnit::Second),
+        );
+
+        self.spec
+            .metrics
+            .iter()
+            .map(|(name, formula)| {
+                Ok((name.clone(), eval(formula, &variables)?))
+            })
+            .collect()
+    }
+}
+
+/// Results of running a [BenchmarkSpec] with [BenchmarkSpec::run].
+#[derive(Debug, Clone)]
+pub struct SpecResults {
+    /// Name of the spec ran.
+    pub name: String,
+    /// The workgroup size ran.
+    pub workgroup_size: [u32; 3],
+    /// The benchmark results.
+    pub results: BenchmarkResults,
+    /// The spec's metrics, see [SpecInstance::metrics].
+    pub metrics: Vec<(String, f64)>,
+}
+
+impl BindingUsage {
+    /// The usage of a binding of the given kind, [None] for samplers.
+    fn of(kind: &BindingKind) -> Option<Self> {
+        Some(match kind {
+            BindingKind::Uniform(_) => Self::Uniform,
+            BindingKind::Storage {
+                read_only: true, ..
+            } => Self::ReadOnlyStorage,
+            BindingKind::Storage {
+                read_only: false, ..
+            } => Self::Storage,
+            BindingKind::Texture { .. } => Self::Texture,
+            BindingKind::StorageTexture { .. } => Self::StorageTexture,
+            BindingKind::Sampler { .. } => return None,
+        })
+    }
+}
+
+fn eval(
+    formula: &Formula,
+    variables: &HashMap<String, f64>,
+) -> Result<f64, SpecError> {
+    formula
+        .eval(variables)
+        .map_err(|err| SpecError::Formula(formula.to_string(), err))
+}
+
+fn eval_integer(
+    formula: &Formula,
+    variables: &HashMap<String, f64>,
+) -> Result<u64, SpecError> {
+    formula
+        .eval_integer(variables)
+        .map_err(|err| SpecError::Formula(formula.to_string(), err))
+}
+
+/// Pseudo-random value in `[0, 1)` for the component `i` of a binding, with
+/// SplitMix64 so that it doesn't need any state.
+fn random((group, binding): (u32, u32), i: usize) -> f64 {
+    let seed = ((group as u64) << 48) ^ ((binding as u64) << 32);
+    let mut z = seed.wrapping_add((i as u64).wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Error loading or running a [BenchmarkSpec]
+#[derive(Debug, Error)]
+pub enum SpecError {
+    /// Error reading a spec or shader file
+    #[error("error reading {}: {1}", .0.display())]
+    Read(PathBuf, std::io::Error),
+    /// The spec file isn't `.toml` or `.json`
+    #[error("unknown spec format for {}, expected .toml or .json", .0.display())]
+    UnknownFormat(PathBuf),
+    /// Error deserializing the spec
+    #[error("error parsing spec: {0}")]
+    Parse(String),
+    /// Error evaluating one of the spec's formulas
+    #[error("error evaluating \"{0}\": {1}")]
+    Formula(String, EvalFormulaError),
+    /// The runtime-sized array at `(group, binding)` has no `len`
+    #[error(
+        "@group({0}) @binding({1}) is a runtime-sized array and needs a len"
+    )]
+    MissingLen(u32, u32),
+    /// The binding at `(group, binding)` isn't used as the given usage
+    #[error("@group({0}) @binding({1}) isn't used as {2:?} by the shader")]
+    UsageMismatch(u32, u32, BindingUsage),
+    /// Error reflecting the shader's bindings
+    #[error(transparent)]
+    ShaderAnalysis(#[from] ShaderAnalysisError),
+    /// Error creating the shader's resources
+    #[error(transparent)]
+    CreateResources(#[from] CreateShaderResourcesError),
+    /// Error creating the pipeline
+    #[error(transparent)]
+    CreatePipeline(#[from] CreatePipelineError),
+    /// Error reading the timestamp queries
+    #[error(transparent)]
+    MapTimestamp(#[from] MapTimestampResultError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specs_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../../specs")
+    }
+
+    /// Verifies the specs shipped in the repository load, and that their
+    /// formulas evaluate.
+    #[test]
+    fn repository_specs_load() {
+        let specs = BenchmarkSpec::load_dir(&specs_dir()).unwrap();
+        assert!(!specs.is_empty());
+
+        let saxpy = specs.iter().find(|spec| spec.name == "SAXPY").unwrap();
+        assert!(saxpy.shader_source.contains("$workgroup$"));
+
+        let variables = saxpy.variables([64, 1, 1]);
+        let [x, y, z] = &saxpy.dispatch[0];
+        assert_eq!(
+            [x, y, z].map(|formula| formula.eval_integer(&variables).unwrap()),
+            [saxpy.params["n"] as u64 / 64, 1, 1]
+        );
+    }
+
+    /// Verifies the same spec can be written in JSON, that unknown fields get
+    /// rejected and that the submission and pass modes can be chosen.
+    #[test]
+    fn json_specs_parse() {
+        let spec = BenchmarkSpec::from_json(
+            r#"{
+                "name": "JSON SAXPY",
+                "shader": "saxpy.wgsl",
+                "count": 10,
+                "workgroup_sizes": [[32, 1, 1]],
+                "dispatch": [["n / wx", 1, 1]],
+                "params": { "n": 64 },
+                "bindings": [
+                    { "binding": 0, "len": "n", "init": { "formula": "i" } }
+                ],
+                "metrics": { "gflops": "2 * n / time" }
+            }"#,
+            &specs_dir(),
+        )
+        .unwrap();
+
+        assert_eq!(spec.entry_point, "main");
+        assert_eq!(spec.warmup_count, 0);
+        assert_eq!(spec.submission_mode, SubmissionMode::Single);
+        assert_eq!(spec.pass_mode, PassMode::PerBatch);
+        assert!(matches!(
+            spec.bindings[0].init,
+            InitPattern::Formula(ref formula) if formula.to_string() == "i"
+        ));
+
+        let unknown_field = BenchmarkSpec::from_json(
+            r#"{ "name": "x", "shader": "saxpy.wgsl", "count": 1,
+                 "workgroup_sizes": [], "dispatch": [], "iterations": 5 }"#,
+            &specs_dir(),
+        );
+        assert!(matches!(unknown_field, Err(SpecError::Parse(_))));
+
+        let modes = BenchmarkSpec::from_json(
+            r#"{ "name": "x", "shader": "saxpy.wgsl", "count": 1,
+                 "workgroup_sizes": [], "dispatch": [],
+                 "submission_mode": "per_iteration",
+                 "pass_mode": "per_iteration" }"#,
+            &specs_dir(),
+        )
+        .unwrap();
+        assert_eq!(modes.submission_mode, SubmissionMode::PerIteration);
+        assert_eq!(modes.pass_mode, PassMode::PerIteration);
+    }
+
+    /// Verifies the random init is reproducible and within `[0, 1)`.
+    #[test]
+    fn random_init_is_deterministic() {
+        let values: Vec<f64> = (0..1000).map(|i| random((0, 1), i)).collect();
+
+        assert!(values.iter().all(|value| (0.0..1.0).contains(value)));
+        assert_eq!(
+            values,
+            (0..1000).map(|i| random((0, 1), i)).collect::<Vec<_>>()
+        );
+        assert_ne!(values[0], random((0, 2), 0));
+    }
+
+    /// Runs the SAXPY spec end to end, evaluating its metrics.
+    #[tokio::test]
+    async fn saxpy_spec_runs() {
+        let spec =
+            BenchmarkSpec::load(&specs_dir().join("saxpy.toml")).unwrap();
+        let gpu = GPUContext::new(None).await.unwrap();
+
+        let instance = spec.instantiate(&gpu, [64, 1, 1]).unwrap();
+        let pipeline =
+            BenchmarkComputePipeline::new(instance.pipeline_parameters(&gpu))
+                .await
+                .unwrap();
+        let results = Benchmark {
+            warmup_count: 0,
+            count: 1,
+            ..instance.benchmark()
+        }
+        .run(pipeline)
+        .await
+        .unwrap();
+
+        assert_eq!(results.count, 1);
+        let metrics = instance.metrics(&results).unwrap();
+        assert_eq!(
+            metrics
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            spec.metrics.keys().map(String::as_str).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,33 @@
+--- DOWN ---
+
+-- Postgres can't drop values from an enum, so the types are recreated
+-- without them, along with the results of the 3D benchmarks, which have to
+-- be deleted before the benchmarks they reference.
+
+DELETE FROM benchmark_results
+  USING computational_benchmark
+  WHERE benchmark_results.computational_benchmark_id = computational_benchmark.computational_benchmark_id
+    AND computational_benchmark.kind = 'stencil_3d';
+
+DELETE FROM computational_benchmark WHERE kind = 'stencil_3d';
+
+ALTER TYPE computational_benchmark_kind RENAME TO computational_benchmark_kind_old;
+CREATE TYPE computational_benchmark_kind AS ENUM ('matmul', 'reduction', 'convolution', 'scan');
+ALTER TABLE computational_benchmark
+  ALTER COLUMN kind TYPE computational_benchmark_kind
+  USING kind::text::computational_benchmark_kind;
+DROP TYPE computational_benchmark_kind_old;
+
+DELETE FROM benchmark_results
+  USING memory_benchmark
+  WHERE benchmark_results.memory_benchmark_id = memory_benchmark.memory_benchmark_id
+    AND memory_benchmark.kind = 'texture_3d_to_texture_3d';
+
+DELETE FROM memory_benchmark WHERE kind = 'texture_3d_to_texture_3d';
+
+ALTER TYPE memory_benchmark_kind RENAME TO memory_benchmark_kind_old;
+CREATE TYPE memory_benchmark_kind AS ENUM ('buffer_sequential', 'buffer_shuffled', 'buffer_to_buffer', 'buffer_to_texture', 'texture_to_texture');
+ALTER TABLE memory_benchmark
+  ALTER COLUMN kind TYPE memory_benchmark_kind
+  USING kind::text::memory_benchmark_kind;
+DROP TYPE memory_benchmark_kind_old;
@@ -0,0 +1,5 @@
+--- UP ---
+
+ALTER TYPE computational_benchmark_kind ADD VALUE 'stencil_3d';
+
+ALTER TYPE memory_benchmark_kind ADD VALUE 'texture_3d_to_texture_3d';
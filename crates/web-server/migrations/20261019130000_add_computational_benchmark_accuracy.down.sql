@@ -0,0 +1,6 @@
+--- DOWN ---
+
+ALTER TABLE computational_benchmark
+  DROP COLUMN max_abs_error,
+  DROP COLUMN max_rel_error,
+  DROP COLUMN max_ulp_error;
@@ -0,0 +1,8 @@
+--- UP ---
+
+-- Errors of the output of the benchmark against an f64 CPU reference, NULL
+-- for the results posted before accuracy was checked.
+ALTER TABLE computational_benchmark
+  ADD COLUMN max_abs_error DOUBLE PRECISION CHECK( max_abs_error >= 0 ),
+  ADD COLUMN max_rel_error DOUBLE PRECISION CHECK( max_rel_error >= 0 ),
+  ADD COLUMN max_ulp_error DOUBLE PRECISION CHECK( max_ulp_error >= 0 );
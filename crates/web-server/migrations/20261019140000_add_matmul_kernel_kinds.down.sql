@@ -0,0 +1,20 @@
+--- DOWN ---
+
+-- Postgres can't drop values from an enum, so the type is recreated without
+-- them, along with the results of the tiled matmul kernels.
+-- The results referencing them have to be deleted first.
+
+DELETE FROM benchmark_results
+  USING computational_benchmark
+  WHERE benchmark_results.computational_benchmark_id = computational_benchmark.computational_benchmark_id
+    AND computational_benchmark.kind IN ('matmul_tiled', 'matmul_register_blocked');
+
+DELETE FROM computational_benchmark
+  WHERE kind IN ('matmul_tiled', 'matmul_register_blocked');
+
+ALTER TYPE computational_benchmark_kind RENAME TO computational_benchmark_kind_old;
+CREATE TYPE computational_benchmark_kind AS ENUM ('matmul', 'reduction', 'convolution', 'scan', 'stencil_3d');
+ALTER TABLE computational_benchmark
+  ALTER COLUMN kind TYPE computational_benchmark_kind
+  USING kind::text::computational_benchmark_kind;
+DROP TYPE computational_benchmark_kind_old;
@@ -0,0 +1,4 @@
+--- UP ---
+
+ALTER TYPE computational_benchmark_kind ADD VALUE 'matmul_tiled';
+ALTER TYPE computational_benchmark_kind ADD VALUE 'matmul_register_blocked';
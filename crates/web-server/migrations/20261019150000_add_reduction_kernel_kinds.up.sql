@@ -0,0 +1,4 @@
+--- UP ---
+
+ALTER TYPE computational_benchmark_kind ADD VALUE 'reduction_atomic';
+ALTER TYPE computational_benchmark_kind ADD VALUE 'reduction_single_pass';
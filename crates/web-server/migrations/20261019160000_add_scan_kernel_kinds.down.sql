@@ -0,0 +1,20 @@
+--- DOWN ---
+
+-- Postgres can't drop values from an enum, so the type is recreated without
+-- them, along with the results of the Blelloch and decoupled look-back scans.
+-- The results referencing them have to be deleted first.
+
+DELETE FROM benchmark_results
+  USING computational_benchmark
+  WHERE benchmark_results.computational_benchmark_id = computational_benchmark.computational_benchmark_id
+    AND computational_benchmark.kind IN ('scan_blelloch', 'scan_blelloch_exclusive', 'scan_decoupled_look_back', 'scan_decoupled_look_back_exclusive');
+
+DELETE FROM computational_benchmark
+  WHERE kind IN ('scan_blelloch', 'scan_blelloch_exclusive', 'scan_decoupled_look_back', 'scan_decoupled_look_back_exclusive');
+
+ALTER TYPE computational_benchmark_kind RENAME TO computational_benchmark_kind_old;
+CREATE TYPE computational_benchmark_kind AS ENUM ('matmul', 'reduction', 'convolution', 'scan', 'stencil_3d', 'matmul_tiled', 'matmul_register_blocked', 'reduction_atomic', 'reduction_single_pass');
+ALTER TABLE computational_benchmark
+  ALTER COLUMN kind TYPE computational_benchmark_kind
+  USING kind::text::computational_benchmark_kind;
+DROP TYPE computational_benchmark_kind_old;
@@ -0,0 +1,6 @@
+--- UP ---
+
+ALTER TYPE computational_benchmark_kind ADD VALUE 'scan_blelloch';
+ALTER TYPE computational_benchmark_kind ADD VALUE 'scan_blelloch_exclusive';
+ALTER TYPE computational_benchmark_kind ADD VALUE 'scan_decoupled_look_back';
+ALTER TYPE computational_benchmark_kind ADD VALUE 'scan_decoupled_look_back_exclusive';
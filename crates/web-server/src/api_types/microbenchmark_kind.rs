@@ -1,3 +1,4 @@
+use microbenchmarks::{find_microbenchmark, Microbenchmark};
 use serde::{Deserialize, Serialize};
 use MicrobenchmarkKind::*;
 
@@ -5,17 +6,79 @@ use MicrobenchmarkKind::*;
 #[derive(Clone, Copy, Serialize, Deserialize, Debug)]
 pub enum MicrobenchmarkKind {
     Matmul,
+    MatmulTiled,
+    MatmulRegisterBlocked,
     Reduction,
+    ReductionAtomic,
+    ReductionSinglePass,
     Convolution,
     Scan,
+    ScanBlelloch,
+    ScanBlellochExclusive,
+    ScanDecoupledLookBack,
+    ScanDecoupledLookBackExclusive,
+    Stencil3d,
     BufferSequential,
     BufferShuffled,
     BufferToBuffer,
     BufferToTexture,
     TextureToTexture,
+    Texture3dToTexture3d,
 }
 
 impl MicrobenchmarkKind {
+    /// Every microbenchmark that has a page on the website.
+    pub const ALL: &'static [MicrobenchmarkKind] = &[
+        Matmul,
+        MatmulTiled,
+        MatmulRegisterBlocked,
+        Convolution,
+        Reduction,
+        ReductionAtomic,
+        ReductionSinglePass,
+        Scan,
+        ScanBlelloch,
+        ScanBlellochExclusive,
+        ScanDecoupledLookBack,
+        ScanDecoupledLookBackExclusive,
+        Stencil3d,
+        BufferSequential,
+        BufferShuffled,
+        BufferToBuffer,
+        BufferToTexture,
+        TextureToTexture,
+        Texture3dToTexture3d,
+    ];
+
+    /// The microbenchmark from the [microbenchmarks] registry that gets ran.
+    pub fn microbenchmark(&self) -> &'static dyn Microbenchmark {
+        let name = match self {
+            Matmul => "matmul",
+            MatmulTiled => "matmul_tiled",
+            MatmulRegisterBlocked => "matmul_register_blocked",
+            Reduction => "reduction_sum",
+            ReductionAtomic => "reduction_sum_atomic",
+            ReductionSinglePass => "reduction_sum_single_pass",
+            Convolution => "convolution",
+            Scan => "scan",
+            ScanBlelloch => "scan_blelloch",
+            ScanBlellochExclusive => "scan_blelloch_exclusive",
+            ScanDecoupledLookBack => "scan_decoupled_look_back",
+            ScanDecoupledLookBackExclusive => {
+                "scan_decoupled_look_back_exclusive"
+            }
+            Stencil3d => "stencil_3d",
+            BufferToBuffer => "buffer_to_buffer",
+            BufferToTexture => "buffer_to_texture",
+            TextureToTexture => "texture_to_texture",
+            Texture3dToTexture3d => "texture_3d_to_texture_3d",
+            BufferSequential => "buffer_sequential",
+            BufferShuffled => "buffer_shuffled",
+        };
+
+        find_microbenchmark(name).expect("microbenchmark is registered")
+    }
+
     /// Crafts the call to the JS `run_microbenchmark` function defined on the
     /// [Layout](crate::routes::extractors::Layout) based on the specific
     /// microbenchmark.
@@ -28,110 +91,63 @@ impl MicrobenchmarkKind {
     pub fn run_microbenchmark_fn(&self) -> String {
         format!(
             r#"run_microbenchmark({microbenchmark_json},
-                                      "{wasm_benchmark_fn_str}",
+                                      "{microbenchmark_name}",
                                       {workgroups_array},
-                                      "{custom_result_fn_str}",
-                                      (result) => {create_custom_result},
                                       results_div,
                                       disable_checkbox)"#,
             microbenchmark_json = serde_json::to_string(&self).unwrap(),
-            wasm_benchmark_fn_str = self.wasm_benchmark_function(),
+            microbenchmark_name = self.microbenchmark().name(),
             workgroups_array = self.benchmark_workgroups(),
-            custom_result_fn_str = self.custom_result_function(),
-            create_custom_result = self.custom_result(),
         )
     }
 
-    fn wasm_benchmark_function(&self) -> &'static str {
-        match self {
-            Matmul => "wasm_matmul_benchmark",
-            Reduction => "wasm_reduction_sum_benchmark",
-            Convolution => "wasm_convolution_benchmark",
-            Scan => "wasm_scan_benchmark",
-            BufferToBuffer => "wasm_buffer_to_buffer_benchmark",
-            BufferToTexture => "wasm_buffer_to_texture_benchmark",
-            TextureToTexture => "wasm_texture_to_texture_benchmark",
-            BufferSequential => todo!(),
-            BufferShuffled => todo!(),
-        }
-    }
+    /// JS array with the microbenchmark's default workgroups, as numbers for
+    /// 1D workgroups or as arrays of sizes otherwise.
+    fn benchmark_workgroups(&self) -> String {
+        let microbenchmark = self.microbenchmark();
+        let dims = microbenchmark.workgroup_dimensions();
 
-    fn benchmark_workgroups(&self) -> &'static str {
-        match self {
-            // Accessing same-row should be faster than accessing different rows
-            // which is why we use column-dominant workgroups
-            Matmul | Convolution => {
-                "[[4, 8], [2, 16], [1, 32], [8, 8], [4, 16], [2, 32],
-             [1, 64], [8, 16], [4, 32], [2, 64], [1, 128], [16, 16], [8, 32],
-             [4, 64], [2, 128], [1, 256]]"
-            }
-            Reduction | Scan => "[8, 16, 32, 64, 128, 256]",
-            BufferSequential => todo!(),
-            BufferShuffled => todo!(),
-            BufferToBuffer => "[8, 16, 32, 64, 128, 256]",
-            BufferToTexture | TextureToTexture => {
-                "[[4, 4], [1, 32], [32, 1], [4, 8], [8, 4], [8, 8], [1, 64], [64, 1], [16, 8], [8, 16], [16, 16]]"
-            }
-        }
-    }
+        let workgroups: Vec<String> = microbenchmark
+            .default_workgroups()
+            .into_iter()
+            .map(|(x, y, z)| match dims {
+                1 => x.to_string(),
+                _ => format!("{:?}", &[x, y, z][..dims]),
+            })
+            .collect();
 
-    /// JS instructions for getting an extra line with a custom result such as
-    /// FLOPs or Bandwidth
-    ///
-    /// Assume there is a `result` variable in the script with the benchmark
-    /// results.
-    ///
-    /// The code should be an expression to create a string for the line with
-    /// the custom result.
-    fn custom_result(&self) -> &'static str {
-        match self {
-            Matmul | Reduction | Convolution | Scan => {
-                r#"
-                "GFLOPS: " + (result.flops() / 1_000_000_000).toFixed(3)
-            "#
-            }
-            BufferSequential | BufferShuffled | BufferToBuffer
-            | BufferToTexture | TextureToTexture => {
-                r#"
-                "Bandwidth (GB/s): " + (result.bandwidth() / 1_000_000_000).toFixed(3)
-            "#
-            }
-        }
-    }
-
-    fn custom_result_function(&self) -> &'static str {
-        match self {
-            Matmul | Reduction | Convolution | Scan => "flops",
-            BufferSequential | BufferShuffled | BufferToBuffer
-            | BufferToTexture | TextureToTexture => "bandwidth",
-        }
+        format!("[{}]", workgroups.join(", "))
     }
 
     pub const fn path(&self) -> &'static str {
         match self {
             Matmul => "/matmul",
+            MatmulTiled => "/matmul_tiled",
+            MatmulRegisterBlocked => "/matmul_register_blocked",
             Reduction => "/reduction",
+            ReductionAtomic => "/reduction_atomic",
+            ReductionSinglePass => "/reduction_single_pass",
             Convolution => "/convolution",
             Scan => "/scan",
+            ScanBlelloch => "/scan_blelloch",
+            ScanBlellochExclusive => "/scan_blelloch_exclusive",
+            ScanDecoupledLookBack => "/scan_decoupled_look_back",
+            ScanDecoupledLookBackExclusive => {
+                "/scan_decoupled_look_back_exclusive"
+            }
+            Stencil3d => "/stencil_3d",
             BufferSequential => "/buffer_sequential",
             BufferShuffled => "/buffer_shuffled",
             BufferToBuffer => "/buffer_to_buffer",
             BufferToTexture => "/buffer_to_texture",
             TextureToTexture => "/texture_to_texture",
+            Texture3dToTexture3d => "/texture_3d_to_texture_3d",
         }
     }
 
-    pub const fn title(&self) -> &'static str {
-        match self {
-            Matmul => "Matrix Multiplication",
-            Reduction => "Reduction",
-            Convolution => "Convolution",
-            Scan => "Scan",
-            BufferSequential => "Sequential Buffer Memory Access",
-            BufferShuffled => "Shuffled Buffer Memory Accesses",
-            BufferToBuffer => "Memory Copy From Buffer To Buffer",
-            BufferToTexture => "Memory Copy From Buffer To Texture",
-            TextureToTexture => "Memory Copy From Texture To Texture",
-        }
+    pub fn title(&self) -> &'static str { self.microbenchmark().title() }
+
+    pub fn description(&self) -> &'static str {
+        self.microbenchmark().description()
     }
 }
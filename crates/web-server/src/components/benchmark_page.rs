@@ -1,7 +1,7 @@
 use maud::{html, Markup, PreEscaped, Render};
 
 use super::historical_data::HistoricalData;
-use crate::api_types::MicrobenchmarkKind::{self, *};
+use crate::api_types::MicrobenchmarkKind;
 
 pub struct MicrobenchmarkPage {
     pub microbenchmark: MicrobenchmarkKind,
@@ -13,7 +13,7 @@ impl Render for MicrobenchmarkPage {
         html! {
 
         header { h1 { (self.microbenchmark.title()) } }
-        p { (self.description()) }
+        p { (self.microbenchmark.description()) }
 
         div class="microbenchmark-view-selectors" role="tablist" {
             a href="#execution" id="execution-view-selector" role="tab" aria-controls="execution-view" { "[Microbenchmark Execution]" }
@@ -103,29 +103,3 @@ impl Render for MicrobenchmarkPage {
         }
     }
 }
-
-impl MicrobenchmarkPage {
-    fn description(&self) -> &'static str {
-        match self.microbenchmark {
-            Matmul => "This microbenchmark tests the performance of a naive matrix multiplication between two 1024x1024 matrices of 32bit floats.",
-            Reduction => "This microbenchmark tests the performance of a naive single-pass reduction sum on a 1MiB buffer of random data.",
-            Convolution => "This microbenchmark tests the performance of a naive convolution between a 1024x1024 matrix and a 3x3 kernel.",
-            Scan => "This microbenchmark tests the performance of a naive multi-pass scan using the Sklansky technique over a 1MB buffer of random data.",
-            BufferToBuffer => {
-                "This microbenchmark tests the performance of copying memory between buffers in the GPU. It's a naive un-optimized implementation so it won't reflect the true bandwidth of your GPU."
-            }
-            BufferToTexture => {
-                "This microbenchmark tests the performance of copying memory from a buffer to a storage texture in the GPU. It's a naive un-optimized implementation so it won't reflect the true bandwidth of your GPU."
-            }
-            TextureToTexture => {
-                "This microbenchmark tests the performance of copying memory from a texture to a storage texture in the GPU. It's a naive un-optimized implementation so it won't reflect the true bandwidth of your GPU."
-            }
-            BufferSequential => {
-                "This microbenchmark tests the performance of accessing buffer elements in a sequential manner."
-            }
-            BufferShuffled => {
-                todo!()
-            }
-        }
-    }
-}
@@ -14,6 +14,14 @@ const TABS: &'static [NavLink] = &[
         name: "Matrix Multiplication",
         link: Matmul.path(),
     },
+    NavLink {
+        name: "Tiled Matrix Multiplication",
+        link: MatmulTiled.path(),
+    },
+    NavLink {
+        name: "Register-Blocked Matrix Multiplication",
+        link: MatmulRegisterBlocked.path(),
+    },
     NavLink {
         name: "Convolution",
         link: Convolution.path(),
@@ -22,10 +30,46 @@ const TABS: &'static [NavLink] = &[
         name: "Reduction",
         link: Reduction.path(),
     },
+    NavLink {
+        name: "Atomic Reduction",
+        link: ReductionAtomic.path(),
+    },
+    NavLink {
+        name: "Single-Pass Reduction",
+        link: ReductionSinglePass.path(),
+    },
     NavLink {
         name: "Scan",
         link: Scan.path(),
     },
+    NavLink {
+        name: "Blelloch Scan",
+        link: ScanBlelloch.path(),
+    },
+    NavLink {
+        name: "Exclusive Blelloch Scan",
+        link: ScanBlellochExclusive.path(),
+    },
+    NavLink {
+        name: "Decoupled Look-Back Scan",
+        link: ScanDecoupledLookBack.path(),
+    },
+    NavLink {
+        name: "Exclusive Decoupled Look-Back Scan",
+        link: ScanDecoupledLookBackExclusive.path(),
+    },
+    NavLink {
+        name: "3D Stencil",
+        link: Stencil3d.path(),
+    },
+    NavLink {
+        name: "Sequential Buffer Reads",
+        link: BufferSequential.path(),
+    },
+    NavLink {
+        name: "Shuffled Buffer Reads",
+        link: BufferShuffled.path(),
+    },
     NavLink {
         name: "Buffer To Buffer",
         link: BufferToBuffer.path(),
@@ -38,6 +82,10 @@ const TABS: &'static [NavLink] = &[
         name: "Texture To Texture",
         link: TextureToTexture.path(),
     },
+    NavLink {
+        name: "3D Texture To 3D Texture",
+        link: Texture3dToTexture3d.path(),
+    },
 ];
 
 /// A navbar with certain navlinks
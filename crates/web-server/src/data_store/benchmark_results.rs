@@ -1,5 +1,7 @@
 use sqlx::types::Uuid;
 
+use crate::api_types::MicrobenchmarkKind;
+
 mod computational;
 mod memory;
 
@@ -43,6 +45,58 @@ pub enum DataStoreBenchmarkKind {
     Memory(DataStoreMemoryBenchmark),
 }
 
+/// The kind a microbenchmark's results are stored as, along with the table of
+/// specific info it goes to.
+#[derive(Debug, Clone)]
+pub enum DataStoreMicrobenchmarkKind {
+    /// Stored in the computational benchmark table
+    Computational(DataStoreComputationalBenchmarkKind),
+    /// Stored in the memory benchmark table
+    Memory(DataStoreMemoryBenchmarkKind),
+}
+
+impl From<MicrobenchmarkKind> for DataStoreMicrobenchmarkKind {
+    fn from(kind: MicrobenchmarkKind) -> Self {
+        use DataStoreComputationalBenchmarkKind as Computational;
+        use DataStoreMemoryBenchmarkKind as Memory;
+        use MicrobenchmarkKind::*;
+
+        match kind {
+            Matmul => Self::Computational(Computational::Matmul),
+            MatmulTiled => Self::Computational(Computational::MatmulTiled),
+            MatmulRegisterBlocked => {
+                Self::Computational(Computational::MatmulRegisterBlocked)
+            }
+            Reduction => Self::Computational(Computational::Reduction),
+            ReductionAtomic => {
+                Self::Computational(Computational::ReductionAtomic)
+            }
+            ReductionSinglePass => {
+                Self::Computational(Computational::ReductionSinglePass)
+            }
+            Convolution => Self::Computational(Computational::Convolution),
+            Scan => Self::Computational(Computational::Scan),
+            ScanBlelloch => Self::Computational(Computational::ScanBlelloch),
+            ScanBlellochExclusive => {
+                Self::Computational(Computational::ScanBlellochExclusive)
+            }
+            ScanDecoupledLookBack => {
+                Self::Computational(Computational::ScanDecoupledLookBack)
+            }
+            ScanDecoupledLookBackExclusive => Self::Computational(
+                Computational::ScanDecoupledLookBackExclusive,
+            ),
+            Stencil3d => Self::Computational(Computational::Stencil3d),
+            BufferSequential => Self::Memory(Memory::BufferSequential),
+            BufferShuffled => Self::Memory(Memory::BufferShuffled),
+            BufferToBuffer => Self::Memory(Memory::BufferToBuffer),
+            BufferToTexture => Self::Memory(Memory::BufferToTexture),
+            TextureToTexture => Self::Memory(Memory::TextureToTexture),
+            Texture3dToTexture3d => Self::Memory(Memory::Texture3dToTexture3d),
+        }
+    }
+}
+
 pub struct DataStoreCreateBenchmarkResult {
     /// The ID of the platform this benchmark was run on
     pub platform_id: Uuid,
@@ -4,6 +4,13 @@ pub struct DataStoreComputationalBenchmark {
     pub kind: DataStoreComputationalBenchmarkKind,
     /// FLOPS (Floating Point Operations Per Second)
     pub flops: f64,
+    /// Largest absolute error of the output against an f64 reference
+    pub max_abs_error: Option<f64>,
+    /// Largest relative error of the output against an f64 reference
+    pub max_rel_error: Option<f64>,
+    /// Largest error of the output against an f64 reference in ULPs (units in
+    /// the last place)
+    pub max_ulp_error: Option<f64>,
 }
 
 #[derive(Debug, Clone, sqlx::Type)]
@@ -11,7 +18,17 @@ pub struct DataStoreComputationalBenchmark {
 #[sqlx(rename_all = "snake_case")]
 pub enum DataStoreComputationalBenchmarkKind {
     Matmul,
+    MatmulTiled,
+    MatmulRegisterBlocked,
     Reduction,
+    ReductionAtomic,
+    ReductionSinglePass,
     Convolution,
     Scan,
+    ScanBlelloch,
+    ScanBlellochExclusive,
+    ScanDecoupledLookBack,
+    ScanDecoupledLookBackExclusive,
+    #[sqlx(rename = "stencil_3d")]
+    Stencil3d,
 }
@@ -1,14 +1,15 @@
 use crate::api_types::{BenchmarkResultsFilters, MicrobenchmarkKind, Platform};
-use crate::data_store::benchmark_results::DataStoreMemoryBenchmarkKind;
 use crate::{
     api_types::BenchmarkResultsStatistics,
-    data_store::benchmark_results::computational::DataStoreComputationalBenchmarkKind,
+    data_store::benchmark_results::{
+        DataStoreComputationalBenchmarkKind, DataStoreMemoryBenchmarkKind,
+        DataStoreMicrobenchmarkKind,
+    },
 };
 use sqlx::postgres::PgRow;
 use sqlx::{types::Uuid, Acquire, FromRow, Postgres, Row, Transaction};
 use std::collections::HashMap;
 use std::ops::DerefMut;
-use MicrobenchmarkKind::*;
 
 use crate::data_store::PostgresDataStore;
 
@@ -138,30 +139,11 @@ impl DataStoreBenchmarkResultsInterface for PostgresDataStore {
         let mut client = self.client().await?;
 
         let query = sqlx::query_as(&query);
-        let query = match filters.microbenchmark {
-            Matmul => query.bind(DataStoreComputationalBenchmarkKind::Matmul),
-            Reduction => {
-                query.bind(DataStoreComputationalBenchmarkKind::Reduction)
-            }
-            Convolution => {
-                query.bind(DataStoreComputationalBenchmarkKind::Convolution)
-            }
-            Scan => query.bind(DataStoreComputationalBenchmarkKind::Scan),
-            BufferSequential => {
-                query.bind(DataStoreMemoryBenchmarkKind::BufferSequential)
-            }
-            BufferShuffled => {
-                query.bind(DataStoreMemoryBenchmarkKind::BufferShuffled)
-            }
-            BufferToBuffer => {
-                query.bind(DataStoreMemoryBenchmarkKind::BufferToBuffer)
-            }
-            BufferToTexture => {
-                query.bind(DataStoreMemoryBenchmarkKind::BufferToTexture)
-            }
-            TextureToTexture => {
-                query.bind(DataStoreMemoryBenchmarkKind::TextureToTexture)
+        let query = match filters.microbenchmark.into() {
+            DataStoreMicrobenchmarkKind::Computational(kind) => {
+                query.bind(kind)
             }
+            DataStoreMicrobenchmarkKind::Memory(kind) => query.bind(kind),
         };
 
         let results: Vec<BenchmarkResultsStatistics> =
@@ -332,20 +314,20 @@ impl FromRow<'_, PgRow> for BenchmarkResultsStatistics {
 }
 
 fn custom_metric_column(microbenchmark: MicrobenchmarkKind) -> &'static str {
-    match microbenchmark {
-        Matmul | Reduction | Convolution | Scan => "flops",
-        BufferSequential | BufferShuffled | BufferToBuffer
-        | BufferToTexture | TextureToTexture => "bandwidth",
+    match microbenchmark.into() {
+        DataStoreMicrobenchmarkKind::Computational(_) => "flops",
+        DataStoreMicrobenchmarkKind::Memory(_) => "bandwidth",
     }
 }
 
 fn microbenchmark_table_join(
     microbenchmark: MicrobenchmarkKind,
 ) -> &'static str {
-    match microbenchmark {
-        Matmul | Reduction | Convolution | Scan => "computational_benchmark",
-        BufferSequential | BufferShuffled | BufferToBuffer
-        | BufferToTexture | TextureToTexture => "memory_benchmark",
+    match microbenchmark.into() {
+        DataStoreMicrobenchmarkKind::Computational(_) => {
+            "computational_benchmark"
+        }
+        DataStoreMicrobenchmarkKind::Memory(_) => "memory_benchmark",
     }
 }
 
@@ -355,13 +337,16 @@ async fn create_computational_benchmark(
 ) -> Result<Uuid, sqlx::Error> {
     let id: Uuid = sqlx::query_scalar!(
         r#"
-                INSERT INTO computational_benchmark (kind, flops)
-                VALUES ($1, $2)
+                INSERT INTO computational_benchmark (kind, flops, max_abs_error, max_rel_error, max_ulp_error)
+                VALUES ($1, $2, $3, $4, $5)
                 ON CONFLICT DO NOTHING
                 RETURNING computational_benchmark_id
             "#,
         &create.kind as &DataStoreComputationalBenchmarkKind,
         &create.flops,
+        create.max_abs_error,
+        create.max_rel_error,
+        create.max_ulp_error,
     )
     .fetch_one(tx.deref_mut())
     .await?;
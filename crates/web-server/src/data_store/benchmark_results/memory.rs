@@ -22,4 +22,7 @@ pub enum DataStoreMemoryBenchmarkKind {
     BufferToTexture,
     /// Copying from texture -> texture
     TextureToTexture,
+    /// Copying from 3D texture -> 3D texture
+    #[sqlx(rename = "texture_3d_to_texture_3d")]
+    Texture3dToTexture3d,
 }
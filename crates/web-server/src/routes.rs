@@ -6,7 +6,7 @@ use axum::{
 use extractors::Layout;
 use tower_http::services::ServeDir;
 
-use crate::api_types::MicrobenchmarkKind::*;
+use crate::api_types::MicrobenchmarkKind;
 use crate::app_config::AppConfig;
 
 mod extractors;
@@ -25,42 +25,19 @@ use microbenchmark_page::microbenchmark_page;
 pub fn create_router(config: AppConfig) -> Router {
     let url = config.server_url;
 
-    Router::new()
-        .route("/", get(home::home))
-        .route(
-            Matmul.path(),
-            get(|l: Layout| async { microbenchmark_page(Matmul)(l, url) }),
-        )
-        .route(
-            Convolution.path(),
-            get(|l: Layout| async { microbenchmark_page(Convolution)(l, url) }),
-        )
-        .route(
-            Reduction.path(),
-            get(|l: Layout| async { microbenchmark_page(Reduction)(l, url) }),
-        )
-        .route(
-            Scan.path(),
-            get(|l: Layout| async { microbenchmark_page(Scan)(l, url) }),
-        )
-        .route(
-            BufferToBuffer.path(),
-            get(|l: Layout| async {
-                microbenchmark_page(BufferToBuffer)(l, url)
-            }),
-        )
-        .route(
-            BufferToTexture.path(),
-            get(|l: Layout| async {
-                microbenchmark_page(BufferToTexture)(l, url)
-            }),
-        )
-        .route(
-            TextureToTexture.path(),
-            get(|l: Layout| async {
-                microbenchmark_page(TextureToTexture)(l, url)
-            }),
-        )
+    let router = MicrobenchmarkKind::ALL.iter().fold(
+        Router::new().route("/", get(home::home)),
+        |router, &microbenchmark| {
+            router.route(
+                microbenchmark.path(),
+                get(move |l: Layout| async move {
+                    microbenchmark_page(microbenchmark)(l, url)
+                }),
+            )
+        },
+    );
+
+    router
         .route("/results", post(post_results::post_results))
         .route("/hardwares", get(hardware_options::hardware_options))
         .route("/operating_systems", get(os_options::os_options))
@@ -72,10 +72,8 @@ impl Layout {
                 window.shuffle = shuffle;
 
                 async function run_microbenchmark(microbenchmark_json,
-                                                  wasm_benchmark_fn_str,
+                                                  microbenchmark_name,
                                                   workgroups_array,
-                                                  custom_result_fn_str,
-                                                  create_custom_result_fn,
                                                   results_div,
                                                   disable_checkbox,
                                                   ) {{
@@ -101,16 +99,21 @@ impl Layout {
                               results_header.textContent = ".".repeat(dotCount);
                             }}, 300);
 
-                            let result;
-                            const wasm_benchmark_fn = wasm_module[wasm_benchmark_fn_str];
-                            if (Array.isArray(workgroup_size)) {{
-                                result = await wasm_benchmark_fn(...workgroup_size);
-                            }} else {{
-                                result = await wasm_benchmark_fn(workgroup_size);
-                            }}
+                            const workgroup_size_array = Array.isArray(workgroup_size) ? workgroup_size : [workgroup_size];
+                            const result = await wasm_module.wasm_run_microbenchmark(
+                                microbenchmark_name,
+                                workgroup_size_array,
+                                null,
+                            );
+                            // The microbenchmarks on the website report a
+                            // single metric, either FLOPS or bandwidth
+                            const metric = result.metrics[0];
+                            // Undefined for the benchmarks without an output
+                            // to check
+                            const accuracy = result.accuracy;
 
                             if (!disable_checkbox.checked) {{
-                                post_results(result, workgroup_size, microbenchmark_json, result[custom_result_fn_str]());
+                                post_results(result, workgroup_size, microbenchmark_json, metric.value, accuracy);
                             }}
 
                             clearInterval(results_header_interval);
@@ -123,16 +126,26 @@ impl Layout {
                             }}
 
                             let total_time_spent_p = document.createElement('p');
-                            total_time_spent_p.textContent = "Total time spent: " + result[0].total_time(TimeUnit.Second).toFixed(3) + "s";
+                            total_time_spent_p.textContent = "Total time spent: " + result.results.total_time(TimeUnit.Second).toFixed(3) + "s";
                             results_div.appendChild(total_time_spent_p);
 
                             let time_per_iter_p = document.createElement('p');
-                            time_per_iter_p.textContent = "Time per iteration: " + result[0].time_per_iteration(TimeUnit.Milli).toFixed(3) + "ms";
+                            time_per_iter_p.textContent = "Time per iteration: " + result.results.time_per_iteration(TimeUnit.Milli).toFixed(3) + "ms";
                             results_div.appendChild(time_per_iter_p);
 
+                            let host_time_per_iter_p = document.createElement('p');
+                            host_time_per_iter_p.textContent = "Host time per iteration: " + result.results.host_time_per_iteration(TimeUnit.Milli).toFixed(3) + "ms";
+                            results_div.appendChild(host_time_per_iter_p);
+
                             let custom_result_p = document.createElement('p');
-                            custom_result_p.textContent = create_custom_result_fn(result);
+                            custom_result_p.textContent = metric.label() + ": " + metric.display_value().toFixed(3);
                             results_div.appendChild(custom_result_p);
+
+                            if (accuracy != null) {{
+                                let accuracy_p = document.createElement('p');
+                                accuracy_p.textContent = "Max error vs f64: " + accuracy.max_abs_error.toExponential(3) + " (" + accuracy.max_ulp_error.toFixed(1) + " ULP)";
+                                results_div.appendChild(accuracy_p);
+                            }}
                         }};
                     }} catch (error) {{
                         let error_header = document.createElement('h2');
@@ -154,7 +167,7 @@ impl Layout {
                     }}
                 }}
 
-                async function post_results(result, workgroup_size, microbenchmark_kind_json, custom_result) {{
+                async function post_results(result, workgroup_size, microbenchmark_kind_json, custom_result, accuracy) {{
 
                     const adapter = await navigator.gpu.requestAdapter();
                     const webgpu_adapter_info = {{
@@ -172,7 +185,7 @@ impl Layout {
                     }}
 
                     const platform_info = {{
-                        wgpu_adapter_info: result[0].adapter_info.to_js(),
+                        wgpu_adapter_info: result.results.adapter_info.to_js(),
                         webgpu_adapter_info,
                     }}
 
@@ -180,9 +193,15 @@ impl Layout {
                         platform_info,
                         workgroup_size: workgroup_size_array,
                         benchmark_kind: microbenchmark_kind_json,
-                        count: result[0].count,
-                        total_time_spent: result[0].total_time_spent,
+                        count: result.results.count,
+                        total_time_spent: result.results.total_time_spent,
                         custom_result,
+                        // Non-finite errors become null
+                        accuracy: accuracy == null ? null : {{
+                            max_abs_error: accuracy.max_abs_error,
+                            max_rel_error: accuracy.max_rel_error,
+                            max_ulp_error: accuracy.max_ulp_error,
+                        }},
                     }}
 
                     fetch("{url}/results", {{
@@ -8,6 +8,7 @@ use serde::Deserialize;
 use crate::api_types::{
     BenchmarkResultsFilters, Hardware, MicrobenchmarkKind, Platform,
 };
+use crate::data_store::benchmark_results::DataStoreMicrobenchmarkKind;
 use crate::data_store::DataStore;
 use crate::error::ServerError;
 
@@ -104,10 +105,8 @@ pub async fn historica_data_table(
 }
 
 fn custom_metric_name(microbenchmark: MicrobenchmarkKind) -> &'static str {
-    use MicrobenchmarkKind::*;
-    match microbenchmark {
-        Matmul | Reduction | Convolution | Scan => "GFLOPS",
-        BufferSequential | BufferShuffled | BufferToBuffer
-        | BufferToTexture | TextureToTexture => "Bandwidth (GB/s)",
+    match microbenchmark.into() {
+        DataStoreMicrobenchmarkKind::Computational(_) => "GFLOPS",
+        DataStoreMicrobenchmarkKind::Memory(_) => "Bandwidth (GB/s)",
     }
 }
@@ -4,21 +4,10 @@ use axum::Extension;
 use maud::{html, Markup, PreEscaped};
 use rand::{seq::SliceRandom, thread_rng};
 
-use crate::api_types::MicrobenchmarkKind::{self, *};
+use crate::api_types::MicrobenchmarkKind;
 
 use super::extractors::Layout;
 
-/// Make sure this array contains all the supported microbenchmarks.
-const ALL_MICROBENCHMARKS: &[MicrobenchmarkKind] = &[
-    Matmul,
-    Convolution,
-    Reduction,
-    Scan,
-    BufferToBuffer,
-    BufferToTexture,
-    TextureToTexture,
-];
-
 #[cfg_attr(feature = "debug", axum::debug_handler)]
 pub async fn home(
     layout: Layout,
@@ -29,7 +18,7 @@ pub async fn home(
     static MICROBENCHMARK_DATA: LazyLock<String> = LazyLock::new(|| {
         // randomize order to avoid biasing results towards the first
         // microbenchmarks due to people refreshing or whatever
-        let mut microbenchmarks = ALL_MICROBENCHMARKS.to_vec();
+        let mut microbenchmarks = MicrobenchmarkKind::ALL.to_vec();
         microbenchmarks.shuffle(&mut thread_rng());
         // (title, run_microbenchmark callback)
         let microbenchmarks: Vec<(&'static str, String)> = microbenchmarks
@@ -4,9 +4,8 @@ use crate::{
     data_store::{
         benchmark_results::{
             DataStoreBenchmarkKind, DataStoreComputationalBenchmark,
-            DataStoreComputationalBenchmarkKind,
             DataStoreCreateBenchmarkResult, DataStoreMemoryBenchmark,
-            DataStoreMemoryBenchmarkKind,
+            DataStoreMicrobenchmarkKind,
         },
         non_empty_string::NonEmptyString,
         platform::{
@@ -42,6 +41,16 @@ pub struct PostResultsRequest {
     custom_result: f64,
     workgroup_size: Box<[u32]>,
     benchmark_kind: MicrobenchmarkKind,
+    /// Only sent by computational benchmarks, the errors are [None] if they
+    /// weren't finite.
+    accuracy: Option<Accuracy>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Accuracy {
+    max_abs_error: Option<f64>,
+    max_rel_error: Option<f64>,
+    max_ulp_error: Option<f64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -101,6 +110,7 @@ pub async fn post_results(
         kind: benchmark_kind_to_data_store(
             results.benchmark_kind,
             results.custom_result,
+            results.accuracy,
         ),
     };
 
@@ -120,58 +130,33 @@ fn workgroup_size_to_tuple(size: &[u32]) -> (u32, u32, u32) {
 fn benchmark_kind_to_data_store(
     benchmark: MicrobenchmarkKind,
     custom_result_value: f64,
+    accuracy: Option<Accuracy>,
 ) -> DataStoreBenchmarkKind {
-    use MicrobenchmarkKind::*;
+    match benchmark.into() {
+        DataStoreMicrobenchmarkKind::Computational(kind) => {
+            let error = |error: fn(&Accuracy) -> Option<f64>| {
+                accuracy.as_ref().and_then(error)
+            };
 
-    match benchmark {
-        Matmul | Reduction | Convolution | Scan => {
             DataStoreBenchmarkKind::Computational(
                 DataStoreComputationalBenchmark {
-                    kind: unwrap_computational_kind_to_data_store(benchmark),
+                    kind,
                     flops: custom_result_value,
+                    max_abs_error: error(|accuracy| accuracy.max_abs_error),
+                    max_rel_error: error(|accuracy| accuracy.max_rel_error),
+                    max_ulp_error: error(|accuracy| accuracy.max_ulp_error),
                 },
             )
         }
-        BufferSequential | BufferShuffled | BufferToBuffer
-        | BufferToTexture | TextureToTexture => {
+        DataStoreMicrobenchmarkKind::Memory(kind) => {
             DataStoreBenchmarkKind::Memory(DataStoreMemoryBenchmark {
-                kind: unwrap_memory_kind_to_data_store(benchmark),
+                kind,
                 bandwidth: custom_result_value,
             })
         }
     }
 }
 
-fn unwrap_computational_kind_to_data_store(
-    kind: MicrobenchmarkKind,
-) -> DataStoreComputationalBenchmarkKind {
-    use MicrobenchmarkKind::*;
-
-    match kind {
-        Matmul => DataStoreComputationalBenchmarkKind::Matmul,
-        Reduction => DataStoreComputationalBenchmarkKind::Reduction,
- Convolution => DataStoreComputationalBenchmarkKind::Convolution,
- Scan => DataStoreComputationalBenchmarkKind::Scan,
-        BufferSequential | BufferShuffled | BufferToBuffer
-        | BufferToTexture | TextureToTexture => panic!("unwrap_computational_kind_to_data_store should not be called on memory benchmark {:?}", kind),
-    }
-}
-
-fn unwrap_memory_kind_to_data_store(
-    kind: MicrobenchmarkKind,
-) -> DataStoreMemoryBenchmarkKind {
-    use MicrobenchmarkKind::*;
-
-    match kind {
-        Matmul | Reduction | Convolution | Scan => panic!("unwrap_memory_kind_to_data_store should not be called on computational benchmark {:?}", kind),
-        BufferSequential => DataStoreMemoryBenchmarkKind::BufferSequential,
-        BufferShuffled => DataStoreMemoryBenchmarkKind::BufferShuffled,
-        BufferToBuffer => DataStoreMemoryBenchmarkKind::BufferToBuffer,
-        BufferToTexture => DataStoreMemoryBenchmarkKind::BufferToTexture,
-        TextureToTexture => DataStoreMemoryBenchmarkKind::TextureToTexture,
-    }
-}
-
 fn webgpu_adapter_info_to_data_store(
     info: WebGpuAdapterInfo,
 ) -> DataStoreWebGpuAdapterInfo {
@@ -0,0 +1,17 @@
+@group(0) @binding(0)
+var<storage, read> x: array<f32>;
+
+@group(0) @binding(1)
+var<storage, read_write> y: array<f32>;
+
+@group(0) @binding(2)
+var<uniform> a: f32;
+
+@compute @workgroup_size($workgroup$)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+  let i = global_id.x;
+
+  if (i < arrayLength(&y)) {
+    y[i] = a * x[i] + y[i];
+  }
+}